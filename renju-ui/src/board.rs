@@ -1,6 +1,6 @@
 use egui::{style::Margin, *};
 use renju::{
-    board::{evaluator::RenjuConditions, BoardArr, BoardMarker, Point, Stone},
+    board::{evaluator::RenjuConditions, BoardArr, BoardMarker, Evaluate, Point, Stone},
     board::{Board, MoveIndex, Transformation, VariantType},
     file_reader::renlib::CommandVariant,
     p,
@@ -1,37 +1,560 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
 use renju::errors::ParseError;
 
-
-
 use color_eyre::eyre::WrapErr;
-use renju::board::{Board, BoardArr, BoardMarker, MoveIndex, Point};
-use renju::file_reader::open_file_path;
+use renju::analysis;
+use renju::board::influence::Influence;
+use renju::board::outline::Outline;
+use renju::board::{explain, notation, shapes};
+use renju::board::{Board, BoardArr, BoardMarker, Evaluate, MoveIndex, Point, Stone};
+use renju::config::{self, Config};
+use renju::engine::chart::EvalChart;
+use renju::engine::quality::QualityReport;
+use renju::engine::{self, Outcome};
+use renju::file_reader;
+use renju::game::GameRecord;
+use renju::i18n::{self, tr, Message};
+use renju::kifu;
+use renju::ml::{legality, selfplay};
+use renju::motif::{self, Motif};
+use renju::narration;
+use renju::progress::Progress;
+use renju::project::{self, Collection, Project};
+use renju::puzzle::{self, Verdict};
+use renju::report;
+use renju::session::{self, Session};
+use renju::share;
+use renju::solver::{self, pns};
+use renju::theme::{self, Theme};
+use renju::theory;
+use renju::training::{self, Quality, Training};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 fn main() -> Result<(), color_eyre::Report> {
     let _ = dotenv::dotenv();
     color_eyre::install()?;
-    renju::util::build_logger()?;
     let matches = Command::new("renju-open")
         .arg(
             Arg::new("file")
                 .index(1)
                 .help("File to read from")
-                .required(true),
+                .required_unless_present_any([
+                    "play",
+                    "selfplay",
+                    "legality",
+                    "verify-puzzles",
+                    "stream",
+                    "socket",
+                ])
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .help("File to output to")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .help("Only read the first N moves of every branch, for a quick look at a large library")
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("recover")
+                .long("recover")
+                .help("Keep what can be read of a damaged .lib file, printing what is left out")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .help("Only write the lines with this tag to the output, can be given several times")
+                .action(ArgAction::Append)
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("added-by")
+                .long("added-by")
+                .help("Only write the moves this author added to the output, as logged in the history of the library")
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("added-since")
+                .long("added-since")
+                .help("Only write the moves added on this day, like 2024-06-10, or later to the output")
+                .value_parser(parse_date)
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("added-until")
+                .long("added-until")
+                .help("Only write the moves added on this day, like 2024-06-10, or earlier to the output")
+                .value_parser(parse_date)
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("serve")
+                .long("serve")
+                .help("Serve the file over HTTP on ADDR, like 127.0.0.1:8080")
+                .value_name("ADDR")
+                .value_parser(clap::value_parser!(std::net::SocketAddr)),
+        )
+        .arg(
+            Arg::new("token")
+                .long("token")
+                .help("Only answer requests to --serve with this token")
+                .requires("serve"),
+        )
+        .arg(
+            Arg::new("no-interactive")
+                .short('I')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("quiz")
+                .long("quiz")
+                .help("Train on puzzles found in the file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("duplicates")
+                .long("duplicates")
+                .help("List branches reaching the same position, possibly rotated or mirrored")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("fix")
+                .long("fix")
+                .help("Move the continuations of duplicated branches to the first one before going on")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("holes")
+                .long("holes")
+                .help("List strong replies missing from the library, against COLOR or both")
+                .value_name("COLOR")
+                .num_args(0..=1)
+                .default_missing_value("both")
+                .value_parser(["black", "white", "both"]),
+        )
+        .arg(
+            Arg::new("replay")
+                .long("replay")
+                .help("Step through the main line, or the line through NODE, with comments")
+                .value_name("NODE")
+                .num_args(0..=1)
+                .default_missing_value("main"),
+        )
+        .arg(
+            Arg::new("delay")
+                .long("delay")
+                .help("Time between moves with --replay, like 500ms or 2s")
+                .value_parser(parse_delay)
+                .default_value("1s"),
+        )
+        .arg(
+            Arg::new("training")
+                .long("training")
+                .help("File to keep training progress in, defaults to the session of the file")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("puzzles")
+                .long("puzzles")
+                .help("Train on the puzzles of a pack instead of the ones found in the file")
+                .requires("quiz")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("motif")
+                .long("motif")
+                .help("Only train on puzzles with this motif, like vcf or double-threat, can be given several times")
+                .requires("quiz")
+                .action(ArgAction::Append)
+                .value_parser(clap::value_parser!(Motif)),
+        )
+        .arg(
+            Arg::new("no-session")
+                .long("no-session")
+                .help("Neither resume nor keep the last visited node, bookmarks and quiz progress")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("play")
+                .long("play")
+                .help("Play a game against the engine")
+                .value_name("COLOR")
+                .value_parser(["black", "white"]),
+        )
+        .arg(
+            Arg::new("stream")
+                .long("stream")
+                .help("Read stones like `B h8` and `W i9`, `pass`, `undo` and `clear` from stdin, printing the board and analysis after each")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("socket")
+                .long("socket")
+                .help("Like --stream, reading from connections to the unix socket PATH")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("engine")
+                .long("engine")
+                .help("Search algorithm of the engine [default: alphabeta]")
+                .value_parser(["alphabeta", "mcts"]),
+        )
+        .arg(
+            Arg::new("selfplay")
+                .long("selfplay")
+                .help("Let the engine play GAMES games against itself and write training samples to the output")
+                .value_name("GAMES")
+                .value_parser(clap::value_parser!(usize))
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("legality")
+                .long("legality")
+                .help("Write the forbidden points of COUNT random positions as CSV to the output")
+                .value_name("COUNT")
+                .value_parser(clap::value_parser!(usize))
+                .requires("output"),
+        )
+        .arg(
+            Arg::new("verify-puzzles")
+                .long("verify-puzzles")
+                .help("Check that every puzzle of a pack has its solution as the only VCF")
+                .value_name("PACK")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .help("Seed for everything random, a random one is picked and printed if not given")
+                .value_parser(clap::value_parser!(u64)),
+        )
+        .arg(
+            Arg::new("include-legal")
+                .long("include-legal")
+                .help("Also write legal points with --legality")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("proofs")
+                .long("proofs")
+                .help("File to keep positions proven with `prove` in, reused between sessions")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .arg(
+            Arg::new("timing")
+                .long("timing")
+                .help("Print the time spent per evaluator and parser phase when done")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("Config file with defaults for the flags, see `renju::config`")
+                .value_parser(clap::value_parser!(PathBuf)),
+        )
+        .subcommand_negates_reqs(true)
+        .subcommand(
+            Command::new("daemon")
+                .about("Keep the libraries of directories loaded and indexed, answering on --socket and --serve")
+                .arg(
+                    Arg::new("preload")
+                        .long("preload")
+                        .help("Load every library below DIR, can be given several times")
+                        .value_name("DIR")
+                        .action(ArgAction::Append)
+                        .required(true)
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .help("Answer the commands of --stream on the unix socket PATH")
+                        .value_name("PATH")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(
+                    Arg::new("serve")
+                        .long("serve")
+                        .help("Serve the libraries over HTTP on ADDR, like 127.0.0.1:8080")
+                        .value_name("ADDR")
+                        .value_parser(clap::value_parser!(std::net::SocketAddr)),
+                )
+                .arg(
+                    Arg::new("token")
+                        .long("token")
+                        .help("Only answer requests to --serve with this token")
+                        .requires("serve"),
+                ),
         )
-        .arg(Arg::new("output").short('o').help("File to output to"))
-        .arg(Arg::new("no-interactive").short('I'))
         .get_matches();
 
-    let path = matches.get_one::<std::path::PathBuf>("file").unwrap();
+    let config_path = matches
+        .get_one::<PathBuf>("config")
+        .cloned()
+        .or_else(config::path);
+    let mut config = load_config(config_path.as_deref())?;
+    config.apply_env()?;
+    i18n::set_language(config.language.unwrap_or_else(i18n::Language::from_locale));
+    if std::io::stderr().is_terminal() {
+        theme::set_theme(Theme::new(
+            config.theme.unwrap_or_default(),
+            config.colors.unwrap_or_default(),
+        ));
+    }
+
+    if matches.get_flag("timing") || config.timing == Some(true) {
+        let timing = renju::util::build_logger_with_timing()?;
+        let result = run(&matches, &config);
+        eprintln!("{}", timing.report());
+        result
+    } else {
+        renju::util::build_logger()?;
+        run(&matches, &config)
+    }
+}
+
+/// The rows of the variation tree printed by `tree`.
+const TREE_ROWS: usize = 40;
+
+/// The stones taken back looking for the moves of an imported position.
+const RECONSTRUCT_NODES: u64 = 100_000;
+
+/// The moves added by every diagram of a `figure`, unless given.
+const FIGURE_STEP: usize = 4;
+
+/// The diagrams in a row of a `figure`.
+const FIGURE_COLUMNS: usize = 3;
+
+fn run(matches: &ArgMatches, config: &Config) -> Result<(), color_eyre::Report> {
+    let engine = match matches.get_one::<String>("engine") {
+        Some(engine) => engine.parse()?,
+        None => config.engine.unwrap_or_default(),
+    };
+    let seed = matches
+        .get_one::<u64>("seed")
+        .copied()
+        .unwrap_or_else(rand::random);
+    if let Some(("daemon", matches)) = matches.subcommand() {
+        return daemon(matches, config, engine);
+    }
+    if let Some(color) = matches.get_one::<String>("play") {
+        eprintln!("Seed: {seed}");
+        return play(Stone::from_bool(color == "black"), engine, seed);
+    }
+    let live_board = matches.get_flag("stream") || matches.contains_id("socket");
+    if live_board && !matches.contains_id("file") {
+        return live(matches, engine, None);
+    }
+    if let Some(&games) = matches.get_one::<usize>("selfplay") {
+        let output = matches.get_one::<PathBuf>("output").unwrap();
+        let options = selfplay::SelfPlayOptions {
+            kind: engine,
+            ..selfplay::SelfPlayOptions::default()
+        };
+        let file = std::fs::File::create(output)
+            .wrap_err_with(|| format!("while creating {:?}", output))?;
+        let samples = selfplay::selfplay(games, &options, seed, std::io::BufWriter::new(file))?;
+        eprintln!(
+            "Wrote {samples} samples from {games} games to {:?} (seed {seed})",
+            output
+        );
+        return Ok(());
+    }
+    if let Some(&count) = matches.get_one::<usize>("legality") {
+        let output = matches.get_one::<PathBuf>("output").unwrap();
+        let options = legality::LegalityOptions {
+            include_legal: matches.get_flag("include-legal"),
+            ..legality::LegalityOptions::default()
+        };
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let positions = (0..count)
+            .map(|_| legality::random_position(&mut rng, &options))
+            .collect::<Vec<_>>();
+        let file = std::fs::File::create(output)
+            .wrap_err_with(|| format!("while creating {:?}", output))?;
+        let rows = legality::write_csv(&positions, &options, std::io::BufWriter::new(file))?;
+        eprintln!(
+            "Wrote {rows} rows from {count} positions to {:?} (seed {seed})",
+            output
+        );
+        return Ok(());
+    }
+    if let Some(pack) = matches.get_one::<PathBuf>("verify-puzzles") {
+        let max_depth = puzzle::GenerateOptions::default().max_depth;
+        let (mut sound, mut unique) = (0, 0);
+        let puzzles = read_pack(pack)?;
+        for (i, puzzle) in puzzles.iter().enumerate() {
+            let verification = puzzle.verify(max_depth);
+            sound += usize::from(verification.is_sound());
+            unique += usize::from(verification.is_unique());
+            println!("{}: {verification}", i + 1);
+        }
+        eprintln!(
+            "{} puzzles, {sound} solved as stated, {unique} without duals or cooks",
+            puzzles.len()
+        );
+        return Ok(());
+    }
+    let path = matches.get_one::<PathBuf>("file").unwrap();
     tracing::info!("File: {:?}", path);
-    let graph = open_file_path(path).wrap_err_with(|| format!("while parsing file {:?}", path))?;
+    let show_progress = std::io::IsTerminal::is_terminal(&std::io::stderr());
+    let mut progress = Progress::new(|update| {
+        if let Some(fraction) = update.fraction().filter(|_| show_progress) {
+            eprint!("\r{}: {:3.0}%", update.stage, fraction * 100.0);
+        }
+    });
+    let options = file_reader::ParseOptions {
+        max_depth: matches.get_one::<usize>("max-depth").copied(),
+        recover: matches.get_flag("recover"),
+    };
+    let (mut graph, diagnostics) =
+        file_reader::open_file_path_with_diagnostics(path, &options, &mut progress)
+            .wrap_err_with(|| format!("while parsing file {:?}", path))?;
+    if show_progress {
+        eprintln!();
+    }
+    for diagnostic in &diagnostics {
+        eprintln!("{}: {diagnostic}", path.display());
+    }
+    if live_board {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut libraries = Collection::new();
+        libraries.add(name, graph);
+        return live(matches, engine, Some(&RwLock::new(libraries)));
+    }
+    if matches.get_flag("duplicates") || matches.get_flag("fix") {
+        let duplicates = graph.find_duplicates();
+        for duplicate in &duplicates {
+            eprintln!(
+                "{} duplicates {} ({:?})",
+                graph.describe_path(duplicate.duplicate),
+                graph.describe_path(duplicate.original),
+                duplicate.transformation
+            );
+        }
+        if matches.get_flag("fix") {
+            graph = graph.merge_duplicates(&duplicates);
+            eprintln!("Merged {} duplicates", duplicates.len());
+        } else {
+            eprintln!("{} duplicates", duplicates.len());
+        }
+    }
+
+    let session_path = match matches.get_flag("no-session") || config.session == Some(false) {
+        true => None,
+        false => {
+            let bytes =
+                std::fs::read(path).wrap_err_with(|| format!("while reading {:?}", path))?;
+            session::path(&session::library_key(&bytes))
+        }
+    };
+    let mut session = load_session(session_path.as_deref())?;
+    session.restore(&mut graph);
+    // edits from here on are logged, shown by `history`
+    graph.record_history(config.author.clone());
 
     //let mut file = OpenOptions::new().write(true).create(true).open(format!("{}.dot",path.file_stem().unwrap().to_str().unwrap())).expect("Couldn't create .dot file");
     //write!(file, "{:?}", graph).chain_err(|| "while writing to file");
-    if matches.contains_id("no-interactive") {
+    if matches.get_flag("quiz") {
+        let mut puzzles = match matches.get_one::<PathBuf>("puzzles") {
+            Some(pack) => read_pack(pack)?,
+            None => puzzle::generate(&graph, &Default::default())?,
+        };
+        let motifs = matches
+            .get_many::<Motif>("motif")
+            .map(|motifs| motifs.cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        puzzles.retain(|puzzle| puzzle.has_any(&motifs));
+        // progress used to be kept next to the file, keep using it if it's there
+        let training_path = matches
+            .get_one::<PathBuf>("training")
+            .or(config.training.as_ref())
+            .cloned()
+            .or_else(|| Some(path.with_extension("training.json")).filter(|p| p.exists()));
+        if let Some(training_path) = training_path {
+            let mut training = load_training(&training_path)?;
+            return quiz(&puzzles, &mut training, &mut |training| {
+                save_training(training, &training_path)
+            });
+        }
+        let mut training = std::mem::take(&mut session.training);
+        return quiz(&puzzles, &mut training, &mut |training| {
+            session.training.clone_from(training);
+            save_session(&session, session_path.as_deref())
+        });
+    }
+    if let Some(output) = matches.get_one::<PathBuf>("output") {
+        let options = file_reader::WriteOptions {
+            backups: config.backups.unwrap_or(0),
+            filter: export_filter(matches),
+            ..Default::default()
+        };
+        file_reader::save_file_path_with_options(&graph, output, &options)
+            .wrap_err_with(|| format!("while writing {:?}", output))?;
+        eprintln!("Wrote {:?}", output);
+    }
+    if let Some(color) = matches.get_one::<String>("holes") {
+        return holes(&graph, color);
+    }
+    if let Some(&addr) = matches.get_one::<std::net::SocketAddr>("serve") {
+        let token = matches
+            .get_one::<String>("token")
+            .or(config.server_token.as_ref())
+            .cloned();
+        eprintln!("Serving {:?} on http://{addr}", path);
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut libraries = Collection::new();
+        libraries.add(name, graph);
+        return serve(Arc::new(RwLock::new(libraries)), addr, token);
+    }
+    if let Some(node) = matches.get_one::<String>("replay") {
+        let node = match node.as_str() {
+            "main" => graph.get_root(),
+            node => node.parse()?,
+        };
+        return replay(&graph, node, *matches.get_one("delay").unwrap());
+    }
+    if matches.get_flag("no-interactive") {
         return Ok(());
     }
     eprintln!("{:?}", graph);
+    for text in [graph.title(), graph.comment()].into_iter().flatten() {
+        eprintln!("{text}");
+    }
+    if graph.roots().len() > 1 {
+        eprintln!(
+            "{} independent trees, list them with `roots`",
+            graph.roots().len()
+        );
+    }
+    let proofs_path = matches
+        .get_one::<PathBuf>("proofs")
+        .or(config.proofs.as_ref());
+    let mut proofs = match proofs_path {
+        Some(path) if path.exists() => {
+            let file =
+                std::fs::File::open(path).wrap_err_with(|| format!("while opening {:?}", path))?;
+            pns::ProofTable::read(std::io::BufReader::new(file))
+                .wrap_err_with(|| format!("while reading {:?}", path))?
+        }
+        _ => pns::ProofTable::new(),
+    };
+    if let Some(last) = session.last {
+        eprintln!("Resuming at {last:?} {}", graph.describe_path(last));
+        show(&graph, last)?;
+    }
+    let mut outline = Outline::new();
     let mut rl = rustyline::Editor::<(), _>::new()?;
     loop {
         let read = rl.readline(">> ");
@@ -47,27 +570,470 @@ fn main() -> Result<(), color_eyre::Report> {
             Ok(ref quit) if quit.to_lowercase().starts_with('q') => {
                 return Ok(());
             }
-            Ok(line) => {
-                let node = line.parse()?;
+            Ok(ref roots) if roots == "roots" => {
+                for root in graph.roots() {
+                    let moves = graph
+                        .get_children(root)
+                        .into_iter()
+                        .filter_map(|child| graph.get_move(child))
+                        .map(|marker| marker.point.to_string())
+                        .collect::<Vec<_>>();
+                    eprintln!("{root:?}: {}", moves.join(" "));
+                }
+            }
+            Ok(ref find) if find.starts_with("find ") => {
+                let mut args = find["find ".len()..].split_whitespace();
+                let point: Point = args.next().unwrap_or_default().parse()?;
+                let mut stone = None;
+                let mut depth = None;
+                for arg in args {
+                    match arg {
+                        "black" => stone = Some(Stone::Black),
+                        "white" => stone = Some(Stone::White),
+                        range => {
+                            let (min, max) = range.split_once('-').unwrap_or((range, range));
+                            depth = Some(min.parse()?..=max.parse()?);
+                        }
+                    }
+                }
+                for index in graph.find_moves_at(point, stone, depth) {
+                    eprintln!("{index:?}: {}", graph.describe_path(index));
+                }
+            }
+            Ok(ref solve) if solve.starts_with("solve ") => {
+                let node = solve["solve ".len()..].trim().parse()?;
                 let (board, moves) = traverse(&graph, node)?;
-                eprintln!("{}", board);
-                if let Some(last_point) = moves.last() {
-                    if let Some(BoardMarker {
-                        multiline_comment,
-                        oneline_comment,
-                        ..
-                    }) = board.get_point(*last_point)
-                    {
-                        if let Some(comment) = oneline_comment.as_deref() {
-                            tracing::info!("{}", comment)
+                let to_move = Stone::from_bool(moves.len().is_multiple_of(2));
+                match solver::vcf_proof(&board, to_move, 8) {
+                    Some(proof) => eprint!("{proof}"),
+                    None => eprintln!("No VCF found for {to_move:?}"),
+                }
+            }
+            Ok(ref compare) if compare.starts_with("compare ") => {
+                let mut args = compare["compare ".len()..].split_whitespace();
+                let left = args.next().unwrap_or_default().parse()?;
+                let right = args.next().unwrap_or_default().parse()?;
+                let depth = args.next().map(str::parse).transpose()?;
+                eprint!("{}", graph.compare(left, right, depth)?);
+            }
+            Ok(ref toggle) if toggle.starts_with("tree toggle ") => {
+                let node = toggle["tree toggle ".len()..].trim().parse()?;
+                outline.toggle(node);
+            }
+            Ok(ref tree) if tree == "tree" || tree.starts_with("tree ") => {
+                let start = match tree["tree".len()..].trim() {
+                    "" => 0,
+                    start => start.parse()?,
+                };
+                if let Some(last) = session.last {
+                    outline.set_current(&graph, last);
+                }
+                for row in outline.rows(&graph).skip(start).take(TREE_ROWS) {
+                    eprintln!("{row}  {}", row.index.node_index().index());
+                }
+            }
+            Ok(ref search) if search.starts_with('/') => {
+                let found = outline.search(&graph, &search[1..]);
+                eprintln!("{found} found, list them with tree");
+            }
+            Ok(ref kifu) if kifu.starts_with("kifu ") => {
+                let (args, margin) = crop_margin(&kifu["kifu ".len()..]);
+                let Some((node, path)) = args.split_once(' ') else {
+                    eprintln!("Usage: kifu <node> <file.svg|file.tex> [crop <margin>]");
+                    continue;
+                };
+                let game = GameRecord::from_graph(&graph, node.parse()?)?;
+                let mut sheet = kifu::Sheet::new(&game);
+                if let Some(margin) = margin {
+                    sheet = sheet.cropped(margin);
+                }
+                let path = Path::new(path.trim());
+                let text = match path.extension().and_then(|e| e.to_str()) {
+                    Some("tex") => sheet.to_latex(),
+                    _ => sheet.to_svg(),
+                };
+                std::fs::write(path, text).wrap_err_with(|| format!("while writing {path:?}"))?;
+            }
+            Ok(ref figure) if figure.starts_with("figure ") => {
+                let (args, margin) = crop_margin(&figure["figure ".len()..]);
+                let mut args = args.split_whitespace();
+                let (Some(node), Some(path)) = (args.next(), args.next()) else {
+                    eprintln!(
+                        "Usage: figure <node> <file.svg> [moves per diagram] [crop <margin>]"
+                    );
+                    continue;
+                };
+                let step = match args.next().map(str::parse) {
+                    Some(Ok(step)) => step,
+                    Some(Err(_)) => {
+                        eprintln!("expected a number of moves per diagram");
+                        continue;
+                    }
+                    None => FIGURE_STEP,
+                };
+                let game = GameRecord::from_graph(&graph, node.parse()?)?;
+                let mut figure = kifu::Figure::line(&game, step, FIGURE_COLUMNS);
+                if let Some(margin) = margin {
+                    figure = figure.cropped(margin);
+                }
+                let path = Path::new(path);
+                std::fs::write(path, figure.to_svg())
+                    .wrap_err_with(|| format!("while writing {path:?}"))?;
+            }
+            Ok(ref chart) if chart.starts_with("eval-graph ") => {
+                let Some((node, path)) = chart["eval-graph ".len()..].trim().split_once(' ') else {
+                    eprintln!("Usage: eval-graph <node> <file.svg>");
+                    continue;
+                };
+                let game = GameRecord::from_graph(&graph, node.parse()?)?;
+                let blunders =
+                    analysis::analyze_game(&game, &analysis::AnalyzeOptions::default()).blunders;
+                let chart = EvalChart::new(&game, &mut engine::Heuristic)
+                    .with_marks(blunders.iter().map(|blunder| blunder.ply));
+                let path = Path::new(path.trim());
+                std::fs::write(path, chart.to_svg())
+                    .wrap_err_with(|| format!("while writing {path:?}"))?;
+            }
+            Ok(ref influence) if influence.starts_with("influence") => {
+                let node = match influence["influence".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                let (board, _) = traverse(&graph, node)?;
+                let influence = Influence::new(&board);
+                let (black, white) = influence.area();
+                eprintln!("{influence}");
+                eprintln!("Black holds {black} points, white {white}");
+            }
+            Ok(ref shapes) if shapes.starts_with("shapes") => {
+                let node = match shapes["shapes".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                let (board, _) = traverse(&graph, node)?;
+                for found in shapes::find(&board) {
+                    eprintln!("{found}");
+                }
+            }
+            Ok(ref line) if line.starts_with("theory ") => {
+                let mut args = line["theory ".len()..].split_whitespace();
+                let game =
+                    GameRecord::from_graph(&graph, args.next().unwrap_or_default().parse()?)?;
+                let library = match args.next() {
+                    Some(path) => {
+                        theory::Library::from_graph(&file_reader::open_file_path(Path::new(path))?)
+                    }
+                    None => theory::Library::bundled(),
+                };
+                let result = theory::match_game_with(&game, &library);
+                eprintln!("{result}");
+                for (ply, text) in result.annotations() {
+                    eprintln!("{:4}. {}: {text}", ply + 1, game.moves[ply]);
+                }
+            }
+            Ok(ref tag) if tag.starts_with("motif ") => {
+                let mut args = tag["motif ".len()..].split_whitespace();
+                let node = args.next().unwrap_or_default().parse()?;
+                match args.next() {
+                    None => {
+                        let motifs = motif::motifs_at(&graph, node);
+                        eprintln!(
+                            "{}",
+                            motifs.iter().map(Motif::name).collect::<Vec<_>>().join(" ")
+                        );
+                    }
+                    Some(name) => match name.strip_prefix('-') {
+                        Some(name) => {
+                            if !motif::remove(&mut graph, node, &name.parse()?) {
+                                eprintln!("{node:?} has no motif {name}");
+                            }
                         }
-                        if let Some(comment) = multiline_comment.as_deref() {
-                            tracing::info!("{}", comment)
+                        None => motif::add(&mut graph, node, &name.parse()?),
+                    },
+                }
+            }
+            Ok(ref color) if color.starts_with("color ") => {
+                let mut args = color["color ".len()..].split_whitespace();
+                let node = args.next().unwrap_or_default().parse()?;
+                let color = match args.next() {
+                    None => {
+                        let inferred = match graph.get_parent_strong(&node) {
+                            Some(parent) => graph.inferred_color(parent),
+                            None => Stone::Empty,
+                        };
+                        eprintln!(
+                            "{:?}, inferred {inferred:?}",
+                            graph.color_at(node).unwrap_or(Stone::Empty)
+                        );
+                        continue;
+                    }
+                    Some("b") => Some(Stone::Black),
+                    Some("w") => Some(Stone::White),
+                    Some("auto") => None,
+                    Some(_) => {
+                        eprintln!("Usage: color <node> [b|w|auto]");
+                        continue;
+                    }
+                };
+                graph.set_color_override(node, color);
+            }
+            Ok(ref export) if export.starts_with("export-puzzles ") => {
+                let path = Path::new(export["export-puzzles ".len()..].trim());
+                let puzzles = puzzle::generate(&graph, &Default::default())?;
+                let file = std::fs::File::create(path)
+                    .wrap_err_with(|| format!("while writing {path:?}"))?;
+                puzzle::pack::write(&puzzles, std::io::BufWriter::new(file))?;
+                eprintln!("Wrote {} puzzles to {}", puzzles.len(), path.display());
+            }
+            Ok(ref novelties) if novelties.starts_with("novelties ") => {
+                let dir = Path::new(novelties["novelties ".len()..].trim());
+                let report = project::find_novelties(dir, &graph)?;
+                for (path, error) in &report.failed {
+                    eprintln!("Could not read {}: {error}", path.display());
+                }
+                eprintln!("{report}");
+            }
+            Ok(ref stats) if stats.starts_with("forbidden-stats") => {
+                let stats = match stats["forbidden-stats".len()..].trim() {
+                    "" => {
+                        let mut stats = project::ForbiddenStats::default();
+                        stats.add_graph(&graph);
+                        stats
+                    }
+                    dir => {
+                        let mut collection = Collection::new();
+                        for (path, error) in collection.preload(Path::new(dir))? {
+                            eprintln!("Could not read {}: {error}", path.display());
                         }
-                    } else {
-                        color_eyre::eyre::bail!("Move not found")
+                        collection.forbidden_stats()
+                    }
+                };
+                eprintln!("{stats}");
+            }
+            Ok(ref quality) if quality.starts_with("quality ") => {
+                let Some((dir, path)) = quality["quality ".len()..].trim().split_once(' ') else {
+                    eprintln!("Usage: quality <dir> <file.csv|file.json>");
+                    continue;
+                };
+                let mut collection = Collection::new();
+                for (path, error) in collection.preload(Path::new(dir))? {
+                    eprintln!("Could not read {}: {error}", path.display());
+                }
+                let mut quality = QualityReport::new();
+                for library in collection.libraries() {
+                    quality.add_graph(
+                        &library.graph,
+                        &mut engine::Heuristic,
+                        &analysis::AnalyzeOptions::default(),
+                    );
+                }
+                let path = Path::new(path.trim());
+                let file = std::fs::File::create(path)
+                    .wrap_err_with(|| format!("while writing {path:?}"))?;
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some("json") => write_quality_json(&quality, file)?,
+                    _ => quality.write_csv(std::io::BufWriter::new(file))?,
+                }
+            }
+            Ok(ref prepare) if prepare.starts_with("prepare ") => {
+                let args = prepare["prepare ".len()..]
+                    .split_whitespace()
+                    .collect::<Vec<_>>();
+                let [manifest, opening, path] = args[..] else {
+                    eprintln!("Usage: prepare <project.toml> <opening> <file.md|file.html>");
+                    continue;
+                };
+                let project = open_project(Path::new(manifest))?;
+                let preparation = report::preparation(&project, opening)?;
+                for (path, error) in &preparation.failed {
+                    eprintln!("Could not read {}: {error}", path.display());
+                }
+                let path = Path::new(path);
+                let text = match path.extension().and_then(|e| e.to_str()) {
+                    Some("html" | "htm") => preparation.to_html(),
+                    _ => preparation.to_markdown(),
+                };
+                std::fs::write(path, text).wrap_err_with(|| format!("while writing {path:?}"))?;
+            }
+            Ok(ref view) if view == "board" || view.starts_with("board ") => {
+                let node = match view["board".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                let node = board_view(&mut graph, node)?;
+                show(&graph, node)?;
+                session.last = Some(node);
+                save_session(&session, session_path.as_deref())?;
+            }
+            Ok(ref why) if why.starts_with("why ") => {
+                let mut args = why["why ".len()..].split_whitespace();
+                let point: Point = args.next().unwrap_or_default().parse()?;
+                let node = match args.next() {
+                    Some(node) => node.parse()?,
+                    None => session.last.unwrap_or_else(|| graph.get_root()),
+                };
+                let (board, _) = traverse(&graph, node)?;
+                eprintln!("{}", theme::board(&board, &[point]));
+                for stone in [Stone::Black, Stone::White] {
+                    eprint!("{}", explain::explain(&board, stone, point));
+                }
+            }
+            Ok(ref prove) if prove.starts_with("prove ") => {
+                let node = prove["prove ".len()..].trim().parse()?;
+                let (board, moves) = traverse(&graph, node)?;
+                let to_move = Stone::from_bool(moves.len().is_multiple_of(2));
+                let limits = engine::Limits {
+                    nodes: Some(config.prove_nodes.unwrap_or(100_000)),
+                    ..engine::Limits::default()
+                };
+                match pns::solve(&board, to_move, &limits, &mut proofs) {
+                    Outcome::Known(solution) => eprintln!(
+                        "{to_move:?} {:?}, best {:?} ({} nodes)",
+                        solution.value, solution.best, solution.nodes
+                    ),
+                    Outcome::Unknown => eprintln!("Not proven within {:?} nodes", limits.nodes),
+                }
+                if let Some(path) = proofs_path {
+                    let file = std::fs::File::create(path)
+                        .wrap_err_with(|| format!("while creating {:?}", path))?;
+                    proofs.write(std::io::BufWriter::new(file))?;
+                }
+            }
+            Ok(ref copy) if copy.starts_with("copy-pos") => {
+                let node = match copy["copy-pos".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                let (_, moves) = traverse(&graph, node)?;
+                let text = notation::write_moves(&moves);
+                eprintln!("{text}");
+                if let Err(e) = copy_to_clipboard(&text) {
+                    tracing::warn!("couldn't copy to the clipboard: {e}");
+                }
+            }
+            Ok(ref narrate) if narrate.starts_with("narrate") => {
+                let node = match narrate["narrate".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                println!("{}", narration::narrate_line(&graph, node)?);
+            }
+            Ok(ref link) if link.starts_with("share") => {
+                let node = match link["share".len()..].trim() {
+                    "" => session.last.unwrap_or_else(|| graph.get_root()),
+                    node => node.parse()?,
+                };
+                let (_, moves) = traverse(&graph, node)?;
+                let url = share::encode_url(&moves);
+                print_qr(&url);
+                eprintln!("{url}");
+            }
+            Ok(ref paste) if paste.starts_with("paste-pos") => {
+                let text = match paste["paste-pos".len()..].trim() {
+                    "" => paste_from_clipboard()?,
+                    text => text.to_owned(),
+                };
+                let moves = match text.trim().starts_with(share::SCHEME) {
+                    true => {
+                        let shared = share::decode_url(&text)?;
+                        let board = shared.board();
+                        if let Some(reason) = board.unreachable() {
+                            eprintln!("{board}");
+                            eprintln!("Not a position of a game: {reason}");
+                            continue;
+                        }
+                        let Some(moves) = shared.moves() else {
+                            eprintln!("{}", shared.board());
+                            eprintln!("Black must have as many stones as white or one more");
+                            continue;
+                        };
+                        moves
+                    }
+                    false => notation::parse_moves(&text)?,
+                };
+                match graph.find_position(&moves) {
+                    Some(node) => {
+                        eprintln!("{node:?} {}", graph.describe_path(node));
+                        show(&graph, node)?;
+                        session.last = Some(node);
+                        save_session(&session, session_path.as_deref())?;
+                    }
+                    None => {
+                        let moves = notation::write_moves(&moves);
+                        eprintln!("{}", tr(Message::NotInLibrary, &[&moves]));
+                    }
+                }
+            }
+            Ok(ref animate) if animate.starts_with("animate ") => {
+                let (args, margin) = crop_margin(&animate["animate ".len()..]);
+                let mut args = args.split_whitespace();
+                let (Some(node), Some(path)) = (args.next(), args.next()) else {
+                    eprintln!("Usage: animate <node> <file.png> [delay] [crop <margin>]");
+                    continue;
+                };
+                let delay = args
+                    .next()
+                    .map(parse_delay)
+                    .transpose()
+                    .map_err(|e| color_eyre::eyre::eyre!(e))?;
+                animate_line(&graph, node.parse()?, Path::new(path), delay, margin)?;
+            }
+            Ok(ref import) if import.starts_with("import-image ") => {
+                let position = import_image(Path::new(import["import-image ".len()..].trim()))?;
+                eprintln!("{position}");
+                if let Some(node) = add_position(&mut graph, &position) {
+                    session.last = Some(node);
+                    save_session(&session, session_path.as_deref())?;
+                }
+            }
+            Ok(ref edit) if edit.starts_with("edit ") => {
+                let node = edit["edit ".len()..].trim().parse()?;
+                let (position, _) = traverse(&graph, node)?;
+                if let Some(node) = edit_position(&mut graph, position)? {
+                    session.last = Some(node);
+                    save_session(&session, session_path.as_deref())?;
+                }
+            }
+            Ok(ref history) if history == "history" => {
+                for edit in graph.history() {
+                    eprintln!("{edit}");
+                }
+            }
+            Ok(ref bookmarks) if bookmarks == "bookmarks" => {
+                for (name, index) in graph.bookmarks() {
+                    eprintln!("{name}: {index:?} {}", graph.describe_path(*index));
+                }
+            }
+            Ok(ref goto) if goto.starts_with("goto-bookmark ") => {
+                let name = goto["goto-bookmark ".len()..].trim();
+                match graph.get_bookmark(name) {
+                    Some(node) => {
+                        show(&graph, node)?;
+                        session.last = Some(node);
+                        save_session(&session, session_path.as_deref())?;
                     }
+                    None => eprintln!("{}", tr(Message::NoBookmark, &[&format!("{name:?}")])),
+                }
+            }
+            Ok(ref bookmark) if bookmark.starts_with("bookmark ") => {
+                let Some((node, name)) = bookmark["bookmark ".len()..].trim().split_once(' ')
+                else {
+                    eprintln!("Usage: bookmark <node> <name>");
+                    continue;
+                };
+                let node = node.parse()?;
+                if graph.get_move(node).is_none() {
+                    color_eyre::eyre::bail!("Move not found")
                 }
+                graph.set_bookmark(name.trim(), node);
+                session.update(&graph);
+                save_session(&session, session_path.as_deref())?;
+            }
+            Ok(line) => {
+                let node = line.parse()?;
+                show(&graph, node)?;
+                session.last = Some(node);
+                save_session(&session, session_path.as_deref())?;
             }
             Err(rustyline::error::ReadlineError::Eof) => return Ok(()),
             _ => {}
@@ -75,6 +1041,798 @@ fn main() -> Result<(), color_eyre::Report> {
     }
 }
 
+/// `args` without a trailing `crop <margin>`, and the margin.
+fn crop_margin(args: &str) -> (&str, Option<u32>) {
+    let args = args.trim();
+    let cropped = args.rsplit_once(' ').and_then(|(rest, margin)| {
+        let rest = rest.trim_end().strip_suffix(" crop")?;
+        Some((rest.trim_end(), margin.parse().ok()?))
+    });
+    match cropped {
+        Some((rest, margin)) => (rest, Some(margin)),
+        None => (args, None),
+    }
+}
+
+/// Parse a date like `2024-06-10`, as the start of the day.
+fn parse_date(date: &str) -> Result<u64, String> {
+    renju::board::history::parse_date(date).ok_or_else(|| format!("invalid date {date:?}"))
+}
+
+/// The filter for the output set by `--tag`, `--added-by`, `--added-since` and `--added-until`,
+/// `None` without any.
+fn export_filter(matches: &ArgMatches) -> Option<file_reader::ExportFilter> {
+    let mut filter: Option<file_reader::ExportFilter> = None;
+    for tag in matches.get_many::<String>("tag").into_iter().flatten() {
+        filter = Some(filter.unwrap_or_default().or_tagged(tag));
+    }
+    if let Some(author) = matches.get_one::<String>("added-by") {
+        filter = Some(filter.unwrap_or_default().added_by(author));
+    }
+    let since = matches.get_one::<u64>("added-since").copied();
+    // until the end of the day
+    let until = matches
+        .get_one::<u64>("added-until")
+        .map(|day| day + 60 * 60 * 24 - 1);
+    if since.is_some() || until.is_some() {
+        let range = since.unwrap_or(0)..=until.unwrap_or(u64::MAX);
+        filter = Some(filter.unwrap_or_default().added_between(range));
+    }
+    filter
+}
+
+/// Parse a duration like `500ms`, `2s` or `1.5s`, plain numbers are milliseconds.
+fn parse_delay(delay: &str) -> Result<std::time::Duration, String> {
+    let invalid = || format!("invalid duration {delay:?}");
+    if let Some(millis) = delay.strip_suffix("ms") {
+        let millis = millis.parse().map_err(|_| invalid())?;
+        return Ok(std::time::Duration::from_millis(millis));
+    }
+    if let Some(seconds) = delay.strip_suffix('s') {
+        let seconds = seconds.parse().map_err(|_| invalid())?;
+        return std::time::Duration::try_from_secs_f64(seconds).map_err(|_| invalid());
+    }
+    let millis = delay.parse().map_err(|_| invalid())?;
+    Ok(std::time::Duration::from_millis(millis))
+}
+
+/// Play the moves to `node` and then its main line, one every `delay`.
+fn replay(
+    graph: &Board,
+    node: MoveIndex,
+    delay: std::time::Duration,
+) -> Result<(), color_eyre::Report> {
+    let mut line = graph.down_to_root(&node);
+    line.reverse();
+    line.extend(graph.main_line(node));
+    // redraw in place when watched, print every board otherwise
+    let redraw = std::io::IsTerminal::is_terminal(&std::io::stderr());
+    let mut board = BoardArr::new(15);
+    let mut number = 0;
+    for (i, index) in line.into_iter().enumerate() {
+        let marker = graph
+            .get_move(index)
+            .ok_or_else(|| color_eyre::eyre::eyre!("Move not found"))?;
+        if !marker.point.is_null {
+            board.set(marker.clone())?;
+        }
+        // the root holds the title and comment of the library
+        if i > 0 && marker.command.is_move() {
+            number += 1;
+        }
+        if redraw {
+            eprint!("\x1b[2J\x1b[H");
+        }
+        if i > 0 {
+            eprintln!("{number}. {:?} {}", marker.color, marker.point);
+        }
+        eprintln!("{}", theme::board(&board, &[marker.point]));
+        for comment in [&marker.oneline_comment, &marker.multiline_comment]
+            .into_iter()
+            .flatten()
+        {
+            eprintln!("{comment}");
+        }
+        std::thread::sleep(delay);
+    }
+    Ok(())
+}
+
+/// Print the holes of `graph`, the strongest first.
+fn holes(graph: &Board, color: &str) -> Result<(), color_eyre::Report> {
+    let options = analysis::HoleOptions {
+        prepared: match color {
+            "both" => None,
+            color => Some(Stone::from_bool(color == "black")),
+        },
+        ..analysis::HoleOptions::default()
+    };
+    let holes = analysis::find_holes(graph, &options)?;
+    for hole in &holes {
+        let path = graph.describe_path(hole.index);
+        let kind = match &hole.kind {
+            analysis::HoleKind::Win { win } => format!("wins in {}", win.len().div_ceil(2)),
+            analysis::HoleKind::Four => "four".to_owned(),
+        };
+        println!("{:?} {} ({kind}) after {path}", hole.stone, hole.reply);
+    }
+    eprintln!("{} holes", holes.len());
+    Ok(())
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(text: &str) -> Result<(), color_eyre::Report> {
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn copy_to_clipboard(_text: &str) -> Result<(), color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the clipboard feature")
+}
+
+#[cfg(feature = "clipboard")]
+fn paste_from_clipboard() -> Result<String, color_eyre::Report> {
+    Ok(arboard::Clipboard::new()?.get_text()?)
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn paste_from_clipboard() -> Result<String, color_eyre::Report> {
+    color_eyre::eyre::bail!(
+        "built without the clipboard feature, give the moves as `paste-pos H8 I9`"
+    )
+}
+
+/// Browse and add to `graph` from `node` on a board drawn in the terminal. A left click on a
+/// point goes to the move played there, or plays it after the move shown. A right click shows
+/// what the point is for both colors, like `why`. Returns the move shown last.
+#[cfg(feature = "tui")]
+fn board_view(graph: &mut Board, node: MoveIndex) -> Result<MoveIndex, color_eyre::Report> {
+    use crossterm::{event, terminal};
+
+    let mut out = std::io::stderr();
+    terminal::enable_raw_mode()?;
+    let mut node = node;
+    let result = crossterm::execute!(
+        out,
+        terminal::EnterAlternateScreen,
+        event::EnableMouseCapture
+    )
+    .map_err(Into::into)
+    .and_then(|()| browse(graph, &mut node, &mut out));
+    // leave the terminal as it was whatever happened
+    crossterm::execute!(
+        out,
+        event::DisableMouseCapture,
+        terminal::LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()?;
+    result.map(|()| node)
+}
+
+#[cfg(not(feature = "tui"))]
+fn board_view(_graph: &mut Board, _node: MoveIndex) -> Result<MoveIndex, color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the tui feature")
+}
+
+/// The loop of [`board_view`], drawing the board at the top left of `out` so that the cells of
+/// mouse events are the ones of [`BoardArr::point_at`].
+#[cfg(feature = "tui")]
+fn browse(
+    graph: &mut Board,
+    node: &mut MoveIndex,
+    out: &mut impl std::io::Write,
+) -> Result<(), color_eyre::Report> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+    use crossterm::{cursor, terminal};
+
+    let mut info = String::new();
+    let mut redraw = true;
+    loop {
+        let (board, moves) = traverse(graph, *node)?;
+        if redraw {
+            let text = theme::board(&board, &moves[moves.len().saturating_sub(1)..]);
+            crossterm::queue!(
+                out,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0)
+            )?;
+            // the terminal is in raw mode, lines don't go back to the first column by themselves
+            for line in [&text, &graph.describe_path(*node), &info] {
+                write!(out, "{}\r\n", line.replace('\n', "\r\n"))?;
+            }
+            write!(
+                out,
+                "Left click to play or go to a move, right click to explain a point, \
+                backspace to go back, q to leave\r\n"
+            )?;
+            out.flush()?;
+        }
+        // mouse moves and releases change nothing
+        redraw = true;
+        let mouse = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                info.clear();
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Backspace | KeyCode::Left => {
+                        if let Some(parent) = graph.get_parent_strong(node) {
+                            *node = parent;
+                        }
+                    }
+                    KeyCode::Right => {
+                        if let Some(next) = graph.main_line(*node).first() {
+                            *node = *next;
+                        }
+                    }
+                    _ => redraw = false,
+                }
+                continue;
+            }
+            Event::Resize(..) => continue,
+            Event::Mouse(mouse) => mouse,
+            _ => {
+                redraw = false;
+                continue;
+            }
+        };
+        let point = board.point_at(mouse.column.into(), mouse.row.into());
+        match (mouse.kind, point) {
+            (MouseEventKind::Down(MouseButton::Left), Some(point)) if moves.contains(&point) => {
+                info.clear();
+                // back to the move played there
+                if let Some(played) = graph
+                    .down_to_root(node)
+                    .into_iter()
+                    .find(|index| graph.get_move(*index).is_some_and(|m| m.point == point))
+                {
+                    *node = played;
+                }
+            }
+            (MouseEventKind::Down(MouseButton::Left), Some(point)) => {
+                info.clear();
+                let next = graph
+                    .get_children(node)
+                    .into_iter()
+                    .find(|child| graph.get_move(*child).is_some_and(|m| m.point == point));
+                if let Some(next) = next {
+                    *node = next;
+                    continue;
+                }
+                if !board.get_point(point).is_some_and(|m| m.color.is_empty()) {
+                    continue;
+                }
+                let stone = graph.inferred_color(*node);
+                let reason = match stone.is_black() {
+                    true => board.renju_conditions(stone, Some(&[point])).reason(point),
+                    false => None,
+                };
+                match reason {
+                    Some(reason) => {
+                        info = tr(Message::Forbidden, &[&point, &tr(reason.into(), &[])]);
+                    }
+                    None => *node = graph.insert_move(*node, BoardMarker::new(point, stone)),
+                }
+            }
+            (MouseEventKind::Down(MouseButton::Right), Some(point)) => {
+                info = [Stone::Black, Stone::White]
+                    .map(|stone| explain::explain(&board, stone, point).to_string())
+                    .concat();
+            }
+            _ => redraw = false,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve(
+    libraries: Arc<RwLock<Collection>>,
+    addr: std::net::SocketAddr,
+    token: Option<String>,
+) -> Result<(), color_eyre::Report> {
+    use renju::server::{self, Libraries, Token};
+    let libraries = Arc::new(Libraries::shared(libraries, engine::Limits::default()));
+    match token {
+        Some(token) => server::serve_with_auth(addr, libraries, Token::new(token))?,
+        None => server::serve(addr, libraries)?,
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(
+    _libraries: Arc<RwLock<Collection>>,
+    _addr: std::net::SocketAddr,
+    _token: Option<String>,
+) -> Result<(), color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the server feature")
+}
+
+#[cfg(feature = "qr")]
+fn print_qr(url: &str) {
+    match share::qr(url) {
+        Ok(code) => eprintln!("{code}"),
+        Err(e) => tracing::warn!("couldn't make a QR code: {e}"),
+    }
+}
+
+#[cfg(not(feature = "qr"))]
+fn print_qr(_url: &str) {}
+
+/// Find `position` in `graph`, adding it with a [reconstructed](BoardArr::reconstruct_moves)
+/// order of the moves if it's missing.
+fn add_position(graph: &mut Board, position: &BoardArr) -> Option<MoveIndex> {
+    let limits = engine::Limits {
+        nodes: Some(RECONSTRUCT_NODES),
+        ..engine::Limits::default()
+    };
+    let moves = match position.reconstruct_moves(&limits) {
+        Outcome::Known(Ok(moves)) => moves,
+        Outcome::Known(Err(reason)) => {
+            eprintln!("Not a position of a game: {reason}");
+            return None;
+        }
+        Outcome::Unknown => {
+            eprintln!("Could not find an order of the moves reaching the position");
+            return None;
+        }
+    };
+    let node = match graph.find_position(&moves) {
+        Some(node) => node,
+        None => {
+            let node = graph.add_line(&moves);
+            eprintln!("Added to the library");
+            node
+        }
+    };
+    eprintln!("{node:?} {}", graph.describe_path(node));
+    Some(node)
+}
+
+/// Set up a position starting from `position`, stone by stone, adding it to `graph` with `done`.
+fn edit_position(
+    graph: &mut Board,
+    mut position: BoardArr,
+) -> Result<Option<MoveIndex>, color_eyre::Report> {
+    eprintln!("b|w <point>, x <point> to remove, c <point> to recolor, shift <area> <dx> <dy>,");
+    eprintln!("clear [area], done to add the position to the library, q to leave it");
+    eprintln!("{}", theme::board(&position, &[]));
+    let mut rl = rustyline::Editor::<(), _>::new()?;
+    loop {
+        let line = match rl.readline("edit> ") {
+            Ok(line) => line,
+            Err(rustyline::error::ReadlineError::Eof) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let args = line.split_whitespace().collect::<Vec<_>>();
+        let edited = match args[..] {
+            [] => continue,
+            ["q" | "quit"] => return Ok(None),
+            ["done"] => match add_position(graph, &position) {
+                Some(node) => return Ok(Some(node)),
+                None => continue,
+            },
+            [color @ ("b" | "w"), point] => point.parse().map_err(Into::into).map(|point| {
+                position.set_point(point, Stone::from_bool(color == "b"));
+                position.unreachable()
+            }),
+            ["x", point] => point
+                .parse()
+                .map_err(Into::into)
+                .and_then(|point| position.remove_stone(point)),
+            ["c", point] => point
+                .parse()
+                .map_err(Into::into)
+                .and_then(|point| position.recolor(point)),
+            ["shift", area, dx, dy] => match (area.parse(), dx.parse(), dy.parse()) {
+                (Ok(area), Ok(dx), Ok(dy)) => position.shift(area, dx, dy),
+                _ => {
+                    eprintln!("Usage: shift <area like A1:C3> <columns right> <rows up>");
+                    continue;
+                }
+            },
+            ["clear"] => Ok(position.clear_area("A1:O15".parse()?).1),
+            ["clear", area] => area
+                .parse()
+                .map_err(Into::into)
+                .map(|area| position.clear_area(area).1),
+            _ => {
+                eprintln!("Unknown edit {line:?}");
+                continue;
+            }
+        };
+        match edited {
+            Ok(unreachable) => {
+                eprintln!("{}", theme::board(&position, &[]));
+                if let Some(reason) = unreachable {
+                    eprintln!("Not a position of a game: {reason}");
+                }
+            }
+            Err(e) => eprintln!("{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+fn import_image(path: &Path) -> Result<BoardArr, color_eyre::Report> {
+    renju::screenshot::open(path).wrap_err_with(|| format!("while reading {:?}", path))
+}
+
+#[cfg(not(feature = "image"))]
+fn import_image(_path: &Path) -> Result<BoardArr, color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the image feature")
+}
+
+/// Write the line to `node` as an animated PNG to `path`, `delay` per move, cropped to the stones
+/// and `margin` lines around them if given.
+#[cfg(feature = "image")]
+fn animate_line(
+    graph: &Board,
+    node: MoveIndex,
+    path: &Path,
+    delay: Option<std::time::Duration>,
+    margin: Option<u32>,
+) -> Result<(), color_eyre::Report> {
+    let (_, moves) = traverse(graph, node)?;
+    let mut options = renju::render::AnimationOptions::default();
+    options.delay = delay.unwrap_or(options.delay);
+    options.margin = margin;
+    let file = std::fs::File::create(path).wrap_err_with(|| format!("while writing {path:?}"))?;
+    renju::render::write_apng(
+        std::io::BufWriter::new(file),
+        &BoardArr::new(15),
+        &moves,
+        Stone::Black,
+        &options,
+    )?;
+    Ok(())
+}
+
+#[cfg(not(feature = "image"))]
+fn animate_line(
+    _graph: &Board,
+    _node: MoveIndex,
+    _path: &Path,
+    _delay: Option<std::time::Duration>,
+    _margin: Option<u32>,
+) -> Result<(), color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the image feature")
+}
+
+/// Print the board at `node` and its comments.
+fn show(graph: &Board, node: MoveIndex) -> Result<(), color_eyre::Report> {
+    let (board, moves) = traverse(graph, node)?;
+    eprintln!(
+        "{}",
+        theme::board(&board, &moves[moves.len().saturating_sub(1)..])
+    );
+    if let Some(last_point) = moves.last() {
+        if let Some(BoardMarker {
+            multiline_comment,
+            oneline_comment,
+            ..
+        }) = board.get_point(*last_point)
+        {
+            if let Some(comment) = oneline_comment.as_deref() {
+                tracing::info!("{}", comment)
+            }
+            if let Some(comment) = multiline_comment.as_deref() {
+                tracing::info!("{}", comment)
+            }
+        } else {
+            color_eyre::eyre::bail!("Move not found")
+        }
+    }
+    Ok(())
+}
+
 fn traverse(graph: &Board, index: MoveIndex) -> Result<(BoardArr, Vec<Point>), ParseError> {
     graph.as_board(&index)
 }
+
+/// Follow the board streamed to stdin, or to the socket of `--socket`, see `renju::live`.
+fn live(
+    matches: &ArgMatches,
+    kind: engine::EngineKind,
+    libraries: Option<&RwLock<Collection>>,
+) -> Result<(), color_eyre::Report> {
+    let limits = engine::Limits::default();
+    if let Some(path) = matches.get_one::<PathBuf>("socket") {
+        eprintln!("Listening on {:?}", path);
+        return Ok(renju::live::listen(path, kind, &limits, libraries)?);
+    }
+    renju::live::LiveBoard::new().run(
+        std::io::stdin().lock(),
+        std::io::stdout().lock(),
+        kind,
+        &limits,
+        libraries,
+    )?;
+    Ok(())
+}
+
+/// Load and index the libraries of `--preload` once, then answer on `--socket` and `--serve`
+/// until stopped.
+fn daemon(
+    matches: &ArgMatches,
+    config: &Config,
+    kind: engine::EngineKind,
+) -> Result<(), color_eyre::Report> {
+    let socket = matches.get_one::<PathBuf>("socket").cloned();
+    let addr = matches.get_one::<std::net::SocketAddr>("serve").copied();
+    if socket.is_none() && addr.is_none() {
+        color_eyre::eyre::bail!("give --socket, --serve or both to answer on");
+    }
+    let started = std::time::Instant::now();
+    let mut libraries = Collection::new();
+    for dir in matches.get_many::<PathBuf>("preload").into_iter().flatten() {
+        let failed = libraries
+            .preload(dir)
+            .wrap_err_with(|| format!("while preloading {:?}", dir))?;
+        for (path, e) in failed {
+            eprintln!("Skipped {:?}: {e}", path);
+        }
+    }
+    eprintln!(
+        "Loaded {} libraries with {} positions in {:.1?}",
+        libraries.len(),
+        libraries.positions(),
+        started.elapsed()
+    );
+    let libraries = Arc::new(RwLock::new(libraries));
+    let Some(addr) = addr else {
+        return live(matches, kind, Some(&libraries));
+    };
+    if socket.is_some() {
+        let (matches, libraries) = (matches.clone(), Arc::clone(&libraries));
+        std::thread::spawn(move || {
+            if let Err(e) = live(&matches, kind, Some(&libraries)) {
+                tracing::error!("the socket stopped: {e}");
+            }
+        });
+    }
+    eprintln!("Serving on http://{addr}");
+    let token = matches
+        .get_one::<String>("token")
+        .or(config.server_token.as_ref())
+        .cloned();
+    serve(libraries, addr, token)
+}
+
+/// Play a game against the engine, the user plays `human`.
+fn play(human: Stone, kind: engine::EngineKind, seed: u64) -> Result<(), color_eyre::Report> {
+    let limits = engine::Limits {
+        depth: 6,
+        time: Some(std::time::Duration::from_secs(5)),
+        seed: Some(seed),
+        ..Default::default()
+    };
+    let mut board = BoardArr::new(15);
+    let mut to_move = Stone::Black;
+    let mut rl = rustyline::Editor::<(), _>::new()?;
+    loop {
+        eprintln!("{}", theme::board(&board, &[]));
+        let point = if to_move == human {
+            let point: Point = match rl.readline("move> ") {
+                Ok(line) if line.to_lowercase().starts_with('q') => return Ok(()),
+                Ok(line) => match line.parse() {
+                    Ok(point) => point,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+                Err(rustyline::error::ReadlineError::Eof) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            if !board.get_point(point).is_some_and(|m| m.color.is_empty()) {
+                eprintln!("{} is not empty", point);
+                continue;
+            }
+            let reason = match to_move.is_black() {
+                true => board
+                    .renju_conditions(to_move, Some(&[point]))
+                    .reason(point),
+                false => None,
+            };
+            if let Some(reason) = reason {
+                eprintln!(
+                    "{}",
+                    tr(Message::Forbidden, &[&point, &tr(reason.into(), &[])])
+                );
+                continue;
+            }
+            point
+        } else {
+            let result = engine::search(&board, to_move, kind, &limits);
+            let Some(point) = result.best else {
+                eprintln!("No moves left, draw");
+                return Ok(());
+            };
+            eprintln!(
+                "Engine plays {} (score {}, depth {})",
+                point, result.score, result.depth
+            );
+            point
+        };
+        let five = solver::makes_five(&board, point, to_move);
+        board.set_point(point, to_move);
+        if five {
+            eprintln!("{}", theme::board(&board, &[point]));
+            eprintln!("{}", tr(Message::Wins, &[&i18n::stone_name(to_move)]));
+            return Ok(());
+        }
+        to_move = to_move.opposite();
+    }
+}
+
+/// Quiz the user on the `puzzles` that are due today, calling `save` after every puzzle.
+fn quiz(
+    puzzles: &[puzzle::Puzzle],
+    training: &mut Training,
+    save: &mut dyn FnMut(&Training) -> Result<(), color_eyre::Report>,
+) -> Result<(), color_eyre::Report> {
+    let today = training::today();
+    let due = training.due(puzzles, today);
+    eprintln!("{}", tr(Message::PuzzlesDue, &[&due.len(), &puzzles.len()]));
+    let mut rl = rustyline::Editor::<(), _>::new()?;
+    for puzzle in due {
+        let mut board = puzzle.position.clone();
+        let mut attempt = vec![];
+        let mut mistakes = 0;
+        eprintln!("{}", theme::board(&board, &[]));
+        let to_move = i18n::stone_name(puzzle.to_move);
+        eprintln!("{}", tr(Message::ToMove, &[&to_move]));
+        let verdict = loop {
+            let point: Point = match rl.readline("move> ") {
+                Ok(line) if line.to_lowercase().starts_with('q') => return save(training),
+                Ok(line) => match line.parse() {
+                    Ok(point) => point,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+                Err(rustyline::error::ReadlineError::Eof) => return save(training),
+                Err(e) => return Err(e.into()),
+            };
+            attempt.push(point);
+            match puzzle.check_attempt(&attempt) {
+                Verdict::Correct { next } if !next.is_empty() => {
+                    // play the opponent's reply
+                    board.set_point(point, puzzle.to_move);
+                    board.set_point(next[0], puzzle.to_move.opposite());
+                    attempt.push(next[0]);
+                    eprintln!("{}", theme::board(&board, &[next[0]]));
+                    eprintln!("{}", tr(Message::Correct, &[&next[0]]));
+                }
+                Verdict::Wrong { refutation, .. } => {
+                    attempt.pop();
+                    mistakes += 1;
+                    match refutation {
+                        Some(refutation) => {
+                            eprintln!("{}", tr(Message::WrongRefuted, &[&refutation]))
+                        }
+                        None => eprintln!("{}", tr(Message::Wrong, &[])),
+                    }
+                    if mistakes >= 3 {
+                        break Verdict::Wrong {
+                            ply: attempt.len(),
+                            refutation,
+                        };
+                    }
+                }
+                verdict => break verdict,
+            }
+        };
+        if verdict == Verdict::Solved {
+            eprintln!("{}", tr(Message::Solved, &[]));
+        } else if let Some(solution) = puzzle.solution.first() {
+            eprintln!("{}", tr(Message::Solution, &[&solution.point]));
+        }
+        training.record(puzzle, Quality::from_verdict(&verdict, mistakes), today);
+        save(training)?;
+    }
+    Ok(())
+}
+
+fn read_pack(path: &Path) -> Result<Vec<puzzle::Puzzle>, color_eyre::Report> {
+    let file = std::fs::File::open(path).wrap_err_with(|| format!("while reading {path:?}"))?;
+    puzzle::pack::parse(std::io::BufReader::new(file))
+        .wrap_err_with(|| format!("while reading {path:?}"))
+}
+
+#[cfg(feature = "serde")]
+fn load_training(path: &Path) -> Result<Training, color_eyre::Report> {
+    Training::load(path).wrap_err_with(|| format!("while reading {:?}", path))
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_training(_path: &Path) -> Result<Training, color_eyre::Report> {
+    tracing::warn!("built without the serde feature, training progress will not be kept");
+    Ok(Training::default())
+}
+
+#[cfg(feature = "serde")]
+fn save_training(training: &Training, path: &Path) -> Result<(), color_eyre::Report> {
+    training
+        .save(path)
+        .wrap_err_with(|| format!("while writing {:?}", path))
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_training(_training: &Training, _path: &Path) -> Result<(), color_eyre::Report> {
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn write_quality_json(
+    quality: &QualityReport,
+    file: std::fs::File,
+) -> Result<(), color_eyre::Report> {
+    Ok(quality.write_json(std::io::BufWriter::new(file))?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn write_quality_json(
+    _quality: &QualityReport,
+    _file: std::fs::File,
+) -> Result<(), color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the serde feature, write CSV instead")
+}
+
+#[cfg(feature = "serde")]
+fn open_project(manifest: &Path) -> Result<Project, color_eyre::Report> {
+    Project::open(manifest).wrap_err_with(|| format!("while reading {:?}", manifest))
+}
+
+#[cfg(not(feature = "serde"))]
+fn open_project(_manifest: &Path) -> Result<Project, color_eyre::Report> {
+    color_eyre::eyre::bail!("built without the serde feature, projects can't be read")
+}
+
+#[cfg(feature = "serde")]
+fn load_session(path: Option<&Path>) -> Result<Session, color_eyre::Report> {
+    match path {
+        Some(path) => Session::load(path).wrap_err_with(|| format!("while reading {:?}", path)),
+        None => Ok(Session::default()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_session(_path: Option<&Path>) -> Result<Session, color_eyre::Report> {
+    tracing::debug!("built without the serde feature, the session will not be kept");
+    Ok(Session::default())
+}
+
+#[cfg(feature = "serde")]
+fn save_session(session: &Session, path: Option<&Path>) -> Result<(), color_eyre::Report> {
+    match path {
+        Some(path) => session
+            .save(path)
+            .wrap_err_with(|| format!("while writing {:?}", path)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn save_session(_session: &Session, _path: Option<&Path>) -> Result<(), color_eyre::Report> {
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn load_config(path: Option<&Path>) -> Result<Config, color_eyre::Report> {
+    match path {
+        Some(path) => Config::load(path).wrap_err_with(|| format!("while reading {:?}", path)),
+        None => Ok(Config::default()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_config(path: Option<&Path>) -> Result<Config, color_eyre::Report> {
+    if path.is_some_and(Path::exists) {
+        // the logger isn't set up yet
+        eprintln!("built without the serde feature, ignoring the config file");
+    }
+    Ok(Config::default())
+}
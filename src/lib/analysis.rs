@@ -0,0 +1,418 @@
+//! Post-mortem analysis of played games.
+//!
+//! Only proven results are reported, a win is a VCF found by [`solver::vcf`]. Slower wins and
+//! positional mistakes are not detected.
+//!
+//! [`find_holes`] checks a library instead, for strong replies the library has no answer to.
+
+use crate::board::{Board, BoardArr, BoardMarker, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+use crate::game::GameRecord;
+use crate::progress::{Cancelled, Progress};
+use crate::solver;
+
+/// What was wrong with a move.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BlunderKind {
+    /// The player had a VCF but played something else. `win` is one of the winning lines.
+    MissedWin { win: Vec<Point> },
+    /// The move let the opponent win by VCF while `defense` would have stopped it.
+    AllowedWin { win: Vec<Point>, defense: Point },
+}
+
+/// A flagged move in a game.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Blunder {
+    /// Index of the move in [`GameRecord::moves`].
+    pub ply: usize,
+    pub point: Point,
+    pub stone: Stone,
+    pub kind: BlunderKind,
+}
+
+/// Options for [`analyze_game`].
+#[derive(Clone, Debug)]
+pub struct AnalyzeOptions {
+    /// Maximum amount of fours searched for.
+    pub max_depth: usize,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self { max_depth: 8 }
+    }
+}
+
+/// The result of [`analyze_game`].
+#[derive(Debug)]
+pub struct Analysis {
+    /// The game with blunders commented and the winning lines added as variations.
+    pub graph: Board,
+    /// The moves of the game, in order.
+    pub moves: Vec<MoveIndex>,
+    pub blunders: Vec<Blunder>,
+}
+
+/// Replay `game` and flag every move that missed a forced win or allowed one.
+#[must_use]
+pub fn analyze_game(game: &GameRecord, options: &AnalyzeOptions) -> Analysis {
+    analyze_game_with_progress(game, options, &mut Progress::none()).expect("no token to cancel")
+}
+
+/// Like [`analyze_game`], reporting every move analyzed.
+#[tracing::instrument(skip(game, progress))]
+pub fn analyze_game_with_progress(
+    game: &GameRecord,
+    options: &AnalyzeOptions,
+    progress: &mut Progress,
+) -> Result<Analysis, Cancelled> {
+    // the solver only needs to know when to stop
+    let mut solver_progress = Progress::none().with_token(progress.token().clone());
+    let mut blunders = vec![];
+    // explanations of the winning lines, commented on the attacker moves
+    let mut proofs = vec![];
+    let mut board = BoardArr::new(15);
+    let total = game.moves.len() as u64;
+    for (ply, point) in game.moves.iter().enumerate() {
+        progress.update("analyze", ply as u64, Some(total))?;
+        let stone = GameRecord::stone_at(ply);
+        if !point.is_null {
+            if let Some(kind) = check_move(
+                &board,
+                *point,
+                stone,
+                options.max_depth,
+                &mut solver_progress,
+            )? {
+                tracing::debug!(ply, ?point, ?kind, "found blunder");
+                proofs.push(match &kind {
+                    BlunderKind::MissedWin { win } => solver::explain(&board, stone, win),
+                    BlunderKind::AllowedWin { win, .. } => {
+                        let mut after = board.clone();
+                        after.set_point(*point, stone);
+                        solver::explain(&after, stone.opposite(), win)
+                    }
+                });
+                blunders.push(Blunder {
+                    ply,
+                    point: *point,
+                    stone,
+                    kind,
+                });
+            }
+            board.set_point(*point, stone);
+        }
+    }
+
+    let (mut graph, moves) = game.to_graph();
+    for (blunder, proof) in blunders.iter().zip(&proofs) {
+        let index = moves[blunder.ply];
+        let (comment, win, branch_from, first) = match &blunder.kind {
+            BlunderKind::MissedWin { win } => (
+                format!("Missed win: {}", format_line(win)),
+                win,
+                // the winning line replaces the move
+                graph
+                    .get_parent_strong(&index)
+                    .unwrap_or_else(|| graph.get_root()),
+                blunder.stone,
+            ),
+            BlunderKind::AllowedWin { win, defense } => (
+                format!(
+                    "Allows win: {}, {} would have defended",
+                    format_line(win),
+                    defense
+                ),
+                win,
+                index,
+                blunder.stone.opposite(),
+            ),
+        };
+        if let Some(marker) = graph.get_move_mut(index) {
+            marker.set_oneline_comment(comment);
+        }
+        let mut parent = branch_from;
+        let mut stone = first;
+        for point in win {
+            // reuse a variation already added by an earlier blunder
+            let existing = graph.get_children(&parent).into_iter().find(|child| {
+                graph
+                    .get_move(*child)
+                    .is_some_and(|m| m.point == *point && m.color == stone)
+            });
+            parent = existing.unwrap_or_else(|| {
+                let mut marker = BoardMarker::new(*point, stone);
+                let step = proof.as_ref().and_then(|proof| {
+                    proof
+                        .steps
+                        .iter()
+                        .find(|step| proof.stone == stone && step.attack == *point)
+                });
+                if let Some(step) = step {
+                    marker.set_oneline_comment(step.to_string());
+                }
+                graph.insert_move(parent, marker)
+            });
+            stone = stone.opposite();
+        }
+    }
+
+    progress.update("analyze", total, Some(total))?;
+    Ok(Analysis {
+        graph,
+        moves,
+        blunders,
+    })
+}
+
+/// Check `stone` playing `point` in `board`.
+fn check_move(
+    board: &BoardArr,
+    point: Point,
+    stone: Stone,
+    max_depth: usize,
+    progress: &mut Progress,
+) -> Result<Option<BlunderKind>, Cancelled> {
+    let wins = solver::vcf_first_moves_with_progress(board, stone, max_depth, progress)?;
+    if !wins.is_empty() {
+        return Ok(wins
+            .iter()
+            .all(|win| win[0] != point)
+            .then(|| BlunderKind::MissedWin {
+                win: wins[0].clone(),
+            }));
+    }
+
+    let opponent = stone.opposite();
+    let mut after = board.clone();
+    after.set_point(point, stone);
+    let Some(win) = solver::vcf_with_progress(&after, opponent, max_depth, progress)? else {
+        return Ok(None);
+    };
+    // only a blunder if a defense exists, try the points of the winning line
+    for &defense in &win {
+        let mut board = board.clone();
+        board.set_point(defense, stone);
+        if solver::vcf_with_progress(&board, opponent, max_depth, progress)?.is_none() {
+            return Ok(Some(BlunderKind::AllowedWin { win, defense }));
+        }
+    }
+    Ok(None)
+}
+
+/// How strong the reply of a [`Hole`] is.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum HoleKind {
+    /// The reply starts a VCF, `win` is the winning line.
+    Win { win: Vec<Point> },
+    /// The reply makes a four.
+    Four,
+}
+
+/// A strong reply missing from a library.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Hole {
+    /// The position the reply is played in.
+    pub index: MoveIndex,
+    /// The moves leading to the position, from the root.
+    pub path: Vec<Point>,
+    /// The side playing the reply.
+    pub stone: Stone,
+    pub reply: Point,
+    pub kind: HoleKind,
+}
+
+/// Options for [`find_holes`].
+#[derive(Clone, Debug)]
+pub struct HoleOptions {
+    /// Maximum amount of fours searched for.
+    pub max_depth: usize,
+    /// Only check replies against this side, i.e positions after its moves. Both if `None`.
+    pub prepared: Option<Stone>,
+    /// Also check positions without any continuation in the library.
+    pub include_leaves: bool,
+}
+
+impl Default for HoleOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            prepared: None,
+            include_leaves: false,
+        }
+    }
+}
+
+/// Find the positions in `graph` where the side to move has a winning reply or a four that the
+/// library has no continuation for.
+///
+/// The holes are ranked with wins first, shorter wins before longer ones, then by how early in
+/// the library they are.
+#[tracing::instrument(skip(graph))]
+pub fn find_holes(graph: &Board, options: &HoleOptions) -> Result<Vec<Hole>, ParseError> {
+    let mut holes = vec![];
+    let mut stack = graph.roots().to_vec();
+    while let Some(index) = stack.pop() {
+        let children = graph.get_children(&index);
+        stack.extend(children.iter().copied());
+        let Some(marker) = graph.get_move(index) else {
+            continue;
+        };
+        if marker.color.is_empty()
+            || options.prepared.is_some_and(|stone| stone != marker.color)
+            || (children.is_empty() && !options.include_leaves)
+        {
+            continue;
+        }
+        let to_move = marker.color.opposite();
+        let covered = |point: Point| {
+            children
+                .iter()
+                .any(|child| graph.get_move(*child).is_some_and(|m| m.point == point))
+        };
+        let (position, path) = graph.as_board(&index)?;
+        let wins = solver::vcf_first_moves(&position, to_move, options.max_depth);
+        let mut found = vec![];
+        for win in wins {
+            if !covered(win[0]) && found.iter().all(|(reply, _)| *reply != win[0]) {
+                found.push((win[0], HoleKind::Win { win }));
+            }
+        }
+        for four in solver::four_points(&position, to_move) {
+            if !covered(four) && found.iter().all(|(reply, _)| *reply != four) {
+                found.push((four, HoleKind::Four));
+            }
+        }
+        for (reply, kind) in found {
+            tracing::debug!(
+                path = graph.describe_path(index),
+                ?reply,
+                ?kind,
+                "found hole"
+            );
+            holes.push(Hole {
+                index,
+                path: path.clone(),
+                stone: to_move,
+                reply,
+                kind,
+            });
+        }
+    }
+    holes.sort_by_key(|hole| {
+        let strength = match &hole.kind {
+            HoleKind::Win { win } => win.len(),
+            HoleKind::Four => usize::MAX,
+        };
+        (strength, hole.path.len(), hole.reply)
+    });
+    Ok(holes)
+}
+
+fn format_line(line: &[Point]) -> String {
+    line.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn missed_and_allowed_wins() {
+        // white gets J4 J5 J6 G7 H7 against black's J3, the four-three at J7 wins
+        let mut moves = p![
+            [J, 3],
+            [J, 4],
+            [A, 1],
+            [J, 5],
+            [A, 15],
+            [J, 6],
+            [O, 1],
+            [G, 7],
+            [O, 15],
+            [H, 7],
+            [N, 8],
+            [B, 2]
+        ]
+        .to_vec();
+        let analysis = analyze_game(&GameRecord::new(moves.clone()), &Default::default());
+        let kinds = analysis
+            .blunders
+            .iter()
+            .map(|b| (b.ply, matches!(b.kind, BlunderKind::MissedWin { .. })))
+            .collect::<Vec<_>>();
+        // N8 let white win, white then missed it with B2
+        assert_eq!(kinds, [(10, false), (11, true)], "{:?}", analysis.blunders);
+
+        let n8 = analysis.moves[10];
+        let comment = analysis.graph.get_move(n8).unwrap().oneline_comment.clone();
+        assert!(comment.unwrap().starts_with("Allows win: J7"));
+        // the game continuation and the winning variation
+        assert_eq!(analysis.graph.get_children(&n8).len(), 2);
+        let j7 = analysis
+            .graph
+            .get_children(&n8)
+            .into_iter()
+            .find(|c| analysis.graph.get_move(*c).unwrap().point == p![J, 7])
+            .unwrap();
+        let comment = analysis.graph.get_move(j7).unwrap().oneline_comment.clone();
+        assert_eq!(comment.as_deref(), Some("J7 four, J8 is forced"));
+
+        moves.pop();
+        moves.push(p![J, 7]);
+        let analysis = analyze_game(&GameRecord::new(moves), &Default::default());
+        assert_eq!(analysis.blunders.len(), 1);
+    }
+
+    #[test]
+    fn holes() {
+        let mut graph = Board::new();
+        let mut index = graph.get_root();
+        let mut stone = Stone::White;
+        for point in p![
+            [J, 3],
+            [J, 4],
+            [A, 1],
+            [J, 5],
+            [A, 15],
+            [J, 6],
+            [O, 1],
+            [G, 7],
+            [O, 15],
+            [H, 7],
+            [N, 8]
+        ] {
+            stone = stone.opposite();
+            index = graph.insert_move(index, BoardMarker::new(point, stone));
+        }
+        let n8 = index;
+        graph.insert_move(n8, BoardMarker::new(p![B, 2], Stone::White));
+
+        let holes = find_holes(&graph, &HoleOptions::default()).unwrap();
+        let first = &holes[0];
+        assert_eq!(first.index, n8);
+        assert_eq!(first.path.len(), 11);
+        assert_eq!(first.stone, Stone::White);
+        assert_eq!(first.reply, p![J, 7]);
+        assert!(matches!(first.kind, HoleKind::Win { .. }));
+        assert!(holes[1..]
+            .iter()
+            .all(|hole| hole.kind == HoleKind::Four && hole.stone == Stone::White));
+
+        // the win is covered once it is in the library
+        graph.insert_move(n8, BoardMarker::new(p![J, 7], Stone::White));
+        let holes = find_holes(&graph, &HoleOptions::default()).unwrap();
+        assert!(holes.iter().all(|hole| matches!(hole.kind, HoleKind::Four)));
+
+        // black never gets a four
+        let options = HoleOptions {
+            prepared: Some(Stone::White),
+            ..HoleOptions::default()
+        };
+        assert!(find_holes(&graph, &options).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+//! Results computed for the nodes of a graph, like engine evaluations.
+//!
+//! Annotations are kept beside the graph rather than in its nodes, together with the
+//! [revision](Board::revision) of the graph they were computed at. Once the graph is edited they
+//! are stale: [`Annotations::get`] returns nothing and [`Annotations::get_or_compute`] computes the
+//! value again, so a value is only recomputed when it is asked for.
+//!
+//! Any edit makes every value stale, even of nodes the edit didn't change, as an edit can change
+//! the position of every node after it.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use crate::board::{Board, MoveIndex, NodeIndex, Payload};
+use crate::engine::external::Client;
+use crate::engine::{self, EngineKind, Limits, SearchResult};
+use crate::errors::ParseError;
+use crate::game::GameRecord;
+
+/// Values computed for the nodes of a graph, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Annotations<V> {
+    /// The revision of the graph the values are from, `None` before any value is added.
+    revision: Option<u64>,
+    values: HashMap<NodeIndex, V>,
+}
+
+/// Engine evaluations of the position at every node, for the side to move.
+pub type Evaluations = Annotations<SearchResult>;
+
+impl<V> Annotations<V> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            revision: None,
+            values: HashMap::new(),
+        }
+    }
+
+    /// The revision of the graph the values were computed at.
+    #[must_use]
+    pub fn revision(&self) -> Option<u64> {
+        self.revision
+    }
+
+    /// Whether `graph` was edited after the values were computed.
+    #[must_use]
+    pub fn is_stale<T: Payload>(&self, graph: &Board<T>) -> bool {
+        self.revision
+            .is_some_and(|revision| revision != graph.revision())
+    }
+
+    /// The value of `index`, if it was computed at the current revision of `graph`.
+    #[must_use]
+    pub fn get<T: Payload>(&self, graph: &Board<T>, index: MoveIndex) -> Option<&V> {
+        if self.is_stale(graph) {
+            return None;
+        }
+        self.values.get(&index.node_index())
+    }
+
+    /// Set the value of `index`, dropping every value if `graph` was edited since.
+    pub fn insert<T: Payload>(&mut self, graph: &Board<T>, index: MoveIndex, value: V) {
+        self.refresh(graph);
+        self.values.insert(index.node_index(), value);
+    }
+
+    /// The value of `index`, computed with `compute` unless it is known at the current revision.
+    pub fn get_or_compute<T: Payload>(
+        &mut self,
+        graph: &Board<T>,
+        index: MoveIndex,
+        compute: impl FnOnce(&Board<T>, MoveIndex) -> V,
+    ) -> &V {
+        self.refresh(graph);
+        self.values
+            .entry(index.node_index())
+            .or_insert_with(|| compute(graph, index))
+    }
+
+    /// The values computed at the current revision of `graph`, in no particular order.
+    pub fn iter<T: Payload>(&self, graph: &Board<T>) -> impl Iterator<Item = (MoveIndex, &V)> {
+        let values = (!self.is_stale(graph)).then_some(&self.values);
+        values
+            .into_iter()
+            .flatten()
+            .map(|(node, value)| (MoveIndex::new_node(*node), value))
+    }
+
+    /// The amount of values, stale or not.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn refresh<T: Payload>(&mut self, graph: &Board<T>) {
+        if self.revision != Some(graph.revision()) {
+            if !self.values.is_empty() {
+                tracing::debug!(
+                    stale = self.values.len(),
+                    revision = graph.revision(),
+                    "dropping stale annotations"
+                );
+            }
+            self.values.clear();
+            self.revision = Some(graph.revision());
+        }
+    }
+}
+
+impl<V> Default for Annotations<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluations {
+    /// The evaluation of the position at `index`, searched with `kind` and `limits` unless it is
+    /// known at the current revision of `graph`.
+    ///
+    /// The evaluations are not keyed by the engine, keep one [`Evaluations`] per engine setting.
+    pub fn search(
+        &mut self,
+        graph: &Board,
+        index: MoveIndex,
+        kind: EngineKind,
+        limits: &Limits,
+    ) -> Result<&SearchResult, ParseError> {
+        if self.get(graph, index).is_none() {
+            let (board, moves) = graph.as_board(&index)?;
+            let result = engine::search(&board, GameRecord::stone_at(moves.len()), kind, limits);
+            self.insert(graph, index, result);
+        }
+        Ok(self
+            .get(graph, index)
+            .expect("the evaluation was just added"))
+    }
+
+    /// Like [`Evaluations::search`], asking the engine program `engine`.
+    pub fn search_external<R: BufRead, W: Write>(
+        &mut self,
+        graph: &Board,
+        index: MoveIndex,
+        engine: &mut Client<R, W>,
+        limits: &Limits,
+    ) -> crate::Result<&SearchResult> {
+        if self.get(graph, index).is_none() {
+            let (board, moves) = graph.as_board(&index)?;
+            let result = engine.search(&board, GameRecord::stone_at(moves.len()), limits)?;
+            self.insert(graph, index, result);
+        }
+        Ok(self
+            .get(graph, index)
+            .expect("the evaluation was just added"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardMarker, Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn stale_after_edits() {
+        let mut graph = Board::new();
+        let i9 = graph.add_line(&p![[H, 8], [I, 9]]);
+        let mut computed = 0;
+        let mut depths = Annotations::new();
+        let mut depth = |graph: &Board, index| {
+            computed += 1;
+            graph.moves_to_root(&index)
+        };
+        assert_eq!(*depths.get_or_compute(&graph, i9, &mut depth), 2);
+        assert_eq!(*depths.get_or_compute(&graph, i9, &mut depth), 2);
+        assert_eq!(depths.get(&graph, i9), Some(&2));
+
+        // moving through the graph is not an edit
+        graph.move_to_root();
+        assert!(!depths.is_stale(&graph));
+        let values = depths.iter(&graph).collect::<Vec<_>>();
+        assert_eq!(values.len(), 1);
+        assert_eq!(values[0].0.node_index(), i9.node_index());
+
+        graph.insert_move(i9, BoardMarker::new(p![J, 10], Stone::Black));
+        assert!(depths.is_stale(&graph));
+        assert_eq!(depths.get(&graph, i9), None);
+        assert_eq!(depths.iter(&graph).count(), 0);
+        assert_eq!(*depths.get_or_compute(&graph, i9, &mut depth), 2);
+        assert_eq!(computed, 2);
+        assert_eq!(depths.revision(), Some(graph.revision()));
+
+        let mut evaluations = Evaluations::new();
+        let result = evaluations
+            .search(&graph, i9, EngineKind::AlphaBeta, &Limits::default())
+            .unwrap();
+        assert!(result.best.is_some());
+        assert_eq!(evaluations.len(), 1);
+    }
+}
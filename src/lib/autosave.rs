@@ -0,0 +1,256 @@
+//! Saving a library being edited every now and then.
+//!
+//! An editor calls [`Autosave::changed`] after every edit and [`Autosave::tick`] from its timer or
+//! event loop, which saves the library when it has changed and the interval has passed. Saves go
+//! through [`save_file_path_with_options`], so the file on disk is always either the previous or
+//! the new library, never half of one.
+//!
+//! Another program, or another editor of a shared library, may write the file in between. Once
+//! the editor has called [`Autosave::opened`], a file changed by someone else is never saved
+//! over: saving fails with [`Error::Changed`] until [`Autosave::reconcile`] has read the file
+//! again and reapplied the edits made since, reporting the ones that conflict.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::board::merge::Conflict;
+use crate::board::Board;
+use crate::file_reader::{open_file_path, save_file_path_with_options, WriteOptions};
+use crate::Error;
+
+/// Saves a library to a file when it has changed, see the [module documentation](self).
+#[derive(Debug)]
+pub struct Autosave {
+    path: PathBuf,
+    interval: Duration,
+    /// When the library was last saved, or the autosave created.
+    saved: Instant,
+    changed: bool,
+    options: WriteOptions,
+    /// The file as the autosave last read or wrote it.
+    disk: Option<OnDisk>,
+}
+
+#[derive(Debug)]
+struct OnDisk {
+    /// The library in the file, the base of the edits on both sides.
+    library: Board,
+    stamp: Stamp,
+}
+
+/// What tells a file apart from the same file written again.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> crate::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+impl Autosave {
+    /// Save to `path` at most every `interval`. The format is chosen by the extension like
+    /// [`save_file_path_with_options`].
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+            saved: Instant::now(),
+            changed: false,
+            options: WriteOptions::default(),
+            disk: None,
+        }
+    }
+
+    /// Save with `options`, e.g. to keep backups of the replaced files.
+    #[must_use]
+    pub fn with_options(mut self, options: WriteOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Mark the library as edited since the last save.
+    pub fn changed(&mut self) {
+        self.changed = true;
+    }
+
+    /// Whether the library has edits that are not saved.
+    #[must_use]
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Remember the file as it is now, right after the library being edited was read from it, to
+    /// notice when someone else writes it.
+    pub fn opened(&mut self) -> crate::Result<()> {
+        self.disk = Some(self.read()?);
+        Ok(())
+    }
+
+    /// Whether the file was written by someone else since it was [opened](Self::opened),
+    /// reconciled or saved. A file removed by someone else is not stale, saving writes it again.
+    #[must_use]
+    pub fn is_stale(&self) -> bool {
+        match (&self.disk, Stamp::of(&self.path)) {
+            (Some(disk), Ok(stamp)) => disk.stamp != stamp,
+            _ => false,
+        }
+    }
+
+    /// Read the file again and reapply the edits made to `board` since it was last read or saved,
+    /// with a [three-way merge](crate::board::merge). `board` becomes the merged library, holding
+    /// the edits of both sides and their [history](crate::board::history).
+    ///
+    /// The conflicts are returned for the editor to show, they are resolved in favor of `board`.
+    /// Before the file was [opened](Self::opened), everything in `board` is taken as an edit.
+    pub fn reconcile(&mut self, board: &mut Board) -> crate::Result<Vec<Conflict>> {
+        let theirs = self.read()?;
+        let merged = match &self.disk {
+            Some(base) => Board::merge3(&base.library, board, &theirs.library),
+            None => Board::merge3(&Board::new(), board, &theirs.library),
+        };
+        tracing::debug!(
+            path = ?self.path,
+            conflicts = merged.conflicts.len(),
+            "reconciled library changed on disk"
+        );
+        *board = merged.graph;
+        self.disk = Some(theirs);
+        Ok(merged.conflicts)
+    }
+
+    fn read(&self) -> crate::Result<OnDisk> {
+        // stamped first, so that a write while reading makes it stale rather than unnoticed
+        let stamp = Stamp::of(&self.path)?;
+        Ok(OnDisk {
+            library: open_file_path(&self.path)?,
+            stamp,
+        })
+    }
+
+    /// Whether [`tick`](Self::tick) at `now` would save.
+    #[must_use]
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.changed && now.saturating_duration_since(self.saved) >= self.interval
+    }
+
+    /// Save `board` if it is due, returning whether it was saved. Fails like [`save`](Self::save)
+    /// when the file is stale.
+    pub fn tick(&mut self, board: &Board) -> crate::Result<bool> {
+        let now = Instant::now();
+        if !self.is_due(now) {
+            return Ok(false);
+        }
+        self.save(board)?;
+        Ok(true)
+    }
+
+    /// Save `board` now, e.g. when the editor is closed.
+    ///
+    /// Fails with [`Error::Changed`] without saving when the file [is stale](Self::is_stale), to
+    /// be [reconciled](Self::reconcile) first.
+    pub fn save(&mut self, board: &Board) -> crate::Result<()> {
+        if self.is_stale() {
+            return Err(Error::Changed(self.path.clone()));
+        }
+        save_file_path_with_options(board, &self.path, &self.options)?;
+        if self.disk.is_some() {
+            self.disk = Some(self.read()?);
+        }
+        tracing::debug!(path = ?self.path, "autosaved library");
+        self.saved = Instant::now();
+        self.changed = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Point;
+    use crate::p;
+
+    #[test]
+    fn saves_when_due() {
+        let dir = std::env::temp_dir().join(format!("renju-autosave-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("edited.txt");
+        let mut graph = Board::new();
+        let mut autosave = Autosave::new(&path, Duration::ZERO);
+        assert!(!autosave.tick(&graph).unwrap());
+        assert!(!path.exists());
+
+        graph.add_line(&p![[H, 8], [I, 9]]);
+        autosave.changed();
+        assert!(autosave.tick(&graph).unwrap());
+        assert!(!autosave.is_changed());
+        let mut saved = Board::new();
+        crate::file_reader::read_bytes(
+            std::fs::File::open(&path).unwrap(),
+            Some(&crate::file_reader::FileType::Text),
+            &mut saved,
+        )
+        .unwrap();
+        assert_eq!(saved.main_line(saved.get_root()).len(), 2);
+
+        let mut hourly = Autosave::new(&path, Duration::from_secs(3600));
+        hourly.changed();
+        assert!(!hourly.tick(&graph).unwrap());
+        assert!(hourly.is_due(Instant::now() + Duration::from_secs(3600)));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reconciles_writes_of_others() {
+        let dir = std::env::temp_dir().join(format!("renju-reconcile-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("shared.txt");
+        let mut shared = Board::new();
+        shared.add_line(&p![[H, 8], [I, 9]]);
+        crate::file_reader::save_file_path(&shared, &path).unwrap();
+
+        let mut graph = open_file_path(&path).unwrap();
+        let mut autosave = Autosave::new(&path, Duration::ZERO);
+        autosave.opened().unwrap();
+        assert!(!autosave.is_stale());
+        let ours = graph.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        graph.get_move_mut(ours).unwrap().oneline_comment = Some("ours".to_owned());
+        autosave.changed();
+
+        // someone else adds another line and comments the same move
+        let theirs = shared.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        shared.get_move_mut(theirs).unwrap().oneline_comment = Some("theirs".to_owned());
+        shared.add_line(&p![[H, 8], [G, 7]]);
+        crate::file_reader::save_file_path(&shared, &path).unwrap();
+        assert!(autosave.is_stale());
+        assert!(matches!(autosave.tick(&graph), Err(Error::Changed(_))));
+
+        let conflicts = autosave.reconcile(&mut graph).unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert!(!autosave.is_stale());
+        let j10 = graph.find_position(&p![[H, 8], [I, 9], [J, 10]]).unwrap();
+        assert_eq!(
+            graph.get_move(j10).unwrap().oneline_comment.as_deref(),
+            Some("ours")
+        );
+        assert!(graph.find_position(&p![[H, 8], [G, 7]]).is_some());
+        assert!(autosave.tick(&graph).unwrap());
+        assert!(!autosave.is_stale());
+        let saved = open_file_path(&path).unwrap();
+        assert!(saved.find_position(&p![[H, 8], [G, 7]]).is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,13 +1,39 @@
 use crate::errors::ParseError;
+use crate::file_reader::renlib::CommandVariant;
+pub use backend::{BoardRead, BoardWrite};
+pub use bitboard::BitBoard;
 pub use board_logic::{BoardArr, BoardMarker, Point, Stone};
 use daggy;
 use daggy::Walker;
+pub use evaluator::Evaluate;
+pub use fixed::BoardConst;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use std::ops::RangeInclusive;
 
 use std::str::FromStr;
 
+pub mod backend;
+pub mod bitboard;
 pub mod board_logic;
+#[cfg(feature = "solver")]
+pub mod candidates;
+pub mod compare;
+pub mod duplicates;
 pub mod evaluator;
+pub mod explain;
+pub mod fixed;
+pub mod history;
+pub mod influence;
+pub mod lines;
+pub mod merge;
+pub mod notation;
+pub mod outline;
+pub mod pages;
+pub mod patterns;
+pub mod reachable;
+pub mod setup;
+pub mod shapes;
 
 pub type BigU = usize;
 pub type NodeIndex = daggy::NodeIndex<BigU>;
@@ -20,7 +46,36 @@ pub type EdgeIndex = daggy::EdgeIndex<BigU>;
 //    fn max() -> Self { BigUint::MAX }
 //}
 
-//pub type MoveGraph = daggy::Dag<NodeIndex, EdgeIndex>;
+/// A graph of moves with `T` on every node, see [`Payload`].
+pub type MoveGraph<T = BoardMarker> = Board<T>;
+
+/// The data on a node of a [`Board`], a move and anything kept along with it.
+///
+/// Engines can store e.g evaluations or visit counts on the nodes instead of in a map beside the
+/// graph. The parsers produce a graph of plain [`BoardMarker`]s, which
+/// [`map_payload`](Board::map_payload) converts.
+pub trait Payload {
+    /// A node for `marker` with the rest of the data at its initial value.
+    fn from_marker(marker: BoardMarker) -> Self;
+
+    fn marker(&self) -> &BoardMarker;
+
+    fn marker_mut(&mut self) -> &mut BoardMarker;
+}
+
+impl Payload for BoardMarker {
+    fn from_marker(marker: BoardMarker) -> Self {
+        marker
+    }
+
+    fn marker(&self) -> &BoardMarker {
+        self
+    }
+
+    fn marker_mut(&mut self) -> &mut BoardMarker {
+        self
+    }
+}
 
 #[derive(Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -46,6 +101,12 @@ impl MoveIndex {
         }
     }
 
+    /// The node, the same for every edge leading to it.
+    #[must_use]
+    pub fn node_index(&self) -> NodeIndex {
+        self.node_index
+    }
+
     #[must_use]
     pub fn from_option(edge_node_option: Option<(EdgeIndex, NodeIndex)>) -> Option<Self> {
         edge_node_option.map(|edge_node| Self {
@@ -83,11 +144,31 @@ impl FromStr for MoveIndex {
 }
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Board {
-    graph: daggy::Dag<BoardMarker, BigU, BigU>,
+pub struct Board<T = BoardMarker> {
+    graph: daggy::Dag<T, BigU, BigU>,
     /// List of moves currently done
     move_list: Vec<MoveIndex>,
     index: usize,
+    /// The root of every independent tree, the first one is the start of `move_list`.
+    roots: Vec<MoveIndex>,
+    /// Named nodes.
+    bookmarks: BTreeMap<String, MoveIndex>,
+    /// The nodes of every tag, e.g the lines prepared for a tournament.
+    #[cfg_attr(feature = "serde", serde(default))]
+    tags: BTreeMap<String, Vec<MoveIndex>>,
+    /// The moves played with another color than the one they were added with, see
+    /// [`set_color_override`](Self::set_color_override).
+    #[cfg_attr(feature = "serde", serde(default))]
+    colors: BTreeMap<NodeIndex, Stone>,
+    /// Bumped by every edit, see [`revision`](Self::revision).
+    #[cfg_attr(feature = "serde", serde(skip))]
+    revision: u64,
+    /// The logged edits, oldest first.
+    #[cfg_attr(feature = "serde", serde(default))]
+    history: Vec<history::Edit>,
+    /// The author of new edits, none are logged without one.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    author: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -102,27 +183,137 @@ pub enum VariantType {
 impl Board {
     #[must_use]
     pub fn new() -> Self {
+        Self::with_payload()
+    }
+}
+
+impl<T: Payload> Board<T> {
+    /// An empty graph with nodes of `T`.
+    #[must_use]
+    pub fn with_payload() -> Self {
         let mut board = Self {
             graph: daggy::Dag::with_capacity(255, 255),
             move_list: vec![],
             index: 0,
+            roots: vec![],
+            bookmarks: BTreeMap::new(),
+            tags: BTreeMap::new(),
+            colors: BTreeMap::new(),
+            revision: 0,
+            history: vec![],
+            author: None,
         };
 
-        let root = board.new_root(BoardMarker::null());
+        let root = board.add_root(T::from_marker(BoardMarker::null()));
         board.move_list.push(root);
         board
     }
 
-    fn new_root(&mut self, marker: BoardMarker) -> MoveIndex {
+    /// A copy of the graph with `f` applied to every node. The indexes stay the same.
+    #[must_use]
+    pub fn map_payload<U>(&self, mut f: impl FnMut(&T) -> U) -> Board<U> {
+        Board {
+            graph: self.graph.map(|_, node| f(node), |_, edge| *edge),
+            move_list: self.move_list.clone(),
+            index: self.index,
+            roots: self.roots.clone(),
+            bookmarks: self.bookmarks.clone(),
+            tags: self.tags.clone(),
+            colors: self.colors.clone(),
+            revision: self.revision,
+            history: self.history.clone(),
+            author: self.author.clone(),
+        }
+    }
+
+    /// A number that grows with every edit of the graph, its nodes, bookmarks and tags, but not
+    /// with moving through it. Results computed for the graph, like the
+    /// [`Annotations`](crate::annotations::Annotations) of engine evaluations, keep the revision
+    /// they were computed at to tell when they are stale.
+    ///
+    /// [`get_move_mut`](Self::get_move_mut) counts as an edit. The revision is not saved, a graph
+    /// read from a file starts over.
+    #[must_use]
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn edited(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Log the edits made from now on as made by `author`, or stop logging with `None`. See
+    /// [`history`].
+    pub fn record_history(&mut self, author: Option<String>) {
+        self.author = author;
+    }
+
+    /// The logged edits, oldest first.
+    #[must_use]
+    pub fn history(&self) -> &[history::Edit] {
+        &self.history
+    }
+
+    /// Add an edit to the log, e.g one read from a file.
+    pub fn push_history(&mut self, edit: history::Edit) {
+        self.history.push(edit);
+    }
+
+    fn log(&mut self, action: history::Action, index: MoveIndex) {
+        let Some(author) = &self.author else {
+            return;
+        };
+        let edit = history::Edit {
+            author: author.clone(),
+            time: history::now(),
+            action,
+            path: self.describe_path(index),
+        };
+        self.history.push(edit);
+    }
+
+    fn new_root(&mut self, marker: T) -> MoveIndex {
+        self.edited();
         MoveIndex::new_node(self.graph.add_node(marker))
     }
 
-    pub fn insert_move(&mut self, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
+    /// Add the root of another independent tree, e.g a different setup position.
+    pub fn add_root(&mut self, marker: T) -> MoveIndex {
+        let root = self.new_root(marker);
+        self.roots.push(root);
+        root
+    }
+
+    /// The root of every tree, [`get_root`](Self::get_root) first.
+    #[must_use]
+    pub fn roots(&self) -> &[MoveIndex] {
+        &self.roots
+    }
+
+    /// Add `marker` as a child of `parent`.
+    ///
+    /// # Panics
+    ///
+    /// If `parent` is not in the graph, see [`try_insert_move`](Self::try_insert_move).
+    pub fn insert_move(&mut self, parent: MoveIndex, marker: T) -> MoveIndex {
         // tracing::trace!(
         //     index_in_file = format!("0x{:X}", marker.index_in_file.unwrap_or_default()),
         //     "inserting move to graph"
         // );
-        MoveIndex::new(self.graph.add_child(parent.node_index, 255, marker))
+        self.edited();
+        let index = MoveIndex::new(self.graph.add_child(parent.node_index, 255, marker));
+        self.log(history::Action::Add, index);
+        index
+    }
+
+    /// Like [`insert_move`](Self::insert_move), but fails if `parent` is not in the graph.
+    pub fn try_insert_move(&mut self, parent: MoveIndex, marker: T) -> crate::Result<MoveIndex> {
+        if self.get_move(parent).is_none() {
+            return Err(crate::Error::Graph(format!(
+                "{parent:?} is not in the graph"
+            )));
+        }
+        Ok(self.insert_move(parent, marker))
     }
 
     #[tracing::instrument(skip(self))]
@@ -131,14 +322,16 @@ impl Board {
         left: &MoveIndex,
         right: &MoveIndex,
     ) -> Result<(), daggy::WouldCycle<usize>> {
+        self.edited();
         self.graph
             .add_edge(left.node_index, right.node_index, 0)
             .map(|_| ())
     }
     /// Add move to graph and move_list
-    pub fn add_move(&mut self, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
-        let idx = self.insert_move(parent, marker.clone());
-        if marker.command.is_move() {
+    pub fn add_move(&mut self, parent: MoveIndex, marker: T) -> MoveIndex {
+        let is_move = marker.marker().command.is_move();
+        let idx = self.insert_move(parent, marker);
+        if is_move {
             self.add_move_to_move_list(idx);
         }
         idx
@@ -154,17 +347,48 @@ impl Board {
         self.index = idx;
     }
 
-    pub fn get_move_mut(&mut self, node: MoveIndex) -> Option<&mut BoardMarker> {
+    pub fn get_move_mut(&mut self, node: MoveIndex) -> Option<&mut T> {
+        self.edited();
+        if self.graph.node_weight(node.node_index).is_some() {
+            self.log(history::Action::Edit, node);
+        }
         self.graph.node_weight_mut(node.node_index)
     }
 
     #[must_use]
-    pub fn get_move(&self, node: MoveIndex) -> Option<&BoardMarker> {
+    pub fn get_move(&self, node: MoveIndex) -> Option<&T> {
         self.graph.node_weight(node.node_index)
     }
 
-    pub fn rm_move(&mut self, node: MoveIndex) -> Option<BoardMarker> {
-        self.graph.remove_node(node.node_index)
+    /// Remove `node` with its bookmarks, tags and color override. The last node of the graph
+    /// takes its index.
+    pub fn rm_move(&mut self, node: MoveIndex) -> Option<T> {
+        self.edited();
+        self.graph.node_weight(node.node_index)?;
+        self.log(history::Action::Remove, node);
+        let last = NodeIndex::new(self.graph.node_count() - 1);
+        let removed = self.graph.remove_node(node.node_index);
+        let removed_node = node.node_index;
+        self.colors.remove(&removed_node);
+        self.bookmarks
+            .retain(|_, index| index.node_index != removed_node);
+        for nodes in self.tags.values_mut() {
+            nodes.retain(|index| index.node_index != removed_node);
+        }
+        self.tags.retain(|_, nodes| !nodes.is_empty());
+        if last != removed_node {
+            if let Some(color) = self.colors.remove(&last) {
+                self.colors.insert(removed_node, color);
+            }
+            let moved = self
+                .bookmarks
+                .values_mut()
+                .chain(self.tags.values_mut().flatten());
+            for index in moved.filter(|index| index.node_index == last) {
+                *index = MoveIndex::new_node(removed_node);
+            }
+        }
+        removed
     }
 
     #[must_use]
@@ -242,8 +466,8 @@ impl Board {
 
         let mut board: BoardArr = BoardArr::new(15);
         for index_marker in move_list.iter().rev() {
-            let m = match self.get_move(*index_marker) {
-                Some(val) => val.clone(),
+            let mut m = match self.get_move(*index_marker) {
+                Some(val) => val.marker().clone(),
                 None => {
                     return Err(ParseError::Other(format!(
                         "Couldn't get move at: {:?}",
@@ -251,11 +475,21 @@ impl Board {
                     )))
                 }
             };
+            if let Some(color) = self.color_override(*index_marker) {
+                m.color = color;
+            }
             if m.command.is_move() {
                 moves.push(m.point)
             };
             if !m.point.is_null {
-                board.set(m)?;
+                match board.get_point(m.point) {
+                    // board text or a mark on a stone played before it keeps the stone
+                    Some(stone) if !m.command.is_move() && !stone.color.is_empty() => {
+                        let color = stone.color;
+                        board.set(BoardMarker { color, ..m })?;
+                    }
+                    _ => board.set(m)?,
+                }
             }
         }
         //tracing::info!("board is = {}", board.board);
@@ -301,7 +535,7 @@ impl Board {
     pub fn set_pos(&mut self, node: MoveIndex, point: Point) -> Result<(), ParseError> {
         {
             let marker: &mut BoardMarker = match self.get_move_mut(node) {
-                Some(val) => val,
+                Some(val) => val.marker_mut(),
                 None => {
                     return Err(ParseError::Other(format!(
                         "Couldn't set position: {:?} at node {:?}",
@@ -328,6 +562,108 @@ impl Board {
         self.get_children(index)
     }
 
+    /// The moves after `index` taking the first continuation of every node, to the end of the
+    /// line.
+    #[must_use]
+    pub fn main_line(&self, index: MoveIndex) -> Vec<MoveIndex> {
+        let mut line = vec![];
+        // children are listed newest first
+        while let Some(next) = self.get_children(line.last().unwrap_or(&index)).pop() {
+            line.push(next);
+        }
+        line
+    }
+
+    /// The moves leading to `index`, like `H8 I9 J6 (var 2) K7`.
+    ///
+    /// A move that is not the first continuation of its parent is followed by its variation
+    /// number, counting the continuations in the order they were added. Moves in any tree but the
+    /// first start with its number, like `(tree 2) H8`.
+    #[must_use]
+    pub fn describe_path(&self, index: MoveIndex) -> String {
+        let path = self.down_to_root(&index);
+        let mut parts = vec![];
+        let root = path.last().expect("the path has at least the node itself");
+        if let Some(tree) = self
+            .roots
+            .iter()
+            .position(|r| r.node_index == root.node_index)
+            .filter(|tree| *tree > 0)
+        {
+            parts.push(format!("(tree {})", tree + 1));
+        }
+        for pair in path.windows(2).rev() {
+            let (child, parent) = (pair[0], pair[1]);
+            let Some(marker) = self.get_move(child).map(T::marker) else {
+                continue;
+            };
+            if !marker.command.is_move() {
+                continue;
+            }
+            parts.push(if marker.point.is_null {
+                "pass".to_owned()
+            } else {
+                marker.point.to_string()
+            });
+            // children are listed newest first
+            let variation = self
+                .get_children(&parent)
+                .iter()
+                .rev()
+                .position(|c| c.node_index == child.node_index)
+                .map_or(1, |i| i + 1);
+            if variation > 1 {
+                parts.push(format!("(var {variation})"));
+            }
+        }
+        parts.join(" ")
+    }
+
+    /// Every node where `point` is played, by `stone` if given, `depth` moves from the root if
+    /// given. The first move is at depth 1. A node reached at several depths is found at any of
+    /// them, and listed once.
+    #[must_use]
+    pub fn find_moves_at(
+        &self,
+        point: Point,
+        stone: Option<Stone>,
+        depth: Option<RangeInclusive<usize>>,
+    ) -> Vec<MoveIndex> {
+        let mut found = vec![];
+        let mut found_nodes = HashSet::new();
+        // the depth of a node depends on the way it's reached
+        let mut visited = HashSet::new();
+        let mut stack = self
+            .roots
+            .iter()
+            .rev()
+            .map(|root| (*root, 0))
+            .collect::<Vec<_>>();
+        while let Some((index, d)) = stack.pop() {
+            if !visited.insert((index.node_index, d)) {
+                continue;
+            }
+            let (Some(marker), Some(color)) =
+                (self.get_move(index).map(T::marker), self.color_at(index))
+            else {
+                continue;
+            };
+            if marker.point == point
+                && !marker.point.is_null
+                && stone.map_or(!color.is_empty(), |stone| color == stone)
+                && depth.as_ref().is_none_or(|depth| depth.contains(&d))
+                && found_nodes.insert(index.node_index)
+            {
+                found.push(index);
+            }
+            if depth.as_ref().is_none_or(|depth| d < *depth.end()) {
+                // children are listed newest first
+                stack.extend(self.get_children(&index).into_iter().map(|c| (c, d + 1)));
+            }
+        }
+        found
+    }
+
     /// Get indexes for all paths which lead to the same outcome
     #[tracing::instrument(skip(self))]
     pub fn get_variants_and_transformations(
@@ -335,9 +671,9 @@ impl Board {
         index: MoveIndex,
     ) -> Result<Vec<(BoardMarker, MoveIndex, Transformation, VariantType)>, ParseError> {
         // recursive walk up the tree, discarding all branches that don't fit.
-        fn walk_up(
+        fn walk_up<T: Payload>(
             walked: Vec<(Point, &Stone, &MoveIndex)>,
-            graph: &Board,
+            graph: &Board<T>,
             move_list: &Vec<(&Point, &Stone, &MoveIndex)>,
             index: MoveIndex,
         ) -> Vec<(BoardMarker, MoveIndex, Transformation, VariantType)> {
@@ -417,7 +753,7 @@ impl Board {
                     }
                     let diff = diff.unwrap();
                     // we've found a variant, return it.
-                    let mut marker = graph.get_move(*diff.1).unwrap().clone();
+                    let mut marker = graph.get_move(*diff.1).unwrap().marker().clone();
                     marker.point = *diff.0;
                     tracing::debug!("we got a variant on {transform:?}, {marker:?} at {index:?}");
                     let variant_type = if move_list
@@ -441,12 +777,10 @@ impl Board {
 
             let mut result = Vec::new();
             for child in children {
-                if let Some(child_m) = graph.get_move(child) {
+                if let Some(child_m) = graph.get_move(child).map(T::marker) {
                     let mut new_walked = walked.clone();
                     new_walked.push((child_m.point, &child_m.color, &child));
                     result.extend(walk_up(new_walked, graph, move_list, child));
-                } else {
-                    todo!()
                 }
             }
             result
@@ -455,7 +789,7 @@ impl Board {
         let moves = self
             .move_list()
             .iter()
-            .filter_map(|mi| Some((self.get_move(*mi)?, mi)))
+            .filter_map(|mi| Some((self.get_move(*mi)?.marker(), mi)))
             .filter(|(m, _)| !m.color.is_empty())
             .map(|(m, mi)| (&m.point, &m.color, mi))
             .collect::<Vec<_>>();
@@ -488,7 +822,7 @@ impl Board {
                     command,
                     ..
                 },
-            ) = self.get_move(node)
+            ) = self.get_move(node).map(T::marker)
             {
                 if !command.is_down() {
                     return None;
@@ -500,7 +834,7 @@ impl Board {
                     // Get
                     for right in self.get_right(&node) {
                         if let Some(marker @ BoardMarker { point: point2, .. }) =
-                            self.get_move(right)
+                            self.get_move(right).map(T::marker)
                         {
                             if point2 == point {
                                 return Some((marker, right));
@@ -522,6 +856,7 @@ impl Board {
             .expect("index should be up to date with move_list")
     }
 
+    /// The root of the first tree.
     #[must_use]
     pub fn get_root(&self) -> MoveIndex {
         *self
@@ -530,6 +865,206 @@ impl Board {
             .expect("move_list should never be empty")
     }
 
+    /// The marker of the root. It is not a move, it holds the title and comment of the library.
+    #[must_use]
+    pub fn root_marker(&self) -> &BoardMarker {
+        self.get_move(self.get_root())
+            .expect("the root should always be in the graph")
+            .marker()
+    }
+
+    pub fn root_marker_mut(&mut self) -> &mut BoardMarker {
+        let root = self.get_root();
+        self.get_move_mut(root)
+            .expect("the root should always be in the graph")
+            .marker_mut()
+    }
+
+    /// The title of the library, the one line comment of the root.
+    #[must_use]
+    pub fn title(&self) -> Option<&str> {
+        self.root_marker().oneline_comment.as_deref()
+    }
+
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.root_marker_mut().oneline_comment = title;
+    }
+
+    /// The comment of the library, the multiline comment of the root.
+    #[must_use]
+    pub fn comment(&self) -> Option<&str> {
+        self.root_marker().multiline_comment.as_deref()
+    }
+
+    pub fn set_comment(&mut self, comment: Option<String>) {
+        self.root_marker_mut().multiline_comment = comment;
+    }
+
+    /// Name `index`, replacing any node with the same name.
+    pub fn set_bookmark(&mut self, name: impl Into<String>, index: MoveIndex) {
+        self.edited();
+        let name = name.into();
+        self.log(history::Action::Bookmark(name.clone()), index);
+        self.bookmarks.insert(name, index);
+    }
+
+    pub fn remove_bookmark(&mut self, name: &str) -> Option<MoveIndex> {
+        self.edited();
+        let removed = self.bookmarks.remove(name)?;
+        self.log(history::Action::RemoveBookmark(name.to_owned()), removed);
+        Some(removed)
+    }
+
+    #[must_use]
+    pub fn get_bookmark(&self, name: &str) -> Option<MoveIndex> {
+        self.bookmarks.get(name).copied()
+    }
+
+    /// Every bookmark, by name.
+    #[must_use]
+    pub fn bookmarks(&self) -> &BTreeMap<String, MoveIndex> {
+        &self.bookmarks
+    }
+
+    /// The names of `index`.
+    #[must_use]
+    pub fn bookmarks_at(&self, index: MoveIndex) -> Vec<&str> {
+        self.bookmarks
+            .iter()
+            .filter(|(_, bookmark)| bookmark.node_index == index.node_index)
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Tag `index` with `tag`.
+    pub fn tag(&mut self, index: MoveIndex, tag: impl Into<String>) {
+        self.edited();
+        let tag = tag.into();
+        let nodes = self.tags.entry(tag.clone()).or_default();
+        if !nodes.iter().any(|node| node.node_index == index.node_index) {
+            nodes.push(index);
+            self.log(history::Action::Tag(tag), index);
+        }
+    }
+
+    /// Remove `tag` from `index`, returning whether it was tagged.
+    pub fn untag(&mut self, index: MoveIndex, tag: &str) -> bool {
+        self.edited();
+        let Some(nodes) = self.tags.get_mut(tag) else {
+            return false;
+        };
+        let len = nodes.len();
+        nodes.retain(|node| node.node_index != index.node_index);
+        let removed = nodes.len() != len;
+        if nodes.is_empty() {
+            self.tags.remove(tag);
+        }
+        if removed {
+            self.log(history::Action::Untag(tag.to_owned()), index);
+        }
+        removed
+    }
+
+    /// The nodes tagged with `tag`, in the order they were tagged.
+    #[must_use]
+    pub fn tagged(&self, tag: &str) -> &[MoveIndex] {
+        self.tags.get(tag).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every tag with its nodes.
+    #[must_use]
+    pub fn tags(&self) -> &BTreeMap<String, Vec<MoveIndex>> {
+        &self.tags
+    }
+
+    /// The tags of `index`.
+    #[must_use]
+    pub fn tags_at(&self, index: MoveIndex) -> Vec<&str> {
+        self.tags
+            .iter()
+            .filter(|(_, nodes)| nodes.iter().any(|node| node.node_index == index.node_index))
+            .map(|(tag, _)| tag.as_str())
+            .collect()
+    }
+
+    /// Play `index` with `color` whatever color it was added with, or with the color it was added
+    /// with again for `None`. Formats giving the color of every move keep it this way where it
+    /// isn't the one [inferred](Self::inferred_color), e.g after setup stones or passes.
+    pub fn set_color_override(&mut self, index: MoveIndex, color: Option<Stone>) {
+        self.edited();
+        match color {
+            Some(color) => self.colors.insert(index.node_index, color),
+            None => self.colors.remove(&index.node_index),
+        };
+    }
+
+    /// The color `index` is played with instead of the one it was added with, if any.
+    #[must_use]
+    pub fn color_override(&self, index: MoveIndex) -> Option<Stone> {
+        self.colors.get(&index.node_index).copied()
+    }
+
+    /// Every move with a [color override](Self::set_color_override), in the order they were added
+    /// to the graph.
+    pub fn color_overrides(&self) -> impl Iterator<Item = (MoveIndex, Stone)> + '_ {
+        self.colors
+            .iter()
+            .map(|(node, color)| (MoveIndex::new_node(*node), *color))
+    }
+
+    /// The color `index` is played with, its override or the color it was added with.
+    #[must_use]
+    pub fn color_at(&self, index: MoveIndex) -> Option<Stone> {
+        let marker = self.get_move(index)?.marker();
+        Some(self.color_override(index).unwrap_or(marker.color))
+    }
+
+    /// The color of a move after `parent`, the other color than the last move with a stone
+    /// before it, black for the first one.
+    #[must_use]
+    pub fn inferred_color(&self, parent: MoveIndex) -> Stone {
+        self.down_to_root(&parent)
+            .into_iter()
+            .filter(|index| {
+                self.get_move(*index)
+                    .is_some_and(|m| m.marker().command.is_move())
+            })
+            .filter_map(|index| self.color_at(index))
+            .find(|color| !color.is_empty())
+            .map_or(Stone::Black, Stone::opposite)
+    }
+
+    /// The move flagged as the start position of the library, the position RenLib opens it at.
+    #[must_use]
+    pub fn start(&self) -> Option<MoveIndex> {
+        let mut stack = self
+            .roots
+            .iter()
+            .flat_map(|root| self.get_children(root))
+            .collect::<Vec<_>>();
+        while let Some(index) = stack.pop() {
+            if self
+                .get_move(index)
+                .is_some_and(|m| m.marker().command.is_start())
+            {
+                return Some(index);
+            }
+            stack.extend(self.get_children(&index));
+        }
+        None
+    }
+
+    /// Flag `start` as the start position, clearing the flag from every other move.
+    pub fn set_start(&mut self, start: Option<MoveIndex>) {
+        self.edited();
+        for marker in self.graph.node_weights_mut() {
+            marker.marker_mut().command.remove(CommandVariant::START);
+        }
+        if let Some(marker) = start.and_then(|start| self.get_move_mut(start)) {
+            marker.marker_mut().command.insert(CommandVariant::START);
+        }
+    }
+
     #[must_use]
     pub fn prev_move(&self) -> Option<MoveIndex> {
         self.move_list.get(self.index.checked_sub(1)?).copied()
@@ -797,6 +1332,72 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn board_text_keeps_stones() {
+        let mut graph = Board::new();
+        let h8 = graph.add_line(&p![[H, 8]]);
+        let mut text = BoardMarker::new(p![H, 8], Stone::Empty);
+        text.board_text = Some("A".to_owned());
+        text.command = crate::file_reader::renlib::Command::new(
+            (CommandVariant::BOARDTEXT | CommandVariant::NOMOVE).bits(),
+        )
+        .unwrap();
+        let text = graph.add_move(h8, text);
+        let (board, moves) = graph.as_board(&text).unwrap();
+        assert_eq!(moves, p![[H, 8]]);
+        let marker = board.get_point(p![H, 8]).unwrap();
+        assert_eq!(marker.color, Stone::Black);
+        assert_eq!(marker.board_text.as_deref(), Some("A"));
+    }
+
+    #[test]
+    fn color_overrides() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let pass = graph.insert_move(h8, BoardMarker::new(Point::null(), Stone::White));
+        assert_eq!(graph.inferred_color(root), Stone::Black);
+        assert_eq!(graph.inferred_color(pass), Stone::Black);
+        let i9 = graph.insert_move(pass, BoardMarker::new(p![I, 9], Stone::White));
+        graph.set_color_override(i9, Some(Stone::Black));
+        assert_eq!(graph.color_at(i9), Some(Stone::Black));
+        assert_eq!(graph.inferred_color(i9), Stone::White);
+        let (board, moves) = graph.as_board(&i9).unwrap();
+        assert_eq!(moves.len(), 3);
+        assert_eq!(board.get_point(p![I, 9]).unwrap().color, Stone::Black);
+        assert_eq!(
+            graph.find_moves_at(p![I, 9], Some(Stone::Black), None),
+            [i9]
+        );
+        graph.set_color_override(i9, None);
+        assert!(graph.color_overrides().next().is_none());
+        assert_eq!(graph.color_at(i9), Some(Stone::White));
+    }
+
+    #[test]
+    fn rm_move_keeps_moved_node() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        let j10 = graph.insert_move(h8, BoardMarker::new(p![J, 10], Stone::White));
+        graph.set_color_override(i9, Some(Stone::Black));
+        graph.set_color_override(j10, Some(Stone::Black));
+        graph.tag(i9, "removed");
+        graph.tag(j10, "kept");
+        graph.set_bookmark("J10", j10);
+
+        graph.rm_move(i9);
+        // J10 was the last node, it takes the index of I9
+        let j10 = graph.get_bookmark("J10").unwrap();
+        assert_eq!(j10.node_index(), i9.node_index());
+        assert_eq!(graph.get_move(j10).unwrap().point, p![J, 10]);
+        assert_eq!(graph.color_at(j10), Some(Stone::Black));
+        assert_eq!(graph.tagged("kept"), [j10]);
+        assert!(graph.tagged("removed").is_empty());
+        assert_eq!(graph.color_overrides().count(), 1);
+    }
+
     macro_rules! t {
         (@m |) => {Mirror::Vertical};
         (@m -) => {Mirror::Horizontal};
@@ -815,6 +1416,156 @@ mod tests {
         };
     }
 
+    #[test]
+    fn bookmarks() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        graph.set_bookmark("Main trap", i9);
+        graph.set_bookmark("Tournament prep 2024", i9);
+        graph.set_bookmark("Start", h8);
+        assert_eq!(graph.get_bookmark("Main trap"), Some(i9));
+        assert_eq!(
+            graph.bookmarks_at(MoveIndex::new_node(i9.node_index)),
+            ["Main trap", "Tournament prep 2024"]
+        );
+        graph.set_bookmark("Start", i9);
+        assert_eq!(graph.remove_bookmark("Start"), Some(i9));
+        assert_eq!(graph.bookmarks().len(), 2);
+        assert!(graph.bookmarks_at(h8).is_empty());
+    }
+
+    #[test]
+    fn tags() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        graph.tag(i9, "tournament2024");
+        graph.tag(i9, "tournament2024");
+        graph.tag(h8, "tournament2024");
+        graph.tag(i9, "trap");
+        assert_eq!(graph.tagged("tournament2024"), [i9, h8]);
+        assert_eq!(graph.tags_at(i9), ["tournament2024", "trap"]);
+        assert!(graph.untag(i9, "trap"));
+        assert!(!graph.untag(i9, "trap"));
+        assert_eq!(graph.tags().len(), 1);
+        assert!(graph.tagged("trap").is_empty());
+    }
+
+    #[test]
+    fn describe_path() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        let j6 = graph.insert_move(i9, BoardMarker::new(p![J, 6], Stone::Black));
+        let j7 = graph.insert_move(i9, BoardMarker::new(p![J, 7], Stone::Black));
+        let k7 = graph.insert_move(j7, BoardMarker::new(p![K, 7], Stone::White));
+        let other = graph.add_root(BoardMarker::null());
+        let h8_again = graph.insert_move(other, BoardMarker::new(p![H, 8], Stone::Black));
+
+        assert_eq!(graph.describe_path(root), "");
+        assert_eq!(graph.describe_path(j6), "H8 I9 J6");
+        assert_eq!(graph.describe_path(k7), "H8 I9 J7 (var 2) K7");
+        assert_eq!(graph.describe_path(h8_again), "(tree 2) H8");
+    }
+
+    #[test]
+    fn main_line() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        graph.insert_move(h8, BoardMarker::new(p![J, 8], Stone::White));
+        let k11 = graph.insert_move(i9, BoardMarker::new(p![K, 11], Stone::Black));
+
+        let nodes = |line: Vec<MoveIndex>| line.iter().map(|i| i.node_index).collect::<Vec<_>>();
+        assert_eq!(nodes(graph.main_line(root)), nodes(vec![h8, i9, k11]));
+        assert_eq!(nodes(graph.main_line(i9)), nodes(vec![k11]));
+        assert!(graph.main_line(k11).is_empty());
+    }
+
+    #[test]
+    fn moves_at() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        let k11 = graph.insert_move(i9, BoardMarker::new(p![K, 11], Stone::Black));
+        let j8 = graph.insert_move(h8, BoardMarker::new(p![J, 8], Stone::White));
+        let k11_white = graph.insert_move(j8, BoardMarker::new(p![K, 11], Stone::White));
+        let other = graph.add_root(BoardMarker::null());
+        let k11_first = graph.insert_move(other, BoardMarker::new(p![K, 11], Stone::Black));
+
+        let mut all = graph.find_moves_at(p![K, 11], None, None);
+        all.sort_by_key(|index| index.node_index);
+        assert!(all == [k11, k11_white, k11_first]);
+        let black = graph.find_moves_at(p![K, 11], Some(Stone::Black), None);
+        assert!(black.len() == 2 && !black.contains(&k11_white));
+        assert!(graph.find_moves_at(p![K, 11], Some(Stone::Black), Some(3..=3)) == [k11]);
+        assert!(graph.find_moves_at(p![K, 11], None, Some(1..=2)) == [k11_first]);
+        assert!(graph.find_moves_at(p![A, 1], None, None).is_empty());
+
+        // L12 is two moves deep, and three through a transposition in the second tree
+        let l12 = graph.insert_move(h8, BoardMarker::new(p![L, 12], Stone::White));
+        let g7 = graph.insert_move(k11_first, BoardMarker::new(p![G, 7], Stone::White));
+        graph.add_edge(&g7, &l12).unwrap();
+        let nodes = |found: Vec<MoveIndex>| found.iter().map(|i| i.node_index).collect::<Vec<_>>();
+        let l12 = vec![l12.node_index];
+        assert_eq!(
+            nodes(graph.find_moves_at(p![L, 12], None, Some(3..=3))),
+            l12
+        );
+        assert_eq!(
+            nodes(graph.find_moves_at(p![L, 12], None, Some(2..=2))),
+            l12
+        );
+        assert_eq!(nodes(graph.find_moves_at(p![L, 12], None, None)), l12);
+    }
+
+    #[test]
+    fn payload() {
+        #[derive(Debug)]
+        struct Visited {
+            marker: BoardMarker,
+            visits: u32,
+        }
+        impl Payload for Visited {
+            fn from_marker(marker: BoardMarker) -> Self {
+                Self { marker, visits: 0 }
+            }
+            fn marker(&self) -> &BoardMarker {
+                &self.marker
+            }
+            fn marker_mut(&mut self) -> &mut BoardMarker {
+                &mut self.marker
+            }
+        }
+
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = graph.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        graph.set_bookmark("Main", i9);
+
+        let mut visited: MoveGraph<Visited> =
+            graph.map_payload(|m| Visited::from_marker(m.clone()));
+        visited.get_move_mut(i9).unwrap().visits += 3;
+        let j10 = visited.insert_move(
+            i9,
+            Visited::from_marker(BoardMarker::new(p![J, 10], Stone::Black)),
+        );
+        assert_eq!(visited.get_move(i9).unwrap().visits, 3);
+        assert_eq!(visited.get_bookmark("Main"), Some(i9));
+        assert_eq!(visited.describe_path(j10), "H8 I9 J10");
+        assert_eq!(
+            visited.as_board(&j10).unwrap().1,
+            p![[H, 8], [I, 9], [J, 10]]
+        );
+    }
+
     #[test]
     #[allow(clippy::zero_prefixed_literal)]
     fn transforms_are_correct() {
@@ -876,13 +1627,13 @@ mod tests {
 #[error("index is out of bounds")]
 pub struct IndexOutOfBoundsError;
 
-impl Default for Board {
+impl<T: Payload> Default for Board<T> {
     fn default() -> Self {
-        Self::new()
+        Self::with_payload()
     }
 }
 
-impl fmt::Debug for Board {
+impl<T: fmt::Debug> fmt::Debug for Board<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
@@ -0,0 +1,91 @@
+//! Reading and writing stones, for every board backend.
+//!
+//! The evaluator and the solvers only place and read stones, so they work on any board
+//! implementing [`BoardRead`] and [`BoardWrite`]: the [`BoardArr`] of the library, the fixed size
+//! [`BoardConst`](super::BoardConst) and the [`BitBoard`](super::BitBoard). A backend only has to
+//! implement the required methods, the rest have defaults it can replace with faster ones.
+
+use super::lines::LineTable;
+use super::{BoardArr, Point, Stone};
+
+/// Reading the stones of a board.
+pub trait BoardRead {
+    /// The lines of the board, see [`LineTable::for_size`].
+    fn table(&self) -> &LineTable;
+
+    /// The stone on `point`, empty for a point off the board.
+    fn stone_at(&self, point: Point) -> Stone;
+
+    /// The stone on `point`, which is at `index` of line `line` of [`Self::table`]. Boards keeping
+    /// the stones of every line can read them from there.
+    fn line_stone(&self, line: usize, index: usize, point: Point) -> Stone {
+        let _ = (line, index);
+        self.stone_at(point)
+    }
+
+    /// The amount of points along an edge of the board.
+    fn size(&self) -> u32 {
+        self.table().size()
+    }
+
+    /// Whether `point` is on the board.
+    fn contains(&self, point: Point) -> bool {
+        !point.is_null && point.x < self.size() && point.y < self.size()
+    }
+
+    /// Every point of the board, row by row.
+    fn points(&self) -> impl Iterator<Item = Point> + '_ {
+        let size = self.size();
+        (0..size * size).map(move |i| Point::from_1d(i, size))
+    }
+
+    /// Every stone on the board, row by row.
+    fn stones(&self) -> impl Iterator<Item = (Point, Stone)> + '_ {
+        self.points()
+            .map(|point| (point, self.stone_at(point)))
+            .filter(|(_, stone)| !stone.is_empty())
+    }
+}
+
+/// Placing stones on a board.
+pub trait BoardWrite: BoardRead {
+    /// Place `stone` on `point`, nothing happens for a point off the board.
+    fn set_stone(&mut self, point: Point, stone: Stone);
+
+    /// Remove every stone.
+    fn clear_stones(&mut self) {
+        let stones = self.stones().map(|(point, _)| point).collect::<Vec<_>>();
+        for point in stones {
+            self.set_stone(point, Stone::Empty);
+        }
+    }
+}
+
+impl BoardRead for BoardArr {
+    fn table(&self) -> &LineTable {
+        self.lines().table()
+    }
+
+    fn stone_at(&self, point: Point) -> Stone {
+        self.get_point(point)
+            .map_or(Stone::Empty, |marker| marker.color)
+    }
+
+    fn line_stone(&self, line: usize, index: usize, _point: Point) -> Stone {
+        self.lines().stone(line, index)
+    }
+
+    fn size(&self) -> u32 {
+        BoardArr::size(self)
+    }
+}
+
+impl BoardWrite for BoardArr {
+    fn set_stone(&mut self, point: Point, stone: Stone) {
+        self.set_point(point, stone);
+    }
+
+    fn clear_stones(&mut self) {
+        self.clear();
+    }
+}
@@ -0,0 +1,208 @@
+//! A 15x15 board as one bitset per color.
+//!
+//! [`BitBoard`] fits in 64 bytes and is copied instead of allocated. Every point is a bit in
+//! [`Point::to_1d`] order, so counting stones or testing a set of points is a few instructions.
+//! It is a [backend](super::backend) of the evaluator like [`BoardArr`].
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::evaluator::Evaluate;
+use super::lines::LineTable;
+use super::{BoardArr, BoardRead, BoardWrite, Point, Stone};
+use crate::errors::ParseError;
+
+const SIZE: u32 = 15;
+const WORDS: usize = (SIZE * SIZE).div_ceil(64) as usize;
+
+/// A set of points of a 15x15 board.
+pub type Bits = [u64; WORDS];
+
+/// A 15x15 board, see the [module documentation](self).
+#[derive(Clone)]
+pub struct BitBoard {
+    black: Bits,
+    white: Bits,
+    table: Arc<LineTable>,
+}
+
+impl BitBoard {
+    /// An empty board.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            black: [0; WORDS],
+            white: [0; WORDS],
+            table: LineTable::for_size(SIZE),
+        }
+    }
+
+    /// The points with a stone of `stone`, none for an empty stone.
+    #[must_use]
+    pub fn bits(&self, stone: Stone) -> Bits {
+        match stone {
+            Stone::Black => self.black,
+            Stone::White => self.white,
+            Stone::Empty => [0; WORDS],
+        }
+    }
+
+    /// The amount of stones of `stone`.
+    #[must_use]
+    pub fn count(&self, stone: Stone) -> u32 {
+        self.bits(stone).iter().map(|word| word.count_ones()).sum()
+    }
+
+    /// The word and bit of `point`, `None` if it is not on the board.
+    fn bit(point: Point) -> Option<(usize, u64)> {
+        if point.is_null || point.x >= SIZE || point.y >= SIZE {
+            return None;
+        }
+        let i = point.to_1d(SIZE) as usize;
+        Some((i / 64, 1 << (i % 64)))
+    }
+}
+
+impl Default for BitBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardRead for BitBoard {
+    fn table(&self) -> &LineTable {
+        &self.table
+    }
+
+    #[inline]
+    fn stone_at(&self, point: Point) -> Stone {
+        match Self::bit(point) {
+            Some((word, bit)) if self.black[word] & bit != 0 => Stone::Black,
+            Some((word, bit)) if self.white[word] & bit != 0 => Stone::White,
+            _ => Stone::Empty,
+        }
+    }
+
+    fn size(&self) -> u32 {
+        SIZE
+    }
+}
+
+impl BoardWrite for BitBoard {
+    #[inline]
+    fn set_stone(&mut self, point: Point, stone: Stone) {
+        let Some((word, bit)) = Self::bit(point) else {
+            return;
+        };
+        self.black[word] &= !bit;
+        self.white[word] &= !bit;
+        match stone {
+            Stone::Black => self.black[word] |= bit,
+            Stone::White => self.white[word] |= bit,
+            Stone::Empty => {}
+        }
+    }
+
+    fn clear_stones(&mut self) {
+        self.black = [0; WORDS];
+        self.white = [0; WORDS];
+    }
+}
+
+impl Evaluate for BitBoard {}
+
+impl PartialEq for BitBoard {
+    fn eq(&self, other: &Self) -> bool {
+        (self.black, self.white) == (other.black, other.white)
+    }
+}
+
+impl Eq for BitBoard {}
+
+impl fmt::Debug for BitBoard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitBoard")
+            .field("black", &self.count(Stone::Black))
+            .field("white", &self.count(Stone::White))
+            .finish_non_exhaustive()
+    }
+}
+
+impl From<&BitBoard> for BoardArr {
+    fn from(board: &BitBoard) -> Self {
+        let mut arr = BoardArr::new(SIZE);
+        for (point, stone) in board.stones() {
+            arr.set_point(point, stone);
+        }
+        arr
+    }
+}
+
+impl TryFrom<&BoardArr> for BitBoard {
+    type Error = ParseError;
+
+    /// The stones of `board`, which must be 15x15.
+    fn try_from(board: &BoardArr) -> Result<Self, Self::Error> {
+        if board.size() != SIZE {
+            return Err(ParseError::Other(format!(
+                "a board of size {} is not {SIZE}x{SIZE}",
+                board.size()
+            )));
+        }
+        let mut bits = Self::new();
+        for (point, stone) in BoardRead::stones(board) {
+            bits.set_stone(point, stone);
+        }
+        Ok(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardConst;
+    use crate::p;
+
+    #[test]
+    fn backends_agree() {
+        let mut board = BoardArr::new(15);
+        for point in p![
+            [G, 8],
+            [H, 8],
+            [F, 7],
+            [F, 6],
+            [K, 2],
+            [K, 3],
+            [K, 4],
+            [O, 15]
+        ] {
+            board.set_point(point, Stone::Black);
+        }
+        for point in p![[B, 12], [C, 12], [D, 12], [A, 1]] {
+            board.set_point(point, Stone::White);
+        }
+        let bits = BitBoard::try_from(&board).unwrap();
+        let fixed = BoardConst::<15>::try_from(&board).unwrap();
+        assert_eq!((bits.count(Stone::Black), bits.count(Stone::White)), (8, 4));
+        let stones = BoardRead::stones(&board).collect::<Vec<_>>();
+        assert_eq!(bits.stones().collect::<Vec<_>>(), stones);
+        assert_eq!(fixed.stones().collect::<Vec<_>>(), stones);
+        assert_eq!(
+            BoardRead::stones(&BoardArr::from(&bits)).collect::<Vec<_>>(),
+            stones
+        );
+        let conditions = board.renju_conditions_both(None);
+        assert_eq!(bits.renju_conditions_both(None), conditions);
+        assert_eq!(fixed.renju_conditions_both(None), conditions);
+        assert_eq!(
+            *bits.forbidden(Stone::Black),
+            *board.forbidden(Stone::Black)
+        );
+
+        let mut bits = bits;
+        bits.set_stone(p![H, 8], Stone::White);
+        assert_eq!(bits.stone_at(p![H, 8]), Stone::White);
+        bits.clear_stones();
+        assert_eq!(bits, BitBoard::new());
+    }
+}
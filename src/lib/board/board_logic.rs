@@ -4,10 +4,15 @@ use crate::errors::ParseError;
 use crate::file_reader::renlib::Command;
 use crate::file_reader::renlib::CommandVariant;
 
+use super::evaluator::Evaluate;
+use super::lines::Lines;
+
 use std::char;
+use std::collections::BTreeSet;
 use std::fmt;
 use std::iter::FromIterator;
 use std::ops::Deref;
+use std::sync::OnceLock;
 
 #[macro_export]
 macro_rules! p {
@@ -132,6 +137,41 @@ impl fmt::Debug for Point {
     }
 }
 
+impl fmt::Display for Point {
+    /// Board notation, e.g. `H8`, or the coordinates of a point outside a 15x15 board.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_null {
+            return write!(f, "pass");
+        }
+        if self.x >= 15 || self.y >= 15 {
+            return write!(f, "({}, {})", self.x, self.y);
+        }
+        write!(f, "{}{}", (self.x as u8 + b'A') as char, 15 - self.y)
+    }
+}
+
+impl std::str::FromStr for Point {
+    type Err = ParseError;
+
+    /// Parse a point in board notation, e.g. `H8`, the same notation as [`p!`](crate::p).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let mut chars = s.chars();
+        let column = chars
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .ok_or_else(|| ParseError::Other(format!("invalid point {s:?}")))?;
+        let row: u32 = chars.as_str().parse()?;
+        let x = u32::from(column.to_ascii_uppercase() as u8 - b'A');
+        if x > 14 || !(1..=15).contains(&row) {
+            return Err(ParseError::Other(format!(
+                "point {s:?} is outside the board"
+            )));
+        }
+        Ok(Point::new(x, 15 - row))
+    }
+}
+
 /// Holds info about the marker at `Point` or a move.
 ///
 /// # Notes
@@ -185,7 +225,7 @@ impl BoardMarker {
         m
     }
 
-    pub fn from_pos_info(pos: u8, info: u32) -> Result<Self, color_eyre::eyre::Error> {
+    pub fn from_pos_info(pos: u8, info: u32) -> crate::Result<Self> {
         Ok(Self {
             point: Point::from_byte(pos)?,
             color: Stone::Empty,
@@ -262,9 +302,17 @@ impl fmt::Display for BoardMarker {
                 "."
             } else {
                 match self.color {
-                    Stone::Empty if self.oneline_comment.is_some() => {
-                        &self.oneline_comment.as_deref().unwrap()[0..1]
-                    }
+                    // board text is drawn over the marks of comments
+                    Stone::Empty if self.board_text.is_some() => self
+                        .board_text
+                        .as_deref()
+                        .and_then(|text| text.get(..text.chars().next()?.len_utf8()))
+                        .unwrap_or("."),
+                    Stone::Empty if self.oneline_comment.is_some() => self
+                        .oneline_comment
+                        .as_deref()
+                        .and_then(|comment| comment.get(..comment.chars().next()?.len_utf8()))
+                        .unwrap_or("."),
                     Stone::Empty => ".",
                     Stone::White => "O",
                     Stone::Black => "X",
@@ -289,6 +337,14 @@ impl Point {
             u32::from(byte >> 4),
         ))
     }
+    /// The byte of the point read by [`Point::from_byte`], 0 for a pass.
+    #[must_use]
+    pub fn to_byte(self) -> u8 {
+        if self.is_null {
+            return 0;
+        }
+        ((self.y as u8) << 4) + self.x as u8 + 1
+    }
     /// Makes a `Point` at (`x`, `y`)
     #[must_use]
     pub const fn new(x: u32, y: u32) -> Self {
@@ -307,13 +363,16 @@ impl Point {
             y: 0,
         }
     }
-    /// Converts a 1D coord to a `Point`
+    /// Converts a 1D coord to a `Point`, null if it is not on a board of `width`.
     #[must_use]
     pub fn from_1d(idx: u32, width: u32) -> Self {
-        Self {
-            is_null: idx >/*=*/ width*width,
-            x: idx % width,
-            y: idx / width,
+        match (idx.checked_rem(width), idx.checked_div(width)) {
+            (Some(x), Some(y)) if y < width => Self {
+                is_null: false,
+                x,
+                y,
+            },
+            _ => Self::null(),
         }
     }
     /// Convert back a `Point` to a 1D coord
@@ -324,29 +383,102 @@ impl Point {
 }
 
 /// Holds all `BoardMarker`'s in a `Board`.
+///
+/// The [`Lines`] of the board are kept up to date by [`Self::set`], [`Self::set_point`] and
+/// [`Self::clear`]. Changing the color of a marker through the `get_*_mut` accessors does not update
+/// them, those are meant for e.g. comments.
+///
+/// The forbidden points for black are cached by [`Self::forbidden_points`] until the board is
+/// changed through one of the same methods.
 #[derive(Debug, Clone)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct BoardArr(Vec<BoardMarker>, u32);
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "RawBoardArr", into = "RawBoardArr")
+)]
+pub struct BoardArr(Vec<BoardMarker>, u32, Lines, OnceLock<BTreeSet<Point>>);
+
+/// The serialized form of a [`BoardArr`], without the lines.
+#[cfg(feature = "serde")]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+struct RawBoardArr(Vec<BoardMarker>, u32);
+
+#[cfg(feature = "serde")]
+impl From<RawBoardArr> for BoardArr {
+    fn from(RawBoardArr(markers, size): RawBoardArr) -> Self {
+        let mut lines = Lines::new(size);
+        for marker in &markers {
+            lines.set(marker.point, marker.color);
+        }
+        Self(markers, size, lines, OnceLock::new())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<BoardArr> for RawBoardArr {
+    fn from(BoardArr(markers, size, ..): BoardArr) -> Self {
+        Self(markers, size)
+    }
+}
 
 impl BoardArr {
     pub fn new(size: u32) -> Self {
-        let mut b = Self(vec![BoardMarker::null(); (size * size) as usize], size);
+        let mut b = Self(
+            vec![BoardMarker::null(); size as usize * size as usize],
+            size,
+            Lines::new(size),
+            OnceLock::new(),
+        );
         for idx in 0..(size * size) {
             b.get_mut(idx as usize).unwrap().point = Point::from_1d(idx, size);
         }
         b
     }
 
+    /// A board of `size` with the stones of `black` and `white`, for tests.
+    #[cfg(test)]
+    pub(crate) fn from_stones(size: u32, black: &[Point], white: &[Point]) -> Self {
+        let mut board = Self::new(size);
+        for point in black {
+            board.set_point(*point, Stone::Black);
+        }
+        for point in white {
+            board.set_point(*point, Stone::White);
+        }
+        board
+    }
+
     pub fn size(&self) -> u32 {
         self.1
     }
 
+    /// Every row, column and diagonal of the board.
+    #[must_use]
+    pub fn lines(&self) -> &Lines {
+        &self.2
+    }
+
+    /// The points forbidden for `stone`, always empty for white.
+    ///
+    /// This is [`RenjuConditions::forbidden`](super::evaluator::RenjuConditions::forbidden), cached
+    /// until the board changes.
+    pub fn forbidden_points(&self, stone: Stone) -> &BTreeSet<Point> {
+        static NONE: BTreeSet<Point> = BTreeSet::new();
+        if !stone.is_black() {
+            return &NONE;
+        }
+        self.3
+            .get_or_init(|| self.renju_conditions(Stone::Black, None).forbidden)
+    }
+
     pub fn set(&mut self, marker: BoardMarker) -> Result<(), ParseError> {
-        let idx = marker.point.to_1d(self.1) as usize;
-        let mut_marker = self.0.get_mut(idx).ok_or_else(|| {
-            ParseError::Other(format!("Couldn't get index {} in board array", idx))
-        })?;
+        let mut_marker = self
+            .get_point_mut(marker.point)
+            .ok_or_else(|| ParseError::Other(format!("{} is not on the board", marker.point)))?;
+        let (point, color) = (marker.point, marker.color);
         *mut_marker = marker;
+        self.2.set(point, color);
+        self.3.take();
         Ok(())
     }
 
@@ -360,30 +492,32 @@ impl BoardArr {
         self.0 = (0..self.1 * self.1)
             .map(|idx| BoardMarker::new(Point::from_1d(idx, self.1), Stone::Empty))
             .collect();
+        self.2.clear();
+        self.3.take();
     }
     /// Returns a immutable reference to the `BoardMarker` at `pos`
     #[must_use]
     #[track_caller]
     pub fn get_point(&self, pos: Point) -> Option<&BoardMarker> {
-        let marker = self.0.get(pos.to_1d(self.1) as usize);
+        let marker = self.0.get(self.index_of(pos)?);
         if let Some(marker) = marker {
-            assert_eq!(marker.point, pos);
+            debug_assert_eq!(marker.point, pos);
         }
         marker
     }
     /// Returns a immutable reference to the `BoardMarker` at (`x`,`y`)
     #[must_use]
     pub fn get_xy(&self, x: u32, y: u32) -> Option<&BoardMarker> {
-        self.0.get((x + y * self.1) as usize)
+        self.get_point(Point::new(x, y))
     }
     /// Returns a mutable reference to the `BoardMarker` at (`x`,`y`)
     #[must_use]
     pub fn get_xy_mut(&mut self, x: u32, y: u32) -> Option<&mut BoardMarker> {
-        self.0.get_mut((x + y * self.1) as usize)
+        self.get_point_mut(Point::new(x, y))
     }
     #[must_use]
     pub fn get_i32xy(&self, x: i32, y: i32) -> Option<&BoardMarker> {
-        if (x + 1).is_positive() && (y + 1).is_positive() {
+        if x >= 0 && y >= 0 {
             // O is also valid
             self.get_xy(x as u32, y as u32)
         } else {
@@ -392,16 +526,37 @@ impl BoardArr {
     }
     /// Returns a mutable reference to the `BoardMarker` at `pos`
     pub fn get_point_mut(&mut self, pos: Point) -> Option<&mut BoardMarker> {
-        self.0.get_mut(pos.to_1d(self.1) as usize)
+        let index = self.index_of(pos)?;
+        self.0.get_mut(index)
+    }
+
+    /// The index of `pos` in the array, `None` if it is not on the board.
+    fn index_of(&self, pos: Point) -> Option<usize> {
+        (!pos.is_null && pos.x < self.1 && pos.y < self.1).then(|| pos.to_1d(self.1) as usize)
     }
 
     /// Returns a mutable reference to the `BoardMarker` at `pos`
     pub fn get_mut(&mut self, pos: usize) -> Option<&mut BoardMarker> {
         self.0.get_mut(pos)
     }
-    /// Sets the `BoardMarker` at `pos` to `color`
+    /// Sets the `BoardMarker` at `pos` to `color`, does nothing if `pos` is not on the board.
     pub fn set_point(&mut self, pos: Point, color: Stone) {
-        self.0[pos.to_1d(self.1) as usize].color = color;
+        let Some(marker) = self.get_point_mut(pos) else {
+            return;
+        };
+        marker.color = color;
+        self.2.set(pos, color);
+        self.3.take();
+    }
+
+    /// The point drawn at `column` and `row` of the board as it's displayed, counted from 0, e.g.
+    /// to map the mouse clicks on the board the `board` command of the CLI draws. The space after
+    /// a point counts as the point.
+    #[must_use]
+    pub fn point_at(&self, column: usize, row: usize) -> Option<Point> {
+        let x = u32::try_from(column.checked_sub(3)? / 2).ok()?;
+        let point = Point::new(x, u32::try_from(row).ok()?);
+        self.index_of(point).map(|_| point)
     }
 }
 
@@ -422,10 +577,12 @@ pub struct DisplayBoard {
 }
 
 impl fmt::Display for BoardArr {
+    /// The board, with the first letter of the board text on empty points. Board texts that
+    /// don't fit, under a stone or longer than a letter, are listed under the board.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Not sure if needed - let vec: Vec<BoardMarker> = *self;
         let mut dy: u32 = 0;
-        let width: u32 = self.last().unwrap().point.y + 1;
+        let width: u32 = self.last().map_or(0, |marker| marker.point.y + 1);
         write!(f, "15:")?;
         for marker in self.iter() {
             if marker.point.y == dy {
@@ -446,7 +603,16 @@ impl fmt::Display for BoardArr {
                 .map(|d| (d as char).to_string())
                 .collect::<Vec<_>>()
                 .join(" ")
-        )
+        )?;
+        for marker in self.iter() {
+            match marker.board_text.as_deref() {
+                Some(text) if !marker.color.is_empty() || text.chars().nth(1).is_some() => {
+                    write!(f, "\n{}: {text}", marker.point)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
     }
 }
 
@@ -510,4 +676,51 @@ mod tests {
         tracing::info!("Board - Cleared:\n{}", board);
         assert_eq!(board.get_point(p).unwrap().color, Stone::Empty);
     }
+
+    #[test]
+    fn board_text() {
+        let mut board = BoardArr::new(15);
+        board.set_point(crate::p![H, 8], Stone::Black);
+        for (point, text) in [
+            (crate::p![I, 9], "A"),
+            (crate::p![H, 8], "B"),
+            (crate::p![J, 10], "Cd"),
+        ] {
+            board.get_point_mut(point).unwrap().board_text = Some(text.to_owned());
+        }
+        board
+            .get_point_mut(crate::p![I, 9])
+            .unwrap()
+            .oneline_comment = Some("x".to_owned());
+        let text = board.to_string();
+        let rows = text.lines().collect::<Vec<_>>();
+        assert_eq!(rows[15 - 10], "10:. . . . . . . . . C . . . . . ");
+        assert_eq!(rows[15 - 9], " 9:. . . . . . . . A . . . . . . ");
+        assert_eq!(rows[15 - 8], " 8:. . . . . . . X . . . . . . . ");
+        assert_eq!(rows[16..], ["J10: Cd", "H8: B"]);
+    }
+
+    #[test]
+    fn point_at() {
+        let mut board = BoardArr::new(15);
+        board.set_point(crate::p![H, 8], Stone::Black);
+        let text = board.to_string();
+        let rows = text.lines().collect::<Vec<_>>();
+        let column = rows[15 - 8].find('X').unwrap();
+        assert_eq!(board.point_at(column, 15 - 8), Some(crate::p![H, 8]));
+        assert_eq!(board.point_at(column + 1, 15 - 8), Some(crate::p![H, 8]));
+        assert_eq!(board.point_at(3, 0), Some(crate::p![A, 15]));
+        assert_eq!(board.point_at(2, 0), None);
+        assert_eq!(board.point_at(3 + 2 * 15, 0), None);
+        assert_eq!(board.point_at(3, 15), None);
+    }
+
+    #[test]
+    fn parse_point() {
+        assert_eq!("H8".parse::<Point>().unwrap(), crate::p![H, 8]);
+        assert_eq!("a15".parse::<Point>().unwrap(), crate::p![A, 15]);
+        assert!("P1".parse::<Point>().is_err());
+        assert!("H16".parse::<Point>().is_err());
+        assert!("8H".parse::<Point>().is_err());
+    }
 }
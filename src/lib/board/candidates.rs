@@ -0,0 +1,173 @@
+//! Candidate move generation for searches.
+//!
+//! Moves far away from every stone are almost never good in renju, so only points close to
+//! existing stones are considered. Candidates are ordered with forcing moves first, which is what
+//! makes pruning in a search effective.
+
+use super::evaluator::RenjuCondition;
+use super::{BoardArr, Evaluate, Point, Stone};
+use crate::solver;
+
+use std::collections::BTreeMap;
+
+/// Move ordering scores, higher is tried first.
+mod score {
+    pub const FOUR: i32 = 1_000;
+    pub const BLOCK_FOUR: i32 = 500;
+    pub const THREE: i32 = 100;
+    pub const BLOCK_THREE: i32 = 50;
+}
+
+impl BoardArr {
+    /// Plausible moves for `stone`, best first, at most `max` of them.
+    ///
+    /// Only empty points within `radius` of a stone are generated, on an empty board the center is
+    /// the only candidate. Forbidden points are excluded for black. If `stone` can make a five only
+    /// fives are returned, and if the opponent can make a five only the blocks are returned. There
+    /// are none for an empty stone.
+    #[must_use]
+    pub fn candidate_moves(&self, stone: Stone, radius: u32, max: usize) -> Vec<Point> {
+        if stone.is_empty() {
+            return vec![];
+        }
+        let opponent = stone.opposite();
+        let size = self.size();
+
+        let fives = solver::five_points(self, stone);
+        if !fives.is_empty() {
+            return fives.into_iter().take(max).collect();
+        }
+        let forbidden = self.forbidden_points(stone);
+        let blocks = solver::five_points(self, opponent);
+        if !blocks.is_empty() {
+            return blocks
+                .into_iter()
+                .filter(|p| !forbidden.contains(p))
+                .take(max)
+                .collect();
+        }
+
+        let mut scores: BTreeMap<Point, i32> = BTreeMap::new();
+        let radius = radius as i32;
+        for marker in self.iter().filter(|m| !m.color.is_empty()) {
+            let (x, y) = (marker.point.x as i32, marker.point.y as i32);
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let (x, y) = (x + dx, y + dy);
+                    if !(0..size as i32).contains(&x) || !(0..size as i32).contains(&y) {
+                        continue;
+                    }
+                    let Some(neighbour) = self.get_xy(x as u32, y as u32) else {
+                        continue;
+                    };
+                    if neighbour.color.is_empty() && !forbidden.contains(&neighbour.point) {
+                        // closer stones count more, own stones more than the opponent's
+                        let weight = radius + 1 - dx.abs().max(dy.abs());
+                        let weight = if marker.color == stone {
+                            2 * weight
+                        } else {
+                            weight
+                        };
+                        *scores.entry(neighbour.point).or_default() += weight;
+                    }
+                }
+            }
+        }
+        if scores.is_empty() {
+            let center = Point::new(size / 2, size / 2);
+            return match self.get_point(center) {
+                Some(m) if m.color.is_empty() && max > 0 => vec![center],
+                _ => vec![],
+            };
+        }
+
+        let mut bonus = |points: Vec<Point>, bonus: i32| {
+            for point in points {
+                if let Some(score) = scores.get_mut(&point) {
+                    *score += bonus;
+                }
+            }
+        };
+        bonus(solver::four_points(self, stone), score::FOUR);
+        bonus(solver::four_points(self, opponent), score::BLOCK_FOUR);
+        let (black, white) = self.renju_conditions_both(None);
+        let three_places = |stone: Stone| {
+            let conditions = if stone.is_black() { &black } else { &white };
+            conditions
+                .conditions
+                .iter()
+                .filter(|c| {
+                    matches!(
+                        c,
+                        RenjuCondition::UnbrokenThree { .. } | RenjuCondition::BrokenThree { .. }
+                    )
+                })
+                .map(|c| *c.place())
+                .collect::<Vec<_>>()
+        };
+        bonus(three_places(stone), score::THREE);
+        bonus(three_places(opponent), score::BLOCK_THREE);
+
+        let mut candidates = scores.into_iter().collect::<Vec<_>>();
+        // stable sort, ties keep the point order
+        candidates.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+        candidates
+            .into_iter()
+            .take(max)
+            .map(|(point, _)| point)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn empty_board() {
+        let board = BoardArr::new(15);
+        assert_eq!(board.candidate_moves(Stone::Black, 2, 10), [p![H, 8]]);
+    }
+
+    #[test]
+    fn near_stones() {
+        let board = BoardArr::from_stones(15, &p![[H, 8]], &p![[I, 9]]);
+        let moves = board.candidate_moves(Stone::Black, 1, 100);
+        assert!(!moves.is_empty());
+        assert!(moves
+            .iter()
+            .all(|p| p.x.abs_diff(7) <= 2 && p.y.abs_diff(7) <= 2));
+        assert_eq!(board.candidate_moves(Stone::Black, 1, 3).len(), 3);
+    }
+
+    #[test]
+    fn forcing_moves_first() {
+        // white must block black's four, black must complete it
+        let four =
+            BoardArr::from_stones(15, &p![[D, 8], [E, 8], [F, 8], [G, 8]], &p![[C, 8], [A, 1]]);
+        assert_eq!(four.candidate_moves(Stone::Black, 2, 10), [p![H, 8]]);
+        assert_eq!(four.candidate_moves(Stone::White, 2, 10), [p![H, 8]]);
+
+        // the open three gets extended to a four first
+        let three = BoardArr::from_stones(15, &p![[E, 8], [F, 8], [G, 8]], &p![[H, 10], [A, 1]]);
+        let moves = three.candidate_moves(Stone::Black, 2, 10);
+        assert!([p![D, 8], p![H, 8]].contains(&moves[0]), "{moves:?}");
+    }
+
+    #[test]
+    fn no_forbidden_candidates() {
+        // F8 makes a double three for black
+        let board = BoardArr::from_stones(
+            15,
+            &p![[G, 8], [H, 8], [F, 9], [F, 10]],
+            &p![[A, 1], [A, 2]],
+        );
+        assert!(!board
+            .candidate_moves(Stone::Black, 2, 100)
+            .contains(&p![F, 8]));
+        assert!(board
+            .candidate_moves(Stone::White, 2, 100)
+            .contains(&p![F, 8]));
+    }
+}
@@ -0,0 +1,147 @@
+//! Two continuations of a position side by side, to decide which one to keep as the main line.
+//!
+//! [`Board::compare`] follows the main lines of two siblings for as many moves on both sides, and
+//! [`Comparison`] shows the boards at the end of them next to each other, with the moves of both
+//! lines and the comments where they differ.
+
+use std::fmt;
+
+use super::{Board, BoardArr, BoardMarker, MoveIndex};
+use crate::{theme, Error};
+
+/// The columns between the two boards.
+const GAP: usize = 4;
+
+/// Two lines from the same node, see the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    /// The number of the first move of both lines.
+    pub first: usize,
+    pub left: Vec<BoardMarker>,
+    pub right: Vec<BoardMarker>,
+    /// The boards after `left` and after `right`.
+    pub boards: (BoardArr, BoardArr),
+}
+
+impl Board {
+    /// Compare the siblings `left` and `right` and their main lines, as deep as the shorter of
+    /// them or `depth` moves.
+    pub fn compare(
+        &self,
+        left: MoveIndex,
+        right: MoveIndex,
+        depth: Option<usize>,
+    ) -> crate::Result<Comparison> {
+        if left == right || !self.get_siblings(&left).contains(&right) {
+            return Err(Error::Graph(format!(
+                "{left:?} and {right:?} are not different continuations of the same node"
+            )));
+        }
+        let line = |start: MoveIndex| {
+            let mut line = vec![start];
+            line.extend(self.main_line(start));
+            line
+        };
+        let (mut left, mut right) = (line(left), line(right));
+        let depth = left
+            .len()
+            .min(right.len())
+            .min(depth.unwrap_or(usize::MAX).max(1));
+        left.truncate(depth);
+        right.truncate(depth);
+        let (left_board, moves) = self.as_board(left.last().expect("lines are never empty"))?;
+        let (right_board, _) = self.as_board(right.last().expect("lines are never empty"))?;
+        let markers = |line: Vec<MoveIndex>| {
+            line.into_iter()
+                .filter_map(|index| self.get_move(index).cloned())
+                .collect::<Vec<_>>()
+        };
+        Ok(Comparison {
+            first: moves.len() + 1 - depth,
+            left: markers(left),
+            right: markers(right),
+            boards: (left_board, right_board),
+        })
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let last =
+            |line: &[BoardMarker]| line.last().map(|m| m.point).into_iter().collect::<Vec<_>>();
+        let left = theme::board(&self.boards.0, &last(&self.left));
+        let right = theme::board(&self.boards.1, &last(&self.right));
+        let width = left.lines().map(theme::width).max().unwrap_or(0) + GAP;
+        let mut rows = (left.lines(), right.lines());
+        loop {
+            match (rows.0.next(), rows.1.next()) {
+                (None, None) => break,
+                (l, r) => {
+                    let l = l.unwrap_or("");
+                    let pad = width - theme::width(l);
+                    writeln!(f, "{l}{:pad$}{}", "", r.unwrap_or("").trim_end())?
+                }
+            }
+        }
+        for (i, (l, r)) in self.left.iter().zip(&self.right).enumerate() {
+            let number = self.first + i;
+            writeln!(
+                f,
+                "{:width$}{number}. {}",
+                format!("{number}. {}", l.point),
+                r.point
+            )?;
+            let comment = |m: &BoardMarker| {
+                [&m.oneline_comment, &m.multiline_comment]
+                    .into_iter()
+                    .flatten()
+                    .map(|c| c.trim().to_owned())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let (l, r) = (comment(l), comment(r));
+            if l != r {
+                writeln!(f, "{l:width$}{r}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn siblings() {
+        let mut graph = Board::new();
+        let j10 = graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        let g7 = graph.add_line(&p![[H, 8], [I, 9], [G, 7]]);
+        let j10 = graph.down_to_root(&j10)[1];
+        graph.get_move_mut(g7).unwrap().oneline_comment = Some("solid".to_owned());
+
+        let comparison = graph.compare(j10, g7, None).unwrap();
+        assert_eq!(comparison.first, 3);
+        assert_eq!(comparison.left.len(), 1);
+        assert_eq!(comparison.left[0].point, p![J, 10]);
+        assert_eq!(
+            comparison.boards.1.get_point(p![G, 7]).unwrap().color,
+            Stone::Black
+        );
+        let text = comparison.to_string();
+        let rows = text.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 16 + 2, "{text}");
+        assert!(rows[0].starts_with("15:") && rows[0].contains("    15:"));
+        assert!(rows[16].starts_with("3. J10") && rows[16].ends_with("3. G7"));
+        assert!(rows[17].ends_with("solid"));
+
+        let deeper = graph.compare(j10, g7, Some(5)).unwrap();
+        assert_eq!(deeper.right.len(), 1);
+        assert!(graph.compare(j10, j10, None).is_err());
+        assert!(graph
+            .compare(j10, graph.down_to_root(&g7)[1], None)
+            .is_err());
+    }
+}
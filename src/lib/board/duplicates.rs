@@ -0,0 +1,482 @@
+//! Branches of a library reaching the same position, possibly on a rotated or mirrored board.
+//!
+//! [`Board::find_duplicates`] reports them, [`Board::merge_duplicates`] moves the continuations of
+//! every duplicate to the branch seen first. [`Board::positions`] lists every position once, with
+//! all nodes reaching it.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{
+    Board, BoardArr, BoardMarker, BoardRead, MoveIndex, NodeIndex, Point, Stone, Transformation,
+};
+
+/// A position reached in two places of a [`Board`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Duplicate {
+    /// Where the position is reached first, walking the tree depth first and the oldest branch
+    /// first.
+    pub original: MoveIndex,
+    pub duplicate: MoveIndex,
+    /// Maps the points of `duplicate` onto the points of `original`.
+    pub transformation: Transformation,
+}
+
+/// The stones of a position, sorted.
+type Stones = Vec<(Point, Stone)>;
+
+fn transformed(stones: &[(Point, Stone)], transformation: Transformation) -> Stones {
+    let mut stones = stones
+        .iter()
+        .map(|(point, stone)| (transformation.apply(*point), *stone))
+        .collect::<Vec<_>>();
+    stones.sort();
+    stones
+}
+
+/// A key of the position on `board`, the same for every rotation and mirroring of it.
+#[must_use]
+pub fn position_key(board: &BoardArr) -> Vec<(Point, Stone)> {
+    canonical(&BoardRead::stones(board).collect::<Vec<_>>())
+}
+
+/// The smallest of the transformations of `stones`, the same for every symmetric position.
+fn canonical(stones: &[(Point, Stone)]) -> Stones {
+    Transformation::types()
+        .into_iter()
+        .map(|transformation| transformed(stones, transformation))
+        .min()
+        .expect("there are transformations")
+}
+
+impl Board {
+    /// Find every position reached through more than one branch.
+    ///
+    /// Only the first node of a duplicated branch is reported, the positions after it are
+    /// duplicates as well as long as the branches follow each other.
+    #[must_use]
+    pub fn find_duplicates(&self) -> Vec<Duplicate> {
+        let mut duplicates = vec![];
+        // the canonical form of every position seen, with its node and the stones as played
+        let mut seen: BTreeMap<Stones, (MoveIndex, Stones)> = BTreeMap::new();
+        let mut visited = HashSet::new();
+        let mut stack = self
+            .roots()
+            .iter()
+            .rev()
+            .map(|root| (*root, vec![]))
+            .collect::<Vec<_>>();
+        while let Some((index, mut stones)) = stack.pop() {
+            if !visited.insert(index.node_index) {
+                continue;
+            }
+            let Some(marker) = self.get_move(index) else {
+                continue;
+            };
+            if !marker.color.is_empty() && !marker.point.is_null {
+                stones.push((marker.point, marker.color));
+                stones.sort();
+                let canonical = canonical(&stones);
+                if let Some((original, original_stones)) = seen.get(&canonical) {
+                    let transformation = Transformation::types()
+                        .into_iter()
+                        .find(|t| transformed(&stones, *t) == *original_stones)
+                        .expect("the canonical forms are equal");
+                    tracing::debug!(?original, duplicate = ?index, ?transformation, "found duplicate");
+                    duplicates.push(Duplicate {
+                        original: *original,
+                        duplicate: index,
+                        transformation,
+                    });
+                    continue;
+                }
+                seen.insert(canonical, (index, stones.clone()));
+            }
+            // children are listed newest first, visit the oldest first
+            for child in self.get_children(&index) {
+                stack.push((child, stones.clone()));
+            }
+        }
+        duplicates
+    }
+
+    /// Every position at most `max_depth` moves from the root, with the nodes reaching it.
+    ///
+    /// Positions that are the same on a rotated or mirrored board are only listed once, as the
+    /// board of the node reaching it first. Passes reach the same position as their parent. The
+    /// board is updated move by move while walking, and only copied for new positions.
+    pub fn positions(&self, max_depth: usize) -> impl Iterator<Item = (BoardArr, Vec<MoveIndex>)> {
+        let mut walk = Positions {
+            graph: self,
+            max_depth,
+            board: BoardArr::new(15),
+            stones: vec![],
+            visited: HashSet::new(),
+            seen: BTreeMap::new(),
+            positions: vec![],
+        };
+        for root in self.roots() {
+            walk.visit(*root, 0);
+        }
+        walk.positions.into_iter()
+    }
+
+    /// A copy of the board where the continuations of every duplicate are moved to its original.
+    ///
+    /// The duplicates are kept, without any continuations, so that every move order is still in
+    /// the library. Continuations already in the original are merged, keeping the comments of the
+    /// original. Bookmarks, tags and color overrides are moved along, and the logged edits of moved nodes follow
+    /// them so the author and date of a continuation are kept. The move list of the copy only
+    /// holds the first root.
+    #[must_use]
+    pub fn merge_duplicates(&self, duplicates: &[Duplicate]) -> Board {
+        let mut merged = Board::new();
+        *merged.root_marker_mut() = self.root_marker().clone();
+        let mut merge = Merge {
+            from: self,
+            to: merged,
+            cut: duplicates.iter().map(|d| d.duplicate.node_index).collect(),
+            extra: BTreeMap::new(),
+            copied: HashMap::new(),
+            copies: HashMap::new(),
+        };
+        for duplicate in duplicates {
+            merge
+                .extra
+                .entry(duplicate.original.node_index)
+                .or_default()
+                .push((duplicate.duplicate, duplicate.transformation));
+        }
+        for (i, root) in self.roots().iter().enumerate() {
+            let copy = match i {
+                0 => merge.to.get_root(),
+                _ => {
+                    let marker = self
+                        .get_move(*root)
+                        .cloned()
+                        .unwrap_or_else(BoardMarker::null);
+                    merge.to.add_root(marker)
+                }
+            };
+            merge.copies.insert(root.node_index, copy);
+            merge.copy_children(*root, copy, &[]);
+        }
+        for (name, index) in self.bookmarks() {
+            if let Some(copy) = merge.copies.get(&index.node_index) {
+                merge.to.set_bookmark(name.clone(), *copy);
+            }
+        }
+        for (index, color) in self.color_overrides() {
+            if let Some(copy) = merge.copies.get(&index.node_index) {
+                merge.to.set_color_override(*copy, Some(color));
+            }
+        }
+        for (tag, nodes) in self.tags() {
+            for index in nodes {
+                if let Some(copy) = merge.copies.get(&index.node_index) {
+                    merge.to.tag(*copy, tag.clone());
+                }
+            }
+        }
+        // the log refers to nodes by their moves, which change for the moved continuations
+        let paths = merge
+            .copies
+            .iter()
+            .map(|(node, copy)| {
+                let path = self.describe_path(MoveIndex::new_node(*node));
+                (path, merge.to.describe_path(*copy))
+            })
+            .collect::<HashMap<_, _>>();
+        for edit in self.history() {
+            let mut edit = edit.clone();
+            if let Some(path) = paths.get(&edit.path) {
+                edit.path.clone_from(path);
+            }
+            merge.to.push_history(edit);
+        }
+        merge.to.record_history(self.author.clone());
+        merge.to
+    }
+}
+
+struct Positions<'a> {
+    graph: &'a Board,
+    max_depth: usize,
+    /// The position of the node visited.
+    board: BoardArr,
+    stones: Stones,
+    visited: HashSet<NodeIndex>,
+    /// The index in `positions` of every canonical position.
+    seen: BTreeMap<Stones, usize>,
+    positions: Vec<(BoardArr, Vec<MoveIndex>)>,
+}
+
+impl Positions<'_> {
+    fn visit(&mut self, index: MoveIndex, depth: usize) {
+        if !self.visited.insert(index.node_index) {
+            return;
+        }
+        let Some(marker) = self.graph.get_move(index) else {
+            return;
+        };
+        let placed = (!marker.color.is_empty() && !marker.point.is_null)
+            .then_some((marker.point, marker.color));
+        if let Some((point, stone)) = placed {
+            self.board.set_point(point, stone);
+            self.stones.push((point, stone));
+        }
+        let canonical = canonical(&self.stones);
+        match self.seen.get(&canonical) {
+            Some(&i) => self.positions[i].1.push(index),
+            None => {
+                self.seen.insert(canonical, self.positions.len());
+                self.positions.push((self.board.clone(), vec![index]));
+            }
+        }
+        if depth < self.max_depth {
+            for child in self.graph.get_children(&index).into_iter().rev() {
+                self.visit(child, depth + 1);
+            }
+        }
+        if let Some((point, _)) = placed {
+            self.board.set_point(point, Stone::Empty);
+            self.stones.pop();
+        }
+    }
+}
+
+struct Merge<'a> {
+    from: &'a Board,
+    to: Board,
+    /// Duplicates, copied without their continuations.
+    cut: HashSet<NodeIndex>,
+    /// Continuations of duplicates to add to their originals.
+    extra: BTreeMap<NodeIndex, Vec<(MoveIndex, Transformation)>>,
+    /// Nodes copied from `from` without transformation, to keep nodes with several parents shared.
+    copied: HashMap<NodeIndex, MoveIndex>,
+    /// The first copy of every node, to move the bookmarks, tags, color overrides and log.
+    copies: HashMap<NodeIndex, MoveIndex>,
+}
+
+impl Merge<'_> {
+    /// Copy the continuations of `from` in the source to `to`, applying `transformations` in order
+    /// to every point.
+    fn copy_children(
+        &mut self,
+        from: MoveIndex,
+        to: MoveIndex,
+        transformations: &[Transformation],
+    ) {
+        // oldest first, to keep the order of the children
+        for child in self.from.get_children(&from).into_iter().rev() {
+            let Some(marker) = self.from.get_move(child) else {
+                continue;
+            };
+            if transformations.is_empty() {
+                if let Some(&copy) = self.copied.get(&child.node_index) {
+                    if let Err(e) = self.to.add_edge(&to, &copy) {
+                        tracing::warn!(?e, "could not share node");
+                    }
+                    continue;
+                }
+            }
+            let mut marker = marker.clone();
+            if !marker.point.is_null {
+                marker.point = transformations
+                    .iter()
+                    .fold(marker.point, |point, t| t.apply(point));
+            }
+            let copy = find_or_insert(&mut self.to, to, marker);
+            if transformations.is_empty() {
+                self.copied.insert(child.node_index, copy);
+            }
+            self.copies.entry(child.node_index).or_insert(copy);
+            if self.cut.contains(&child.node_index) {
+                continue;
+            }
+            self.copy_children(child, copy, transformations);
+            for (duplicate, transformation) in self
+                .extra
+                .get(&child.node_index)
+                .cloned()
+                .unwrap_or_default()
+            {
+                // onto the original first, then as the original is copied
+                let mut all = vec![transformation];
+                all.extend_from_slice(transformations);
+                self.copy_children(duplicate, copy, &all);
+            }
+        }
+    }
+}
+
+/// The child of `parent` with the point and color of `marker`, added if missing.
+fn find_or_insert(board: &mut Board, parent: MoveIndex, marker: BoardMarker) -> MoveIndex {
+    let existing = board.get_children(&parent).into_iter().find(|child| {
+        board
+            .get_move(*child)
+            .is_some_and(|m| m.point == marker.point && m.color == marker.color)
+    });
+    let Some(existing) = existing else {
+        return board.insert_move(parent, marker);
+    };
+    if let Some(m) = board.get_move_mut(existing) {
+        if m.oneline_comment.is_none() {
+            m.oneline_comment = marker.oneline_comment;
+        }
+        if m.multiline_comment.is_none() {
+            m.multiline_comment = marker.multiline_comment;
+        }
+    }
+    existing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::history, p};
+
+    fn add_line(board: &mut Board, points: &[Point]) -> Vec<MoveIndex> {
+        let mut index = board.get_root();
+        let mut stone = Stone::White;
+        points
+            .iter()
+            .map(|point| {
+                stone = stone.opposite();
+                let marker = BoardMarker::new(*point, stone);
+                index = find_or_insert(board, index, marker);
+                index
+            })
+            .collect()
+    }
+
+    fn find(board: &Board, points: &[Point]) -> Option<MoveIndex> {
+        points.iter().try_fold(board.get_root(), |index, point| {
+            board
+                .get_children(&index)
+                .into_iter()
+                .find(|child| board.get_move(*child).unwrap().point == *point)
+        })
+    }
+
+    #[test]
+    fn duplicates() {
+        let mut board = Board::new();
+        let a = add_line(&mut board, &p![[H, 8], [I, 9], [G, 7], [J, 8], [K, 8]]);
+        // the same position in another order
+        let b = add_line(&mut board, &p![[H, 8], [J, 8], [G, 7], [I, 9], [L, 8]]);
+        // the first line mirrored
+        let c = add_line(&mut board, &p![[H, 8], [G, 9], [I, 7], [F, 8], [M, 8]]);
+
+        let duplicates = board.find_duplicates();
+        assert_eq!(duplicates.len(), 2, "{duplicates:?}");
+        assert_eq!(duplicates[0].original, a[3]);
+        assert_eq!(duplicates[0].duplicate, b[3]);
+        assert_eq!(duplicates[0].transformation, Transformation::identity());
+        assert_eq!(duplicates[1].original, a[1]);
+        assert_eq!(duplicates[1].duplicate, c[1]);
+        let mirror = duplicates[1].transformation;
+        assert_ne!(mirror, Transformation::identity());
+
+        board.set_bookmark("Mirrored", c[4]);
+        let merged = board.merge_duplicates(&duplicates);
+        let bookmark = merged.get_bookmark("Mirrored").unwrap();
+        assert_eq!(
+            merged.get_move(bookmark).unwrap().point,
+            mirror.apply(p![M, 8])
+        );
+        let j8 = find(&merged, &p![[H, 8], [I, 9], [G, 7], [J, 8]]).unwrap();
+        let mut children = merged
+            .get_children(&j8)
+            .into_iter()
+            .map(|child| merged.get_move(child).unwrap().point)
+            .collect::<Vec<_>>();
+        children.sort();
+        let mut expected = vec![p![K, 8], p![L, 8], mirror.apply(p![M, 8])];
+        expected.sort();
+        assert_eq!(children, expected);
+        // the duplicates are kept without continuations
+        let b = find(&merged, &p![[H, 8], [J, 8], [G, 7], [I, 9]]).unwrap();
+        assert!(merged.get_children(&b).is_empty());
+        let c = find(&merged, &p![[H, 8], [G, 9]]).unwrap();
+        assert!(merged.get_children(&c).is_empty());
+        assert_eq!(merged.find_duplicates().len(), 2);
+    }
+
+    #[test]
+    fn merge_tags_and_history() {
+        let mut board = Board::new();
+        add_line(&mut board, &p![[H, 8], [I, 9], [G, 7], [J, 8], [K, 8]]);
+        board.record_history(Some("alice".to_owned()));
+        let c = add_line(&mut board, &p![[H, 8], [G, 9], [I, 7], [F, 8], [M, 8]]);
+        board.tag(c[1], "mirrored");
+        board.tag(c[4], "mirrored");
+        board.tag(c[4], "tournament");
+        board.set_color_override(c[4], Some(Stone::White));
+
+        let duplicates = board.find_duplicates();
+        let mirror = duplicates[0].transformation;
+        let merged = board.merge_duplicates(&duplicates);
+        let m8 = find(
+            &merged,
+            &[
+                p![H, 8],
+                p![I, 9],
+                p![G, 7],
+                p![J, 8],
+                mirror.apply(p![M, 8]),
+            ],
+        )
+        .unwrap();
+        let g9 = find(&merged, &p![[H, 8], [G, 9]]).unwrap();
+        assert_eq!(merged.tagged("mirrored"), [g9, m8]);
+        assert_eq!(merged.tagged("tournament"), [m8]);
+        assert_eq!(merged.color_at(m8), Some(Stone::White));
+
+        // M8 is the last move added
+        let added = merged
+            .history()
+            .iter()
+            .rfind(|edit| edit.action == history::Action::Add)
+            .unwrap();
+        assert_eq!(added.author, "alice");
+        assert_eq!(added.path, merged.describe_path(m8));
+        assert_eq!(merged.history().len(), board.history().len());
+    }
+
+    #[test]
+    fn unique_positions() {
+        let mut board = Board::new();
+        let a = add_line(&mut board, &p![[H, 8], [I, 9], [G, 7], [J, 8], [K, 8]]);
+        let b = add_line(&mut board, &p![[H, 8], [J, 8], [G, 7], [I, 9], [L, 8]]);
+        let c = add_line(&mut board, &p![[H, 8], [G, 9], [I, 7], [F, 8], [M, 8]]);
+
+        // the empty board, H8, H8 I9 twice and H8 J8
+        let positions = board.positions(2).collect::<Vec<_>>();
+        assert_eq!(positions.len(), 4);
+        assert_eq!(positions[0].1, [board.get_root()]);
+        assert_eq!(positions[2].1, [a[1], c[1]]);
+        assert_eq!(
+            positions[2].0.get_point(p![I, 9]).unwrap().color,
+            Stone::White
+        );
+
+        // a second tree reaching H8 again
+        let root = board.add_root(BoardMarker::null());
+        let h8 = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let positions = board.positions(1).collect::<Vec<_>>();
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].1, [board.get_root(), root]);
+        assert_eq!(positions[1].1, [a[0], h8]);
+
+        let positions = board.positions(5).collect::<Vec<_>>();
+        let (position, indexes) = positions
+            .iter()
+            .find(|(_, indexes)| indexes.len() == 3)
+            .unwrap();
+        assert_eq!(indexes, &[a[3], b[3], c[3]]);
+        assert_eq!(position.get_point(p![J, 8]).unwrap().color, Stone::White);
+        // past H8 the three lines only meet after two and four moves
+        assert_eq!(positions.len(), 10);
+        assert_eq!(positions.iter().filter(|(_, i)| i.len() == 1).count(), 5);
+    }
+}
@@ -12,8 +12,10 @@
 //! # Implementation.
 //!
 
-use super::{BoardArr, Point, Stone};
+use super::{BoardArr, BoardWrite, Point, Stone};
+use crate::limits::{Budget, Exhausted, Limits, Outcome};
 
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet};
 
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -162,66 +164,243 @@ impl RenjuCondition {
     }
 }
 
+/// Why a point is forbidden for black.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+pub enum ForbiddenReason {
+    /// Six or more in a row.
+    Overline,
+    /// Two fours at once.
+    DoubleFour,
+    /// Two open threes at once.
+    DoubleThree,
+}
+
+impl ForbiddenReason {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ForbiddenReason::Overline => "overline",
+            ForbiddenReason::DoubleFour => "double-four",
+            ForbiddenReason::DoubleThree => "double-three",
+        }
+    }
+}
+
+impl std::fmt::Display for ForbiddenReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Default)]
 pub struct RenjuConditions {
     pub conditions: BTreeSet<RenjuCondition>,
     pub forbidden: BTreeSet<Point>,
+    /// The reason for every point in `forbidden`. A point forbidden for several reasons gets the
+    /// first of overline, double-four and double-three.
+    pub reasons: BTreeMap<Point, ForbiddenReason>,
     pub threes: BTreeSet<(RenjuCondition, Point)>,
 }
 
-impl BoardArr {
+impl RenjuConditions {
+    /// Why `point` is forbidden, `None` if it isn't.
+    #[must_use]
+    pub fn reason(&self, point: Point) -> Option<ForbiddenReason> {
+        self.reasons.get(&point).copied()
+    }
+}
+
+/// A point of a line, seen from one color.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum S {
+    Same,
+    NotSame,
+    Empty,
+    /// A border point, which is not part of the board.
+    Border,
+}
+
+/// A line padded with two [`S::Border`] on each side.
+pub(crate) type PaddedLine<'a> = (Direction, Vec<(S, &'a Point)>);
+
+static NULL_POINT: Point = Point {
+    x: 0,
+    y: 0,
+    is_null: true,
+};
+
+/// The evaluator, for every [board backend](super::backend).
+pub trait Evaluate: BoardWrite + Clone {
+    /// The points forbidden for `stone`, always empty for white. Backends can cache them, like
+    /// [`BoardArr::forbidden_points`].
+    fn forbidden(&self, stone: Stone) -> Cow<'_, BTreeSet<Point>> {
+        if stone.is_black() {
+            Cow::Owned(self.renju_conditions(Stone::Black, None).forbidden)
+        } else {
+            Cow::Owned(BTreeSet::new())
+        }
+    }
+
     /// A condition is a place where a stone could be placed to create a certain condition.
-    #[tracing::instrument(skip(self, stone, only_including))]
-    pub fn renju_conditions(
+    fn renju_conditions(&self, stone: Stone, only_including: Option<&[Point]>) -> RenjuConditions {
+        self.conditions_with_budget(stone, only_including, &mut Budget::unlimited(), 0)
+            .expect("unlimited budget")
+    }
+
+    /// Like [`Self::renju_conditions`], but gives up when `limits` is reached. Every double-three
+    /// check counts as a node, `limits.depth` is how deeply they may be nested.
+    fn renju_conditions_with_limits(
         &self,
         stone: Stone,
         only_including: Option<&[Point]>,
-    ) -> RenjuConditions {
-        static NULL_POINT: Point = Point {
-            x: 0,
-            y: 0,
-            is_null: true,
+        limits: &Limits,
+    ) -> Outcome<RenjuConditions> {
+        match self.conditions_with_budget(stone, only_including, &mut Budget::new(limits), 0) {
+            Ok(conditions) => Outcome::Known(conditions),
+            Err(Exhausted) => Outcome::Unknown,
+        }
+    }
+
+    /// Conditions for black and for white, in that order.
+    ///
+    /// The lines of the board are read once for both colors, which is cheaper than calling
+    /// [`Self::renju_conditions`] twice.
+    fn renju_conditions_both(
+        &self,
+        only_including: Option<&[Point]>,
+    ) -> (RenjuConditions, RenjuConditions) {
+        let phase = tracing::debug_span!("lines").entered();
+        let [black, white] = self.padded_lines([Stone::Black, Stone::White]);
+        drop(phase);
+        let conditions = |lines, stone| {
+            self.conditions_from_lines(lines, stone, only_including, &mut Budget::unlimited(), 0)
+                .expect("unlimited budget")
         };
-        use S::*;
-        #[derive(Debug, Clone, Copy)]
-        pub enum S {
-            Same,
-            NotSame,
-            Empty,
-            /// A border point, which is not part of the board.
-            Border,
+        (
+            conditions(&black, Stone::Black),
+            conditions(&white, Stone::White),
+        )
+    }
+
+    /// Get the positions of a line on a board. First `usize` is the index of the point itself in the iterator.
+    ///
+    /// `None` if `point` is not on the board.
+    fn get_line(
+        &self,
+        direction: Direction,
+        point: &Point,
+    ) -> Option<(usize, impl Iterator<Item = Point> + '_)> {
+        let table = self.table();
+        let slot = Direction::directions()
+            .iter()
+            .position(|d| *d == direction)
+            .expect("all directions are listed");
+        let (line, idx) = table.through(*point)?[slot];
+        Some((idx, table.line(line).1.iter().copied()))
+    }
+}
+
+impl Evaluate for BoardArr {
+    fn forbidden(&self, stone: Stone) -> Cow<'_, BTreeSet<Point>> {
+        Cow::Borrowed(self.forbidden_points(stone))
+    }
+}
+
+/// The evaluator, for every board that implements [`Evaluate`].
+pub(crate) trait Conditions: Evaluate {
+    fn padded_lines<const N: usize>(&self, stones: [Stone; N]) -> [Vec<PaddedLine<'_>>; N];
+
+    fn conditions_with_budget(
+        &self,
+        stone: Stone,
+        only_including: Option<&[Point]>,
+        budget: &mut Budget,
+        depth: u32,
+    ) -> Result<RenjuConditions, Exhausted>;
+
+    fn conditions_from_lines(
+        &self,
+        lines: &[PaddedLine<'_>],
+        stone: Stone,
+        only_including: Option<&[Point]>,
+        budget: &mut Budget,
+        depth: u32,
+    ) -> Result<RenjuConditions, Exhausted>;
+}
+
+impl<B: Evaluate> Conditions for B {
+    /// The lines long enough for a five, seen from every color in `stones`.
+    fn padded_lines<const N: usize>(&self, stones: [Stone; N]) -> [Vec<PaddedLine<'_>>; N] {
+        let mut out = stones.map(|_| vec![]);
+        let table = self.table();
+        // a five never fits in shorter lines
+        for id in (0..table.len()).filter(|&id| table.line(id).1.len() >= 5) {
+            let (direction, points) = table.line(id);
+            let mut padded = stones.map(|_| {
+                let mut cells = Vec::with_capacity(points.len() + 4);
+                cells.extend([(S::Border, &NULL_POINT); 2]);
+                cells
+            });
+            for (index, p) in points.iter().enumerate() {
+                let s = self.line_stone(id, index, *p);
+                for (cells, stone) in padded.iter_mut().zip(stones) {
+                    let cell = if s.is_empty() {
+                        S::Empty
+                    } else if s == stone {
+                        S::Same
+                    } else {
+                        S::NotSame
+                    };
+                    cells.push((cell, p));
+                }
+            }
+            for (out, mut cells) in out.iter_mut().zip(padded) {
+                cells.extend([(S::Border, &NULL_POINT); 2]);
+                out.push((direction, cells));
+            }
         }
-        assert!(!stone.is_empty());
-        let lines = self
-            .all_lines()
-            .map(|(d, i)| {
-                (
-                    d,
-                    std::iter::once([(Border, &NULL_POINT); 2])
-                        .flatten()
-                        .chain(i.map(|s| {
-                            let s = self.get_xy(s.x, s.y).expect("should be populated");
-                            if s.color.is_empty() {
-                                (Empty, &s.point)
-                            } else if s.color == stone {
-                                (Same, &s.point)
-                            } else {
-                                (NotSame, &s.point)
-                            }
-                        }))
-                        .chain(std::iter::once([(Border, &NULL_POINT); 2]).flatten())
-                        .collect::<Vec<_>>(),
-                )
-            })
-            .collect::<Vec<(Direction, Vec<_>)>>();
+        out
+    }
+
+    #[tracing::instrument(skip(self, stone, only_including, budget))]
+    fn conditions_with_budget(
+        &self,
+        stone: Stone,
+        only_including: Option<&[Point]>,
+        budget: &mut Budget,
+        depth: u32,
+    ) -> Result<RenjuConditions, Exhausted> {
+        if stone.is_empty() {
+            return Ok(RenjuConditions::default());
+        }
+        // phases are timed by `crate::timing`
+        let phase = tracing::debug_span!("lines").entered();
+        let [lines] = self.padded_lines([stone]);
+        drop(phase);
+        self.conditions_from_lines(&lines, stone, only_including, budget, depth)
+    }
+
+    fn conditions_from_lines(
+        &self,
+        lines: &[PaddedLine<'_>],
+        stone: Stone,
+        only_including: Option<&[Point]>,
+        budget: &mut Budget,
+        depth: u32,
+    ) -> Result<RenjuConditions, Exhausted> {
+        budget.spend()?;
+        budget.check_depth(depth)?;
+        use S::*;
         let mut conditions = BTreeSet::new();
         let mut forbidden = BTreeSet::new();
+        let mut reasons = BTreeMap::new();
 
         let mut fives = BTreeSet::new();
 
-        tracing::debug!("checking fives");
-        for (dir, stone_line) in &lines {
+        let phase = tracing::debug_span!("fives").entered();
+        for (dir, stone_line) in lines {
             for line in stone_line.windows(7) {
                 // if let Some(only) = only_including {
                 //     if !line.iter().any(|(_, p)| only.contains(p)) {
@@ -263,9 +442,10 @@ impl BoardArr {
         }
 
         // First check for overlines.
-        tracing::debug!("checking overlines");
+        drop(phase);
+        let phase = tracing::debug_span!("overlines").entered();
         if stone.is_black() {
-            for (_, stone_line) in &lines {
+            for (_, stone_line) in lines {
                 for line in stone_line.windows(6) {
                     // if let Some(only) = only_including {
                     //     if !line.iter().any(|(_, p)| only.contains(p)) {
@@ -297,10 +477,15 @@ impl BoardArr {
             }
         }
 
+        for point in &forbidden {
+            reasons.entry(*point).or_insert(ForbiddenReason::Overline);
+        }
+
         let mut fours = BTreeMap::new();
 
-        tracing::debug!("checking fours");
-        for (dir, stone_line) in &lines {
+        drop(phase);
+        let phase = tracing::debug_span!("fours").entered();
+        for (dir, stone_line) in lines {
             for line in stone_line.windows(7) {
                 if let Some(only) = only_including {
                     if !line.iter().any(|(_, p)| only.contains(p)) {
@@ -434,6 +619,7 @@ impl BoardArr {
         for (k, v) in fours {
             if stone.is_black() && v.len() > 1 {
                 forbidden.insert(**k);
+                reasons.entry(**k).or_insert(ForbiddenReason::DoubleFour);
             } else {
                 conditions.extend(v);
             }
@@ -442,8 +628,9 @@ impl BoardArr {
         let mut threes = BTreeMap::new();
 
         // check for open threes, threes which can become straight fours. To do this, we need to check a huge range, 8 stones to be exact.
-        tracing::debug!("checking threes");
-        for (dir, stone_line) in &lines {
+        drop(phase);
+        let phase = tracing::debug_span!("threes").entered();
+        for (dir, stone_line) in lines {
             for line in stone_line.windows(9) {
                 if let Some(only) = only_including {
                     if !line.iter().any(|(_, p)| only.contains(p)) {
@@ -643,6 +830,8 @@ impl BoardArr {
                 }
             }
         }
+        drop(phase);
+        let phase = tracing::debug_span!("double_threes").entered();
         let mut found_forbidden_threes = BTreeSet::new();
         for (k, v) in &threes {
             let span = tracing::debug_span!("3FCheck", ?k);
@@ -687,8 +876,7 @@ impl BoardArr {
                     let mut allowed_threes = v.len();
                     // Check for double-three, case b).
                     let mut new_board = self.clone();
-                    new_board.set_point(***k, stone);
-                    tracing::debug!("new board\n: {new_board}");
+                    new_board.set_stone(***k, stone);
                     tracing::debug!(stones_added = ?[&k,], "adding stones to board to check for double-three.");
                     for (_c, four_point) in v.iter() {
                         let span = tracing::debug_span!("four_point check", ?four_point,);
@@ -697,8 +885,12 @@ impl BoardArr {
 
                         // TODO
                         tracing::debug!("checking if the four_point is forbidden double-three");
-                        let new_conditions =
-                            new_board.renju_conditions(stone, Some(&[***k, **four_point]));
+                        let new_conditions = new_board.conditions_with_budget(
+                            stone,
+                            Some(&[***k, **four_point]),
+                            budget,
+                            depth + 1,
+                        )?;
 
                         tracing::debug!("checked if the four_point is forbidden double-three");
                         // Now, check condition if more than one allowed straight four can be  made
@@ -740,150 +932,25 @@ impl BoardArr {
                 conditions.extend(v.iter().map(|(c, _)| c.clone()));
             }
         }
-        forbidden.extend(found_forbidden_threes.clone());
-
-        if stone.is_white() {
-            assert!(forbidden.is_empty());
+        for point in &found_forbidden_threes {
+            reasons
+                .entry(**point)
+                .or_insert(ForbiddenReason::DoubleThree);
         }
+        forbidden.extend(found_forbidden_threes.clone());
+        drop(phase);
 
-        if stone.is_white() {
-            assert!(forbidden.is_empty());
-        }
+        debug_assert!(stone.is_black() || forbidden.is_empty());
 
-        RenjuConditions {
+        Ok(RenjuConditions {
             conditions,
             forbidden,
+            reasons,
             threes: threes
                 .into_iter()
                 .flat_map(|(_k, v)| v.into_iter().map(|(c, p)| (c, *p)))
                 .collect(),
-        }
-    }
-
-    fn all_lines(&self) -> impl Iterator<Item = (Direction, impl Iterator<Item = Point>)> + '_ {
-        let size = self.size();
-        std::iter::empty()
-            .chain(
-                // Horizontal
-                (0..size).map(move |y| {
-                    (
-                        Direction::Horizontal,
-                        self.get_line(Direction::Horizontal, &Point::new(0, y)).1,
-                    )
-                }),
-            )
-            .chain(
-                // vertical
-                (0..size).map(move |x| {
-                    (
-                        Direction::Vertical,
-                        self.get_line(Direction::Vertical, &Point::new(x, 0)).1,
-                    )
-                }),
-            )
-            .chain(
-                // Diagonal /
-
-                // walk across in \
-                (0..size).flat_map(move |i| {
-                    [
-                        (
-                            Direction::Diagonal { bottom: true },
-                            self.get_line(Direction::Diagonal { bottom: true }, &Point::new(0, i))
-                                .1,
-                        ),
-                        (
-                            Direction::Diagonal { bottom: true },
-                            self.get_line(
-                                Direction::Diagonal { bottom: true },
-                                &Point::new(size, i),
-                            )
-                            .1,
-                        ),
-                    ]
-                }),
-            )
-            .chain(
-                // Diagonal \
-                (0..size).flat_map(move |i| {
-                    [
-                        (
-                            Direction::Diagonal { bottom: false },
-                            self.get_line(
-                                Direction::Diagonal { bottom: false },
-                                &Point::new(0, size - 1 - i),
-                            )
-                            .1,
-                        ),
-                        (
-                            Direction::Diagonal { bottom: false },
-                            self.get_line(
-                                Direction::Diagonal { bottom: false },
-                                &Point::new(size, size - 1 - i),
-                            )
-                            .1,
-                        ),
-                    ]
-                }),
-            )
-    }
-
-    /// Get the positions of a line on a board. First `usize` is the index of the point itself in the iterator.
-    fn get_line(
-        &self,
-        direction: Direction,
-        point: &Point,
-    ) -> (usize, impl Iterator<Item = Point>) {
-        // idx is the index of the point itself in the iterator
-        let idx;
-        // The first point
-        let start = match direction {
-            Direction::Horizontal => {
-                idx = point.x;
-                // on horizontal -, we need the leftmost point on this row
-                Point::new(0, point.y)
-            }
-            Direction::Vertical => {
-                idx = point.y;
-                // on vertical |, we need the topmost point on this column
-                Point::new(point.x, 0)
-            }
-            Direction::Diagonal { bottom: true } => {
-                // on diagonal /, we need the diagonal bottom leftmost point
-                let steps = std::cmp::min(point.x, self.size() - 1 - point.y);
-                idx = steps;
-                Point::new(point.x - steps, point.y + steps)
-            }
-            Direction::Diagonal { bottom: false } => {
-                // on diagonal \, we need the diagonal top leftmost point
-                let steps = std::cmp::min(point.x, point.y);
-                idx = steps;
-                Point::new(point.x - steps, point.y - steps)
-            }
-        };
-        let mut count = 0;
-        (
-            idx as usize,
-            std::iter::from_fn(move || {
-                let next = match direction {
-                    Direction::Horizontal => Point::new(start.x + count, start.y),
-                    Direction::Vertical => Point::new(start.x, start.y + count),
-                    Direction::Diagonal { bottom: true } => {
-                        Point::new(start.x + count, start.y.checked_sub(count)?)
-                    }
-                    Direction::Diagonal { bottom: false } => {
-                        Point::new(start.x + count, start.y + count)
-                    }
-                };
-                count += 1;
-                if next.is_valid() {
-                    Some(next)
-                } else {
-                    None
-                }
-            })
-            .fuse(),
-        )
+        })
     }
 }
 
@@ -1168,42 +1235,42 @@ mod tests {
         let p = p![H, 8];
 
         // Horizontal
-        let line = board.get_line(Direction::Horizontal, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Horizontal, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A,  8], [B,  8], [C,  8], [D,  8], [E,  8], [F,  8], [G,  8], [H,  8], [I,  8], [J,  8], [K,  8], [L,  8], [M,  8], [N,  8], [O,  8]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Horizontal, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Horizontal, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
         // Vertical
-        let line = board.get_line(Direction::Vertical, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Vertical, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[H, 15], [H, 14], [H, 13], [H, 12], [H, 11], [H, 10], [H,  9], [H,  8], [H,  7], [H,  6], [H,  5], [H,  4], [H,  3], [H,  2], [H,  1]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Vertical, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Vertical, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
         // Diagonal /
-        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A,  1], [B,  2], [C,  3], [D,  4], [E,  5], [F,  6], [G,  7], [H,  8], [I,  9], [J, 10], [K, 11], [L, 12], [M, 13], [N, 14], [O, 15]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Diagonal { bottom: true }, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Diagonal { bottom: true }, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
         // Diagonal \
-        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 15], [B, 14], [C, 13], [D, 12], [E, 11], [F, 10], [G,  9], [H,  8], [I,  7], [J,  6], [K,  5], [L,  4], [M,  3], [N,  2], [O,  1]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Diagonal { bottom: false }, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Diagonal { bottom: false }, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
@@ -1211,43 +1278,43 @@ mod tests {
         let p = p![G, 8];
 
         // Diagonal /
-        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A,  2], [B,  3], [C,  4], [D,  5], [E,  6], [F,  7], [G,  8], [H,  9], [I, 10], [J, 11], [K, 12], [L, 13], [M, 14], [N, 15]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Diagonal { bottom: true }, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Diagonal { bottom: true }, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
         // Diagonal \
-        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 14], [B, 13], [C, 12], [D, 11], [E, 10], [F,  9], [G,  8], [H,  7], [I,  6], [J,  5], [K,  4], [L,  3], [M,  2], [N,  1]];
         tracing::info!("{actual:?}");
         assert_eq!(line, actual);
         for p in &line {
-            let line = board.get_line(Direction::Diagonal { bottom: false }, p).1.collect::<Vec<_>>();
+            let line = board.get_line(Direction::Diagonal { bottom: false }, p).unwrap().1.collect::<Vec<_>>();
             assert_eq!(line, actual);
         }
 
         // special diagonals
         let p = p![A, 15];
-        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 15]];
         assert_eq!(line, actual);
 
         let p = p![A, 14];
-        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: true }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 14], [B, 15]];
         assert_eq!(line, actual);
 
         let p = p![A, 1];
-        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 1]];
         assert_eq!(line, actual);
 
         let p = p![A, 2];
-        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).1.collect::<Vec<_>>();
+        let line = board.get_line(Direction::Diagonal { bottom: false }, &p).unwrap().1.collect::<Vec<_>>();
         let actual = p![[A, 2], [B, 1]];
         assert_eq!(line, actual);
     }
@@ -1257,8 +1324,11 @@ mod tests {
         let board = BoardArr::new(15);
         let mut all_lines = BTreeMap::new();
 
-        for (dir, iter) in board.all_lines() {
-            all_lines.entry(dir).or_insert(vec![]).extend(iter);
+        for line in board.lines().iter() {
+            all_lines
+                .entry(line.direction)
+                .or_insert(vec![])
+                .extend(line.points.iter().copied());
         }
         for (dir, points) in all_lines {
             let mut board = (*board).clone();
@@ -1383,4 +1453,98 @@ mod tests {
         //assert!(is_five_dir(&board, &p1, Direction::AntiDiagonal).unwrap());
         //assert_eq!(is_line(&board, &p1), Ok(Direction::AntiDiagonal));
     }
+
+    #[test]
+    fn forbidden_reasons() {
+        let mut board = BoardArr::new(15);
+        // overline on F8, double-four on E4, double-three on L12
+        for pos in p![
+            [C, 8],
+            [D, 8],
+            [E, 8],
+            [G, 8],
+            [H, 8],
+            [E, 1],
+            [E, 2],
+            [E, 3],
+            [B, 4],
+            [C, 4],
+            [D, 4],
+            [K, 12],
+            [M, 12],
+            [L, 11],
+            [L, 13]
+        ] {
+            board.set_point(pos, Stone::Black);
+        }
+        let conditions = board.renju_conditions(Stone::Black, None);
+        assert_eq!(conditions.reason(p![F, 8]), Some(ForbiddenReason::Overline));
+        assert_eq!(
+            conditions.reason(p![E, 4]),
+            Some(ForbiddenReason::DoubleFour)
+        );
+        assert_eq!(
+            conditions.reason(p![L, 12]),
+            Some(ForbiddenReason::DoubleThree)
+        );
+        assert_eq!(
+            conditions.reasons.keys().collect::<BTreeSet<_>>(),
+            conditions.forbidden.iter().collect()
+        );
+    }
+
+    #[test]
+    fn cached_forbidden_points() {
+        let mut board = BoardArr::new(15);
+        for pos in p![[G, 8], [I, 8], [H, 7], [H, 9]] {
+            board.set_point(pos, Stone::Black);
+        }
+        assert!(board.forbidden_points(Stone::Black).contains(&p![H, 8]));
+        assert!(board.forbidden_points(Stone::White).is_empty());
+        let cloned = board.clone();
+        board.set_point(p![J, 8], Stone::White);
+        assert!(!board.forbidden_points(Stone::Black).contains(&p![H, 8]));
+        assert!(cloned.forbidden_points(Stone::Black).contains(&p![H, 8]));
+        board.clear();
+        assert!(board.forbidden_points(Stone::Black).is_empty());
+    }
+
+    #[test]
+    fn both_colors() {
+        let mut board = BoardArr::new(15);
+        for pos in p![[G, 8], [I, 8], [H, 7], [H, 9], [C, 3]] {
+            board.set_point(pos, Stone::Black);
+        }
+        for pos in p![[J, 10], [J, 11], [J, 12], [K, 4]] {
+            board.set_point(pos, Stone::White);
+        }
+        let (black, white) = board.renju_conditions_both(None);
+        assert_eq!(black, board.renju_conditions(Stone::Black, None));
+        assert_eq!(white, board.renju_conditions(Stone::White, None));
+        assert!(black.forbidden.contains(&p![H, 8]));
+        assert!(!white.conditions.is_empty());
+    }
+
+    #[test]
+    fn limited_conditions() {
+        let mut board = BoardArr::new(15);
+        for pos in p![[G, 8], [I, 8], [H, 7], [H, 9]] {
+            board.set_point(pos, Stone::Black);
+        }
+        let limits = crate::limits::Limits::default();
+        let conditions = board
+            .renju_conditions_with_limits(Stone::Black, None, &limits)
+            .known()
+            .unwrap();
+        assert_eq!(conditions, board.renju_conditions(Stone::Black, None));
+
+        // the double-three needs a nested check
+        let limits = crate::limits::Limits {
+            depth: 0,
+            ..Default::default()
+        };
+        assert!(board
+            .renju_conditions_with_limits(Stone::Black, None, &limits)
+            .is_unknown());
+    }
 }
@@ -0,0 +1,212 @@
+//! Why the evaluator judges a point the way it does, to check its verdicts by hand.
+//!
+//! [`explain`] lists every five, four and three of a color going through a point, with the line
+//! they are on, and whether the point is forbidden. For a three it lists the points making it a
+//! straight four and which of those are forbidden themselves, which is what decides whether a
+//! double-three is allowed by rule 9.3.
+
+use std::fmt;
+
+use super::evaluator::{Direction, ForbiddenReason, RenjuCondition};
+use super::{Evaluate, Point, Stone};
+
+/// Points of a line shown on each side of the point explained.
+const REACH: usize = 5;
+
+/// What a condition through the point is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Through {
+    pub condition: RenjuCondition,
+    /// For a three, the points making it a straight four, with whether they are forbidden.
+    pub four_points: Vec<(Point, bool)>,
+    /// The line of the condition around the point, see [`Explanation`].
+    pub line: String,
+}
+
+/// The conditions of a color through a point, see the [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub point: Point,
+    pub stone: Stone,
+    /// The stone on the point, if it isn't empty.
+    pub taken: Stone,
+    pub reason: Option<ForbiddenReason>,
+    /// Fives and fours first, then threes.
+    pub through: Vec<Through>,
+}
+
+/// The conditions of `stone` through `point` on `board`.
+///
+/// The lines are drawn from the top left, `X` for black, `O` for white and `*` for the point
+/// when it's empty, e.g. `E8-O8 . . X X * . . . O . .`.
+#[must_use]
+pub fn explain(board: &impl Evaluate, stone: Stone, point: Point) -> Explanation {
+    let conditions = board.renju_conditions(stone, None);
+    let touches = |c: &RenjuCondition| *c.place() == point || c.stones().contains(&point);
+    let mut through = conditions
+        .conditions
+        .iter()
+        .filter(|c| !is_three(c) && touches(c))
+        .map(|condition| Through {
+            condition: condition.clone(),
+            four_points: vec![],
+            line: line(board, direction(condition), point),
+        })
+        .collect::<Vec<_>>();
+    for (condition, four_point) in conditions.threes.iter().filter(|(c, _)| touches(c)) {
+        let forbidden = conditions.forbidden.contains(four_point);
+        match through.iter_mut().find(|t| t.condition == *condition) {
+            Some(three) => three.four_points.push((*four_point, forbidden)),
+            None => through.push(Through {
+                condition: condition.clone(),
+                four_points: vec![(*four_point, forbidden)],
+                line: line(board, direction(condition), point),
+            }),
+        }
+    }
+    Explanation {
+        point,
+        stone,
+        taken: board.stone_at(point),
+        reason: conditions.reason(point),
+        through,
+    }
+}
+
+fn is_three(condition: &RenjuCondition) -> bool {
+    matches!(
+        condition,
+        RenjuCondition::UnbrokenThree { .. } | RenjuCondition::BrokenThree { .. }
+    )
+}
+
+fn direction(condition: &RenjuCondition) -> Direction {
+    match condition {
+        RenjuCondition::UnbrokenThree { direction, .. }
+        | RenjuCondition::BrokenThree { direction, .. }
+        | RenjuCondition::StraightFour { direction, .. }
+        | RenjuCondition::ClosedFour { direction, .. }
+        | RenjuCondition::BrokenFour { direction, .. }
+        | RenjuCondition::Five { direction, .. } => *direction,
+    }
+}
+
+fn name(condition: &RenjuCondition) -> &'static str {
+    match condition {
+        RenjuCondition::UnbrokenThree { .. } => "three",
+        RenjuCondition::BrokenThree { .. } => "broken three",
+        RenjuCondition::StraightFour { .. } => "straight four",
+        RenjuCondition::ClosedFour { .. } => "four",
+        RenjuCondition::BrokenFour { .. } => "broken four",
+        RenjuCondition::Five { .. } => "five",
+    }
+}
+
+/// The points of the line through `point` within [`REACH`] of it, with their ends.
+fn line(board: &impl Evaluate, direction: Direction, point: Point) -> String {
+    let Some((index, points)) = board.get_line(direction, &point) else {
+        return String::new();
+    };
+    let points = points
+        .skip(index.saturating_sub(REACH))
+        .take(index.min(REACH) + REACH + 1)
+        .collect::<Vec<_>>();
+    let cells = points
+        .iter()
+        .map(|&p| match board.stone_at(p) {
+            Stone::Empty if p == point => "*".to_owned(),
+            stone => stone.to_string(),
+        })
+        .collect::<Vec<_>>();
+    match (points.first(), points.last()) {
+        (Some(first), Some(last)) => format!("{first}-{last} {}", cells.join(" ")),
+        _ => String::new(),
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self { point, stone, .. } = self;
+        let theme = crate::theme::theme();
+        let forbidden = theme.paint(theme.forbidden, "forbidden");
+        match (self.taken, self.reason) {
+            (Stone::Empty, Some(reason)) => {
+                writeln!(f, "{point} is {forbidden} for {stone:?}: {reason}")?
+            }
+            (Stone::Empty, None) => writeln!(f, "{point} is allowed for {stone:?}")?,
+            (taken, _) => writeln!(f, "{point} is taken by {taken:?}, seen by {stone:?}")?,
+        }
+        if self.through.is_empty() {
+            writeln!(f, "  nothing of {stone:?} goes through {point}")?;
+        }
+        for through in &self.through {
+            let stones = through
+                .condition
+                .stones()
+                .iter()
+                .map(Point::to_string)
+                .collect::<Vec<_>>();
+            write!(
+                f,
+                "  {} at {} of {}",
+                name(&through.condition),
+                through.condition.place(),
+                stones.join(" ")
+            )?;
+            if !through.four_points.is_empty() {
+                let fours = through
+                    .four_points
+                    .iter()
+                    .map(|(p, forbidden)| match forbidden {
+                        true => format!("{p} ({forbidden})"),
+                        false => p.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, ", straight four at {}", fours.join(", "))?;
+            }
+            let line = through
+                .line
+                .replacen('*', &theme.paint(theme.highlight, "*"), 1);
+            writeln!(f, "\n    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardArr;
+    use crate::p;
+
+    #[test]
+    fn double_three() {
+        let mut board = BoardArr::new(15);
+        for point in p![[H, 8], [I, 8], [G, 9], [G, 10]] {
+            board.set_point(point, Stone::Black);
+        }
+        let explanation = explain(&board, Stone::Black, p![G, 8]);
+        assert_eq!(explanation.reason, Some(ForbiddenReason::DoubleThree));
+        assert_eq!(explanation.taken, Stone::Empty);
+        let threes = explanation
+            .through
+            .iter()
+            .filter(|t| *t.condition.place() == p![G, 8])
+            .collect::<Vec<_>>();
+        assert_eq!(threes.len(), 2, "{explanation}");
+        assert!(threes.iter().all(|t| !t.four_points.is_empty()));
+        let text = explanation.to_string();
+        assert!(
+            text.starts_with("G8 is forbidden for Black: double-three\n"),
+            "{text}"
+        );
+        assert!(text.contains("B8-L8 . . . . . * X X . . ."), "{text}");
+
+        let white = explain(&board, Stone::White, p![H, 8]);
+        assert_eq!(white.taken, Stone::Black);
+        assert!(white.through.is_empty());
+        assert!(white
+            .to_string()
+            .contains("nothing of White goes through H8"));
+    }
+}
@@ -0,0 +1,204 @@
+//! A board with its size fixed at compile time, for engines.
+//!
+//! [`BoardConst`] keeps its stones inline in an array, so cloning it in a search never allocates
+//! and every lookup is into a known size. It is a [backend](super::backend) of the evaluator like
+//! [`BoardArr`], and converts to and from it.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::evaluator::Evaluate;
+use super::lines::LineTable;
+use super::{BoardArr, BoardRead, BoardWrite, Point, Stone};
+use crate::errors::ParseError;
+
+/// A board of `N` by `N` points, see the [module documentation](self).
+#[derive(Clone)]
+pub struct BoardConst<const N: usize> {
+    /// The stones of every row.
+    stones: [[Stone; N]; N],
+    /// Shared with every board of the size, see [`LineTable::for_size`].
+    table: Arc<LineTable>,
+}
+
+impl<const N: usize> BoardConst<N> {
+    /// An empty board.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            stones: [[Stone::Empty; N]; N],
+            table: LineTable::for_size(N as u32),
+        }
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> u32 {
+        N as u32
+    }
+
+    /// The stones of every row, top first.
+    #[must_use]
+    pub fn rows(&self) -> &[[Stone; N]; N] {
+        &self.stones
+    }
+
+    /// Remove every stone.
+    pub fn clear(&mut self) {
+        self.stones = [[Stone::Empty; N]; N];
+    }
+}
+
+impl<const N: usize> Default for BoardConst<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> BoardRead for BoardConst<N> {
+    fn table(&self) -> &LineTable {
+        &self.table
+    }
+
+    fn size(&self) -> u32 {
+        N as u32
+    }
+
+    #[inline]
+    fn stone_at(&self, point: Point) -> Stone {
+        if point.is_null {
+            return Stone::Empty;
+        }
+        self.stones
+            .get(point.y as usize)
+            .and_then(|row| row.get(point.x as usize))
+            .copied()
+            .unwrap_or(Stone::Empty)
+    }
+}
+
+impl<const N: usize> BoardWrite for BoardConst<N> {
+    #[inline]
+    fn set_stone(&mut self, point: Point, stone: Stone) {
+        if point.is_null {
+            return;
+        }
+        if let Some(cell) = self
+            .stones
+            .get_mut(point.y as usize)
+            .and_then(|row| row.get_mut(point.x as usize))
+        {
+            *cell = stone;
+        }
+    }
+
+    fn clear_stones(&mut self) {
+        self.clear();
+    }
+}
+
+impl<const N: usize> Evaluate for BoardConst<N> {}
+
+impl<const N: usize> PartialEq for BoardConst<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.stones == other.stones
+    }
+}
+
+impl<const N: usize> Eq for BoardConst<N> {}
+
+impl<const N: usize> fmt::Debug for BoardConst<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BoardConst<{N}>")?;
+        for row in &self.stones {
+            for stone in row {
+                let c = match stone {
+                    Stone::Black => 'X',
+                    Stone::White => 'O',
+                    Stone::Empty => '.',
+                };
+                write!(f, "{c}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> From<&BoardConst<N>> for BoardArr {
+    fn from(board: &BoardConst<N>) -> Self {
+        let mut arr = BoardArr::new(N as u32);
+        for (y, row) in board.stones.iter().enumerate() {
+            for (x, stone) in row.iter().enumerate() {
+                if !stone.is_empty() {
+                    arr.set_point(Point::new(x as u32, y as u32), *stone);
+                }
+            }
+        }
+        arr
+    }
+}
+
+impl<const N: usize> TryFrom<&BoardArr> for BoardConst<N> {
+    type Error = ParseError;
+
+    /// The stones of `board`, which must be `N` by `N`.
+    fn try_from(board: &BoardArr) -> Result<Self, Self::Error> {
+        if board.size() as usize != N {
+            return Err(ParseError::Other(format!(
+                "a board of size {} is not {N}x{N}",
+                board.size()
+            )));
+        }
+        let mut fixed = Self::new();
+        for marker in board.iter().filter(|m| !m.color.is_empty()) {
+            fixed.set_stone(marker.point, marker.color);
+        }
+        Ok(fixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn agrees_with_board_arr() {
+        let mut board = BoardArr::new(15);
+        // a double-three on F8, a four for black and a three for white
+        for point in p![
+            [G, 8],
+            [H, 8],
+            [F, 7],
+            [F, 6],
+            [K, 2],
+            [K, 3],
+            [K, 4],
+            [K, 5]
+        ] {
+            board.set_point(point, Stone::Black);
+        }
+        for point in p![[B, 12], [C, 12], [D, 12]] {
+            board.set_point(point, Stone::White);
+        }
+        let fixed = BoardConst::<15>::try_from(&board).unwrap();
+        let stones =
+            |board: &BoardArr| board.iter().map(|m| (m.point, m.color)).collect::<Vec<_>>();
+        assert_eq!(stones(&BoardArr::from(&fixed)), stones(&board));
+        assert_eq!(
+            fixed.renju_conditions_both(None),
+            board.renju_conditions_both(None)
+        );
+        assert!(fixed
+            .renju_conditions(Stone::Black, None)
+            .forbidden
+            .contains(&p![F, 8]));
+        assert!(BoardConst::<19>::try_from(&board).is_err());
+
+        let mut fixed = fixed;
+        fixed.set_stone(Point::new(15, 3), Stone::White);
+        fixed.set_stone(Point::null(), Stone::White);
+        assert_eq!(fixed.stone_at(Point::new(15, 3)), Stone::Empty);
+        assert_eq!(fixed.stone_at(p![H, 8]), Stone::Black);
+    }
+}
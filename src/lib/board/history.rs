@@ -0,0 +1,211 @@
+//! A log of the edits of a library, to see who added which variation of a shared library.
+//!
+//! Nothing is logged until an author is set with [`Board::record_history`]. From then on every
+//! edit through the [`Board`] is logged as an [`Edit`] with the author, the time and the moves of
+//! the node edited. The log is kept with the library by the [text](crate::file_reader::text) and
+//! [binary](crate::file_reader::binary) formats.
+//!
+//! [`Board::get_move_mut`] is logged as an [`Action::Edit`] of the node, whether or not the node
+//! was changed.
+//!
+//! [`Board`]: super::Board
+//! [`Board::record_history`]: super::Board::record_history
+//! [`Board::get_move_mut`]: super::Board::get_move_mut
+
+use std::fmt;
+
+/// Seconds since the unix epoch.
+pub type Timestamp = u64;
+
+/// The current time, in seconds since the unix epoch.
+#[must_use]
+pub fn now() -> Timestamp {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// What an edit did to a node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    Add,
+    Remove,
+    /// Changed the comments, board text or flags of the node.
+    Edit,
+    Bookmark(String),
+    RemoveBookmark(String),
+    Tag(String),
+    Untag(String),
+}
+
+impl Action {
+    /// The name of the action in the text format, e.g `add`.
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Remove => "remove",
+            Self::Edit => "edit",
+            Self::Bookmark(_) => "bookmark",
+            Self::RemoveBookmark(_) => "remove-bookmark",
+            Self::Tag(_) => "tag",
+            Self::Untag(_) => "untag",
+        }
+    }
+
+    /// The name of the bookmark or tag.
+    #[must_use]
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            Self::Bookmark(label)
+            | Self::RemoveBookmark(label)
+            | Self::Tag(label)
+            | Self::Untag(label) => Some(label),
+            Self::Add | Self::Remove | Self::Edit => None,
+        }
+    }
+
+    /// The action named `name`, with the bookmark or tag `label`.
+    #[must_use]
+    pub fn from_name(name: &str, label: Option<String>) -> Option<Self> {
+        Some(match (name, label) {
+            ("add", None) => Self::Add,
+            ("remove", None) => Self::Remove,
+            ("edit", None) => Self::Edit,
+            ("bookmark", Some(label)) => Self::Bookmark(label),
+            ("remove-bookmark", Some(label)) => Self::RemoveBookmark(label),
+            ("tag", Some(label)) => Self::Tag(label),
+            ("untag", Some(label)) => Self::Untag(label),
+            _ => return None,
+        })
+    }
+}
+
+/// An edit of a library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edit {
+    pub author: String,
+    pub time: Timestamp,
+    pub action: Action,
+    /// The moves to the node, as written by [`Board::describe_path`](super::Board::describe_path)
+    /// when it was edited.
+    pub path: String,
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let days = self.time / (60 * 60 * 24);
+        let minutes = self.time % (60 * 60 * 24) / 60;
+        let (year, month, day) = civil_from_days(days);
+        write!(
+            f,
+            "{year}-{month:02}-{day:02} {:02}:{:02} {} {}",
+            minutes / 60,
+            minutes % 60,
+            self.author,
+            self.action.name(),
+        )?;
+        if let Some(label) = self.action.label() {
+            write!(f, " {label:?}")?;
+        }
+        match self.path.as_str() {
+            "" => write!(f, " at the root"),
+            path => write!(f, " at {path}"),
+        }
+    }
+}
+
+/// The start of the day of `date`, like `2024-06-10`, in seconds since the unix epoch. `None` for
+/// anything else, or a day before the epoch.
+#[must_use]
+pub fn parse_date(date: &str) -> Option<Timestamp> {
+    let mut parts = date.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+        parts.next()?.ok()?,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day)?;
+    // e.g the 31st of April is not a day
+    (civil_from_days(days) == (year, month, day)).then_some(days * 60 * 60 * 24)
+}
+
+/// The days since the unix epoch of a date in the proleptic Gregorian calendar, the inverse of
+/// [`civil_from_days`].
+fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    // shifted to start on March 1st of year 0, so that the leap day is last
+    let year = year.checked_sub(u64::from(month <= 2))?;
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    (era * 146_097 + day_of_era).checked_sub(719_468)
+}
+
+/// The date of a day since the unix epoch, in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // shifted to start on March 1st of year 0, so that the leap day is last
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, BoardMarker, Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn records_edits() {
+        let mut graph = Board::new();
+        graph.add_line(&p![[H, 8], [I, 9]]);
+        assert!(graph.history().is_empty());
+
+        graph.record_history(Some("alice".to_owned()));
+        let i9 = graph.find_position(&p![[H, 8], [I, 9]]).unwrap();
+        let j10 = graph.insert_move(i9, BoardMarker::new(p![J, 10], Stone::Black));
+        graph.set_bookmark("Trap", j10);
+        graph.record_history(None);
+        graph.tag(j10, "not logged");
+
+        let history = graph.history();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].action, Action::Add);
+        assert_eq!(history[0].path, "H8 I9 J10");
+        assert_eq!(history[1].action, Action::Bookmark("Trap".to_owned()));
+        assert_eq!(history[1].author, "alice");
+
+        let edit = Edit {
+            time: 1_718_022_896,
+            ..history[1].clone()
+        };
+        assert_eq!(
+            edit.to_string(),
+            "2024-06-10 12:34 alice bookmark \"Trap\" at H8 I9 J10"
+        );
+        assert_eq!(
+            parse_date("2024-06-10"),
+            Some(1_718_022_896 / 86_400 * 86_400)
+        );
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800));
+        for date in ["2023-02-29", "2024-13-01", "1969-12-31", "2024-06", "June"] {
+            assert_eq!(parse_date(date), None, "{date}");
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! How much of the board each side's stones reach, to show strategic balance when teaching.
+//!
+//! Every stone spreads influence over the points around it, halving with every step away in any
+//! of the eight directions and stopping after [`REACH`] steps. Black's influence counts up and
+//! white's down, so the sign of a point tells which side holds it. Unlike the conditions of the
+//! [`evaluator`](super::evaluator) this says nothing about concrete threats.
+
+use std::fmt;
+
+use super::{BoardArr, BoardRead, Point, Stone};
+
+/// The steps a stone reaches.
+pub const REACH: u32 = 4;
+
+/// Influence under which a point is held by neither side in the [`Display`](fmt::Display) of an
+/// [`Influence`].
+pub const HELD: f32 = 0.5;
+
+/// The influence on every point of a board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Influence {
+    size: u32,
+    values: Vec<f32>,
+    stones: Vec<Stone>,
+}
+
+impl Influence {
+    #[must_use]
+    pub fn new(board: &BoardArr) -> Self {
+        let size = board.size();
+        let mut values = vec![0.0; (size * size) as usize];
+        let mut stones = vec![Stone::Empty; (size * size) as usize];
+        for marker in board.iter() {
+            let point = marker.point;
+            let sign = match board.stone_at(point) {
+                Stone::Black => 1.0,
+                Stone::White => -1.0,
+                Stone::Empty => continue,
+            };
+            stones[point.to_1d(size) as usize] = board.stone_at(point);
+            let (x, y) = (point.x as i64, point.y as i64);
+            let reach = i64::from(REACH);
+            for ty in (y - reach).max(0)..=(y + reach).min(i64::from(size) - 1) {
+                for tx in (x - reach).max(0)..=(x + reach).min(i64::from(size) - 1) {
+                    let steps = (tx - x).abs().max((ty - y).abs());
+                    let index = (ty * i64::from(size) + tx) as usize;
+                    values[index] += sign / f32::powi(2.0, steps as i32);
+                }
+            }
+        }
+        Self {
+            size,
+            values,
+            stones,
+        }
+    }
+
+    /// The influence on `point`, positive for black, 0 off the board.
+    #[must_use]
+    pub fn at(&self, point: Point) -> f32 {
+        match !point.is_null && point.x < self.size && point.y < self.size {
+            true => self.values[point.to_1d(self.size) as usize],
+            false => 0.0,
+        }
+    }
+
+    /// The side holding `point`, if its influence is at least [`HELD`] either way.
+    #[must_use]
+    pub fn holder(&self, point: Point) -> Stone {
+        match self.at(point) {
+            value if value >= HELD => Stone::Black,
+            value if value <= -HELD => Stone::White,
+            _ => Stone::Empty,
+        }
+    }
+
+    /// The empty points held by black and by white.
+    #[must_use]
+    pub fn area(&self) -> (usize, usize) {
+        let mut area = (0, 0);
+        for index in 0..self.values.len() as u32 {
+            let point = Point::from_1d(index, self.size);
+            if self.stones[index as usize] != Stone::Empty {
+                continue;
+            }
+            match self.holder(point) {
+                Stone::Black => area.0 += 1,
+                Stone::White => area.1 += 1,
+                Stone::Empty => {}
+            }
+        }
+        area
+    }
+}
+
+/// The board with `x` and `o` on the empty points held by black and white.
+impl fmt::Display for Influence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.size {
+            write!(f, "{:2}:", self.size - y)?;
+            for x in 0..self.size {
+                let point = Point::new(x, y);
+                let c = match (
+                    self.stones[point.to_1d(self.size) as usize],
+                    self.holder(point),
+                ) {
+                    (Stone::Black, _) => 'X',
+                    (Stone::White, _) => 'O',
+                    (_, Stone::Black) => 'x',
+                    (_, Stone::White) => 'o',
+                    _ => '.',
+                };
+                write!(f, "{c} ")?;
+            }
+            writeln!(f)?;
+        }
+        let letters = (0..self.size)
+            .map(|x| char::from(b'A' + x as u8).to_string())
+            .collect::<Vec<_>>();
+        write!(f, "   {}", letters.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn spreads_and_halves() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![A, 1], Stone::White);
+        let influence = Influence::new(&board);
+        assert_eq!(influence.at(p![H, 8]), 1.0);
+        assert_eq!(influence.at(p![I, 9]), 0.5);
+        assert_eq!(influence.at(p![J, 6]), 0.25);
+        assert_eq!(influence.at(p![M, 8]), 0.0);
+        assert_eq!(influence.at(p![B, 2]), -0.5);
+        assert_eq!(influence.holder(p![G, 7]), Stone::Black);
+        assert_eq!(influence.holder(p![G, 6]), Stone::Empty);
+        // the 8 neighbours of each stone, 3 in the corner
+        assert_eq!(influence.area(), (8, 3));
+
+        let text = influence.to_string();
+        let rows = text.lines().collect::<Vec<_>>();
+        assert_eq!(rows[15 - 8], " 8:. . . . . . x X x . . . . . . ");
+        assert_eq!(rows[15 - 1], " 1:O o . . . . . . . . . . . . . ");
+    }
+}
@@ -0,0 +1,297 @@
+//! Every row, column and diagonal of a board, kept up to date as stones are placed.
+//!
+//! The geometry of the lines only depends on the board size and is kept in a [`LineTable`], the
+//! stones of every line are stored contiguously so that pattern matching can scan them
+//! without looking up points on the board.
+
+use std::sync::{Arc, OnceLock};
+
+use super::evaluator::Direction;
+use super::{Point, Stone};
+
+/// The points of every line of a board size, and which lines go through every point.
+///
+/// Tables for 15x15 and 19x19 are built once and shared, see [`LineTable::for_size`].
+#[derive(Debug)]
+pub struct LineTable {
+    size: u32,
+    /// The direction and points of every line, in order along the line.
+    lines: Vec<(Direction, Vec<Point>)>,
+    /// For every point in [`Point::to_1d`] order, the line and the index in it for each direction.
+    through: Vec<[(usize, usize); 4]>,
+    /// For every point in [`Point::to_1d`] order, the up to eight points next to it.
+    neighbours: Vec<Vec<Point>>,
+}
+
+impl LineTable {
+    /// The table for boards of size `size`.
+    #[must_use]
+    pub fn for_size(size: u32) -> Arc<LineTable> {
+        static TABLE_15: OnceLock<Arc<LineTable>> = OnceLock::new();
+        static TABLE_19: OnceLock<Arc<LineTable>> = OnceLock::new();
+        match size {
+            15 => TABLE_15.get_or_init(|| Arc::new(Self::new(15))).clone(),
+            19 => TABLE_19.get_or_init(|| Arc::new(Self::new(19))).clone(),
+            _ => Arc::new(Self::new(size)),
+        }
+    }
+
+    fn new(size: u32) -> Self {
+        let width = size;
+        let size = size as i32;
+        let mut starts = vec![];
+        for i in 0..size {
+            starts.push((Direction::Horizontal, (0, i), (1, 0)));
+        }
+        for i in 0..size {
+            starts.push((Direction::Vertical, (i, 0), (0, 1)));
+        }
+        // `/` starts on the left column and the bottom row
+        for i in 0..size {
+            starts.push((Direction::Diagonal { bottom: true }, (0, i), (1, -1)));
+        }
+        for i in 1..size {
+            starts.push((Direction::Diagonal { bottom: true }, (i, size - 1), (1, -1)));
+        }
+        // `\` starts on the left column and the top row
+        for i in (0..size).rev() {
+            starts.push((Direction::Diagonal { bottom: false }, (0, i), (1, 1)));
+        }
+        for i in 1..size {
+            starts.push((Direction::Diagonal { bottom: false }, (i, 0), (1, 1)));
+        }
+
+        let on_board = |x: i32, y: i32| (0..size).contains(&x) && (0..size).contains(&y);
+        let mut through = vec![[(0, 0); 4]; (size * size) as usize];
+        let lines = starts
+            .into_iter()
+            .enumerate()
+            .map(|(line, (direction, (mut x, mut y), (dx, dy)))| {
+                let slot = Direction::directions()
+                    .iter()
+                    .position(|d| *d == direction)
+                    .expect("all directions are listed");
+                let mut points = vec![];
+                while on_board(x, y) {
+                    let point = Point::new(x as u32, y as u32);
+                    through[point.to_1d(width) as usize][slot] = (line, points.len());
+                    points.push(point);
+                    x += dx;
+                    y += dy;
+                }
+                (direction, points)
+            })
+            .collect();
+        let neighbours = (0..width * width)
+            .map(|i| {
+                let point = Point::from_1d(i, width);
+                let (x, y) = (point.x as i32, point.y as i32);
+                (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+                    .filter(|&(nx, ny)| (nx, ny) != (x, y) && on_board(nx, ny))
+                    .map(|(nx, ny)| Point::new(nx as u32, ny as u32))
+                    .collect()
+            })
+            .collect();
+        Self {
+            size: width,
+            lines,
+            through,
+            neighbours,
+        }
+    }
+
+    #[must_use]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The amount of lines, line ids are `0..len()`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The direction and points of line `id`.
+    ///
+    /// # Panics
+    ///
+    /// If there is no line `id`.
+    #[must_use]
+    pub fn line(&self, id: usize) -> (Direction, &[Point]) {
+        let (direction, points) = &self.lines[id];
+        (*direction, points)
+    }
+
+    /// The id of the line through `point` and the index of `point` in it, for every direction in
+    /// the order of [`Direction::directions`]. `None` if `point` is not on the board.
+    #[must_use]
+    pub fn through(&self, point: Point) -> Option<&[(usize, usize); 4]> {
+        if point.x >= self.size || point.y >= self.size {
+            return None;
+        }
+        self.through.get(point.to_1d(self.size) as usize)
+    }
+
+    /// The points next to `point`, including diagonally.
+    #[must_use]
+    pub fn neighbours(&self, point: Point) -> &[Point] {
+        if point.x >= self.size || point.y >= self.size {
+            return &[];
+        }
+        &self.neighbours[point.to_1d(self.size) as usize]
+    }
+}
+
+/// A single line of a board.
+#[derive(Clone, Copy, Debug)]
+pub struct Line<'a> {
+    pub direction: Direction,
+    /// The points of the line, in order.
+    pub points: &'a [Point],
+    /// The stone on every point in `points`.
+    pub stones: &'a [Stone],
+}
+
+/// All lines of a board, see [`BoardArr::lines`](super::BoardArr::lines).
+#[derive(Clone, Debug)]
+pub struct Lines {
+    table: Arc<LineTable>,
+    stones: Vec<Vec<Stone>>,
+}
+
+impl Lines {
+    /// Lines of an empty board of size `size`.
+    #[must_use]
+    pub fn new(size: u32) -> Self {
+        let table = LineTable::for_size(size);
+        let stones = table
+            .lines
+            .iter()
+            .map(|(_, points)| vec![Stone::Empty; points.len()])
+            .collect();
+        Self { table, stones }
+    }
+
+    /// The geometry of the lines.
+    #[must_use]
+    pub fn table(&self) -> &LineTable {
+        &self.table
+    }
+
+    /// Every line, each point is in exactly one line per direction. Lines too short to ever hold a
+    /// five are included.
+    pub fn iter(&self) -> impl Iterator<Item = Line<'_>> + '_ {
+        (0..self.stones.len()).map(move |i| self.line(i))
+    }
+
+    /// The four lines through `point`, with the index of `point` in each.
+    pub fn through(&self, point: Point) -> impl Iterator<Item = (Line<'_>, usize)> + '_ {
+        self.table
+            .through(point)
+            .into_iter()
+            .flatten()
+            .map(move |&(line, index)| (self.line(line), index))
+    }
+
+    /// Update the lines through `point`.
+    pub(crate) fn set(&mut self, point: Point, stone: Stone) {
+        if let Some(through) = self.table.through(point) {
+            for &(line, index) in through {
+                self.stones[line][index] = stone;
+            }
+        }
+    }
+
+    /// The stone at `index` of line `id`.
+    pub(crate) fn stone(&self, id: usize, index: usize) -> Stone {
+        self.stones[id][index]
+    }
+
+    /// Empty every line.
+    pub(crate) fn clear(&mut self) {
+        for line in &mut self.stones {
+            line.fill(Stone::Empty);
+        }
+    }
+
+    fn line(&self, i: usize) -> Line<'_> {
+        let (direction, points) = self.table.line(i);
+        Line {
+            direction,
+            points,
+            stones: &self.stones[i],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardArr;
+    use crate::p;
+
+    #[test]
+    fn lines_follow_the_board() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![A, 1], Stone::White);
+        board.set_point(p![A, 1], Stone::Empty);
+        for line in board.lines().iter() {
+            for (point, stone) in line.points.iter().zip(line.stones) {
+                assert_eq!(board.get_point(*point).unwrap().color, *stone);
+            }
+        }
+
+        let through = board.lines().through(p![H, 8]).collect::<Vec<_>>();
+        assert_eq!(through.len(), 4);
+        for (line, index) in through {
+            assert_eq!(line.points[index], p![H, 8]);
+            assert_eq!(line.stones[index], Stone::Black);
+            assert_eq!(line.points.len(), 15);
+        }
+        let corner = board.lines().through(p![A, 15]).collect::<Vec<_>>();
+        assert_eq!(
+            corner.iter().map(|(line, _)| line.points.len()).max(),
+            Some(15)
+        );
+        assert_eq!(
+            corner.iter().map(|(line, _)| line.points.len()).min(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn shared_tables() {
+        assert!(Arc::ptr_eq(
+            &LineTable::for_size(15),
+            &LineTable::for_size(15)
+        ));
+        assert!(Arc::ptr_eq(
+            &LineTable::for_size(19),
+            &LineTable::for_size(19)
+        ));
+        for size in [15, 19, 9] {
+            let table = LineTable::for_size(size);
+            // rows, columns and both diagonals
+            assert_eq!(table.len() as u32, 2 * size + 2 * (2 * size - 1));
+            for i in 0..size * size {
+                let point = Point::from_1d(i, size);
+                for (slot, &(line, index)) in table.through(point).unwrap().iter().enumerate() {
+                    let (direction, points) = table.line(line);
+                    assert_eq!(direction, Direction::directions()[slot]);
+                    assert_eq!(points[index], point);
+                }
+            }
+            assert_eq!(table.neighbours(Point::new(0, 0)).len(), 3);
+            assert_eq!(table.neighbours(Point::new(0, 4)).len(), 5);
+            assert_eq!(table.neighbours(Point::new(4, 4)).len(), 8);
+            assert!(table.through(Point::new(size, 0)).is_none());
+        }
+    }
+}
@@ -0,0 +1,581 @@
+//! Three-way merges of libraries edited by several people, see [`Board::merge3`].
+//!
+//! Nodes are matched by the moves leading to them from the root of their tree, and the trees by
+//! their order in [`Board::roots`]. Every change made on one side only is taken. When both sides
+//! changed the same thing differently the merge still succeeds, following these rules, and
+//! reports a [`Conflict`] for it:
+//!
+//! - A comment, board text or the flags of a node changed differently: _ours_ is kept.
+//! - A bookmark moved to different nodes, or removed on one side and moved on the other: _ours_
+//!   is kept.
+//! - A node removed on one side while it or a node after it was changed on the other: the node is
+//!   kept, with the changes.
+//!
+//! Continuations are listed in the order of _ours_, followed by the ones only added by _theirs_.
+//! Tags are merged as sets and never conflict, and the [history](super::history) is every edit
+//! of both sides by time. Nodes with several parents are merged once for every parent.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::{notation, Board, BoardMarker, MoveIndex, Point, Stone};
+use crate::file_reader::renlib::CommandVariant;
+
+/// A move, identifying a node among its siblings.
+type Key = (Point, Stone);
+
+/// The result of [`Board::merge3`].
+#[derive(Debug)]
+pub struct Merged {
+    pub graph: Board,
+    /// Every conflict, in the order of the nodes in the merged graph.
+    pub conflicts: Vec<Conflict>,
+}
+
+/// Where a node is, in a way that is the same in every version of a library.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodePath {
+    /// The index of the tree in [`Board::roots`].
+    pub root: usize,
+    /// The moves from the root to the node.
+    pub moves: Vec<(Point, Stone)>,
+}
+
+impl fmt::Display for NodePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.root != 0 {
+            write!(f, "tree {}: ", self.root + 1)?;
+        }
+        if self.moves.is_empty() {
+            return write!(f, "root");
+        }
+        let points = self
+            .moves
+            .iter()
+            .map(|(point, _)| *point)
+            .collect::<Vec<_>>();
+        write!(f, "{}", notation::write_moves(&points))
+    }
+}
+
+/// A text of a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextField {
+    OnelineComment,
+    MultilineComment,
+    BoardText,
+}
+
+impl TextField {
+    const ALL: [Self; 3] = [
+        Self::OnelineComment,
+        Self::MultilineComment,
+        Self::BoardText,
+    ];
+
+    fn get(self, marker: &BoardMarker) -> &Option<String> {
+        match self {
+            Self::OnelineComment => &marker.oneline_comment,
+            Self::MultilineComment => &marker.multiline_comment,
+            Self::BoardText => &marker.board_text,
+        }
+    }
+
+    fn get_mut(self, marker: &mut BoardMarker) -> &mut Option<String> {
+        match self {
+            Self::OnelineComment => &mut marker.oneline_comment,
+            Self::MultilineComment => &mut marker.multiline_comment,
+            Self::BoardText => &mut marker.board_text,
+        }
+    }
+}
+
+/// One side of a merge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// What both sides changed differently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConflictKind {
+    /// A text of the node, _ours_ is kept.
+    Text {
+        field: TextField,
+        base: Option<String>,
+        ours: Option<String>,
+        theirs: Option<String>,
+    },
+    /// The flags of the node other than the layout flags of RenLib, _ours_ are kept.
+    Flags { base: u32, ours: u32, theirs: u32 },
+    /// The node was removed by `removed_by` while the other side changed it or the nodes after it.
+    /// The node is kept.
+    RemovedChanged { removed_by: Side },
+    /// A bookmark was added or moved to different nodes, or removed on one side and moved on the
+    /// other. The one of _ours_ is kept, the conflict is at it or else at the one of _base_.
+    Bookmark {
+        name: String,
+        ours: Option<NodePath>,
+        theirs: Option<NodePath>,
+    },
+}
+
+/// A conflict resolved by the rules of the [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conflict {
+    pub path: NodePath,
+    pub kind: ConflictKind,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: ", self.path)?;
+        match &self.kind {
+            ConflictKind::Text { field, .. } => write!(f, "{field:?} changed on both sides"),
+            ConflictKind::Flags { .. } => write!(f, "flags changed on both sides"),
+            ConflictKind::RemovedChanged {
+                removed_by: Side::Ours,
+            } => write!(f, "removed by us and changed by them, kept"),
+            ConflictKind::RemovedChanged {
+                removed_by: Side::Theirs,
+            } => write!(f, "removed by them and changed by us, kept"),
+            ConflictKind::Bookmark { name, .. } => {
+                write!(f, "bookmark {name:?} moved on both sides")
+            }
+        }
+    }
+}
+
+/// A node of a library as a tree.
+struct Node {
+    marker: BoardMarker,
+    /// In the order they were added.
+    children: Vec<(Key, Node)>,
+}
+
+impl Node {
+    fn of(board: &Board, index: MoveIndex) -> Option<Self> {
+        let marker = board.get_move(index)?.clone();
+        // children are listed newest first
+        let children = board
+            .get_children(&index)
+            .into_iter()
+            .rev()
+            .filter_map(|child| Some((key(board.get_move(child)?), Self::of(board, child)?)))
+            .collect();
+        Some(Self { marker, children })
+    }
+
+    fn child(&self, key: &Key) -> Option<&Node> {
+        self.children
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, node)| node)
+    }
+
+    /// Whether the nodes and every node after them are the same, in any order.
+    fn same(&self, other: &Node) -> bool {
+        TextField::ALL
+            .iter()
+            .all(|field| field.get(&self.marker) == field.get(&other.marker))
+            && flags(&self.marker) == flags(&other.marker)
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .all(|(key, child)| other.child(key).is_some_and(|other| child.same(other)))
+    }
+}
+
+fn key(marker: &BoardMarker) -> Key {
+    (marker.point, marker.color)
+}
+
+/// The flags of `marker` without the layout flags, which depend on the tree.
+fn flags(marker: &BoardMarker) -> CommandVariant {
+    *marker.command - (CommandVariant::DOWN | CommandVariant::RIGHT)
+}
+
+/// `ours` or `theirs`, whichever changed from `base`, `None` if both did differently.
+fn three_way<'a, V: PartialEq>(base: &'a V, ours: &'a V, theirs: &'a V) -> Option<&'a V> {
+    if ours == theirs || theirs == base {
+        Some(ours)
+    } else if ours == base {
+        Some(theirs)
+    } else {
+        None
+    }
+}
+
+/// The path of `index`, to find the node of a bookmark or tag in the merged graph.
+fn path_of(board: &Board, index: MoveIndex) -> Option<NodePath> {
+    let mut nodes = board.down_to_root(&index);
+    let root = nodes.pop()?;
+    let root = board
+        .roots()
+        .iter()
+        .position(|r| r.node_index() == root.node_index())?;
+    let moves = nodes
+        .iter()
+        .rev()
+        .map(|index| board.get_move(*index).map(key))
+        .collect::<Option<_>>()?;
+    Some(NodePath { root, moves })
+}
+
+struct Merge {
+    graph: Board,
+    conflicts: Vec<Conflict>,
+    /// The node of every path in the merged graph.
+    nodes: BTreeMap<NodePath, MoveIndex>,
+}
+
+impl Merge {
+    fn conflict(&mut self, path: &NodePath, kind: ConflictKind) {
+        tracing::debug!(%path, ?kind, "merge conflict");
+        self.conflicts.push(Conflict {
+            path: path.clone(),
+            kind,
+        });
+    }
+
+    /// The marker of a node on both sides.
+    fn marker(
+        &mut self,
+        path: &NodePath,
+        base: Option<&Node>,
+        ours: &Node,
+        theirs: &Node,
+    ) -> BoardMarker {
+        let mut marker = ours.marker.clone();
+        let empty = BoardMarker::new(ours.marker.point, ours.marker.color);
+        let base = base.map_or(&empty, |base| &base.marker);
+        for field in TextField::ALL {
+            let (b, o, t) = (
+                field.get(base),
+                field.get(&ours.marker),
+                field.get(&theirs.marker),
+            );
+            match three_way(b, o, t) {
+                Some(text) => field.get_mut(&mut marker).clone_from(text),
+                None => self.conflict(
+                    path,
+                    ConflictKind::Text {
+                        field,
+                        base: b.clone(),
+                        ours: o.clone(),
+                        theirs: t.clone(),
+                    },
+                ),
+            }
+        }
+        let (b, o, t) = (flags(base), flags(&ours.marker), flags(&theirs.marker));
+        match three_way(&b, &o, &t) {
+            Some(&merged) => *marker.command = merged | (*ours.marker.command - o),
+            None => self.conflict(
+                path,
+                ConflictKind::Flags {
+                    base: b.bits(),
+                    ours: o.bits(),
+                    theirs: t.bits(),
+                },
+            ),
+        }
+        marker
+    }
+
+    /// Merge the versions of a node, adding it as a child of `parent` or as a root if it is kept.
+    fn node(
+        &mut self,
+        path: NodePath,
+        base: Option<&Node>,
+        ours: Option<&Node>,
+        theirs: Option<&Node>,
+        parent: Option<MoveIndex>,
+    ) {
+        let (ours, theirs) = match (base, ours, theirs) {
+            (_, Some(ours), Some(theirs)) => (ours, theirs),
+            // added on one side
+            (None, Some(node), None) | (None, None, Some(node)) => {
+                let index = self.add(&path, parent, node.marker.clone());
+                self.copy(path, node, index);
+                return;
+            }
+            // removed on one side
+            (Some(base), Some(kept), None) | (Some(base), None, Some(kept)) => {
+                if !kept.same(base) {
+                    let removed_by = match ours {
+                        Some(_) => Side::Theirs,
+                        None => Side::Ours,
+                    };
+                    self.conflict(&path, ConflictKind::RemovedChanged { removed_by });
+                    let index = self.add(&path, parent, kept.marker.clone());
+                    self.copy(path, kept, index);
+                }
+                return;
+            }
+            (_, None, None) => return,
+        };
+        let marker = self.marker(&path, base, ours, theirs);
+        let index = self.add(&path, parent, marker);
+        self.nodes.insert(path.clone(), index);
+        let mut keys = ours
+            .children
+            .iter()
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        keys.extend(
+            theirs
+                .children
+                .iter()
+                .map(|(key, _)| *key)
+                .filter(|key| ours.child(key).is_none()),
+        );
+        for key in keys {
+            let mut child = path.clone();
+            child.moves.push(key);
+            self.node(
+                child,
+                base.and_then(|base| base.child(&key)),
+                ours.child(&key),
+                theirs.child(&key),
+                Some(index),
+            );
+        }
+    }
+
+    fn add(
+        &mut self,
+        path: &NodePath,
+        parent: Option<MoveIndex>,
+        marker: BoardMarker,
+    ) -> MoveIndex {
+        match (parent, path.root) {
+            (Some(parent), _) => self.graph.insert_move(parent, marker),
+            (None, 0) => {
+                *self.graph.root_marker_mut() = marker;
+                self.graph.get_root()
+            }
+            (None, _) => self.graph.add_root(marker),
+        }
+    }
+
+    /// Copy the nodes after `node`, a node only on one side.
+    fn copy(&mut self, path: NodePath, node: &Node, index: MoveIndex) {
+        for (key, child) in &node.children {
+            let copy = self.graph.insert_move(index, child.marker.clone());
+            let mut path = path.clone();
+            path.moves.push(*key);
+            self.copy(path, child, copy);
+        }
+        self.nodes.insert(path, index);
+    }
+}
+
+impl Board {
+    /// Merge the changes `ours` and `theirs` made to `base`, see the [module
+    /// documentation](super::merge).
+    ///
+    /// The merged graph is always complete, the conflicts list the places where a change of
+    /// `theirs` was not taken or a removal was not done, to be checked by hand.
+    #[must_use]
+    pub fn merge3(base: &Board, ours: &Board, theirs: &Board) -> Merged {
+        let trees = |board: &Board| {
+            board
+                .roots()
+                .iter()
+                .filter_map(|root| Node::of(board, *root))
+                .collect::<Vec<_>>()
+        };
+        let (base_trees, our_trees, their_trees) = (trees(base), trees(ours), trees(theirs));
+        let mut merge = Merge {
+            graph: Board::new(),
+            conflicts: vec![],
+            nodes: BTreeMap::new(),
+        };
+        let len = base_trees.len().max(our_trees.len()).max(their_trees.len());
+        for root in 0..len {
+            let path = NodePath {
+                root,
+                moves: vec![],
+            };
+            merge.node(
+                path,
+                base_trees.get(root),
+                our_trees.get(root),
+                their_trees.get(root),
+                None,
+            );
+        }
+
+        // bookmarks, by name
+        let bookmarks = |board: &Board| {
+            board
+                .bookmarks()
+                .iter()
+                .filter_map(|(name, index)| Some((name.clone(), path_of(board, *index)?)))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let (base_marks, our_marks, their_marks) =
+            (bookmarks(base), bookmarks(ours), bookmarks(theirs));
+        let names = our_marks
+            .keys()
+            .chain(their_marks.keys())
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            let (b, o, t) = (
+                base_marks.get(name),
+                our_marks.get(name),
+                their_marks.get(name),
+            );
+            let path = match three_way(&b, &o, &t) {
+                Some(path) => *path,
+                None => {
+                    let kind = ConflictKind::Bookmark {
+                        name: name.clone(),
+                        ours: o.cloned(),
+                        theirs: t.cloned(),
+                    };
+                    if let Some(at) = o.or(b) {
+                        merge.conflict(at, kind);
+                    }
+                    o
+                }
+            };
+            if let Some(index) = path.and_then(|path| merge.nodes.get(path)) {
+                merge.graph.set_bookmark(name.clone(), *index);
+            }
+        }
+
+        // tags, as sets of nodes
+        let tags = |board: &Board| {
+            board
+                .tags()
+                .iter()
+                .flat_map(|(tag, nodes)| {
+                    nodes
+                        .iter()
+                        .filter_map(|index| Some((tag.clone(), path_of(board, *index)?)))
+                })
+                .collect::<BTreeSet<_>>()
+        };
+        let (base_tags, our_tags, their_tags) = (tags(base), tags(ours), tags(theirs));
+        for (tag, path) in our_tags.union(&their_tags) {
+            let entry = (tag.clone(), path.clone());
+            let kept = our_tags.contains(&entry) && their_tags.contains(&entry)
+                || !base_tags.contains(&entry);
+            if let Some(index) = merge.nodes.get(path).filter(|_| kept) {
+                merge.graph.tag(*index, tag.clone());
+            }
+        }
+        // the edits of both sides, by time
+        let mut history = ours.history().to_vec();
+        for edit in theirs.history() {
+            if !history.contains(edit) {
+                history.push(edit.clone());
+            }
+        }
+        history.sort_by_key(|edit| edit.time);
+        for edit in history {
+            merge.graph.push_history(edit);
+        }
+        Merged {
+            graph: merge.graph,
+            conflicts: merge.conflicts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn merges() {
+        let mut base = Board::new();
+        base.set_title(Some("Club library".to_owned()));
+        base.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        let g7 = base.add_line(&p![[H, 8], [G, 7]]);
+        base.set_bookmark("Trap", g7);
+        base.add_line(&p![[H, 8], [I, 7], [J, 6]]);
+
+        let mut ours = base.map_payload(Clone::clone);
+        let j10 = ours.find_position(&p![[H, 8], [I, 9], [J, 10]]).unwrap();
+        ours.get_move_mut(j10)
+            .unwrap()
+            .set_oneline_comment("Ours".to_owned());
+        ours.add_line(&p![[H, 8], [I, 9], [K, 11]]);
+        let i7 = ours.find_position(&p![[H, 8], [I, 7]]).unwrap();
+        ours.get_move_mut(i7)
+            .unwrap()
+            .set_oneline_comment("Sharp".to_owned());
+        ours.tag(i7, "prep");
+
+        let mut theirs = base.map_payload(Clone::clone);
+        let j10 = theirs.find_position(&p![[H, 8], [I, 9], [J, 10]]).unwrap();
+        theirs
+            .get_move_mut(j10)
+            .unwrap()
+            .set_oneline_comment("Theirs".to_owned());
+        theirs.set_comment(Some("Shared over git".to_owned()));
+        // the newest nodes, so that no other node moves to their index
+        let i7 = theirs.find_position(&p![[H, 8], [I, 7]]).unwrap();
+        let j6 = theirs.find_position(&p![[H, 8], [I, 7], [J, 6]]).unwrap();
+        theirs.rm_move(j6);
+        theirs.rm_move(i7);
+        theirs.add_line(&p![[H, 8], [I, 9], [G, 9]]);
+
+        let Merged { graph, conflicts } = Board::merge3(&base, &ours, &theirs);
+        assert_eq!(graph.title(), Some("Club library"));
+        assert_eq!(graph.comment(), Some("Shared over git"));
+        let i9 = graph.find_position(&p![[H, 8], [I, 9]]).unwrap();
+        let children = graph
+            .get_children(&i9)
+            .into_iter()
+            .rev()
+            .map(|child| graph.get_move(child).unwrap().point)
+            .collect::<Vec<_>>();
+        assert_eq!(children, p![[J, 10], [K, 11], [G, 9]]);
+        let j10 = graph.find_position(&p![[H, 8], [I, 9], [J, 10]]).unwrap();
+        assert_eq!(
+            graph.get_move(j10).unwrap().oneline_comment.as_deref(),
+            Some("Ours")
+        );
+        assert_eq!(
+            graph.get_bookmark("Trap"),
+            graph.find_position(&p![[H, 8], [G, 7]])
+        );
+
+        // the line was removed by them but changed by us
+        let i7 = graph.find_position(&p![[H, 8], [I, 7]]).unwrap();
+        assert_eq!(graph.tagged("prep"), [i7]);
+        assert!(graph.find_position(&p![[H, 8], [I, 7], [J, 6]]).is_some());
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].kind,
+            ConflictKind::Text {
+                field: TextField::OnelineComment,
+                base: None,
+                ours: Some("Ours".to_owned()),
+                theirs: Some("Theirs".to_owned()),
+            }
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "H8 I9 J10: OnelineComment changed on both sides"
+        );
+        assert_eq!(
+            conflicts[1].kind,
+            ConflictKind::RemovedChanged {
+                removed_by: Side::Theirs
+            }
+        );
+    }
+}
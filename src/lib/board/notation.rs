@@ -0,0 +1,255 @@
+//! Positions as a line of moves, e.g `H8 I9 J10`, for sharing outside of a library.
+//!
+//! [`write_moves`] writes the moves separated by spaces. [`parse_moves`] also reads the compact
+//! form without spaces, `h8i9j10`, and moves separated by commas. [`position_moves`] gives a
+//! line for a position without a known move order, e.g from [`crate::screenshot`].
+
+use std::collections::BTreeSet;
+
+use super::{Board, BoardArr, BoardMarker, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+
+/// Write `moves` in board notation, separated by spaces.
+#[must_use]
+pub fn write_moves(moves: &[Point]) -> String {
+    moves
+        .iter()
+        .map(Point::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse a line of moves written by [`write_moves`] or without spaces.
+pub fn parse_moves(text: &str) -> Result<Vec<Point>, ParseError> {
+    let mut moves = vec![];
+    for token in text.split(|c: char| c.is_whitespace() || c == ',' || c == ';') {
+        if token.eq_ignore_ascii_case("pass") {
+            moves.push(Point::null());
+            continue;
+        }
+        // every move starts with its column
+        let mut rest = token;
+        while !rest.is_empty() {
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| c.is_ascii_alphabetic())
+                .map_or(rest.len(), |(i, _)| i);
+            moves.push(rest[..end].parse()?);
+            rest = &rest[end..];
+        }
+    }
+    Ok(moves)
+}
+
+/// Moves reaching the stones of `board`, black and white alternating and each in board order, if
+/// black has as many stones as white or one more.
+#[must_use]
+pub fn position_moves(board: &BoardArr) -> Option<Vec<Point>> {
+    let stones = |stone: Stone| {
+        let mut points = board
+            .iter()
+            .filter(|m| m.color == stone)
+            .map(|m| m.point)
+            .collect::<Vec<_>>();
+        points.sort();
+        points
+    };
+    let (black, white) = (stones(Stone::Black), stones(Stone::White));
+    if black.len() != white.len() && black.len() != white.len() + 1 {
+        return None;
+    }
+    let mut moves = Vec::with_capacity(black.len() + white.len());
+    for (i, point) in black.into_iter().enumerate() {
+        moves.push(point);
+        moves.extend(white.get(i));
+    }
+    Some(moves)
+}
+
+impl Board {
+    /// The node playing `moves` from the first root, adding the moves missing from the library.
+    pub fn add_line(&mut self, moves: &[Point]) -> MoveIndex {
+        let mut index = self.get_root();
+        for (i, point) in moves.iter().enumerate() {
+            let existing = self
+                .get_children(&index)
+                .into_iter()
+                .find(|child| self.get_move(*child).is_some_and(|m| m.point == *point));
+            index = match existing {
+                Some(child) => child,
+                None => {
+                    let stone = Stone::from_bool(i % 2 == 0);
+                    self.insert_move(index, BoardMarker::new(*point, stone))
+                }
+            };
+        }
+        index
+    }
+
+    /// The node playing `moves` after `from`, adding the moves missing, e.g. to keep the
+    /// continuation suggested by an engine. The first move is played by the side to move after
+    /// `from`. `None` if `from` is not in the graph.
+    pub fn add_variation(&mut self, from: MoveIndex, moves: &[Point]) -> Option<MoveIndex> {
+        let mut index = from;
+        let mut stone = match self.get_move(from)?.color {
+            Stone::Empty => Stone::Black,
+            color => color.opposite(),
+        };
+        for point in moves {
+            let existing = self
+                .get_children(&index)
+                .into_iter()
+                .find(|child| self.get_move(*child).is_some_and(|m| m.point == *point));
+            index = match existing {
+                Some(child) => child,
+                None => self.insert_move(index, BoardMarker::new(*point, stone)),
+            };
+            stone = stone.opposite();
+        }
+        Some(index)
+    }
+
+    /// A node reaching the position after `moves`, played alternately starting with black, in
+    /// any move order. Passes only change who is to move. A node playing `moves` in the given
+    /// order is preferred.
+    #[must_use]
+    pub fn find_position(&self, moves: &[Point]) -> Option<MoveIndex> {
+        let mut black = BTreeSet::new();
+        let mut white = BTreeSet::new();
+        for (i, point) in moves.iter().enumerate().filter(|(_, p)| !p.is_null) {
+            if i % 2 == 0 {
+                black.insert(*point);
+            } else {
+                white.insert(*point);
+            }
+        }
+        let stones = moves.iter().filter(|p| !p.is_null).collect::<Vec<_>>();
+        // (node, stones placed, still in the given order)
+        let mut stack = self
+            .roots()
+            .iter()
+            .map(|root| (*root, 0, true))
+            .collect::<Vec<_>>();
+        let mut transposed = None;
+        while let Some((index, placed, in_order)) = stack.pop() {
+            if placed == stones.len() {
+                if in_order {
+                    return Some(index);
+                }
+                transposed.get_or_insert(index);
+                continue;
+            }
+            let path = self.down_to_root(&index);
+            for child in self.get_children(&index) {
+                let Some(marker) = self.get_move(child) else {
+                    continue;
+                };
+                let wanted = match marker.color {
+                    Stone::Black => &black,
+                    Stone::White => &white,
+                    Stone::Empty => continue,
+                };
+                let played = || {
+                    path.iter()
+                        .any(|i| self.get_move(*i).is_some_and(|m| m.point == marker.point))
+                };
+                if marker.point.is_null || !wanted.contains(&marker.point) || played() {
+                    continue;
+                }
+                let in_order = in_order && *stones[placed] == marker.point;
+                stack.push((child, placed + 1, in_order));
+            }
+        }
+        transposed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardMarker;
+    use crate::p;
+
+    #[test]
+    fn moves() {
+        let moves = vec![p![H, 8], p![I, 9], Point::null(), p![J, 10]];
+        assert_eq!(write_moves(&moves), "H8 I9 pass J10");
+        assert_eq!(parse_moves("H8 I9 pass J10").unwrap(), moves);
+        assert_eq!(parse_moves("h8i9, pass j10").unwrap(), moves);
+        assert!(parse_moves("h8 i99").is_err());
+    }
+
+    #[test]
+    fn position_line() {
+        let mut position = BoardArr::new(15);
+        position.set_point(p![H, 8], Stone::Black);
+        position.set_point(p![I, 9], Stone::White);
+        position.set_point(p![G, 7], Stone::Black);
+        let moves = position_moves(&position).unwrap();
+        assert_eq!(moves.len(), 3);
+
+        let mut board = Board::new();
+        let added = board.add_line(&moves);
+        assert_eq!(board.add_line(&moves).node_index, added.node_index);
+        assert_eq!(
+            board.find_position(&moves).map(|i| i.node_index),
+            Some(added.node_index)
+        );
+        assert_eq!(board.as_board(&added).unwrap().1, moves);
+
+        let i9 = board.find_position(&moves[..2]).unwrap();
+        let variation = board.add_variation(i9, &p![[J, 10], [K, 11]]).unwrap();
+        assert_eq!(
+            board.get_move(variation).map(|m| (m.point, m.color)),
+            Some((p![K, 11], Stone::White))
+        );
+        let line = board.add_line(&[moves[0], moves[1], p![J, 10], p![K, 11]]);
+        assert_eq!(line.node_index, variation.node_index);
+
+        position.set_point(p![J, 10], Stone::Black);
+        assert_eq!(position_moves(&position), None);
+    }
+
+    #[test]
+    fn find_position() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let line = |board: &mut Board, moves: &[(Point, Stone)]| {
+            moves.iter().fold(root, |parent, (point, stone)| {
+                board.insert_move(parent, BoardMarker::new(*point, *stone))
+            })
+        };
+        let transposed = line(
+            &mut board,
+            &[
+                (p![H, 8], Stone::Black),
+                (p![I, 9], Stone::White),
+                (p![G, 7], Stone::Black),
+            ],
+        );
+        let direct = line(
+            &mut board,
+            &[
+                (p![G, 7], Stone::Black),
+                (p![I, 9], Stone::White),
+                (p![H, 8], Stone::Black),
+            ],
+        );
+        let moves = [p![G, 7], p![I, 9], p![H, 8]];
+        assert_eq!(
+            board.find_position(&moves).map(|i| i.node_index),
+            Some(direct.node_index)
+        );
+        let moves = [p![H, 8], p![I, 9], p![G, 7]];
+        assert_eq!(
+            board.find_position(&moves).map(|i| i.node_index),
+            Some(transposed.node_index)
+        );
+        assert_eq!(board.find_position(&[p![I, 9]]), None);
+        assert_eq!(
+            board.find_position(&[]).map(|i| i.node_index),
+            Some(root.node_index)
+        );
+    }
+}
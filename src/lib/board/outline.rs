@@ -0,0 +1,274 @@
+//! The variation tree as a collapsible outline, to browse libraries too large to print whole.
+//!
+//! [`Outline`] keeps which branches are expanded, the path to the current move and what is
+//! searched for. [`Outline::rows`] walks only the visible part of the tree, so showing a
+//! screenful of a library with tens of thousands of nodes costs about a screenful of work.
+//!
+//! A move with a single continuation is followed by it on the next row, the continuations of a
+//! branch are indented under it, the first one added on top.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use super::{Board, MoveIndex, NodeIndex, Point, Stone};
+use crate::motif::{self, Motif, TAG_PREFIX};
+
+/// What is shown of a [`Board`], see the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Outline {
+    expanded: HashSet<NodeIndex>,
+    /// The current move and the moves leading to it.
+    path: HashSet<NodeIndex>,
+    query: Option<Search>,
+}
+
+#[derive(Clone, Debug)]
+struct Search {
+    matched: HashSet<NodeIndex>,
+    /// The matches and the moves leading to them.
+    shown: HashSet<NodeIndex>,
+}
+
+/// A move of the [`Outline`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Row {
+    pub index: MoveIndex,
+    /// The branches the move is in.
+    pub depth: usize,
+    pub number: usize,
+    pub point: Point,
+    pub color: Stone,
+    /// The continuations of the move, it's a branch with more than one.
+    pub branches: usize,
+    pub expanded: bool,
+    pub commented: bool,
+    /// The move is the current one or leads to it.
+    pub current: bool,
+    pub matched: bool,
+}
+
+impl Outline {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `index` the current move, expanding the branches leading to it.
+    pub fn set_current(&mut self, board: &Board, index: MoveIndex) {
+        self.path = board
+            .down_to_root(&index)
+            .iter()
+            .map(MoveIndex::node_index)
+            .collect();
+        self.expanded.extend(self.path.iter().copied());
+    }
+
+    /// Expand or collapse the branch at `index`, returns whether it's expanded now.
+    pub fn toggle(&mut self, index: MoveIndex) -> bool {
+        let node = index.node_index();
+        if !self.expanded.remove(&node) {
+            self.expanded.insert(node);
+        }
+        self.expanded.contains(&node)
+    }
+
+    /// Show only the moves at `query`, e.g `h8`, or with a comment containing it, ignoring case,
+    /// and the moves leading to them, which are expanded. A query like `motif:vcf` shows the moves
+    /// with the [`Motif`]. An empty query shows everything again.
+    ///
+    /// Returns the number of moves found.
+    pub fn search(&mut self, board: &Board, query: &str) -> usize {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            self.query = None;
+            return 0;
+        }
+        let mut search = Search {
+            matched: HashSet::new(),
+            shown: HashSet::new(),
+        };
+        let with_motif = query
+            .strip_prefix(TAG_PREFIX)
+            .and_then(|name| name.parse::<Motif>().ok())
+            .map(|motif| {
+                motif::with_motif(board, &motif)
+                    .iter()
+                    .map(MoveIndex::node_index)
+                    .collect::<HashSet<_>>()
+            });
+        let mut stack = board.roots().to_vec();
+        while let Some(index) = stack.pop() {
+            stack.extend(board.get_children(&index));
+            let Some(marker) = board.get_move(index) else {
+                continue;
+            };
+            let found = board.get_parent_strong(&index).is_some()
+                && match &with_motif {
+                    Some(nodes) => nodes.contains(&index.node_index()),
+                    None => {
+                        marker.point.to_string().to_lowercase() == query
+                            || [&marker.oneline_comment, &marker.multiline_comment]
+                                .into_iter()
+                                .flatten()
+                                .any(|c| c.to_lowercase().contains(&query))
+                    }
+                };
+            if found {
+                search.matched.insert(index.node_index());
+                for step in board.down_to_root(&index).into_iter().skip(1) {
+                    if !search.shown.insert(step.node_index()) {
+                        break;
+                    }
+                    self.expanded.insert(step.node_index());
+                }
+                search.shown.insert(index.node_index());
+            }
+        }
+        let found = search.matched.len();
+        self.query = Some(search);
+        found
+    }
+
+    /// The visible moves of every tree of `board`, from the top.
+    #[must_use]
+    pub fn rows<'a>(&'a self, board: &'a Board) -> Rows<'a> {
+        let mut rows = Rows {
+            board,
+            outline: self,
+            stack: vec![],
+        };
+        for root in board.roots().iter().rev() {
+            rows.push(*root, 0, 1);
+        }
+        rows
+    }
+
+    fn is_shown(&self, index: MoveIndex) -> bool {
+        self.query
+            .as_ref()
+            .is_none_or(|search| search.shown.contains(&index.node_index()))
+    }
+}
+
+/// The rows of an [`Outline`], walked as they are taken.
+pub struct Rows<'a> {
+    board: &'a Board,
+    outline: &'a Outline,
+    /// The moves still to show with their depth and number, the next one last.
+    stack: Vec<(MoveIndex, usize, usize)>,
+}
+
+impl Rows<'_> {
+    /// Queue the shown continuations of `parent`.
+    fn push(&mut self, parent: MoveIndex, depth: usize, number: usize) {
+        // children are listed newest first, so the first one added is taken first
+        let children = self.board.get_children(&parent);
+        let depth = match children.len() {
+            1 => depth,
+            _ => depth + 1,
+        };
+        self.stack.extend(
+            children
+                .into_iter()
+                .filter(|child| self.outline.is_shown(*child))
+                .map(|child| (child, depth, number)),
+        );
+    }
+}
+
+impl Iterator for Rows<'_> {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        let (index, depth, number) = self.stack.pop()?;
+        let node = index.node_index();
+        let marker = self.board.get_move(index)?;
+        let branches = self.board.get_children(&index).len();
+        let expanded = branches < 2 || self.outline.expanded.contains(&node);
+        if expanded {
+            self.push(index, depth, number + 1);
+        }
+        Some(Row {
+            index,
+            depth,
+            number,
+            point: marker.point,
+            color: marker.color,
+            branches,
+            expanded,
+            commented: marker.oneline_comment.is_some() || marker.multiline_comment.is_some(),
+            current: self.outline.path.contains(&node),
+            matched: (self.outline.query.as_ref())
+                .is_some_and(|search| search.matched.contains(&node)),
+        })
+    }
+}
+
+/// Like `> + 12. [H8] #`, `>` on the current path, `+` and `-` on collapsed and expanded
+/// branches, the move in brackets if it's found and `#` if it has a comment.
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let current = if self.current { '>' } else { ' ' };
+        let fold = match (self.branches > 1, self.expanded) {
+            (false, _) => ' ',
+            (true, true) => '-',
+            (true, false) => '+',
+        };
+        let indent = "  ".repeat(self.depth);
+        write!(f, "{current}{indent}{fold} {}. ", self.number)?;
+        match self.matched {
+            true => write!(f, "[{}]", self.point)?,
+            false => write!(f, "{}", self.point)?,
+        }
+        if self.commented {
+            write!(f, " #")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn collapses_and_searches() {
+        let mut graph = Board::new();
+        let k11 = graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        let g7 = graph.add_line(&p![[H, 8], [I, 9], [G, 7]]);
+        graph.get_move_mut(g7).unwrap().oneline_comment = Some("Solid".to_owned());
+        let i9 = graph.down_to_root(&g7)[1];
+
+        let mut outline = Outline::new();
+        let rows = outline.rows(&graph).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].point, p![I, 9]);
+        assert_eq!((rows[1].branches, rows[1].expanded), (2, false));
+        assert_eq!(rows[1].to_string(), " + 2. I9");
+
+        outline.set_current(&graph, k11);
+        let rows = outline.rows(&graph).collect::<Vec<_>>();
+        let moves = rows.iter().map(|r| r.point).collect::<Vec<_>>();
+        assert_eq!(moves, p![[H, 8], [I, 9], [J, 10], [K, 11], [G, 7]]);
+        assert_eq!(rows[2].depth, 1);
+        assert_eq!(rows[3].to_string(), ">    4. K11");
+        assert_eq!(rows[4].to_string(), "     3. G7 #");
+        assert_eq!(outline.rows(&graph).take(2).count(), 2);
+
+        assert!(!outline.toggle(i9));
+        assert_eq!(outline.rows(&graph).count(), 2);
+
+        assert_eq!(outline.search(&graph, "solid"), 1);
+        let rows = outline.rows(&graph).collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3, "{rows:?}");
+        assert_eq!(rows[2].to_string(), "     3. [G7] #");
+        assert_eq!(outline.search(&graph, "h8"), 1);
+        assert_eq!(outline.rows(&graph).count(), 1);
+        motif::add(&mut graph, k11, &Motif::ForbiddenTrap);
+        assert_eq!(outline.search(&graph, "Motif:Forbidden-Trap"), 1);
+        assert_eq!(outline.rows(&graph).count(), 4);
+        assert_eq!(outline.search(&graph, ""), 0);
+        assert_eq!(outline.rows(&graph).count(), 5);
+    }
+}
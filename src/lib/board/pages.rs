@@ -0,0 +1,169 @@
+//! Nodes a few at a time, for frontends browsing libraries too large to send at once.
+//!
+//! [`Board::children_page`] lists part of the continuations of a node as [`NodeSummary`]s, which
+//! hold what is needed to show a move and decide whether to open it, but none of the nodes after
+//! it. With the `serde` feature they serialize to JSON.
+
+use std::collections::HashSet;
+
+use super::{Board, MoveIndex, Payload, Point, Stone};
+
+/// What a frontend shows of a node before opening it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeSummary {
+    /// The number of the node, as parsed by [`MoveIndex`]'s `FromStr`.
+    pub id: usize,
+    pub point: Point,
+    pub color: Stone,
+    pub has_children: bool,
+    /// Whether the node has a comment or board text.
+    pub has_comment: bool,
+    pub stats: NodeStats,
+}
+
+/// Sizes of the tree after a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeStats {
+    pub children: usize,
+    /// Every node after the node, nodes with several parents counted once.
+    pub descendants: usize,
+    /// The continuations without continuations of their own.
+    pub leaves: usize,
+}
+
+/// A part of a list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Page<T> {
+    /// The index of the first item in the whole list.
+    pub offset: usize,
+    /// The length of the whole list.
+    pub total: usize,
+    pub items: Vec<T>,
+}
+
+impl<T: Payload> Board<T> {
+    /// A summary of `index`, `None` if it is not in the graph. The stats walk every node after it.
+    #[must_use]
+    pub fn summary(&self, index: MoveIndex) -> Option<NodeSummary> {
+        let marker = self.get_move(index)?.marker();
+        let children = self.get_children(&index);
+        Some(NodeSummary {
+            id: index.node_index().index(),
+            point: marker.point,
+            color: marker.color,
+            has_children: !children.is_empty(),
+            has_comment: marker.oneline_comment.is_some()
+                || marker.multiline_comment.is_some()
+                || marker.board_text.is_some(),
+            stats: self.stats(index, children.len()),
+        })
+    }
+
+    fn stats(&self, index: MoveIndex, children: usize) -> NodeStats {
+        let mut stats = NodeStats {
+            children,
+            descendants: 0,
+            leaves: 0,
+        };
+        let mut visited = HashSet::new();
+        let mut stack = self.get_children(&index);
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node.node_index()) {
+                continue;
+            }
+            stats.descendants += 1;
+            let children = self.get_children(&node);
+            if children.is_empty() {
+                stats.leaves += 1;
+            }
+            stack.extend(children);
+        }
+        stats
+    }
+
+    /// At most `limit` continuations of `index` from the `offset`th, oldest first.
+    ///
+    /// The page is empty past the end, or if `index` is not in the graph.
+    #[must_use]
+    pub fn children_page(
+        &self,
+        index: MoveIndex,
+        offset: usize,
+        limit: usize,
+    ) -> Page<NodeSummary> {
+        // children are listed newest first
+        let mut children = self.get_children(&index);
+        children.reverse();
+        Page {
+            offset,
+            total: children.len(),
+            items: children
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .filter_map(|child| self.summary(child))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn pages() {
+        let mut graph = Board::new();
+        let h8 = graph.add_line(&p![[H, 8]]);
+        for point in p![[I, 9], [I, 8], [I, 7], [H, 9], [H, 7]] {
+            graph.add_line(&[p![H, 8], point]);
+        }
+        let i9 = graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        graph.add_line(&p![[H, 8], [I, 9], [J, 10], [G, 7]]);
+        let i9 = graph.down_to_root(&i9)[2];
+        graph
+            .get_move_mut(i9)
+            .unwrap()
+            .set_oneline_comment("Main line".to_owned());
+
+        let page = graph.children_page(h8, 0, 2);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.items.len(), 2);
+        let first = &page.items[0];
+        assert_eq!((first.point, first.color), (p![I, 9], Stone::White));
+        assert!(first.has_children && first.has_comment);
+        assert_eq!(
+            first.stats,
+            NodeStats {
+                children: 1,
+                descendants: 3,
+                leaves: 2
+            }
+        );
+        assert_eq!(
+            first
+                .id
+                .to_string()
+                .parse::<MoveIndex>()
+                .unwrap()
+                .node_index(),
+            i9.node_index()
+        );
+        assert!(!page.items[1].has_children);
+
+        let last = graph.children_page(h8, 4, 2);
+        assert_eq!(last.items.len(), 1);
+        assert_eq!(last.items[0].point, p![H, 7]);
+        assert!(graph.children_page(h8, 10, 2).items.is_empty());
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&last).unwrap();
+            assert_eq!(json["total"], 5);
+            assert_eq!(json["items"][0]["has_comment"], false);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Pattern matching of five, four and three templates on [`Lines`].
+//!
+//! The templates are the window shapes matched by
+//! [`BoardArr::renju_conditions`](super::BoardArr::renju_conditions), without its bookkeeping of
+//! forbidden points and fives. A line is padded with [`PADDING`] border cells on both sides, as
+//! the evaluator does, and [`scan`] returns the index in the line of the point to play for every
+//! match.
+//!
+//! With the `simd` feature, lines are matched as bitboards in a `u64`, testing every offset at
+//! once. Without it, or for lines too long to fit, [`scan_scalar`] compares the cells of every
+//! window.
+
+use std::collections::BTreeSet;
+
+use super::lines::{Line, Lines};
+use super::{Point, Stone};
+
+/// Border cells added on both sides of a line.
+pub const PADDING: usize = 2;
+
+/// What a template requires of a cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cell {
+    /// A stone of the color matched for.
+    Same,
+    /// An empty point.
+    Empty,
+    /// Anything but a stone of the color matched for, including the border.
+    Open,
+    /// Anything, including the border.
+    Any,
+}
+
+/// What playing on the place of a template creates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PatternKind {
+    Five,
+    /// Six or more in a row, only matched for black.
+    Overline,
+    /// Any four, see [`RenjuCondition`](super::evaluator::RenjuCondition) for the kinds.
+    Four,
+    /// A three which can become a straight four.
+    Three,
+}
+
+/// A template of consecutive cells in a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub cells: &'static [Cell],
+    /// The index in `cells` of the point to play, always an [`Cell::Empty`].
+    pub place: usize,
+}
+
+use Cell::{Any as A, Empty as E, Open as O, Same as S};
+
+const fn pattern(kind: PatternKind, cells: &'static [Cell], place: usize) -> Pattern {
+    Pattern { kind, cells, place }
+}
+
+const BLACK_FIVES: &[Pattern] = &[
+    pattern(PatternKind::Five, &[O, S, S, S, S, E, O], 5),
+    pattern(PatternKind::Five, &[O, E, S, S, S, S, O], 1),
+];
+
+const WHITE_FIVES: &[Pattern] = &[
+    pattern(PatternKind::Five, &[A, S, S, S, S, E, A], 5),
+    pattern(PatternKind::Five, &[A, E, S, S, S, S, A], 1),
+];
+
+const OVERLINES: &[Pattern] = &[
+    pattern(PatternKind::Overline, &[E, S, S, S, S, S], 0),
+    pattern(PatternKind::Overline, &[S, E, S, S, S, S], 1),
+    pattern(PatternKind::Overline, &[S, S, E, S, S, S], 2),
+    pattern(PatternKind::Overline, &[S, S, S, E, S, S], 3),
+    pattern(PatternKind::Overline, &[S, S, S, S, E, S], 4),
+    pattern(PatternKind::Overline, &[S, S, S, S, S, E], 5),
+];
+
+const FOURS: &[Pattern] = &[
+    pattern(PatternKind::Four, &[A, E, E, S, S, S, O], 2),
+    pattern(PatternKind::Four, &[O, E, E, S, S, S, O], 1),
+    pattern(PatternKind::Four, &[O, S, S, S, E, E, A], 4),
+    pattern(PatternKind::Four, &[O, S, S, S, E, E, O], 5),
+    pattern(PatternKind::Four, &[A, E, S, E, S, S, O], 3),
+    pattern(PatternKind::Four, &[O, E, S, E, S, S, O], 1),
+    pattern(PatternKind::Four, &[O, S, S, E, S, E, A], 3),
+    pattern(PatternKind::Four, &[O, S, S, E, S, E, O], 5),
+];
+
+const WHITE_THREES: &[Pattern] = &[
+    pattern(PatternKind::Three, &[A, E, E, E, S, S, E, O, A], 3),
+    pattern(PatternKind::Three, &[O, E, E, E, S, S, E, O, A], 2),
+    pattern(PatternKind::Three, &[A, O, E, S, S, E, E, E, A], 5),
+    pattern(PatternKind::Three, &[A, O, E, S, S, E, E, E, O], 6),
+    pattern(PatternKind::Three, &[A, E, E, S, E, S, E, O, A], 4),
+    pattern(PatternKind::Three, &[O, E, E, S, E, S, E, O, A], 2),
+    pattern(PatternKind::Three, &[O, E, S, E, S, E, E, A, A], 3),
+    pattern(PatternKind::Three, &[O, E, S, E, S, E, E, O, A], 5),
+    pattern(PatternKind::Three, &[O, E, S, E, E, S, E, O, A], 3),
+    pattern(PatternKind::Three, &[O, E, S, E, E, S, E, O, A], 4),
+];
+
+/// As for white, except that `X.._XX.X` and `X.XX_..X` can't become a straight four without an
+/// overline.
+const BLACK_THREES: &[Pattern] = &[
+    pattern(PatternKind::Three, &[O, E, E, E, S, S, E, O, A], 3),
+    pattern(PatternKind::Three, &[S, E, E, E, S, S, E, O, O], 3),
+    pattern(PatternKind::Three, &[O, E, E, E, S, S, E, O, A], 2),
+    pattern(PatternKind::Three, &[A, O, E, S, S, E, E, E, O], 5),
+    pattern(PatternKind::Three, &[O, O, E, S, S, E, E, E, S], 5),
+    pattern(PatternKind::Three, &[A, O, E, S, S, E, E, E, O], 6),
+    pattern(PatternKind::Three, &[A, E, E, S, E, S, E, O, A], 4),
+    pattern(PatternKind::Three, &[O, E, E, S, E, S, E, O, A], 2),
+    pattern(PatternKind::Three, &[O, E, S, E, S, E, E, A, A], 3),
+    pattern(PatternKind::Three, &[O, E, S, E, S, E, E, O, A], 5),
+    pattern(PatternKind::Three, &[O, E, S, E, E, S, E, O, A], 3),
+    pattern(PatternKind::Three, &[O, E, S, E, E, S, E, O, A], 4),
+];
+
+/// The templates of `kind` for `stone`, none for an empty stone.
+#[must_use]
+pub fn patterns(kind: PatternKind, stone: Stone) -> &'static [Pattern] {
+    if stone.is_empty() {
+        return &[];
+    }
+    match (kind, stone.is_black()) {
+        (PatternKind::Five, true) => BLACK_FIVES,
+        (PatternKind::Five, false) => WHITE_FIVES,
+        (PatternKind::Overline, true) => OVERLINES,
+        (PatternKind::Overline, false) => &[],
+        (PatternKind::Four, _) => FOURS,
+        (PatternKind::Three, true) => BLACK_THREES,
+        (PatternKind::Three, false) => WHITE_THREES,
+    }
+}
+
+/// The index in `line` of the place of every match of `pattern` for `stone`, in order.
+#[must_use]
+pub fn scan(line: &Line<'_>, stone: Stone, pattern: &Pattern) -> Vec<usize> {
+    #[cfg(feature = "simd")]
+    if let Some(places) = scan_simd(line, stone, pattern) {
+        return places;
+    }
+    scan_scalar(line, stone, pattern)
+}
+
+/// [`scan`] by comparing every window of the line.
+#[must_use]
+pub fn scan_scalar(line: &Line<'_>, stone: Stone, pattern: &Pattern) -> Vec<usize> {
+    // `None` is the border
+    let cell = |i: usize| {
+        i.checked_sub(PADDING)
+            .and_then(|i| line.stones.get(i))
+            .copied()
+    };
+    let width = pattern.cells.len();
+    let padded = line.stones.len() + 2 * PADDING;
+    (0..(padded + 1).saturating_sub(width))
+        .filter(|offset| {
+            pattern.cells.iter().enumerate().all(|(k, c)| {
+                let s = cell(offset + k);
+                match c {
+                    Cell::Same => s == Some(stone),
+                    Cell::Empty => s == Some(Stone::Empty),
+                    Cell::Open => s != Some(stone),
+                    Cell::Any => true,
+                }
+            })
+        })
+        .map(|offset| offset + pattern.place - PADDING)
+        .collect()
+}
+
+/// [`scan`] on bitboards of the line, `None` if the padded line doesn't fit in a `u64`.
+#[cfg(feature = "simd")]
+#[must_use]
+pub fn scan_simd(line: &Line<'_>, stone: Stone, pattern: &Pattern) -> Option<Vec<usize>> {
+    let padded = line.stones.len() + 2 * PADDING;
+    let width = pattern.cells.len();
+    if padded > 64 || padded < width {
+        return (padded <= 64).then(Vec::new);
+    }
+    let inside = if padded == 64 {
+        u64::MAX
+    } else {
+        (1 << padded) - 1
+    };
+    let (mut same, mut empty) = (0u64, 0u64);
+    for (i, s) in line.stones.iter().enumerate() {
+        if *s == stone {
+            same |= 1 << (i + PADDING);
+        } else if s.is_empty() {
+            empty |= 1 << (i + PADDING);
+        }
+    }
+    let open = !same & inside;
+    // bit `offset` is set if the window starting at `offset` matches every cell
+    let mut matches = inside >> (width - 1);
+    for (k, c) in pattern.cells.iter().enumerate() {
+        matches &= match c {
+            Cell::Same => same >> k,
+            Cell::Empty => empty >> k,
+            Cell::Open => open >> k,
+            Cell::Any => continue,
+        };
+    }
+    let mut places = Vec::with_capacity(matches.count_ones() as usize);
+    while matches != 0 {
+        let offset = matches.trailing_zeros() as usize;
+        places.push(offset + pattern.place - PADDING);
+        matches &= matches - 1;
+    }
+    Some(places)
+}
+
+/// Every point on which `stone` would create a pattern of `kind`, by the templates alone.
+#[must_use]
+pub fn places(lines: &Lines, stone: Stone, kind: PatternKind) -> BTreeSet<Point> {
+    let patterns = patterns(kind, stone);
+    lines
+        .iter()
+        .flat_map(|line| {
+            patterns
+                .iter()
+                .flat_map(move |pattern| scan(&line, stone, pattern))
+                .map(move |i| line.points[i])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::evaluator::{ForbiddenReason, RenjuCondition};
+    use crate::board::{BoardArr, Evaluate};
+    use rand::rngs::SmallRng;
+    use rand::seq::IteratorRandom;
+    use rand::{Rng, SeedableRng};
+
+    /// Random positions of 10 to 40 stones within 5 lines of the center, black to move.
+    fn boards() -> impl Iterator<Item = BoardArr> {
+        let mut rng = SmallRng::seed_from_u64(3);
+        (0..60).map(move |_| {
+            let mut board = BoardArr::new(15);
+            let stones = rng.gen_range(10..=40) & !1;
+            for i in 0..stones {
+                let point = board
+                    .iter()
+                    .filter(|m| {
+                        m.color.is_empty()
+                            && m.point.x.abs_diff(7) <= 5
+                            && m.point.y.abs_diff(7) <= 5
+                    })
+                    .map(|m| m.point)
+                    .choose(&mut rng)
+                    .expect("there are empty points");
+                board.set_point(point, Stone::from_bool(i % 2 == 0));
+            }
+            board
+        })
+    }
+
+    fn condition_places(
+        conditions: &BTreeSet<RenjuCondition>,
+        f: impl Fn(&RenjuCondition) -> bool,
+    ) -> BTreeSet<Point> {
+        conditions
+            .iter()
+            .filter(|c| f(c))
+            .map(|c| *c.place())
+            .collect()
+    }
+
+    #[test]
+    fn matches_the_evaluator() {
+        for board in boards() {
+            let lines = board.lines();
+            let black = board.renju_conditions(Stone::Black, None);
+            let white = board.renju_conditions(Stone::White, None);
+            let is_five = |c: &RenjuCondition| matches!(c, RenjuCondition::Five { .. });
+            let black_fives = condition_places(&black.conditions, is_five);
+            let white_fives = condition_places(&white.conditions, is_five);
+            assert_eq!(places(lines, Stone::Black, PatternKind::Five), black_fives);
+            assert_eq!(places(lines, Stone::White, PatternKind::Five), white_fives);
+
+            let overlines = black
+                .reasons
+                .iter()
+                .filter(|(_, r)| **r == ForbiddenReason::Overline)
+                .map(|(p, _)| *p)
+                .collect::<BTreeSet<_>>();
+            assert_eq!(
+                places(lines, Stone::Black, PatternKind::Overline),
+                overlines
+            );
+
+            // black fours and threes are filtered by forbidden points
+            let fours = condition_places(&white.conditions, |c| {
+                matches!(
+                    c,
+                    RenjuCondition::StraightFour { .. }
+                        | RenjuCondition::ClosedFour { .. }
+                        | RenjuCondition::BrokenFour { .. }
+                )
+            });
+            assert_eq!(places(lines, Stone::White, PatternKind::Four), fours);
+            // and threes of both colors next to fives
+            if white_fives.is_empty() {
+                let threes = white.threes.iter().map(|(c, _)| *c.place()).collect();
+                assert_eq!(places(lines, Stone::White, PatternKind::Three), threes);
+            }
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_matches_scalar() {
+        for board in boards() {
+            for line in board.lines().iter() {
+                for stone in [Stone::Black, Stone::White] {
+                    for kind in [
+                        PatternKind::Five,
+                        PatternKind::Overline,
+                        PatternKind::Four,
+                        PatternKind::Three,
+                    ] {
+                        for pattern in patterns(kind, stone) {
+                            assert_eq!(
+                                scan_simd(&line, stone, pattern),
+                                Some(scan_scalar(&line, stone, pattern)),
+                                "{pattern:?} on {:?}",
+                                line.stones
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
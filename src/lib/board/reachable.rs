@@ -0,0 +1,308 @@
+//! Whether a position can come up in a game, to catch positions set up by hand or imported
+//! wrong.
+//!
+//! Only what the stones themselves tell is checked: black moves first, so has as many stones as
+//! white or one more, and the game ends with the first row of five or more, so every such row has
+//! to go through the last move, played by the side with the last stone.
+//!
+//! [`BoardArr::reconstruct_moves`] goes further and looks for an order of the moves reaching the
+//! position, for positions without one, like the ones read from an image, to store them in a
+//! library.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use super::{BoardArr, Point, Stone};
+use crate::limits::{Budget, Exhausted, Limits, Outcome};
+
+/// Why a position can't come up in a game, see [`BoardArr::unreachable`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Unreachable {
+    /// Black doesn't have as many stones as white or one more.
+    StoneCount { black: usize, white: usize },
+    /// Both sides have a row of five.
+    BothWon,
+    /// `stone` has a row of five, but the other side moved last.
+    NotLastMove { stone: Stone },
+    /// The rows of five of `stone` don't share a stone, so the game was over before the last move.
+    PlayedOn { stone: Stone },
+    /// Every order of the moves ends the game early or has black play a forbidden point.
+    NoOrder,
+}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unreachable::StoneCount { black, white } => write!(
+                f,
+                "black has {black} stones and white {white}, black must have as many stones as white or one more"
+            ),
+            Unreachable::BothWon => write!(f, "both sides have a row of five"),
+            Unreachable::NotLastMove { stone } => {
+                write!(f, "{stone:?} has a row of five but did not move last")
+            }
+            Unreachable::PlayedOn { stone } => write!(
+                f,
+                "{stone:?} has rows of five no single move made, the game went on after it ended"
+            ),
+            Unreachable::NoOrder => write!(
+                f,
+                "every order of the moves ends the game early or has black play a forbidden point"
+            ),
+        }
+    }
+}
+
+impl BoardArr {
+    /// Whether the position can come up in a game, see [`Self::unreachable`].
+    #[must_use]
+    pub fn is_reachable(&self) -> bool {
+        self.unreachable().is_none()
+    }
+
+    /// Why the position can't come up in a game, see the [module documentation](self).
+    #[must_use]
+    pub fn unreachable(&self) -> Option<Unreachable> {
+        let count = |stone| self.iter().filter(|m| m.color == stone).count();
+        let (black, white) = (count(Stone::Black), count(Stone::White));
+        if black != white && black != white + 1 {
+            return Some(Unreachable::StoneCount { black, white });
+        }
+        let (black_rows, white_rows) = self.rows();
+        let rows = match (black_rows.is_empty(), white_rows.is_empty()) {
+            (true, true) => return None,
+            (false, false) => return Some(Unreachable::BothWon),
+            (false, true) => (Stone::Black, black_rows),
+            (true, false) => (Stone::White, white_rows),
+        };
+        let (stone, mut rows) = rows;
+        if stone != self.last_mover() {
+            return Some(Unreachable::NotLastMove { stone });
+        }
+        let mut common = rows.pop().unwrap_or_default();
+        for row in rows {
+            common.retain(|point| row.contains(point));
+        }
+        common.is_empty().then_some(Unreachable::PlayedOn { stone })
+    }
+
+    /// Moves reaching the position, black and white alternating, or why there are none.
+    ///
+    /// The moves are taken back one at a time from the last, farthest from the center first so the
+    /// game starts in the middle. A move can be taken back if there is no row of five or more
+    /// without it, and for black if it isn't forbidden without it, but for the last move, which
+    /// may have ended the game. Every stone taken back counts as a node of `limits`, the search is
+    /// [`Outcome::Unknown`] when they run out.
+    #[must_use]
+    pub fn reconstruct_moves(&self, limits: &Limits) -> Outcome<Result<Vec<Point>, Unreachable>> {
+        if let Some(reason) = self.unreachable() {
+            return Outcome::Known(Err(reason));
+        }
+        let mut board = self.clone();
+        let mut moves = vec![];
+        let mut budget = Budget::new(limits);
+        let last = self.last_mover();
+        let mut failed = BTreeSet::new();
+        match take_back(&mut board, last, true, &mut moves, &mut failed, &mut budget) {
+            Ok(true) => Outcome::Known(Ok(moves)),
+            Ok(false) => Outcome::Known(Err(Unreachable::NoOrder)),
+            Err(Exhausted) => Outcome::Unknown,
+        }
+    }
+
+    /// The side of the last move, white when both sides have as many stones.
+    fn last_mover(&self) -> Stone {
+        let count = |stone| self.iter().filter(|m| m.color == stone).count();
+        match count(Stone::Black) == count(Stone::White) {
+            true => Stone::White,
+            false => Stone::Black,
+        }
+    }
+
+    /// The rows of five or more of black and of white, each as the stones that could have been
+    /// played last.
+    fn rows(&self) -> (Vec<BTreeSet<Point>>, Vec<BTreeSet<Point>>) {
+        let (mut black_rows, mut white_rows) = (vec![], vec![]);
+        for line in self.lines().iter() {
+            let mut start = 0;
+            while start < line.stones.len() {
+                let stone = line.stones[start];
+                let end = start
+                    + line.stones[start..]
+                        .iter()
+                        .take_while(|s| **s == stone)
+                        .count();
+                if end - start >= 5 {
+                    let row = &line.points[start..end];
+                    match stone {
+                        Stone::Black => black_rows.push(last_moves(row)),
+                        Stone::White => white_rows.push(last_moves(row)),
+                        _ => {}
+                    }
+                }
+                start = end;
+            }
+        }
+        (black_rows, white_rows)
+    }
+}
+
+/// Take back the moves of `board` from a move of `stone`, the `last` one of the game, pushing them
+/// to `moves` from the first one if they all can be. `failed` holds the positions no order was
+/// found for.
+fn take_back(
+    board: &mut BoardArr,
+    stone: Stone,
+    last: bool,
+    moves: &mut Vec<Point>,
+    failed: &mut BTreeSet<Vec<Point>>,
+    budget: &mut Budget,
+) -> Result<bool, Exhausted> {
+    let mut stones = board
+        .iter()
+        .filter(|m| !m.color.is_empty())
+        .map(|m| m.point)
+        .collect::<Vec<_>>();
+    if stones.is_empty() {
+        return Ok(true);
+    }
+    stones.sort();
+    if failed.contains(&stones) {
+        return Ok(false);
+    }
+    let center = board.size() / 2;
+    let distance = |point: &Point| point.x.abs_diff(center).max(point.y.abs_diff(center));
+    let mut candidates = stones
+        .iter()
+        .copied()
+        .filter(|point| board.get_point(*point).is_some_and(|m| m.color == stone))
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|point| std::cmp::Reverse(distance(point)));
+    for point in candidates {
+        budget.spend()?;
+        board.set_point(point, Stone::Empty);
+        let (black_rows, white_rows) = board.rows();
+        let playable = black_rows.is_empty()
+            && white_rows.is_empty()
+            && (last || !board.forbidden_points(stone).contains(&point));
+        if playable && take_back(board, stone.opposite(), false, moves, failed, budget)? {
+            board.set_point(point, stone);
+            moves.push(point);
+            return Ok(true);
+        }
+        board.set_point(point, stone);
+    }
+    failed.insert(stones);
+    Ok(false)
+}
+
+/// The stones of `row` that could have been played last, without them there is no row of five
+/// left, the ones at most four stones from either end.
+fn last_moves(row: &[Point]) -> BTreeSet<Point> {
+    let len = row.len();
+    row.iter()
+        .enumerate()
+        .filter(|(i, _)| *i < 5 && len - i <= 5)
+        .map(|(_, point)| *point)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn reachable() {
+        assert!(BoardArr::new(15).is_reachable());
+        let white = p![[A, 1], [A, 3], [A, 5], [A, 7]];
+        let five = p![[H, 8], [I, 8], [J, 8], [K, 8], [L, 8]];
+        assert!(BoardArr::from_stones(15, &five, &white).is_reachable());
+        assert_eq!(
+            BoardArr::from_stones(15, &five[..2], &[]).unreachable(),
+            Some(Unreachable::StoneCount { black: 2, white: 0 })
+        );
+        assert_eq!(
+            BoardArr::from_stones(15, &five, &p![[A, 1], [A, 3], [A, 5], [A, 7], [A, 9]])
+                .unreachable(),
+            Some(Unreachable::NotLastMove {
+                stone: Stone::Black
+            })
+        );
+        // a cross through L8, then two rows without a common stone
+        let cross = p![[L, 4], [L, 5], [L, 6], [L, 7]];
+        let white = p![
+            [A, 1],
+            [A, 3],
+            [A, 5],
+            [A, 7],
+            [A, 9],
+            [A, 11],
+            [A, 13],
+            [C, 1]
+        ];
+        assert!(BoardArr::from_stones(15, &[&five[..], &cross].concat(), &white).is_reachable());
+        let apart = p![[H, 4], [I, 4], [J, 4], [K, 4], [L, 4]];
+        let white = [&white[..], &p![[C, 3]]].concat();
+        assert_eq!(
+            BoardArr::from_stones(15, &[&five[..], &apart].concat(), &white).unreachable(),
+            Some(Unreachable::PlayedOn {
+                stone: Stone::Black
+            })
+        );
+        // an overline ends the game too
+        let six = p![[H, 8], [I, 8], [J, 8], [K, 8], [L, 8], [M, 8]];
+        assert!(BoardArr::from_stones(15, &six, &white[..5]).is_reachable());
+        let both = BoardArr::from_stones(15, &five, &p![[B, 2], [C, 2], [D, 2], [E, 2], [F, 2]]);
+        assert_eq!(both.unreachable(), Some(Unreachable::BothWon));
+        assert_eq!(last_moves(&six).len(), 4);
+    }
+
+    #[test]
+    fn reconstruct() {
+        let limits = Limits {
+            nodes: Some(10_000),
+            ..Limits::default()
+        };
+        // black's five crossing a three
+        let black = p![
+            [H, 8],
+            [I, 8],
+            [J, 8],
+            [K, 8],
+            [L, 8],
+            [G, 7],
+            [H, 7],
+            [H, 9]
+        ];
+        let white = p![[A, 1], [A, 3], [A, 5], [A, 7], [C, 1], [C, 3], [C, 5]];
+        let position = BoardArr::from_stones(15, &black, &white);
+        let moves = position
+            .reconstruct_moves(&limits)
+            .known()
+            .unwrap()
+            .unwrap();
+        let mut replay = BoardArr::new(15);
+        for (i, point) in moves.iter().enumerate() {
+            assert_eq!(replay.rows(), (vec![], vec![]), "{moves:?}");
+            let stone = Stone::from_bool(i % 2 == 0);
+            if i + 1 < moves.len() {
+                assert!(!replay.forbidden_points(stone).contains(point), "{moves:?}");
+            }
+            replay.set_point(*point, stone);
+        }
+        let colors = |board: &BoardArr| board.iter().map(|m| m.color).collect::<Vec<_>>();
+        assert_eq!(colors(&replay), colors(&position));
+
+        assert!(BoardArr::from_stones(15, &black, &white)
+            .reconstruct_moves(&Limits {
+                nodes: Some(1),
+                ..Limits::default()
+            })
+            .is_unknown());
+        assert_eq!(
+            BoardArr::from_stones(15, &black, &[]).reconstruct_moves(&limits),
+            Outcome::Known(Err(Unreachable::StoneCount { black: 8, white: 0 }))
+        );
+    }
+}
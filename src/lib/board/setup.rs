@@ -0,0 +1,200 @@
+//! Editing a position stone by stone, to set up positions for teaching.
+//!
+//! Unlike playing moves, an edit can leave a position no game reaches, so every edit returns why
+//! the position is [unreachable](BoardArr::unreachable) after it, if it is.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use super::reachable::Unreachable;
+use super::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+use crate::Error;
+
+/// The points of a rectangle of the board, like `A1:C3`, corners in any order.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Area {
+    pub from: Point,
+    pub to: Point,
+}
+
+impl Area {
+    /// The smallest area holding `points` and `margin` more lines on every side, as far as a board
+    /// of `size` goes. `None` without points.
+    #[must_use]
+    pub fn around(points: impl IntoIterator<Item = Point>, margin: u32, size: u32) -> Option<Self> {
+        let points = points
+            .into_iter()
+            .filter(|point| !point.is_null && point.x < size && point.y < size)
+            .collect::<Vec<_>>();
+        let low = |at: fn(&Point) -> u32| {
+            points
+                .iter()
+                .map(at)
+                .min()
+                .map(|at| at.saturating_sub(margin))
+        };
+        let high = |at: fn(&Point) -> u32| {
+            points
+                .iter()
+                .map(at)
+                .max()
+                .map(|at| (at + margin).min(size - 1))
+        };
+        Some(Area {
+            from: Point::new(low(|p| p.x)?, low(|p| p.y)?),
+            to: Point::new(high(|p| p.x)?, high(|p| p.y)?),
+        })
+    }
+
+    #[must_use]
+    pub fn contains(&self, point: Point) -> bool {
+        !point.is_null && self.columns().contains(&point.x) && self.rows().contains(&point.y)
+    }
+
+    /// The `x` of the points in the area, from the left.
+    #[must_use]
+    pub fn columns(&self) -> RangeInclusive<u32> {
+        self.from.x.min(self.to.x)..=self.from.x.max(self.to.x)
+    }
+
+    /// The `y` of the points in the area, from the top.
+    #[must_use]
+    pub fn rows(&self) -> RangeInclusive<u32> {
+        self.from.y.min(self.to.y)..=self.from.y.max(self.to.y)
+    }
+}
+
+impl std::str::FromStr for Area {
+    type Err = ParseError;
+
+    /// Two points separated by a colon, or a single point for an area of one point.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s.split_once(':').unwrap_or((s, s));
+        Ok(Area {
+            from: from.parse()?,
+            to: to.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.from, self.to)
+    }
+}
+
+impl BoardArr {
+    /// Take the stone off `point`, returning why the position is unreachable now, if it is.
+    pub fn remove_stone(&mut self, point: Point) -> crate::Result<Option<Unreachable>> {
+        self.stone_at(point)?;
+        self.set_point(point, Stone::Empty);
+        Ok(self.unreachable())
+    }
+
+    /// Turn the stone on `point` to the other color, returning why the position is unreachable
+    /// now, if it is.
+    pub fn recolor(&mut self, point: Point) -> crate::Result<Option<Unreachable>> {
+        let stone = self.stone_at(point)?;
+        self.set_point(point, stone.opposite());
+        Ok(self.unreachable())
+    }
+
+    /// Move the stones of `area` `dx` columns to the right and `dy` rows up, returning why the
+    /// position is unreachable now, if it is.
+    ///
+    /// Nothing is moved if a stone would leave the board or land on a stone outside `area`.
+    pub fn shift(&mut self, area: Area, dx: i32, dy: i32) -> crate::Result<Option<Unreachable>> {
+        let stones = self
+            .iter()
+            .filter(|m| !m.color.is_empty() && area.contains(m.point))
+            .map(|m| (m.point, m.color))
+            .collect::<Vec<_>>();
+        let mut moved = Vec::with_capacity(stones.len());
+        for (point, stone) in stones {
+            let target = u32::try_from(point.x as i32 + dx)
+                .ok()
+                .zip(u32::try_from(point.y as i32 - dy).ok())
+                .map(|(x, y)| Point::new(x, y))
+                .filter(|target| self.get_point(*target).is_some())
+                .ok_or_else(|| Error::Rules(format!("{point} would leave the board")))?;
+            let taken = self.get_point(target).is_some_and(|m| !m.color.is_empty());
+            if taken && !area.contains(target) {
+                return Err(Error::Rules(format!("{target} is taken")));
+            }
+            moved.push((point, target, stone));
+        }
+        for (point, ..) in &moved {
+            self.set_point(*point, Stone::Empty);
+        }
+        for (_, target, stone) in moved {
+            self.set_point(target, stone);
+        }
+        Ok(self.unreachable())
+    }
+
+    /// Take every stone off `area`, returning how many there were and why the position is
+    /// unreachable now, if it is.
+    pub fn clear_area(&mut self, area: Area) -> (usize, Option<Unreachable>) {
+        let stones = self
+            .iter()
+            .filter(|m| !m.color.is_empty() && area.contains(m.point))
+            .map(|m| m.point)
+            .collect::<Vec<_>>();
+        for point in &stones {
+            self.set_point(*point, Stone::Empty);
+        }
+        (stones.len(), self.unreachable())
+    }
+
+    /// The stone on `point`, an error if there is none.
+    fn stone_at(&self, point: Point) -> crate::Result<Stone> {
+        self.get_point(point)
+            .map(|m| m.color)
+            .filter(|stone| !stone.is_empty())
+            .ok_or_else(|| Error::Rules(format!("there is no stone on {point}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn edits() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![I, 9], Stone::White);
+        board.set_point(p![J, 10], Stone::Black);
+        assert_eq!(
+            board.recolor(p![J, 10]).unwrap(),
+            Some(Unreachable::StoneCount { black: 1, white: 2 })
+        );
+        assert!(board.recolor(p![A, 1]).is_err());
+        assert_eq!(board.remove_stone(p![J, 10]).unwrap(), None);
+
+        let area: Area = "G7:I9".parse().unwrap();
+        assert!("I9:G7".parse::<Area>().unwrap().contains(p![H, 8]));
+        assert_eq!(board.shift(area, 2, -1).unwrap(), None);
+        let stone = |board: &BoardArr, point| board.get_point(point).unwrap().color;
+        assert_eq!(stone(&board, p![J, 7]), Stone::Black);
+        assert_eq!(stone(&board, p![K, 8]), Stone::White);
+        assert_eq!(stone(&board, p![H, 8]), Stone::Empty);
+        // an overlapping shift keeps the stones of the area
+        board.shift("J7:K8".parse().unwrap(), 1, 1).unwrap();
+        assert_eq!(stone(&board, p![K, 8]), Stone::Black);
+        assert_eq!(stone(&board, p![L, 9]), Stone::White);
+        assert!(board.shift("K8".parse().unwrap(), 5, 0).is_err());
+        board.set_point(p![A, 1], Stone::Black);
+        assert!(board.shift("L9".parse().unwrap(), 0, -7).is_ok());
+        assert!(board.shift("A1".parse().unwrap(), -1, 0).is_err());
+        assert_eq!(stone(&board, p![A, 1]), Stone::Black);
+
+        assert_eq!(board.clear_area("A1:O15".parse().unwrap()), (3, None));
+
+        let around = Area::around(p![[H, 8], [J, 10], [B, 2]], 2, 15).unwrap();
+        assert_eq!((around.columns(), around.rows()), (0..=11, 3..=14));
+        assert_eq!(Area::around([], 2, 15), None);
+    }
+}
@@ -0,0 +1,356 @@
+//! Named shapes on the board, so annotations and narration can use the terms players do.
+//!
+//! [`find`] locates the shapes of both colors from the
+//! [conditions](super::evaluator::RenjuConditions) of the position: what one more move makes
+//! tells what is already there, a five threatened means a four, a straight four threatened a
+//! three. The shapes of a line come with their vital points, the points completing them or
+//! defending against them, and the points where two lines cross are named as formations of
+//! their own, like a four-three.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use super::evaluator::{Direction, RenjuCondition};
+use super::{BoardArr, BoardRead, Evaluate, Point, Stone};
+
+/// Every shape recognized, strongest first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Shape {
+    /// A point making two fours at once, forbidden for black.
+    DoubleFour,
+    /// A point making a four and an open three at once, the usual winning move.
+    FourThree,
+    /// Four in a row open at both ends, two points make five.
+    StraightFour,
+    /// Four in a row with one point making five.
+    Four,
+    /// Four stones of a five with the point making it inside, like `XX.XX`.
+    SplitFour,
+    /// A point making two open threes at once, forbidden for black.
+    DoubleThree,
+    /// Three in a row that can become a straight four.
+    Three,
+    /// Three stones with a gap that can become a straight four, like `X.XX`.
+    SplitThree,
+    /// Two in a row that can become a three.
+    Two,
+    /// Two stones with a gap that can become a three, like `X.X`.
+    SplitTwo,
+}
+
+impl Shape {
+    /// The catalog, strongest first.
+    pub const ALL: [Shape; 10] = [
+        Shape::DoubleFour,
+        Shape::FourThree,
+        Shape::StraightFour,
+        Shape::Four,
+        Shape::SplitFour,
+        Shape::DoubleThree,
+        Shape::Three,
+        Shape::SplitThree,
+        Shape::Two,
+        Shape::SplitTwo,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::DoubleFour => "double four",
+            Shape::FourThree => "four-three",
+            Shape::StraightFour => "straight four",
+            Shape::Four => "four",
+            Shape::SplitFour => "split four",
+            Shape::DoubleThree => "double three",
+            Shape::Three => "three",
+            Shape::SplitThree => "split three",
+            Shape::Two => "two",
+            Shape::SplitTwo => "split two",
+        }
+    }
+
+    /// Whether the shape is a point where lines cross rather than stones in a line.
+    #[must_use]
+    pub fn is_formation(self) -> bool {
+        matches!(
+            self,
+            Shape::DoubleFour | Shape::FourThree | Shape::DoubleThree
+        )
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A shape on the board.
+///
+/// A formation is the stones of its lines and the point making it, it isn't played yet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    pub shape: Shape,
+    pub stone: Stone,
+    /// The stones of the shape, of every line of a formation.
+    pub stones: Vec<Point>,
+    /// For a line the points completing it, for a formation the point where the lines cross.
+    pub vital: Vec<Point>,
+    /// The vital point of a formation is forbidden, only for black.
+    pub forbidden: bool,
+}
+
+/// Like `black three H8 I8 J8, vital G8 K8`.
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let points = |points: &[Point]| {
+            points
+                .iter()
+                .map(Point::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let stone = format!("{:?}", self.stone).to_lowercase();
+        write!(f, "{stone} {} {}", self.shape, points(&self.stones))?;
+        write!(f, ", vital {}", points(&self.vital))?;
+        if self.forbidden {
+            write!(f, " (forbidden)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every shape of both colors on `board`, strongest first.
+#[must_use]
+pub fn find(board: &BoardArr) -> Vec<Found> {
+    let mut found = vec![];
+    for stone in [Stone::Black, Stone::White] {
+        find_for(board, stone, &mut found);
+    }
+    found.sort_by(|a, b| {
+        (a.shape, a.stone.is_white(), &a.stones).cmp(&(b.shape, b.stone.is_white(), &b.stones))
+    });
+    found
+}
+
+/// The directions of the fours and threes a point makes, and the stones of their lines.
+type Crossing = (Vec<Direction>, Vec<Direction>, Vec<Point>);
+
+fn find_for(board: &BoardArr, stone: Stone, found: &mut Vec<Found>) {
+    let conditions = board.renju_conditions(stone, None);
+    // the stones already on the board of every line, with the points completing it
+    let mut lines: BTreeMap<(Direction, Vec<Point>, usize), Vec<Point>> = BTreeMap::new();
+    let mut crossings: BTreeMap<Point, Crossing> = BTreeMap::new();
+    // the threes made on forbidden points are only kept with their extensions
+    let all = conditions
+        .conditions
+        .iter()
+        .chain(conditions.threes.iter().map(|(condition, _)| condition))
+        .collect::<BTreeSet<_>>();
+    for condition in all {
+        let (direction, made) = match condition {
+            RenjuCondition::Five { direction, .. } => (*direction, 5),
+            RenjuCondition::StraightFour { direction, .. } => (*direction, 4),
+            RenjuCondition::ClosedFour { direction, .. }
+            | RenjuCondition::BrokenFour { direction, .. } => (*direction, 0),
+            RenjuCondition::UnbrokenThree { direction, .. }
+            | RenjuCondition::BrokenThree { direction, .. } => (*direction, 3),
+        };
+        let place = *condition.place();
+        let mut stones = condition
+            .stones()
+            .iter()
+            .copied()
+            .filter(|p| *p != place && board.stone_at(*p) == stone)
+            .collect::<Vec<_>>();
+        stones.sort();
+        if made != 5 {
+            let crossing = crossings.entry(place).or_default();
+            match made {
+                3 => crossing.1.push(direction),
+                _ => crossing.0.push(direction),
+            }
+            crossing.2.extend(&stones);
+        }
+        // a closed four made tells nothing of a shape already there
+        if made != 0 {
+            lines
+                .entry((direction, stones, made))
+                .or_default()
+                .push(place);
+        }
+    }
+    for ((_, stones, made), mut vital) in lines {
+        vital.sort();
+        vital.dedup();
+        let split = !is_unbroken(&stones);
+        let shape = match (made, vital.len(), split) {
+            (5, 1, _) => Shape::Four,
+            (5, _, _) => Shape::StraightFour,
+            (4, _, true) => Shape::SplitThree,
+            (4, _, false) => Shape::Three,
+            (_, _, true) => Shape::SplitTwo,
+            (_, _, false) => Shape::Two,
+        };
+        found.push(Found {
+            shape,
+            stone,
+            stones,
+            vital,
+            forbidden: false,
+        });
+    }
+    split_fours(board, stone, found);
+    for (point, (fours, threes, mut stones)) in crossings {
+        let crossing =
+            |a: &[Direction], b: &[Direction]| a.iter().any(|x| b.iter().any(|y| x != y));
+        let shape = if crossing(&fours, &fours) {
+            Shape::DoubleFour
+        } else if crossing(&fours, &threes) {
+            Shape::FourThree
+        } else if crossing(&threes, &threes) {
+            Shape::DoubleThree
+        } else {
+            continue;
+        };
+        stones.sort();
+        stones.dedup();
+        found.push(Found {
+            shape,
+            stone,
+            stones,
+            vital: vec![point],
+            forbidden: conditions.forbidden.contains(&point),
+        });
+    }
+}
+
+/// The conditions only tell the fives made at the end of a row, find the fours with the point
+/// making five inside them.
+fn split_fours(board: &BoardArr, stone: Stone, found: &mut Vec<Found>) {
+    let size = board.size() as i64;
+    let at = |x: i64, y: i64| {
+        (0..size).contains(&x) && (0..size).contains(&y) && {
+            board.stone_at(Point::new(x as u32, y as u32)) == stone
+        }
+    };
+    for marker in board.iter() {
+        let point = marker.point;
+        if board.stone_at(point) != Stone::Empty {
+            continue;
+        }
+        let (x, y) = (i64::from(point.x), i64::from(point.y));
+        for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+            let run = |sign: i64| {
+                (1..)
+                    .take_while(|&i| at(x + sign * i * dx, y + sign * i * dy))
+                    .count() as i64
+            };
+            let (before, after) = (run(-1), run(1));
+            if before == 0 || after == 0 || before + after != 4 {
+                continue;
+            }
+            let mut stones = (-before..=after)
+                .filter(|&i| i != 0)
+                .map(|i| Point::new((x + i * dx) as u32, (y + i * dy) as u32))
+                .collect::<Vec<_>>();
+            stones.sort();
+            found.push(Found {
+                shape: Shape::SplitFour,
+                stone,
+                stones,
+                vital: vec![point],
+                forbidden: false,
+            });
+        }
+    }
+}
+
+/// Whether sorted `stones` of a line follow each other without a gap.
+fn is_unbroken(stones: &[Point]) -> bool {
+    match (stones.first(), stones.last()) {
+        (Some(first), Some(last)) => {
+            let span = first.x.abs_diff(last.x).max(first.y.abs_diff(last.y));
+            span as usize + 1 == stones.len()
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    fn shapes(found: &[Found], stone: Stone) -> Vec<Shape> {
+        found
+            .iter()
+            .filter(|f| f.stone == stone)
+            .map(|f| f.shape)
+            .collect()
+    }
+
+    #[test]
+    fn lines() {
+        let found = find(&BoardArr::from_stones(15, &p![[H, 8], [I, 8], [J, 8]], &[]));
+        let three = found.iter().find(|f| f.shape == Shape::Three).unwrap();
+        assert_eq!(three.stones, p![[H, 8], [I, 8], [J, 8]]);
+        assert_eq!(three.vital, p![[G, 8], [K, 8]]);
+        assert_eq!(three.to_string(), "black three H8 I8 J8, vital G8 K8");
+
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[H, 8], [I, 8], [J, 8], [K, 8]],
+            &p![[G, 8]],
+        ));
+        assert_eq!(found[0].shape, Shape::Four);
+        assert_eq!(found[0].vital, p![[L, 8]]);
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[H, 8], [I, 8], [K, 8], [L, 8]],
+            &[],
+        ));
+        assert_eq!(found[0].shape, Shape::SplitFour);
+        assert_eq!(found[0].vital, p![[J, 8]]);
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[H, 8], [I, 8], [J, 8], [K, 8]],
+            &[],
+        ));
+        assert_eq!(found[0].shape, Shape::StraightFour);
+
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[H, 8], [J, 8], [K, 8]],
+            &p![[H, 10], [I, 10]],
+        ));
+        assert!(shapes(&found, Stone::Black).contains(&Shape::SplitThree));
+        assert!(shapes(&found, Stone::White).contains(&Shape::Two));
+    }
+
+    #[test]
+    fn formations() {
+        // a four on row 8 and a three on column J are made at J8
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[F, 8], [G, 8], [H, 8], [J, 9], [J, 10]],
+            &p![[E, 8], [A, 1]],
+        ));
+        let four_three = found.iter().find(|f| f.shape == Shape::FourThree).unwrap();
+        assert_eq!(four_three.vital, p![[J, 8]]);
+        assert!(!four_three.forbidden);
+
+        let found = find(&BoardArr::from_stones(
+            15,
+            &p![[H, 8], [I, 8], [G, 9], [G, 10]],
+            &[],
+        ));
+        let double_three = found
+            .iter()
+            .find(|f| f.shape == Shape::DoubleThree)
+            .unwrap();
+        assert_eq!(double_three.vital, p![[G, 8]]);
+        assert!(double_three.forbidden);
+        assert!(double_three.to_string().ends_with("(forbidden)"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Defaults for the CLI, read from a TOML file and overridden by the environment.
+//!
+//! The file is `config.toml` in [`crate::util::config_dir`] and every key can be overridden with
+//! a `RENJU_` variable, e.g `RENJU_PROVE_NODES` for `prove-nodes`:
+//!
+//! ```toml
+//! engine = "mcts"
+//! proofs = "/home/me/renju/proofs.txt"
+//! prove-nodes = 1000000
+//! session = false
+//! language = "sv"
+//! backups = 5
+//! author = "Me"
+//! theme = "high-contrast"
+//! ```
+//!
+//! Flags given on the command line take precedence over both.
+
+use std::path::PathBuf;
+
+#[cfg(feature = "engine")]
+use crate::engine::EngineKind;
+use crate::errors::ParseError;
+use crate::i18n::Language;
+use crate::theme::{Palette, Preset};
+
+/// Every setting is optional, `None` leaves it to the command line or the built in default.
+#[derive(Clone, Default, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields, rename_all = "kebab-case")
+)]
+pub struct Config {
+    /// Search algorithm used with `--play` and `--selfplay`.
+    #[cfg(feature = "engine")]
+    pub engine: Option<EngineKind>,
+    /// File to keep proven positions in, like `--proofs`.
+    pub proofs: Option<PathBuf>,
+    /// File to keep training progress in, like `--training`.
+    pub training: Option<PathBuf>,
+    /// Node budget of `prove`.
+    pub prove_nodes: Option<u64>,
+    /// Resume and keep the session of a library, see [`crate::session`].
+    pub session: Option<bool>,
+    /// Print where time was spent when done, like `--timing`.
+    pub timing: Option<bool>,
+    /// Language of the text shown, `en`, `sv`, `ru` or `zh`, from the locale by default.
+    pub language: Option<Language>,
+    /// Copies to keep of a library replaced with `--output`, see
+    /// [`WriteOptions::backups`](crate::file_reader::WriteOptions::backups).
+    pub backups: Option<usize>,
+    /// Name to log edits of a library under, none are logged without one, see
+    /// [`crate::board::history`].
+    pub author: Option<String>,
+    /// Token clients of `--serve` need, like `--token`. Anyone can connect without one.
+    pub server_token: Option<String>,
+    /// Colors of the boards printed, see [`crate::theme`].
+    pub theme: Option<Preset>,
+    /// Colors replacing the ones of `theme`, like `black=red,forbidden=bright-yellow`.
+    pub colors: Option<Palette>,
+}
+
+/// Where the config is read from, `RENJU_CONFIG` or `config.toml` in
+/// [`crate::util::config_dir`].
+#[must_use]
+pub fn path() -> Option<PathBuf> {
+    std::env::var_os("RENJU_CONFIG")
+        .map(PathBuf::from)
+        .or_else(|| Some(crate::util::config_dir()?.join("config.toml")))
+}
+
+impl Config {
+    /// Load from a TOML file, a missing file gives an empty config.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> crate::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(toml::from_str(&text)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Override settings with the `RENJU_` variables of the environment.
+    pub fn apply_env(&mut self) -> Result<(), ParseError> {
+        self.apply_vars(|key| std::env::var(key).ok())
+    }
+
+    /// Override settings with the variables given by `var`.
+    pub fn apply_vars(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), ParseError> {
+        fn parse<T: std::str::FromStr>(key: &str, value: String) -> Result<T, ParseError> {
+            value
+                .parse()
+                .map_err(|_| ParseError::Other(format!("invalid value {value:?} for {key}")))
+        }
+        #[cfg(feature = "engine")]
+        if let Some(value) = var("RENJU_ENGINE") {
+            self.engine = Some(value.parse()?);
+        }
+        if let Some(value) = var("RENJU_PROOFS") {
+            self.proofs = Some(value.into());
+        }
+        if let Some(value) = var("RENJU_TRAINING") {
+            self.training = Some(value.into());
+        }
+        if let Some(value) = var("RENJU_PROVE_NODES") {
+            self.prove_nodes = Some(parse("RENJU_PROVE_NODES", value)?);
+        }
+        if let Some(value) = var("RENJU_SESSION") {
+            self.session = Some(parse("RENJU_SESSION", value)?);
+        }
+        if let Some(value) = var("RENJU_TIMING") {
+            self.timing = Some(parse("RENJU_TIMING", value)?);
+        }
+        if let Some(value) = var("RENJU_LANG") {
+            self.language = Some(value.parse()?);
+        }
+        if let Some(value) = var("RENJU_BACKUPS") {
+            self.backups = Some(parse("RENJU_BACKUPS", value)?);
+        }
+        if let Some(value) = var("RENJU_AUTHOR") {
+            self.author = Some(value);
+        }
+        if let Some(value) = var("RENJU_SERVER_TOKEN") {
+            self.server_token = Some(value);
+        }
+        if let Some(value) = var("RENJU_THEME") {
+            self.theme = Some(value.parse()?);
+        }
+        if let Some(value) = var("RENJU_COLORS") {
+            self.colors = Some(value.parse()?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides() {
+        let mut config = Config {
+            prove_nodes: Some(10),
+            ..Config::default()
+        };
+        #[cfg(feature = "engine")]
+        {
+            config.engine = Some(EngineKind::AlphaBeta);
+        }
+        let vars = |key: &str| match key {
+            "RENJU_ENGINE" => Some("mcts".to_owned()),
+            "RENJU_SESSION" => Some("false".to_owned()),
+            "RENJU_LANG" => Some("ru_RU.UTF-8".to_owned()),
+            "RENJU_BACKUPS" => Some("3".to_owned()),
+            "RENJU_AUTHOR" => Some("Me".to_owned()),
+            "RENJU_SERVER_TOKEN" => Some("secret".to_owned()),
+            "RENJU_THEME" => Some("high-contrast".to_owned()),
+            "RENJU_COLORS" => Some("white=bright-white".to_owned()),
+            _ => None,
+        };
+        config.apply_vars(vars).unwrap();
+        #[cfg(feature = "engine")]
+        assert_eq!(config.engine, Some(EngineKind::Mcts));
+        assert_eq!(config.prove_nodes, Some(10));
+        assert_eq!(config.session, Some(false));
+        assert_eq!(config.language, Some(Language::Russian));
+        assert_eq!(config.backups, Some(3));
+        assert_eq!(config.author.as_deref(), Some("Me"));
+        assert_eq!(config.server_token.as_deref(), Some("secret"));
+        assert_eq!(config.theme, Some(Preset::HighContrast));
+        assert_eq!(
+            config.colors.and_then(|colors| colors.white),
+            Some(crate::theme::Color::BrightWhite)
+        );
+
+        let error = config
+            .apply_vars(|key| (key == "RENJU_PROVE_NODES").then(|| "many".to_owned()))
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid value \"many\" for RENJU_PROVE_NODES"
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "engine"))]
+    #[test]
+    fn toml() {
+        let config: Config =
+            toml::from_str("engine = \"mcts\"\nproofs = \"/tmp/proofs.txt\"\nprove-nodes = 1000\nlanguage = \"zh\"\n")
+                .unwrap();
+        assert_eq!(config.engine, Some(EngineKind::Mcts));
+        assert_eq!(config.proofs, Some(PathBuf::from("/tmp/proofs.txt")));
+        assert_eq!(config.prove_nodes, Some(1000));
+        assert_eq!(config.language, Some(Language::Chinese));
+        let config: Config = toml::from_str("theme = \"none\"\ncolors = \"black=red\"\n").unwrap();
+        assert_eq!(config.theme, Some(Preset::None));
+        assert_eq!(config.colors.unwrap().to_string(), "black=red");
+        assert!(toml::from_str::<Config>("engin = \"mcts\"").is_err());
+    }
+}
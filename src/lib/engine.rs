@@ -0,0 +1,499 @@
+//! A reference engine, iterative deepening alpha-beta over [`BoardArr::candidate_moves`].
+//!
+//! The engine is not meant to be strong, but simple enough to trust. Positions are scored by an
+//! [`Evaluator`], [`Heuristic`] by default. Fours are searched one ply deeper since the reply is
+//! forced, and a transposition table keyed by a zobrist hash is used both for cutoffs and move
+//! ordering.
+//!
+//! A Monte-Carlo tree search is also available in [`mcts`], pick one with [`EngineKind`].
+//! Stronger engines run as separate programs can be used through [`external`].
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::board::{BoardArr, Point, Stone};
+use crate::solver;
+
+pub use crate::limits::{Limits, Outcome};
+
+pub mod chart;
+pub mod consensus;
+pub mod eval;
+pub mod external;
+pub mod mcts;
+pub mod opening;
+pub mod quality;
+
+pub use eval::{evaluate, Evaluator, Heuristic};
+
+/// Score of a won position, minus the amount of plies until the win.
+pub const WIN: i32 = 1_000_000;
+
+/// The result of a search.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchResult {
+    /// The best move found, `None` if there are no moves left.
+    pub best: Option<Point>,
+    /// Score for the side to move, see [`WIN`].
+    pub score: i32,
+    /// Depth of the last finished iteration.
+    pub depth: u32,
+    pub nodes: u64,
+    /// Expected continuation, starting with `best`.
+    pub pv: Vec<Point>,
+}
+
+impl SearchResult {
+    /// The winning line if the side to move has a forced win, otherwise [`Outcome::Unknown`] as
+    /// the search can't prove that there is none.
+    #[must_use]
+    pub fn outcome(&self) -> Outcome<Vec<Point>> {
+        match self.win_in() {
+            Some(plies) if plies > 0 && self.pv.is_empty() => {
+                Outcome::Known(self.best.into_iter().collect())
+            }
+            Some(plies) if plies > 0 => Outcome::Known(self.pv.clone()),
+            _ => Outcome::Unknown,
+        }
+    }
+
+    /// Plies until the side to move wins, or loses if negative.
+    #[must_use]
+    pub fn win_in(&self) -> Option<i32> {
+        if self.score.abs() > WIN - 1000 {
+            Some(self.score.signum() * (WIN - self.score.abs()))
+        } else {
+            None
+        }
+    }
+}
+
+/// The search algorithm to use.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum EngineKind {
+    /// [`best_move`]
+    #[default]
+    AlphaBeta,
+    /// [`mcts::Mcts`] with [`mcts::RandomPolicy`]
+    Mcts,
+}
+
+impl std::str::FromStr for EngineKind {
+    type Err = crate::errors::ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alphabeta" => Ok(Self::AlphaBeta),
+            "mcts" => Ok(Self::Mcts),
+            _ => Err(crate::errors::ParseError::Other(format!(
+                "unknown engine {s:?}"
+            ))),
+        }
+    }
+}
+
+/// Search for the best move for `stone` in `board` with the engine `kind`.
+pub fn search(board: &BoardArr, stone: Stone, kind: EngineKind, limits: &Limits) -> SearchResult {
+    match kind {
+        EngineKind::AlphaBeta => best_move(board, stone, limits),
+        EngineKind::Mcts => mcts::Mcts::default().search(board, stone, limits),
+    }
+}
+
+/// Anything searching positions, the built in engines or an [external](external::Client) one.
+pub trait Searcher {
+    /// A name to tell the engine apart from others, e.g. in a [`consensus::Consensus`].
+    fn name(&self) -> String;
+
+    /// Search for the best move for `stone` in `board`.
+    fn search(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+    ) -> crate::Result<SearchResult>;
+}
+
+impl Searcher for EngineKind {
+    fn name(&self) -> String {
+        match self {
+            Self::AlphaBeta => "alphabeta",
+            Self::Mcts => "mcts",
+        }
+        .to_owned()
+    }
+
+    fn search(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+    ) -> crate::Result<SearchResult> {
+        Ok(search(board, stone, *self, limits))
+    }
+}
+
+/// Like [`search`], scoring positions with `evaluator`.
+pub fn search_with(
+    board: &BoardArr,
+    stone: Stone,
+    kind: EngineKind,
+    limits: &Limits,
+    evaluator: &mut dyn Evaluator,
+) -> SearchResult {
+    match kind {
+        EngineKind::AlphaBeta => best_move_with(board, stone, limits, evaluator),
+        EngineKind::Mcts => mcts::Mcts::default().search_with(board, stone, limits, evaluator),
+    }
+}
+
+/// Search for the best move for `stone` in `board`.
+pub fn best_move(board: &BoardArr, stone: Stone, limits: &Limits) -> SearchResult {
+    best_move_with(board, stone, limits, &mut Heuristic)
+}
+
+/// Search for the best move for `stone` in `board`, scoring positions with `evaluator`.
+#[tracing::instrument(skip(board, evaluator))]
+pub fn best_move_with(
+    board: &BoardArr,
+    stone: Stone,
+    limits: &Limits,
+    evaluator: &mut dyn Evaluator,
+) -> SearchResult {
+    let mut search = Search::new(board, limits, evaluator);
+    let hash = search.hash(stone);
+    let mut result = SearchResult {
+        best: board.candidate_moves(stone, 2, 1).first().copied(),
+        score: 0,
+        depth: 0,
+        nodes: 0,
+        pv: vec![],
+    };
+    for depth in 1..=limits.depth {
+        let score = search.negamax(hash, stone, depth, 0, -WIN, WIN);
+        if search.aborted {
+            break;
+        }
+        let pv = search.pv(hash, stone, depth as usize * 2);
+        tracing::debug!(
+            depth,
+            score,
+            ?pv,
+            nodes = search.nodes,
+            "finished iteration"
+        );
+        result.best = pv.first().copied().or(result.best);
+        result.score = score;
+        result.depth = depth;
+        result.pv = pv;
+        if score.abs() > WIN - 1000 {
+            break;
+        }
+    }
+    result.nodes = search.nodes;
+    result
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    depth: u32,
+    score: i32,
+    bound: Bound,
+    best: Option<Point>,
+}
+
+struct Search<'a> {
+    board: BoardArr,
+    limits: &'a Limits,
+    evaluator: &'a mut dyn Evaluator,
+    deadline: Option<Instant>,
+    zobrist: Vec<[u64; 2]>,
+    table: HashMap<u64, Entry>,
+    nodes: u64,
+    aborted: bool,
+}
+
+impl<'a> Search<'a> {
+    fn new(board: &BoardArr, limits: &'a Limits, evaluator: &'a mut dyn Evaluator) -> Self {
+        // splitmix64, the table only has to be stable within a search
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = || {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let zobrist = (0..board.len()).map(|_| [next(), next()]).collect();
+        Self {
+            board: board.clone(),
+            limits,
+            evaluator,
+            deadline: limits.time.map(|time| Instant::now() + time),
+            zobrist,
+            table: HashMap::new(),
+            nodes: 0,
+            aborted: false,
+        }
+    }
+
+    fn key(&self, point: Point, stone: Stone) -> u64 {
+        self.zobrist[point.to_1d(self.board.size()) as usize][usize::from(stone.is_black())]
+    }
+
+    /// Hash of the whole board with `to_move` to play.
+    fn hash(&self, to_move: Stone) -> u64 {
+        let stones = self
+            .board
+            .iter()
+            .filter(|m| !m.color.is_empty())
+            .fold(0, |hash, m| hash ^ self.key(m.point, m.color));
+        if to_move.is_black() {
+            !stones
+        } else {
+            stones
+        }
+    }
+
+    fn out_of_budget(&self) -> bool {
+        self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn negamax(
+        &mut self,
+        hash: u64,
+        stone: Stone,
+        mut depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.out_of_budget() {
+            self.aborted = true;
+            return 0;
+        }
+        if !solver::five_points(&self.board, stone).is_empty() {
+            return WIN - ply as i32 - 1;
+        }
+        let threatened = !solver::five_points(&self.board, stone.opposite()).is_empty();
+        // a forced block doesn't count towards the depth
+        if depth == 0 && threatened && ply < self.limits.depth * 4 {
+            depth = 1;
+        }
+        if depth == 0 {
+            let value = self.evaluator.value(&self.board, stone).clamp(-1.0, 1.0);
+            return (value * 10_000.0) as i32;
+        }
+
+        let mut hint = None;
+        if let Some(entry) = self.table.get(&hash) {
+            hint = entry.best;
+            if entry.depth >= depth {
+                let score = from_table(entry.score, ply);
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut moves = self.board.candidate_moves(stone, 2, self.limits.width);
+        if moves.is_empty() {
+            // black can only block on a forbidden point, or the board is full
+            return if threatened {
+                -(WIN - ply as i32 - 2)
+            } else {
+                0
+            };
+        }
+        let priors = self.evaluator.policy(&self.board, stone, &moves);
+        if priors.len() == moves.len() {
+            let mut ordered = moves.into_iter().zip(priors).collect::<Vec<_>>();
+            ordered.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            moves = ordered.into_iter().map(|(point, _)| point).collect();
+        }
+        if let Some(hint) = hint.filter(|hint| moves.contains(hint)) {
+            moves.retain(|m| *m != hint);
+            moves.insert(0, hint);
+        }
+
+        let original_alpha = alpha;
+        let mut best = (i32::MIN, None);
+        for point in moves {
+            self.board.set_point(point, stone);
+            let four = !solver::five_points(&self.board, stone).is_empty();
+            let next_depth = if four && ply < self.limits.depth * 4 {
+                depth
+            } else {
+                depth - 1
+            };
+            let score = -self.negamax(
+                hash ^ self.key(point, stone) ^ !0,
+                stone.opposite(),
+                next_depth,
+                ply + 1,
+                -beta,
+                -alpha,
+            );
+            self.board.set_point(point, Stone::Empty);
+            if self.aborted {
+                return 0;
+            }
+            if score > best.0 {
+                best = (score, Some(point));
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best.0 <= original_alpha {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        let full = self
+            .limits
+            .table_bytes
+            .is_some_and(|bytes| self.table.len() * std::mem::size_of::<(u64, Entry)>() >= bytes);
+        if !full || self.table.contains_key(&hash) {
+            self.table.insert(
+                hash,
+                Entry {
+                    depth,
+                    score: to_table(best.0, ply),
+                    bound,
+                    best: best.1,
+                },
+            );
+        }
+        best.0
+    }
+
+    /// Follow the best moves stored in the table.
+    fn pv(&mut self, mut hash: u64, mut stone: Stone, max: usize) -> Vec<Point> {
+        let mut pv = vec![];
+        while let Some(point) = self.table.get(&hash).and_then(|entry| entry.best) {
+            if pv.len() >= max
+                || !self
+                    .board
+                    .get_point(point)
+                    .is_some_and(|m| m.color.is_empty())
+            {
+                break;
+            }
+            self.board.set_point(point, stone);
+            pv.push(point);
+            hash ^= self.key(point, stone) ^ !0;
+            stone = stone.opposite();
+        }
+        for point in &pv {
+            self.board.set_point(*point, Stone::Empty);
+        }
+        pv
+    }
+}
+
+/// Win scores are stored relative to the node, so they stay correct when found at another ply.
+fn to_table(score: i32, ply: u32) -> i32 {
+    match score {
+        s if s > WIN - 1000 => s + ply as i32,
+        s if s < -(WIN - 1000) => s - ply as i32,
+        s => s,
+    }
+}
+
+fn from_table(score: i32, ply: u32) -> i32 {
+    match score {
+        s if s > WIN - 1000 => s - ply as i32,
+        s if s < -(WIN - 1000) => s + ply as i32,
+        s => s,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn takes_the_five() {
+        let board = BoardArr::from_stones(
+            15,
+            &p![[D, 8], [E, 8], [F, 8], [G, 8]],
+            &p![[C, 8], [A, 1], [A, 3]],
+        );
+        let result = best_move(&board, Stone::Black, &Limits::default());
+        assert_eq!(result.best, Some(p![H, 8]));
+        assert_eq!(result.win_in(), Some(1));
+        assert_eq!(result.outcome(), Outcome::Known(p![[H, 8]].to_vec()));
+
+        // without a table there is no principal variation, but the search still works
+        let limits = Limits {
+            table_bytes: Some(0),
+            ..Limits::default()
+        };
+        let result = best_move(&board, Stone::Black, &limits);
+        assert_eq!(result.best, Some(p![H, 8]));
+        assert_eq!(result.win_in(), Some(1));
+    }
+
+    #[test]
+    fn blocks_the_four() {
+        let board = BoardArr::from_stones(
+            15,
+            &p![[D, 8], [E, 8], [F, 8], [G, 8]],
+            &p![[C, 8], [A, 1], [A, 3]],
+        );
+        let result = best_move(&board, Stone::White, &Limits::default());
+        assert_eq!(result.best, Some(p![H, 8]));
+    }
+
+    #[test]
+    fn finds_four_three() {
+        let board = BoardArr::from_stones(
+            15,
+            &p![[J, 3], [A, 1], [A, 15], [O, 1], [O, 15]],
+            &p![[J, 4], [J, 5], [J, 6], [G, 7], [H, 7]],
+        );
+        let result = best_move(&board, Stone::White, &Limits::default());
+        assert!(result.win_in().is_some_and(|plies| plies > 0), "{result:?}");
+        assert_eq!(result.pv.first(), Some(&p![J, 7]), "{result:?}");
+    }
+
+    #[test]
+    fn respects_node_limit() {
+        let board = BoardArr::from_stones(15, &p![[H, 8]], &p![[I, 9]]);
+        let limits = Limits {
+            depth: 20,
+            nodes: Some(200),
+            ..Limits::default()
+        };
+        let result = best_move(&board, Stone::Black, &limits);
+        assert!(result.best.is_some());
+        assert!(result.nodes <= 200);
+        assert!(result.depth < 20);
+    }
+}
@@ -0,0 +1,142 @@
+//! Evaluation over the moves of a game, drawn as a chart like the ones of chess programs.
+//!
+//! [`EvalChart::new`] scores the position after every move with an [`Evaluator`], from the view
+//! of black, and [`EvalChart::to_svg`] draws the scores with black's advantage up and white's
+//! down. Moves flagged by an analysis, e.g. the blunders of [`crate::analysis`], can be marked.
+
+use std::fmt::Write as _;
+
+use super::Evaluator;
+use crate::game::GameRecord;
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 240;
+/// Pixels around the plot, room for the labels.
+const MARGIN: u32 = 30;
+
+/// The scores of a game, see the [module documentation](self).
+#[derive(Clone, Debug, PartialEq)]
+pub struct EvalChart {
+    /// The score after every move, from `-1.0`, won by white, to `1.0`, won by black.
+    pub values: Vec<f32>,
+    /// Indices of moves to mark, in [`GameRecord::moves`].
+    pub marks: Vec<usize>,
+}
+
+impl EvalChart {
+    /// Score every position of `game` with `evaluator`.
+    pub fn new(game: &GameRecord, evaluator: &mut impl Evaluator) -> Self {
+        let values = (1..=game.moves.len())
+            .map(|ply| {
+                let to_move = GameRecord::stone_at(ply);
+                let value = evaluator.value(&game.position(ply), to_move);
+                match to_move.is_black() {
+                    true => value,
+                    false => -value,
+                }
+            })
+            .collect();
+        Self {
+            values,
+            marks: vec![],
+        }
+    }
+
+    /// Mark the moves at `plies`.
+    #[must_use]
+    pub fn with_marks(mut self, plies: impl IntoIterator<Item = usize>) -> Self {
+        self.marks.extend(plies);
+        self
+    }
+
+    /// The pixel of the score after move `ply`.
+    fn at(&self, ply: usize) -> (f32, f32) {
+        let plot = (WIDTH - 2 * MARGIN) as f32;
+        let step = plot / self.values.len().saturating_sub(1).max(1) as f32;
+        let middle = (HEIGHT / 2) as f32;
+        let reach = (HEIGHT / 2 - MARGIN) as f32;
+        let value = self.values[ply].clamp(-1.0, 1.0);
+        (MARGIN as f32 + step * ply as f32, middle - value * reach)
+    }
+
+    /// The chart as a standalone SVG image.
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        let (left, right, middle) = (MARGIN, WIDTH - MARGIN, HEIGHT / 2);
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="11">"#
+        );
+        let _ = writeln!(
+            svg,
+            r##"<rect width="{WIDTH}" height="{HEIGHT}" fill="#fff"/><rect x="{left}" y="{MARGIN}" width="{}" height="{}" fill="#eee"/>"##,
+            right - left,
+            middle - MARGIN
+        );
+        let _ = writeln!(
+            svg,
+            r##"<line x1="{left}" y1="{middle}" x2="{right}" y2="{middle}" stroke="#888"/>"##
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="4" y="{}">Black</text><text x="4" y="{}">White</text>"#,
+            MARGIN + 4,
+            HEIGHT - MARGIN
+        );
+        if !self.values.is_empty() {
+            let points = (0..self.values.len())
+                .map(|ply| {
+                    let (x, y) = self.at(ply);
+                    format!("{x:.1},{y:.1}")
+                })
+                .collect::<Vec<_>>();
+            let _ = writeln!(
+                svg,
+                r##"<polyline points="{}" fill="none" stroke="#000" stroke-width="2"/>"##,
+                points.join(" ")
+            );
+            for ply in self.marks.iter().filter(|ply| **ply < self.values.len()) {
+                let (x, y) = self.at(*ply);
+                let _ = writeln!(
+                    svg,
+                    r##"<circle cx="{x:.1}" cy="{y:.1}" r="4" fill="#d22"><title>{}</title></circle>"##,
+                    ply + 1
+                );
+            }
+            let last = self.values.len();
+            let _ = writeln!(
+                svg,
+                r#"<text x="{left}" y="{}">1</text><text x="{right}" y="{}" text-anchor="end">{last}</text>"#,
+                HEIGHT - 8,
+                HEIGHT - 8
+            );
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Point;
+    use crate::engine::Heuristic;
+    use crate::p;
+
+    #[test]
+    fn black_up() {
+        // black builds an open three while white plays far away
+        let game = GameRecord::new(p![[H, 8], [A, 1], [I, 8], [A, 3], [J, 8]].to_vec());
+        let chart = EvalChart::new(&game, &mut Heuristic).with_marks([3, 40]);
+        assert_eq!(chart.values.len(), 5);
+        assert!(chart.values[4] > 0.0, "{:?}", chart.values);
+        assert!(chart.values[4] > chart.values[0]);
+
+        let svg = chart.to_svg();
+        assert!(svg.starts_with("<svg "));
+        assert_eq!(svg.matches("<circle ").count(), 1);
+        assert!(svg.contains("<title>4</title>"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
@@ -0,0 +1,201 @@
+//! Several engines searching the same position, to find the positions they disagree on.
+//!
+//! A [`Consensus`] keeps the result of every engine. Where the engines play different moves, or
+//! score the position far apart, the position is worth a look by a person. Kept as
+//! [`Consensuses`] beside a library, [`Consensuses::contested`] lists those nodes.
+//!
+//! Scores of different engines are on different scales, the spread is only meaningful between
+//! engines scoring alike, or for wins found by some engines and not by others.
+
+use super::{Limits, SearchResult, Searcher};
+use crate::annotations::Annotations;
+use crate::board::{Board, BoardArr, MoveIndex, Point, Stone};
+use crate::game::GameRecord;
+
+/// The result of one engine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Opinion {
+    /// The [name](Searcher::name) of the engine.
+    pub engine: String,
+    pub result: SearchResult,
+}
+
+/// The results of several engines for a position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Consensus {
+    pub opinions: Vec<Opinion>,
+}
+
+impl Consensus {
+    /// Whether every engine found the same best move.
+    #[must_use]
+    pub fn agrees(&self) -> bool {
+        self.opinions
+            .windows(2)
+            .all(|pair| pair[0].result.best == pair[1].result.best)
+    }
+
+    /// The best moves found, with the engines finding them, most found first.
+    #[must_use]
+    pub fn best_moves(&self) -> Vec<(Option<Point>, Vec<&str>)> {
+        let mut moves: Vec<(Option<Point>, Vec<&str>)> = vec![];
+        for opinion in &self.opinions {
+            match moves
+                .iter_mut()
+                .find(|(best, _)| *best == opinion.result.best)
+            {
+                Some((_, engines)) => engines.push(&opinion.engine),
+                None => moves.push((opinion.result.best, vec![&opinion.engine])),
+            }
+        }
+        // stable, so ties stay in the order of the engines
+        moves.sort_by_key(|(_, engines)| std::cmp::Reverse(engines.len()));
+        moves
+    }
+
+    /// The difference between the highest and the lowest score, 0 with less than two engines.
+    #[must_use]
+    pub fn spread(&self) -> i64 {
+        let scores = self.opinions.iter().map(|o| i64::from(o.result.score));
+        match (scores.clone().max(), scores.min()) {
+            (Some(max), Some(min)) => max - min,
+            _ => 0,
+        }
+    }
+
+    /// Whether the engines disagree on the best move, or their scores are more than
+    /// `max_spread` apart.
+    #[must_use]
+    pub fn is_contested(&self, max_spread: i64) -> bool {
+        !self.agrees() || self.spread() > max_spread
+    }
+}
+
+/// Search `board` for `stone` with every one of `engines`.
+pub fn consensus(
+    board: &BoardArr,
+    stone: Stone,
+    engines: &mut [&mut dyn Searcher],
+    limits: &Limits,
+) -> crate::Result<Consensus> {
+    let opinions = engines
+        .iter_mut()
+        .map(|engine| {
+            Ok(Opinion {
+                engine: engine.name(),
+                result: engine.search(board, stone, limits)?,
+            })
+        })
+        .collect::<crate::Result<_>>()?;
+    Ok(Consensus { opinions })
+}
+
+/// The consensus of several engines at the nodes of a graph.
+pub type Consensuses = Annotations<Consensus>;
+
+impl Consensuses {
+    /// The consensus of `engines` on the position at `index`, searched unless it is known at the
+    /// current revision of `graph`.
+    pub fn search(
+        &mut self,
+        graph: &Board,
+        index: MoveIndex,
+        engines: &mut [&mut dyn Searcher],
+        limits: &Limits,
+    ) -> crate::Result<&Consensus> {
+        if self.get(graph, index).is_none() {
+            let (board, moves) = graph.as_board(&index)?;
+            let consensus = consensus(&board, GameRecord::stone_at(moves.len()), engines, limits)?;
+            self.insert(graph, index, consensus);
+        }
+        Ok(self
+            .get(graph, index)
+            .expect("the consensus was just added"))
+    }
+
+    /// The nodes the engines disagree on, see [`Consensus::is_contested`], by the node number.
+    #[must_use]
+    pub fn contested(&self, graph: &Board, max_spread: i64) -> Vec<(MoveIndex, &Consensus)> {
+        let mut contested = self
+            .iter(graph)
+            .filter(|(_, consensus)| consensus.is_contested(max_spread))
+            .collect::<Vec<_>>();
+        contested.sort_by_key(|(index, _)| index.node_index());
+        contested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineKind;
+    use crate::p;
+
+    /// Always plays `point` with `score`.
+    struct Fixed(Point, i32);
+
+    impl Searcher for Fixed {
+        fn name(&self) -> String {
+            format!("fixed {}", self.0)
+        }
+
+        fn search(&mut self, _: &BoardArr, _: Stone, _: &Limits) -> crate::Result<SearchResult> {
+            Ok(SearchResult {
+                best: Some(self.0),
+                score: self.1,
+                depth: 1,
+                nodes: 1,
+                pv: vec![self.0],
+            })
+        }
+    }
+
+    #[test]
+    fn contested() {
+        let mut graph = Board::new();
+        let h8 = graph.add_line(&p![[H, 8]]);
+        let i9 = graph.add_line(&p![[H, 8], [I, 9]]);
+        let limits = Limits {
+            depth: 2,
+            ..Limits::default()
+        };
+        let mut consensuses = Consensuses::new();
+
+        let (mut a, mut b, mut c) = (
+            Fixed(p![J, 10], 10),
+            Fixed(p![J, 10], 30),
+            Fixed(p![G, 7], 0),
+        );
+        let consensus = consensuses
+            .search(&graph, h8, &mut [&mut a, &mut b], &limits)
+            .unwrap();
+        assert!(consensus.agrees());
+        assert_eq!(consensus.spread(), 20);
+        assert!(!consensus.is_contested(50));
+
+        let consensus = consensuses
+            .search(&graph, i9, &mut [&mut a, &mut c, &mut b], &limits)
+            .unwrap();
+        assert!(consensus.is_contested(50));
+        assert_eq!(
+            consensus.best_moves(),
+            vec![
+                (Some(p![J, 10]), vec!["fixed J10", "fixed J10"]),
+                (Some(p![G, 7]), vec!["fixed G7"]),
+            ]
+        );
+
+        let contested = consensuses.contested(&graph, 50);
+        assert_eq!(contested.len(), 1);
+        assert_eq!(contested[0].0.node_index(), i9.node_index());
+        assert_eq!(consensuses.contested(&graph, 5).len(), 2);
+
+        let mut engine = EngineKind::AlphaBeta;
+        let board = graph.as_board(&i9).unwrap().0;
+        let consensus =
+            super::consensus(&board, Stone::Black, &mut [&mut engine, &mut a], &limits).unwrap();
+        assert_eq!(consensus.opinions[0].engine, "alphabeta");
+    }
+}
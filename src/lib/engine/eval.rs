@@ -0,0 +1,105 @@
+//! Position evaluation used by the search backends.
+//!
+//! Implement [`Evaluator`] to plug in another evaluation, e.g. a neural network, without touching
+//! the searches. [`Heuristic`] is the built-in one.
+
+use crate::board::evaluator::RenjuCondition;
+use crate::board::{BoardArr, Evaluate, Point, Stone};
+
+/// Evaluates positions for the searches.
+pub trait Evaluator {
+    /// How good `board` is for `stone`, the side to move, from `-1.0` (lost) to `1.0` (won).
+    fn value(&mut self, board: &BoardArr, stone: Stone) -> f32;
+
+    /// Prior probabilities of `moves` for `stone`, in the same order as `moves`.
+    ///
+    /// An empty vec means no opinion, the search keeps its own move order. This is the default.
+    fn policy(&mut self, board: &BoardArr, stone: Stone, moves: &[Point]) -> Vec<f32> {
+        let _ = (board, stone, moves);
+        vec![]
+    }
+}
+
+/// The built-in evaluation, counting threats with [`BoardArr::renju_conditions`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Heuristic;
+
+impl Evaluator for Heuristic {
+    fn value(&mut self, board: &BoardArr, stone: Stone) -> f32 {
+        (evaluate(board, stone) as f32 / 1000.0).tanh()
+    }
+}
+
+/// Static evaluation of `board` for `stone`, positive is good for `stone`.
+#[must_use]
+pub fn evaluate(board: &BoardArr, stone: Stone) -> i32 {
+    let side = |stone: Stone| -> i32 {
+        board
+            .renju_conditions(stone, None)
+            .conditions
+            .iter()
+            .map(|condition| match condition {
+                RenjuCondition::Five { .. } => 1000,
+                RenjuCondition::StraightFour { .. } => 200,
+                RenjuCondition::ClosedFour { .. } | RenjuCondition::BrokenFour { .. } => 50,
+                RenjuCondition::UnbrokenThree { .. } | RenjuCondition::BrokenThree { .. } => 10,
+            })
+            .sum()
+    };
+    side(stone) - side(stone.opposite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{best_move_with, mcts::Mcts, Limits};
+    use crate::p;
+
+    /// Only cares about who holds `target`.
+    struct Target {
+        target: Point,
+        policy_calls: usize,
+    }
+
+    impl Evaluator for Target {
+        fn value(&mut self, board: &BoardArr, stone: Stone) -> f32 {
+            match board.get_point(self.target).map(|m| m.color) {
+                Some(color) if color == stone => 1.0,
+                Some(color) if color == stone.opposite() => -1.0,
+                _ => 0.0,
+            }
+        }
+
+        fn policy(&mut self, _board: &BoardArr, _stone: Stone, moves: &[Point]) -> Vec<f32> {
+            self.policy_calls += 1;
+            moves
+                .iter()
+                .map(|m| if *m == self.target { 1.0 } else { 0.0 })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn searches_use_evaluator() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        let mut evaluator = Target {
+            target: p![I, 9],
+            policy_calls: 0,
+        };
+        let limits = Limits {
+            depth: 1,
+            ..Limits::default()
+        };
+        let result = best_move_with(&board, Stone::White, &limits, &mut evaluator);
+        assert_eq!(result.best, Some(p![I, 9]));
+        assert!(evaluator.policy_calls > 0);
+
+        let limits = Limits {
+            nodes: Some(50),
+            ..Limits::default()
+        };
+        let result = Mcts::default().search_with(&board, Stone::White, &limits, &mut evaluator);
+        assert_eq!(result.best, Some(p![I, 9]));
+    }
+}
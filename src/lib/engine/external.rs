@@ -0,0 +1,518 @@
+//! Engines run as separate programs, like the ones of the Gomocup tournament or Yixin.
+//!
+//! The engine is talked to over its standard input and output in one of two [`Protocol`]s:
+//!
+//! * [`Protocol::Gomocup`], the protocol of Gomocup and Piskvork. The board is sent with `BOARD`
+//!   and the engine answers with its move. Renju is asked for with `INFO rule 4`.
+//! * [`Protocol::Yixin`], the extension of it spoken by Yixin and engines made for its board. It
+//!   numbers the rules differently, renju being `INFO rule 2`, takes a depth and node budget, and
+//!   lists the forbidden points of a position, see [`Client::forbidden`].
+//!
+//! Both take part in tournament [openings](super::opening) through `SWAP2BOARD`, the swap2
+//! extension of the Gomocup protocol.
+//!
+//! A [`Client`] works over any reader and writer, [`External::spawn`] starts a program.
+//!
+//! While searching, engines report their progress in `MESSAGE` or `INFO` lines like
+//! `MESSAGE depth 12 ev +35 n 120K pv h8 i9 j10`. [`Client::search_with_info`] passes what
+//! could be read of them on as [`EngineInfo`]s, and the last one fills in the [`SearchResult`].
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use super::opening::{self, Choice, OpeningPlayer, Step};
+use super::{Limits, SearchResult, Searcher, WIN};
+use crate::board::{BoardArr, BoardRead, Point, Stone};
+use crate::errors::ParseError;
+use crate::Error;
+
+/// The protocol an engine speaks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Protocol {
+    #[default]
+    Gomocup,
+    Yixin,
+}
+
+impl Protocol {
+    /// The value of `INFO rule` asking for renju.
+    fn renju_rule(self) -> u32 {
+        match self {
+            Self::Gomocup => 4,
+            Self::Yixin => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gomocup" => Ok(Self::Gomocup),
+            "yixin" => Ok(Self::Yixin),
+            _ => Err(ParseError::Other(format!("unknown engine protocol {s:?}"))),
+        }
+    }
+}
+
+/// What an engine reported while searching. Engines report different things, anything not
+/// reported is left out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineInfo {
+    /// Depth of the iteration reported.
+    pub depth: Option<u32>,
+    /// Score for the side to move, a forced win as [`WIN`] minus the plies like the built in
+    /// engines.
+    pub score: Option<i32>,
+    pub nodes: Option<u64>,
+    /// Expected continuation, starting with the best move.
+    pub pv: Vec<Point>,
+}
+
+impl EngineInfo {
+    /// Read a report of an engine, without the `MESSAGE` or `INFO`. `None` if nothing in it is
+    /// known, like for messages meant for a person.
+    #[must_use]
+    pub fn parse(report: &str) -> Option<Self> {
+        let mut info = Self::default();
+        let mut tokens = report.split_whitespace();
+        while let Some(key) = tokens.next() {
+            match key.to_ascii_lowercase().as_str() {
+                "depth" => {
+                    // like `12-20`, the nominal depth and the deepest line
+                    let value = tokens.next()?;
+                    info.depth = value.split('-').next()?.parse().ok();
+                }
+                "ev" | "eval" | "score" => info.score = tokens.next().and_then(parse_score),
+                "n" | "nodes" => info.nodes = tokens.next().and_then(parse_count),
+                "pv" => {
+                    info.pv = tokens
+                        .by_ref()
+                        .map_while(|token| token.parse::<Point>().ok())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        (info != Self::default()).then_some(info)
+    }
+}
+
+/// A score like `+35`, `-120` or `M5`, a win in five plies, or `-M4`.
+fn parse_score(score: &str) -> Option<i32> {
+    let (sign, score) = match score.strip_prefix('-') {
+        Some(score) => (-1, score),
+        None => (1, score.strip_prefix('+').unwrap_or(score)),
+    };
+    match score.strip_prefix(['M', 'm']) {
+        Some(plies) => Some(sign * (WIN - plies.parse::<i32>().ok()?)),
+        None => Some(sign * score.parse::<i32>().ok()?),
+    }
+}
+
+/// A count like `1234`, `120K` or `3M`.
+fn parse_count(count: &str) -> Option<u64> {
+    let (count, unit) = match count.char_indices().last()? {
+        (i, 'k' | 'K') => (&count[..i], 1_000),
+        (i, 'm' | 'M') => (&count[..i], 1_000_000),
+        _ => (count, 1),
+    };
+    count.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// A connection to an engine.
+#[derive(Debug)]
+pub struct Client<R, W> {
+    reader: R,
+    writer: W,
+    protocol: Protocol,
+    name: String,
+    /// The size of the board the engine was started with.
+    size: Option<u32>,
+    /// The process of the engine, ended when the client is dropped.
+    child: Option<Child>,
+}
+
+/// An engine running as a program.
+pub type External = Client<BufReader<ChildStdout>, ChildStdin>;
+
+impl External {
+    /// Start `program`, speaking `protocol`.
+    pub fn spawn(program: &Path, protocol: Protocol) -> crate::Result<Self> {
+        let mut child = Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(Error::Engine(format!("no pipes to {program:?}")));
+        };
+        let mut client = Client::new(BufReader::new(stdout), stdin, protocol);
+        client.child = Some(child);
+        if let Some(name) = program.file_stem() {
+            client.name = name.to_string_lossy().into_owned();
+        }
+        Ok(client)
+    }
+}
+
+impl<R: BufRead, W: Write> Client<R, W> {
+    /// A client of an engine reading its commands from `writer` and answering to `reader`.
+    pub fn new(reader: R, writer: W, protocol: Protocol) -> Self {
+        Self {
+            reader,
+            writer,
+            protocol,
+            name: format!("{protocol:?}").to_lowercase(),
+            size: None,
+            child: None,
+        }
+    }
+
+    #[must_use]
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    /// The name of the program, or of the protocol if it wasn't started by the client.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send(&mut self, line: &str) -> crate::Result<()> {
+        tracing::trace!(line, "to engine");
+        writeln!(self.writer, "{line}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// The next answer of the engine, skipping its messages.
+    fn answer(&mut self) -> crate::Result<String> {
+        self.answer_with_info(&mut |_| {})
+    }
+
+    /// Like [`Client::answer`], passing the reports of the engine to `on_info`.
+    fn answer_with_info(&mut self, on_info: &mut dyn FnMut(&EngineInfo)) -> crate::Result<String> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(Error::Engine("the engine quit".to_owned()));
+            }
+            let line = line.trim();
+            tracing::trace!(line, "from engine");
+            let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match command.to_ascii_uppercase().as_str() {
+                "MESSAGE" | "INFO" => {
+                    if let Some(info) = EngineInfo::parse(rest) {
+                        on_info(&info);
+                    }
+                }
+                "" | "DEBUG" => {}
+                "ERROR" | "UNKNOWN" => {
+                    return Err(Error::Engine(format!("the engine answered {rest:?}")))
+                }
+                _ => return Ok(line.to_owned()),
+            }
+        }
+    }
+
+    /// Start a game on a board of `size`, unless it is the size of the last one.
+    fn start(&mut self, size: u32) -> crate::Result<()> {
+        if self.size == Some(size) {
+            return Ok(());
+        }
+        self.send(&format!("START {size}"))?;
+        match self.answer()? {
+            ok if ok.eq_ignore_ascii_case("OK") => {}
+            other => {
+                return Err(Error::Engine(format!(
+                    "unexpected answer {other:?} to START"
+                )))
+            }
+        }
+        self.size = Some(size);
+        self.send(&format!("INFO rule {}", self.protocol.renju_rule()))
+    }
+
+    fn send_limits(&mut self, limits: &Limits) -> crate::Result<()> {
+        if let Some(time) = limits.time {
+            self.send(&format!("INFO timeout_turn {}", time.as_millis()))?;
+        }
+        if let Some(bytes) = limits.table_bytes {
+            self.send(&format!("INFO max_memory {bytes}"))?;
+        }
+        if self.protocol == Protocol::Yixin {
+            self.send(&format!("INFO max_depth {}", limits.depth))?;
+            if let Some(nodes) = limits.nodes {
+                self.send(&format!("INFO max_node {nodes}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the stones of `board` after `command`, the stones of `stone` as the engine's own.
+    fn send_board(&mut self, command: &str, board: &BoardArr, stone: Stone) -> crate::Result<()> {
+        self.send(command)?;
+        for (point, color) in BoardRead::stones(board) {
+            let field = if color == stone { 1 } else { 2 };
+            self.send(&format!("{},{},{field}", point.x, point.y))?;
+        }
+        self.send("DONE")
+    }
+
+    /// Ask the engine for its move for `stone` in `board`.
+    ///
+    /// The score, depth, node count and continuation are the last ones the engine reported,
+    /// zero or only the move if it didn't.
+    pub fn search(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+    ) -> crate::Result<SearchResult> {
+        self.search_with_info(board, stone, limits, &mut |_| {})
+    }
+
+    /// Like [`Client::search`], passing every report of the engine to `on_info` as it comes.
+    pub fn search_with_info(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+        on_info: &mut dyn FnMut(&EngineInfo),
+    ) -> crate::Result<SearchResult> {
+        self.start(board.size())?;
+        self.send_limits(limits)?;
+        self.send_board("BOARD", board, stone)?;
+        let mut last = EngineInfo::default();
+        let answer = self.answer_with_info(&mut |info| {
+            on_info(info);
+            // engines report parts of a line, like the nodes before the pv is known
+            last.depth = info.depth.or(last.depth);
+            last.score = info.score.or(last.score);
+            last.nodes = info.nodes.or(last.nodes);
+            if !info.pv.is_empty() {
+                last.pv.clone_from(&info.pv);
+            }
+        })?;
+        let best = parse_move(&answer, board)
+            .ok_or_else(|| Error::Engine(format!("invalid move {answer:?} from the engine")))?;
+        Ok(SearchResult {
+            best: Some(best),
+            score: last.score.unwrap_or(0),
+            depth: last.depth.unwrap_or(0),
+            nodes: last.nodes.unwrap_or(0),
+            pv: match last.pv.first() {
+                Some(&first) if first == best => last.pv,
+                _ => vec![best],
+            },
+        })
+    }
+
+    /// The points forbidden for black in `board`, as found by the engine. Only for
+    /// [`Protocol::Yixin`].
+    pub fn forbidden(&mut self, board: &BoardArr) -> crate::Result<Vec<Point>> {
+        if self.protocol != Protocol::Yixin {
+            return Err(Error::Engine(format!(
+                "{:?} engines can't list forbidden points",
+                self.protocol
+            )));
+        }
+        self.start(board.size())?;
+        // sets the board without searching it
+        self.send_board("yxboard", board, Stone::Black)?;
+        self.send("yxshowforbid")?;
+        let answer = self.answer()?;
+        parse_forbidden(&answer)
+            .ok_or_else(|| Error::Engine(format!("invalid forbidden points {answer:?}")))
+    }
+}
+
+impl<R: BufRead, W: Write> Searcher for Client<R, W> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn search(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+    ) -> crate::Result<SearchResult> {
+        Client::search(self, board, stone, limits)
+    }
+}
+
+impl<R: BufRead, W: Write> OpeningPlayer for Client<R, W> {
+    /// Swap2 is asked with `SWAP2BOARD`, answered with `SWAP` or the stones to put. Other steps
+    /// are [decided](opening::decide) from searches.
+    fn opening(&mut self, step: &Step, board: &BoardArr, limits: &Limits) -> crate::Result<Choice> {
+        if !matches!(step, Step::Place | Step::AfterThree | Step::AfterFive) {
+            return opening::decide(self, step, board, limits);
+        }
+        self.start(board.size())?;
+        self.send_limits(limits)?;
+        let stone = match step {
+            Step::Place => Stone::Black,
+            _ => Stone::White,
+        };
+        self.send_board("SWAP2BOARD", board, stone)?;
+        let answer = self.answer()?;
+        if answer.eq_ignore_ascii_case("SWAP") {
+            return Ok(Choice::Swap);
+        }
+        let points = answer
+            .split_whitespace()
+            .map(|point| parse_move(point, board))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::Engine(format!("invalid stones {answer:?} from the engine")))?;
+        Ok(match (step, points.as_slice()) {
+            (Step::AfterThree | Step::AfterFive, &[point]) => Choice::Move(point),
+            _ => Choice::Stones(points),
+        })
+    }
+}
+
+impl<R, W> Drop for Client<R, W> {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// A move `x,y` of an engine, if it is an empty point of `board`.
+fn parse_move(answer: &str, board: &BoardArr) -> Option<Point> {
+    let (x, y) = answer.split_once(',')?;
+    let point = Point::new(x.trim().parse().ok()?, y.trim().parse().ok()?);
+    (board.get_point(point)?.color == Stone::Empty).then_some(point)
+}
+
+/// The points of `FORBID xxyyxxyy.`, two digits for every coordinate.
+fn parse_forbidden(answer: &str) -> Option<Vec<Point>> {
+    let points = answer.strip_prefix("FORBID")?.trim().strip_suffix('.')?;
+    if !points.is_ascii() || points.len() % 4 != 0 {
+        return None;
+    }
+    (0..points.len())
+        .step_by(4)
+        .map(|i| {
+            Some(Point::new(
+                points[i..i + 2].parse().ok()?,
+                points[i + 2..i + 4].parse().ok()?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    fn client(answers: &str, protocol: Protocol) -> Client<&[u8], Vec<u8>> {
+        Client::new(answers.as_bytes(), vec![], protocol)
+    }
+
+    #[test]
+    fn protocols() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![I, 9], Stone::White);
+        let limits = Limits {
+            nodes: Some(1000),
+            ..Limits::default()
+        };
+
+        let mut gomocup = client("OK\nMESSAGE thinking\n7,6\n8,8\n", Protocol::Gomocup);
+        let result = gomocup.search(&board, Stone::Black, &limits).unwrap();
+        assert_eq!(result.best, Some(p![H, 9]));
+        assert_eq!(
+            String::from_utf8_lossy(&gomocup.writer),
+            "START 15\nINFO rule 4\nBOARD\n8,6,2\n7,7,1\nDONE\n"
+        );
+        // started once, the stone of the side to move is the engine's
+        gomocup.writer.clear();
+        gomocup.search(&board, Stone::White, &limits).unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&gomocup.writer),
+            "BOARD\n8,6,1\n7,7,2\nDONE\n"
+        );
+        assert!(gomocup.forbidden(&board).is_err());
+
+        let mut yixin = client("OK\n8,6\n", Protocol::Yixin);
+        assert!(matches!(
+            yixin.search(&board, Stone::Black, &limits),
+            Err(Error::Engine(_))
+        ));
+        assert!(String::from_utf8_lossy(&yixin.writer)
+            .starts_with("START 15\nINFO rule 2\nINFO max_depth 4\nINFO max_node 1000\n"));
+
+        let mut yixin = client("OK\nFORBID 06070809.\n", Protocol::Yixin);
+        assert_eq!(
+            yixin.forbidden(&board).unwrap(),
+            vec![Point::new(6, 7), Point::new(8, 9)]
+        );
+        assert!(String::from_utf8_lossy(&yixin.writer).ends_with("DONE\nyxshowforbid\n"));
+
+        let mut reporting = client(
+            "OK\nMESSAGE depth 3 ev +12 n 2K\nMESSAGE depth 5-9 ev -M4 pv H9 G10 h10\n\
+             MESSAGE found a win\n7,6\n",
+            Protocol::Gomocup,
+        );
+        let mut infos = vec![];
+        let result = reporting
+            .search_with_info(&board, Stone::Black, &limits, &mut |info| {
+                infos.push(info.clone());
+            })
+            .unwrap();
+        assert_eq!(infos.len(), 2);
+        assert_eq!(
+            infos[0],
+            EngineInfo {
+                depth: Some(3),
+                score: Some(12),
+                nodes: Some(2000),
+                pv: vec![],
+            }
+        );
+        assert_eq!(infos[1].score, Some(-(WIN - 4)));
+        assert_eq!(
+            (result.depth, result.nodes, result.win_in()),
+            (5, 2000, Some(-4))
+        );
+        assert_eq!(result.pv, p![[H, 9], [G, 10], [H, 10]]);
+
+        let mut swap2 = client("OK\n7,7 8,6 6,6\n8,8 9,9\nSWAP\n", Protocol::Gomocup);
+        let empty = BoardArr::new(15);
+        assert_eq!(
+            swap2.opening(&Step::Place, &empty, &limits).unwrap(),
+            Choice::Stones(p![[H, 8], [I, 9], [G, 9]].to_vec())
+        );
+        assert_eq!(
+            swap2.opening(&Step::AfterThree, &board, &limits).unwrap(),
+            Choice::Stones(p![[I, 7], [J, 6]].to_vec())
+        );
+        assert_eq!(
+            swap2.opening(&Step::AfterFive, &board, &limits).unwrap(),
+            Choice::Swap
+        );
+        assert!(String::from_utf8_lossy(&swap2.writer)
+            .starts_with("START 15\nINFO rule 4\nSWAP2BOARD\nDONE\n"));
+
+        let mut failing = client("ERROR unsupported size\n", Protocol::Gomocup);
+        assert!(failing.search(&board, Stone::Black, &limits).is_err());
+        assert!(client("", Protocol::Gomocup)
+            .search(&board, Stone::Black, &limits)
+            .is_err());
+    }
+}
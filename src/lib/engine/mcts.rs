@@ -0,0 +1,438 @@
+//! Monte-Carlo tree search.
+//!
+//! The tree is expanded with [`BoardArr::candidate_moves`] and every new node is scored by a
+//! playout, a game played to the end by a [`PlayoutPolicy`]. Nodes are selected with UCT.
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore, SeedableRng};
+
+use super::{Evaluator, Limits, SearchResult};
+use crate::board::{BoardArr, Evaluate, Point, Stone};
+use crate::solver;
+
+/// Picks moves during playouts.
+pub trait PlayoutPolicy {
+    /// The move for `stone` in `board`. `None` ends the playout, as a draw on a full board and
+    /// otherwise as a loss for `stone`, which has no move left, e.g. black with only forbidden
+    /// blocks.
+    fn choose(&mut self, board: &BoardArr, stone: Stone, rng: &mut dyn RngCore) -> Option<Point>;
+}
+
+/// Plays fives and blocks fives, otherwise a random empty point next to a stone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RandomPolicy;
+
+impl PlayoutPolicy for RandomPolicy {
+    fn choose(&mut self, board: &BoardArr, stone: Stone, rng: &mut dyn RngCore) -> Option<Point> {
+        if let Some(five) = solver::five_points(board, stone).first() {
+            return Some(*five);
+        }
+        let blocks = solver::five_points(board, stone.opposite());
+        let mut moves = if blocks.is_empty() {
+            board
+                .iter()
+                .filter(|m| m.color.is_empty() && has_neighbour(board, m.point))
+                .map(|m| m.point)
+                .collect()
+        } else {
+            blocks
+        };
+        // forbidden points are rare, only check the chosen one
+        while !moves.is_empty() {
+            let point = moves.swap_remove(rng.gen_range(0..moves.len()));
+            if !stone.is_black()
+                || !board
+                    .renju_conditions(stone, Some(&[point]))
+                    .forbidden
+                    .contains(&point)
+            {
+                return Some(point);
+            }
+        }
+        None
+    }
+}
+
+/// A random move among the best few from [`BoardArr::candidate_moves`]. Stronger but a lot slower
+/// than [`RandomPolicy`].
+#[derive(Clone, Copy, Debug)]
+pub struct CandidatePolicy {
+    pub width: usize,
+}
+
+impl Default for CandidatePolicy {
+    fn default() -> Self {
+        Self { width: 3 }
+    }
+}
+
+impl PlayoutPolicy for CandidatePolicy {
+    fn choose(&mut self, board: &BoardArr, stone: Stone, rng: &mut dyn RngCore) -> Option<Point> {
+        board
+            .candidate_moves(stone, 1, self.width)
+            .choose(rng)
+            .copied()
+    }
+}
+
+/// The result for the side that moved last when the other has no move in `board`, a draw on a
+/// full board and a win otherwise.
+fn no_moves(board: &BoardArr) -> f64 {
+    match board.iter().all(|m| !m.color.is_empty()) {
+        true => 0.5,
+        false => 1.0,
+    }
+}
+
+fn has_neighbour(board: &BoardArr, point: Point) -> bool {
+    let size = board.size() as i32;
+    (-1..=1).any(|dy| {
+        (-1..=1).any(|dx| {
+            let (x, y) = (point.x as i32 + dx, point.y as i32 + dy);
+            (0..size).contains(&x)
+                && (0..size).contains(&y)
+                && board
+                    .get_xy(x as u32, y as u32)
+                    .is_some_and(|m| !m.color.is_empty())
+        })
+    })
+}
+
+struct Node {
+    /// The move leading here, `None` for the root.
+    point: Option<Point>,
+    /// The color that played `point`.
+    stone: Stone,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<Point>,
+    /// The game ended with `point`, a five or no moves left.
+    terminal: Option<f64>,
+    visits: u32,
+    /// Sum of results for `stone`, a win is `1.0` and a draw `0.5`.
+    wins: f64,
+}
+
+/// Monte-Carlo tree search with a playout policy.
+#[derive(Clone, Debug)]
+pub struct Mcts<P> {
+    pub policy: P,
+    /// UCT exploration constant.
+    pub exploration: f64,
+    /// Candidates expanded in every node.
+    pub width: usize,
+    /// Playouts longer than this are scored as draws.
+    pub max_playout: usize,
+}
+
+impl Default for Mcts<RandomPolicy> {
+    fn default() -> Self {
+        Self::new(RandomPolicy)
+    }
+}
+
+impl<P: PlayoutPolicy> Mcts<P> {
+    #[must_use]
+    pub fn new(policy: P) -> Self {
+        Self {
+            policy,
+            exploration: std::f64::consts::SQRT_2,
+            width: 12,
+            max_playout: 60,
+        }
+    }
+
+    /// Search for the best move for `stone` in `board`.
+    ///
+    /// `limits.nodes` is the amount of playouts, 1000 if neither it nor a time is set. With
+    /// `limits.seed` the search is reproducible, given the same limits without a time.
+    /// [`SearchResult::score`] is the win rate of the best move scaled to -1000..=1000.
+    pub fn search(&mut self, board: &BoardArr, stone: Stone, limits: &Limits) -> SearchResult {
+        self.run(board, stone, limits, None)
+    }
+
+    /// Like [`Self::search`], but new nodes are scored by `evaluator` instead of playouts, and
+    /// expanded in the order of its policy.
+    pub fn search_with(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+        evaluator: &mut dyn Evaluator,
+    ) -> SearchResult {
+        self.run(board, stone, limits, Some(evaluator))
+    }
+
+    fn run(
+        &mut self,
+        board: &BoardArr,
+        stone: Stone,
+        limits: &Limits,
+        mut evaluator: Option<&mut dyn Evaluator>,
+    ) -> SearchResult {
+        let seed = limits.seed.unwrap_or_else(rand::random);
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let deadline = limits.time.map(|time| std::time::Instant::now() + time);
+        let max_playouts = match (limits.nodes, limits.time) {
+            (Some(nodes), _) => nodes,
+            (None, Some(_)) => u64::MAX,
+            (None, None) => 1000,
+        };
+
+        let mut nodes = vec![Node {
+            point: None,
+            stone: stone.opposite(),
+            parent: None,
+            children: vec![],
+            untried: self.candidates(board, stone, evaluator.as_deref_mut()),
+            terminal: None,
+            visits: 0,
+            wins: 0.0,
+        }];
+        let mut playouts = 0;
+        let mut max_depth = 0;
+        while playouts < max_playouts
+            && deadline.is_none_or(|deadline| std::time::Instant::now() < deadline)
+        {
+            playouts += 1;
+            let mut board = board.clone();
+            let mut index = 0;
+            let mut depth = 0;
+
+            // selection
+            while nodes[index].untried.is_empty()
+                && !nodes[index].children.is_empty()
+                && nodes[index].terminal.is_none()
+            {
+                index = self.select(&nodes, index);
+                let node = &nodes[index];
+                board.set_point(node.point.unwrap(), node.stone);
+                depth += 1;
+            }
+
+            // expansion
+            if nodes[index].terminal.is_none() && !nodes[index].untried.is_empty() {
+                let untried = &mut nodes[index].untried;
+                let point = if evaluator.is_some() {
+                    untried.pop().unwrap()
+                } else {
+                    untried.swap_remove(rng.gen_range(0..untried.len()))
+                };
+                let played = nodes[index].stone.opposite();
+                let five = solver::makes_five(&board, point, played);
+                board.set_point(point, played);
+                let untried = if five {
+                    vec![]
+                } else {
+                    self.candidates(&board, played.opposite(), evaluator.as_deref_mut())
+                };
+                let terminal = if five {
+                    Some(1.0)
+                } else if untried.is_empty() {
+                    Some(no_moves(&board))
+                } else {
+                    None
+                };
+                nodes.push(Node {
+                    point: Some(point),
+                    stone: played,
+                    parent: Some(index),
+                    children: vec![],
+                    untried,
+                    terminal,
+                    visits: 0,
+                    wins: 0.0,
+                });
+                let child = nodes.len() - 1;
+                nodes[index].children.push(child);
+                index = child;
+                depth += 1;
+            }
+            max_depth = max_depth.max(depth);
+
+            // simulation, the result is for the color that played into `index`
+            let result = match nodes[index].terminal {
+                Some(result) => result,
+                None => match evaluator.as_deref_mut() {
+                    Some(evaluator) => {
+                        let value = evaluator.value(&board, nodes[index].stone.opposite());
+                        f64::from(1.0 - value.clamp(-1.0, 1.0)) / 2.0
+                    }
+                    None => self.playout(&mut board, nodes[index].stone, &mut rng),
+                },
+            };
+
+            // backpropagation
+            let mut result = result;
+            let mut current = Some(index);
+            while let Some(i) = current {
+                nodes[i].visits += 1;
+                nodes[i].wins += result;
+                result = 1.0 - result;
+                current = nodes[i].parent;
+            }
+        }
+
+        // the principal variation follows the most visited children
+        let mut pv = vec![];
+        let mut index = 0;
+        while let Some(&child) = nodes[index]
+            .children
+            .iter()
+            .max_by_key(|&&child| nodes[child].visits)
+        {
+            pv.push(nodes[child].point.unwrap());
+            index = child;
+        }
+        let score = pv.first().map_or(0, |_| {
+            let best = nodes[0]
+                .children
+                .iter()
+                .copied()
+                .max_by_key(|&child| nodes[child].visits)
+                .unwrap();
+            let rate = nodes[best].wins / f64::from(nodes[best].visits.max(1));
+            (rate * 2000.0 - 1000.0).round() as i32
+        });
+        tracing::debug!(playouts, max_depth, score, ?pv, seed, "finished search");
+        SearchResult {
+            best: pv
+                .first()
+                .copied()
+                .or_else(|| nodes[0].untried.first().copied()),
+            score,
+            depth: max_depth,
+            nodes: playouts,
+            pv,
+        }
+    }
+
+    /// Moves to expand, with a policy they are sorted with the best last.
+    fn candidates<'e>(
+        &self,
+        board: &BoardArr,
+        stone: Stone,
+        evaluator: Option<&mut (dyn Evaluator + 'e)>,
+    ) -> Vec<Point> {
+        let moves = board.candidate_moves(stone, 2, self.width);
+        let Some(evaluator) = evaluator else {
+            return moves;
+        };
+        let priors = evaluator.policy(board, stone, &moves);
+        if priors.len() != moves.len() {
+            return moves;
+        }
+        let mut ordered = moves.into_iter().zip(priors).collect::<Vec<_>>();
+        ordered.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        ordered.into_iter().map(|(point, _)| point).collect()
+    }
+
+    /// The child of `index` with the highest UCT value.
+    fn select(&self, nodes: &[Node], index: usize) -> usize {
+        let parent_visits = f64::from(nodes[index].visits).ln();
+        nodes[index]
+            .children
+            .iter()
+            .copied()
+            .max_by(|&a, &b| {
+                let uct = |i: usize| {
+                    let node = &nodes[i];
+                    let visits = f64::from(node.visits.max(1));
+                    node.wins / visits + self.exploration * (parent_visits / visits).sqrt()
+                };
+                uct(a).total_cmp(&uct(b))
+            })
+            .unwrap()
+    }
+
+    /// Play out the game after `last` played, returning the result for `last`.
+    fn playout(&mut self, board: &mut BoardArr, last: Stone, rng: &mut dyn RngCore) -> f64 {
+        let mut stone = last.opposite();
+        for _ in 0..self.max_playout {
+            let Some(point) = self.policy.choose(board, stone, rng) else {
+                let result = no_moves(board);
+                return if stone == last { 1.0 - result } else { result };
+            };
+            let five = solver::makes_five(board, point, stone);
+            board.set_point(point, stone);
+            if five {
+                return if stone == last { 1.0 } else { 0.0 };
+            }
+            stone = stone.opposite();
+        }
+        0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn finds_the_five() {
+        let mut board = BoardArr::new(15);
+        for point in p![[D, 8], [E, 8], [F, 8], [G, 8]] {
+            board.set_point(point, Stone::Black);
+        }
+        for point in p![[C, 8], [A, 1], [A, 3]] {
+            board.set_point(point, Stone::White);
+        }
+        let limits = Limits {
+            nodes: Some(50),
+            ..Limits::default()
+        };
+        let result = Mcts::default().search(&board, Stone::Black, &limits);
+        assert_eq!(result.best, Some(p![H, 8]));
+        assert_eq!(result.score, 1000);
+        assert_eq!(result.nodes, 50);
+
+        // only the block is a candidate, a few slow playouts are enough
+        let limits = Limits {
+            nodes: Some(2),
+            ..Limits::default()
+        };
+        let result = Mcts::new(CandidatePolicy::default()).search(&board, Stone::White, &limits);
+        assert_eq!(result.best, Some(p![H, 8]));
+    }
+
+    #[test]
+    fn forbidden_blocks_lose() {
+        // white's only five is on H8, an overline for black
+        let mut board = BoardArr::new(15);
+        for point in p![[E, 8], [F, 8], [G, 8], [I, 8], [J, 8], [H, 13]] {
+            board.set_point(point, Stone::Black);
+        }
+        for point in p![[H, 9], [H, 10], [H, 11], [H, 12], [A, 1], [A, 3]] {
+            board.set_point(point, Stone::White);
+        }
+        let mut rng = SmallRng::seed_from_u64(0);
+        let mut mcts = Mcts::default();
+        assert_eq!(RandomPolicy.choose(&board, Stone::Black, &mut rng), None);
+        assert_eq!(
+            mcts.playout(&mut board.clone(), Stone::White, &mut rng),
+            1.0
+        );
+        assert_eq!(mcts.playout(&mut board, Stone::Black, &mut rng), 0.0);
+
+        let mut full = BoardArr::new(1);
+        full.set_point(Point::new(0, 0), Stone::Black);
+        assert_eq!(mcts.playout(&mut full, Stone::Black, &mut rng), 0.5);
+    }
+
+    #[test]
+    fn seeded_is_reproducible() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![I, 9], Stone::White);
+        let limits = Limits {
+            nodes: Some(10),
+            seed: Some(7),
+            ..Limits::default()
+        };
+        let first = Mcts::default().search(&board, Stone::Black, &limits);
+        let second = Mcts::default().search(&board, Stone::Black, &limits);
+        assert_eq!(first, second);
+    }
+}
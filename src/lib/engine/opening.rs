@@ -0,0 +1,426 @@
+//! Tournament opening rules, for engines to play matches from the first stone.
+//!
+//! Under [`OpeningRule::Swap2`] and [`OpeningRule::Soosyrv`] the players put stones and choose
+//! colors before the game goes on as usual. [`play_opening`] runs the opening between two
+//! [`OpeningPlayer`]s, asking each for a [`Choice`] at every [`Step`] that is theirs.
+//!
+//! The built in engines decide with [`decide`], from their searches. External engines are asked
+//! with the swap2 extension of their protocol, `SWAP2BOARD`, and decide like the built in ones
+//! under Soosyrv, which the protocols have no commands for.
+
+use std::collections::BTreeSet;
+
+use super::{Limits, Searcher};
+use crate::board::duplicates::position_key;
+use crate::board::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+use crate::game::GameRecord;
+use crate::Error;
+
+/// Scores closer to 0 than this are a balanced position, in which swap2's second player puts
+/// two more stones rather than choosing a color.
+pub const BALANCED: i32 = 200;
+
+/// A rule for the opening.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum OpeningRule {
+    /// The first player puts three stones. The second takes black, plays the fourth move as white
+    /// or puts two more stones, after which the first player chooses the color.
+    Swap2,
+    /// The first player puts three stones and the second may swap. White plays the fourth move and
+    /// black may swap again, then black offers `offers` fifth moves and white chooses one.
+    ///
+    /// The number of fifth moves is part of the rule here, rather than declared with the fourth
+    /// move.
+    Soosyrv { offers: u8 },
+}
+
+impl std::str::FromStr for OpeningRule {
+    type Err = ParseError;
+
+    /// `swap2`, or `soosyrv` followed by the number of fifth moves, e.g. `soosyrv8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("soosyrv") {
+            _ if s == "swap2" => Ok(Self::Swap2),
+            Some(offers) => match offers.parse() {
+                Ok(offers @ 1..=8) => Ok(Self::Soosyrv { offers }),
+                _ => Err(ParseError::Other(format!(
+                    "soosyrv needs 1 to 8 fifth moves, like soosyrv8, not {s:?}"
+                ))),
+            },
+            None => Err(ParseError::Other(format!("unknown opening rule {s:?}"))),
+        }
+    }
+}
+
+/// A decision asked of a player. The player is to move in the board it is asked with.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Step {
+    /// Put the first three stones, black, white and black.
+    Place,
+    /// Swap2, after three stones: [`Choice::Swap`] to take black, [`Choice::Move`] to play the
+    /// fourth move as white or [`Choice::Stones`] to put two more stones.
+    AfterThree,
+    /// Swap2, after five stones: [`Choice::Swap`] to take black or [`Choice::Move`] to play the
+    /// sixth move as white.
+    AfterFive,
+    /// Soosyrv, after three stones: [`Choice::Swap`] to take black or [`Choice::Move`] to play
+    /// the fourth move as white.
+    Swap,
+    /// Soosyrv, offer `count` fifth moves as black with [`Choice::Offers`], or, if `may_swap`,
+    /// take white with [`Choice::Swap`]. No two offers may give the same position rotated or
+    /// mirrored.
+    Offer { count: u8, may_swap: bool },
+    /// Soosyrv, choose one of `offers` as white with [`Choice::Choose`].
+    Choose { offers: Vec<Point> },
+}
+
+/// The answer of a player to a [`Step`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Choice {
+    Swap,
+    Move(Point),
+    Stones(Vec<Point>),
+    Offers(Vec<Point>),
+    Choose(Point),
+}
+
+/// The players of a match, in the order they start the opening.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Seat {
+    First,
+    Second,
+}
+
+impl Seat {
+    #[must_use]
+    pub fn other(self) -> Self {
+        match self {
+            Self::First => Self::Second,
+            Self::Second => Self::First,
+        }
+    }
+}
+
+/// The moves of an opening and who plays black from there.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Opening {
+    pub moves: Vec<Point>,
+    pub black: Seat,
+}
+
+/// An engine that can play the opening under tournament rules.
+pub trait OpeningPlayer: Searcher {
+    /// The choice of the player at `step`, [`decide`] unless the engine decides itself.
+    fn opening(&mut self, step: &Step, board: &BoardArr, limits: &Limits) -> crate::Result<Choice> {
+        decide(self, step, board, limits)
+    }
+}
+
+impl OpeningPlayer for super::EngineKind {}
+
+/// The choice of `engine` at `step`, from its searches of `board`.
+///
+/// Colors are chosen by the score of the side to move, two more stones are put when it is
+/// [`BALANCED`]. Offers are the candidates best for black that give different positions, and the
+/// offer chosen the one best for white.
+pub fn decide<S: Searcher + ?Sized>(
+    engine: &mut S,
+    step: &Step,
+    board: &BoardArr,
+    limits: &Limits,
+) -> crate::Result<Choice> {
+    let mut board = board.clone();
+    let mut best = |board: &BoardArr, stone: Stone| -> crate::Result<(Point, i32)> {
+        let result = engine.search(board, stone, limits)?;
+        let best = result
+            .best
+            .ok_or_else(|| Error::Engine(format!("{} found no move", engine.name())))?;
+        Ok((best, result.score))
+    };
+    Ok(match step {
+        Step::Place => {
+            let center = Point::new(board.size() / 2, board.size() / 2);
+            board.set_point(center, Stone::Black);
+            let (second, _) = best(&board, Stone::White)?;
+            board.set_point(second, Stone::White);
+            let (third, _) = best(&board, Stone::Black)?;
+            Choice::Stones(vec![center, second, third])
+        }
+        Step::AfterThree => match best(&board, Stone::White)? {
+            (_, score) if score < -BALANCED => Choice::Swap,
+            (fourth, score) if score > BALANCED => Choice::Move(fourth),
+            (fourth, _) => {
+                board.set_point(fourth, Stone::White);
+                let (fifth, _) = best(&board, Stone::Black)?;
+                Choice::Stones(vec![fourth, fifth])
+            }
+        },
+        Step::AfterFive | Step::Swap => match best(&board, Stone::White)? {
+            (_, score) if score < 0 => Choice::Swap,
+            (point, _) => Choice::Move(point),
+        },
+        Step::Offer { count, may_swap } => {
+            if *may_swap && best(&board, Stone::Black)?.1 < 0 {
+                return Ok(Choice::Swap);
+            }
+            let mut scored = vec![];
+            for point in board.candidate_moves(Stone::Black, 2, usize::from(*count) * 3) {
+                board.set_point(point, Stone::Black);
+                let (_, score) = best(&board, Stone::White)?;
+                scored.push((-score, point, position_key(&board)));
+                board.set_point(point, Stone::Empty);
+            }
+            scored.sort_by_key(|(score, ..)| std::cmp::Reverse(*score));
+            let mut positions = BTreeSet::new();
+            Choice::Offers(
+                scored
+                    .into_iter()
+                    .filter(|(_, _, position)| positions.insert(position.clone()))
+                    .map(|(_, point, _)| point)
+                    .take(usize::from(*count))
+                    .collect(),
+            )
+        }
+        Step::Choose { offers } => {
+            let mut chosen = None;
+            for &offer in offers {
+                board.set_point(offer, Stone::Black);
+                let (_, score) = best(&board, Stone::White)?;
+                board.set_point(offer, Stone::Empty);
+                if chosen.is_none_or(|(_, best)| score > best) {
+                    chosen = Some((offer, score));
+                }
+            }
+            let (offer, _) =
+                chosen.ok_or_else(|| Error::Rules("no fifth move offered".to_owned()))?;
+            Choice::Choose(offer)
+        }
+    })
+}
+
+/// Play the opening of `rule` between `first` and `second`.
+///
+/// Every choice is checked: the stones must be on empty points and offers must give different
+/// positions. The game goes on from the returned moves as usual.
+pub fn play_opening(
+    rule: OpeningRule,
+    first: &mut dyn OpeningPlayer,
+    second: &mut dyn OpeningPlayer,
+    limits: &Limits,
+) -> crate::Result<Opening> {
+    let mut game = Game {
+        board: BoardArr::new(15),
+        moves: vec![],
+        players: [first, second],
+        limits,
+    };
+    let placed = game.ask(Seat::First, &Step::Place)?;
+    game.place(Seat::First, placed, 3)?;
+    let black = match rule {
+        OpeningRule::Swap2 => match game.ask(Seat::Second, &Step::AfterThree)? {
+            Choice::Swap => {
+                game.next(Seat::First, Stone::White)?;
+                Seat::Second
+            }
+            Choice::Move(point) => {
+                game.place(Seat::Second, Choice::Stones(vec![point]), 1)?;
+                Seat::First
+            }
+            Choice::Stones(stones) => {
+                game.place(Seat::Second, Choice::Stones(stones), 2)?;
+                match game.ask(Seat::First, &Step::AfterFive)? {
+                    Choice::Swap => Seat::First,
+                    Choice::Move(point) => {
+                        game.place(Seat::First, Choice::Stones(vec![point]), 1)?;
+                        Seat::Second
+                    }
+                    choice => return Err(invalid(Seat::First, &choice)),
+                }
+            }
+            choice => return Err(invalid(Seat::Second, &choice)),
+        },
+        OpeningRule::Soosyrv { offers } => {
+            let mut black = match game.ask(Seat::Second, &Step::Swap)? {
+                Choice::Swap => {
+                    game.next(Seat::First, Stone::White)?;
+                    Seat::Second
+                }
+                Choice::Move(point) => {
+                    game.place(Seat::Second, Choice::Stones(vec![point]), 1)?;
+                    Seat::First
+                }
+                choice => return Err(invalid(Seat::Second, &choice)),
+            };
+            let mut step = Step::Offer {
+                count: offers,
+                may_swap: true,
+            };
+            let offered = loop {
+                match game.ask(black, &step)? {
+                    Choice::Swap if matches!(step, Step::Offer { may_swap: true, .. }) => {
+                        black = black.other();
+                        step = Step::Offer {
+                            count: offers,
+                            may_swap: false,
+                        };
+                    }
+                    Choice::Offers(offered) => break offered,
+                    choice => return Err(invalid(black, &choice)),
+                }
+            };
+            game.check_offers(black, &offered, offers)?;
+            match game.ask(
+                black.other(),
+                &Step::Choose {
+                    offers: offered.clone(),
+                },
+            )? {
+                Choice::Choose(point) if offered.contains(&point) => {
+                    game.board.set_point(point, Stone::Black);
+                    game.moves.push(point);
+                }
+                choice => return Err(invalid(black.other(), &choice)),
+            }
+            black
+        }
+    };
+    Ok(Opening {
+        moves: game.moves,
+        black,
+    })
+}
+
+fn invalid(seat: Seat, choice: &Choice) -> Error {
+    Error::Rules(format!("{seat:?} player can't choose {choice:?} now"))
+}
+
+struct Game<'a, 'p> {
+    board: BoardArr,
+    moves: Vec<Point>,
+    players: [&'p mut dyn OpeningPlayer; 2],
+    limits: &'a Limits,
+}
+
+impl Game<'_, '_> {
+    fn player(&mut self, seat: Seat) -> &mut dyn OpeningPlayer {
+        match seat {
+            Seat::First => &mut *self.players[0],
+            Seat::Second => &mut *self.players[1],
+        }
+    }
+
+    fn ask(&mut self, seat: Seat, step: &Step) -> crate::Result<Choice> {
+        let (board, limits) = (self.board.clone(), self.limits);
+        let choice = self.player(seat).opening(step, &board, limits)?;
+        tracing::debug!(?seat, ?step, ?choice, "opening");
+        Ok(choice)
+    }
+
+    /// Put `count` stones chosen by `seat`, alternating colors.
+    fn place(&mut self, seat: Seat, choice: Choice, count: usize) -> crate::Result<()> {
+        let Choice::Stones(stones) = choice else {
+            return Err(invalid(seat, &choice));
+        };
+        if stones.len() != count {
+            return Err(Error::Rules(format!(
+                "{seat:?} player put {} stones instead of {count}",
+                stones.len()
+            )));
+        }
+        for point in stones {
+            self.play(seat, point)?;
+        }
+        Ok(())
+    }
+
+    /// Let `seat` play the next move, as `stone`.
+    fn next(&mut self, seat: Seat, stone: Stone) -> crate::Result<()> {
+        let (board, limits) = (self.board.clone(), self.limits);
+        let result = self.player(seat).search(&board, stone, limits)?;
+        let point = result
+            .best
+            .ok_or_else(|| Error::Engine(format!("{seat:?} player found no move")))?;
+        self.play(seat, point)
+    }
+
+    fn play(&mut self, seat: Seat, point: Point) -> crate::Result<()> {
+        if self.board.get_point(point).map(|m| m.color) != Some(Stone::Empty) {
+            return Err(Error::Rules(format!(
+                "{seat:?} player put a stone on {point}, which is taken or off the board"
+            )));
+        }
+        let stone = GameRecord::stone_at(self.moves.len());
+        self.board.set_point(point, stone);
+        self.moves.push(point);
+        Ok(())
+    }
+
+    fn check_offers(&self, seat: Seat, offers: &[Point], count: u8) -> crate::Result<()> {
+        let mut positions = BTreeSet::new();
+        for &point in offers {
+            if self.board.get_point(point).map(|m| m.color) != Some(Stone::Empty) {
+                return Err(Error::Rules(format!(
+                    "{seat:?} player offered {point}, which is taken"
+                )));
+            }
+            let mut board = self.board.clone();
+            board.set_point(point, Stone::Black);
+            if !positions.insert(position_key(&board)) {
+                return Err(Error::Rules(format!(
+                    "{seat:?} player offered {point}, the same position as another offer"
+                )));
+            }
+        }
+        match offers.len() == usize::from(count) {
+            true => Ok(()),
+            false => Err(Error::Rules(format!(
+                "{seat:?} player offered {} fifth moves instead of {count}",
+                offers.len()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineKind;
+
+    #[test]
+    fn openings() {
+        assert_eq!("swap2".parse::<OpeningRule>().unwrap(), OpeningRule::Swap2);
+        assert_eq!(
+            "soosyrv8".parse::<OpeningRule>().unwrap(),
+            OpeningRule::Soosyrv { offers: 8 }
+        );
+        assert!("soosyrv9".parse::<OpeningRule>().is_err());
+
+        let limits = Limits {
+            depth: 1,
+            width: 6,
+            ..Limits::default()
+        };
+        let (mut first, mut second) = (EngineKind::AlphaBeta, EngineKind::AlphaBeta);
+        let opening = play_opening(OpeningRule::Swap2, &mut first, &mut second, &limits).unwrap();
+        assert!((4..=6).contains(&opening.moves.len()));
+
+        let opening = play_opening(
+            OpeningRule::Soosyrv { offers: 2 },
+            &mut first,
+            &mut second,
+            &limits,
+        )
+        .unwrap();
+        assert_eq!(opening.moves.len(), 5);
+        let distinct = opening.moves.iter().collect::<BTreeSet<_>>();
+        assert_eq!(distinct.len(), 5);
+    }
+}
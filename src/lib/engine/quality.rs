@@ -0,0 +1,255 @@
+//! How well players move over a collection of their games, for coaching dashboards.
+//!
+//! Every move is scored by how much it lost for its player according to an [`Evaluator`], the
+//! score of the position after the best of the [candidate moves] less the score after the move
+//! played, both from the view of the player. The losses are averaged and counted in a histogram
+//! for every player, with the blunders of [`analyze_game`] by [`Phase`] of the game.
+//!
+//! Players are told apart by the names in the [`GameRecord`]s, games without names count for
+//! `Black` and `White`. [`QualityReport::write_csv`] writes a row for every player with the
+//! header
+//!
+//! ```text
+//! player,moves,average_loss,opening_blunders,middle_blunders,end_blunders,loss<0.05,loss<0.15,loss<0.3,loss<0.6,loss>=0.6
+//! ```
+//!
+//! and [`QualityReport::write_json`] the same as JSON.
+//!
+//! [candidate moves]: crate::board::BoardArr::candidate_moves
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::Evaluator;
+use crate::analysis::{analyze_game, AnalyzeOptions};
+use crate::board::{Board, Stone};
+use crate::game::GameRecord;
+use crate::project::games;
+
+/// The candidate moves a move is compared with.
+pub const CANDIDATES: usize = 10;
+
+/// The upper bounds of the buckets of the histogram, the last bucket holds the larger losses.
+pub const LOSS_BUCKETS: [f32; 4] = [0.05, 0.15, 0.3, 0.6];
+
+/// A part of a game, by move number.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Phase {
+    /// The first 10 moves.
+    Opening,
+    /// Moves 11 to 30.
+    Middle,
+    End,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Opening, Phase::Middle, Phase::End];
+
+    /// The phase of the move at `ply`, counting from zero.
+    #[must_use]
+    pub fn of(ply: usize) -> Self {
+        match ply + 1 {
+            ..=10 => Phase::Opening,
+            11..=30 => Phase::Middle,
+            _ => Phase::End,
+        }
+    }
+}
+
+/// The moves of a player.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlayerQuality {
+    /// The moves scored, passes are not.
+    pub moves: usize,
+    /// The losses of every move added up.
+    pub total_loss: f64,
+    /// The moves in every bucket of [`LOSS_BUCKETS`].
+    pub histogram: [usize; LOSS_BUCKETS.len() + 1],
+    /// The blunders in the opening, middle and end of the games, in the order of [`Phase::ALL`].
+    pub blunders: [usize; 3],
+}
+
+impl PlayerQuality {
+    /// The average loss of a move, 0 without any.
+    #[must_use]
+    pub fn average_loss(&self) -> f64 {
+        match self.moves {
+            0 => 0.0,
+            moves => self.total_loss / moves as f64,
+        }
+    }
+
+    fn add_loss(&mut self, loss: f32) {
+        self.moves += 1;
+        self.total_loss += f64::from(loss);
+        let bucket = LOSS_BUCKETS
+            .iter()
+            .position(|bound| loss < *bound)
+            .unwrap_or(LOSS_BUCKETS.len());
+        self.histogram[bucket] += 1;
+    }
+}
+
+/// The quality of the moves of every player, see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QualityReport {
+    pub players: BTreeMap<String, PlayerQuality>,
+}
+
+impl QualityReport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Score the moves of `game` with `evaluator` and count its blunders, found with `options`.
+    pub fn add(
+        &mut self,
+        game: &GameRecord,
+        evaluator: &mut impl Evaluator,
+        options: &AnalyzeOptions,
+    ) {
+        let name = |stone: Stone| {
+            let name = match stone {
+                Stone::Black => game.black.as_deref(),
+                _ => game.white.as_deref(),
+            };
+            name.map_or_else(|| format!("{stone:?}"), str::to_owned)
+        };
+        let mut board = game.position(0);
+        for (ply, point) in game.moves.iter().enumerate() {
+            let stone = GameRecord::stone_at(ply);
+            if point.is_null {
+                continue;
+            }
+            let mut score = |point| {
+                let mut after = board.clone();
+                after.set_point(point, stone);
+                -evaluator.value(&after, stone.opposite())
+            };
+            let played = score(*point);
+            let best = board
+                .candidate_moves(stone, 2, CANDIDATES)
+                .into_iter()
+                .map(&mut score)
+                .fold(played, f32::max);
+            self.players
+                .entry(name(stone))
+                .or_default()
+                .add_loss(best - played);
+            board.set_point(*point, stone);
+        }
+        for blunder in analyze_game(game, options).blunders {
+            let phase = Phase::of(blunder.ply) as usize;
+            self.players
+                .entry(name(blunder.stone))
+                .or_default()
+                .blunders[phase] += 1;
+        }
+    }
+
+    /// Add every game of `graph`, the lines from a root to the end of a branch.
+    pub fn add_graph(
+        &mut self,
+        graph: &Board,
+        evaluator: &mut impl Evaluator,
+        options: &AnalyzeOptions,
+    ) {
+        for moves in games(graph) {
+            self.add(&GameRecord::new(moves), evaluator, options);
+        }
+    }
+
+    /// Write a row for every player, see the [module documentation](self).
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "player,moves,average_loss,opening_blunders,middle_blunders,end_blunders"
+        )?;
+        for bound in LOSS_BUCKETS {
+            write!(writer, ",loss<{bound}")?;
+        }
+        writeln!(writer, ",loss>={}", LOSS_BUCKETS[LOSS_BUCKETS.len() - 1])?;
+        for (player, quality) in &self.players {
+            write!(
+                writer,
+                "{},{},{:.4}",
+                csv_field(player),
+                quality.moves,
+                quality.average_loss()
+            )?;
+            for count in quality.blunders.iter().chain(&quality.histogram) {
+                write!(writer, ",{count}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Write every player as JSON.
+    #[cfg(feature = "serde")]
+    pub fn write_json(&self, writer: impl Write) -> crate::Result<()> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+}
+
+/// `field` quoted if it holds a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Point;
+    use crate::engine::Heuristic;
+    use crate::p;
+
+    #[test]
+    fn players() {
+        // white lets black make an open four
+        let mut game = GameRecord::new(
+            p![
+                [H, 8],
+                [A, 1],
+                [I, 8],
+                [A, 3],
+                [J, 8],
+                [A, 5],
+                [K, 8],
+                [A, 7]
+            ]
+            .to_vec(),
+        );
+        game.black = Some("Kim, J.".to_owned());
+        let mut quality = QualityReport::new();
+        quality.add(&game, &mut Heuristic, &AnalyzeOptions::default());
+        let black = &quality.players["Kim, J."];
+        let white = &quality.players["White"];
+        assert_eq!((black.moves, white.moves), (4, 4));
+        assert_eq!(black.histogram.iter().sum::<usize>(), 4);
+        assert!(white.average_loss() > black.average_loss());
+        assert!(white.blunders.iter().sum::<usize>() > 0);
+        assert_eq!(Phase::of(9), Phase::Opening);
+        assert_eq!(Phase::of(10), Phase::Middle);
+
+        let mut csv = vec![];
+        quality.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let rows = csv.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].ends_with(",loss<0.6,loss>=0.6"), "{csv}");
+        assert!(rows[1].starts_with("\"Kim, J.\",4,"), "{csv}");
+        assert_eq!(rows[1].split(',').count(), 12, "{csv}");
+    }
+}
@@ -1,3 +1,92 @@
+use crate::board::IndexOutOfBoundsError;
+use crate::file_reader::renlib::parser::{ParseBoardTextError, ParseCommentError};
+use crate::file_reader::renlib::CommandError;
+use crate::progress::Cancelled;
+
+/// Any error of the library, by what went wrong.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A library, position or text could not be read.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A move against the rules, e.g. on an occupied or forbidden point.
+    #[error("{0}")]
+    Rules(String),
+    /// A node or move that is not in the graph.
+    #[error("{0}")]
+    Graph(String),
+    /// The engine or a search failed.
+    #[error("{0}")]
+    Engine(String),
+    /// A stored file, like the training progress, a session or the config, is invalid.
+    #[error(transparent)]
+    Db(Box<dyn std::error::Error + Send + Sync>),
+    /// The operation was stopped through its [`CancelToken`](crate::progress::CancelToken).
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+    /// A file was written by someone else since it was read, see
+    /// [`Autosave::reconcile`](crate::autosave::Autosave::reconcile).
+    #[error("{0:?} was changed by someone else")]
+    Changed(std::path::PathBuf),
+}
+
+/// A result with the [`Error`] of the library.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl From<IndexOutOfBoundsError> for Error {
+    fn from(error: IndexOutOfBoundsError) -> Self {
+        Error::Graph(error.to_string())
+    }
+}
+
+impl From<CommandError> for Error {
+    fn from(error: CommandError) -> Self {
+        Error::Parse(ParseError::Other(error.to_string()))
+    }
+}
+
+impl From<ParseCommentError> for Error {
+    fn from(ParseCommentError::Io(error): ParseCommentError) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<ParseBoardTextError> for Error {
+    fn from(ParseBoardTextError::Io(error): ParseBoardTextError) -> Self {
+        Error::Io(error)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Db(Box::new(error))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<postcard::Error> for Error {
+    fn from(error: postcard::Error) -> Self {
+        Error::Db(Box::new(error))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::Db(Box::new(error))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<toml::ser::Error> for Error {
+    fn from(error: toml::ser::Error) -> Self {
+        Error::Db(Box::new(error))
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ParseError {
     #[error("File is not currently supported")]
@@ -17,3 +106,24 @@ pub enum ParseError {
     #[error("{0}")]
     Other(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn categories() {
+        let error = Error::from(ParseError::NotSupported);
+        assert!(matches!(error, Error::Parse(ParseError::NotSupported)));
+        assert!(matches!(
+            Error::from(IndexOutOfBoundsError),
+            Error::Graph(_)
+        ));
+        assert!(matches!(Error::from(Cancelled), Error::Cancelled(_)));
+        let io = std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "eof");
+        assert!(matches!(
+            Error::from(ParseCommentError::Io(io)),
+            Error::Io(_)
+        ));
+    }
+}
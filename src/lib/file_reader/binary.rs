@@ -0,0 +1,121 @@
+//! A compact binary format of the move graph, for cache files.
+//!
+//! A file is the 8 bytes of [`MAGIC`], a [`VERSION`] byte and the [`Board`] in the
+//! [postcard](https://docs.rs/postcard) wire format, keeping every node with its comments and
+//! board text, the bookmarks, the tags, the color overrides, the history and the trees. It is much faster to read than a _.lib_ file and much
+//! smaller than JSON.
+//!
+//! # Stability
+//!
+//! The layout of a version never changes. A change to the layout, e.g. a new field on
+//! [`BoardMarker`](crate::board::BoardMarker), bumps [`VERSION`], and files of an older version
+//! are rejected with [`ParseError::VersionNotSupported`] so that a cache can be rebuilt from its
+//! library.
+
+use std::io::{Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::board::{Board, Payload};
+use crate::errors::ParseError;
+
+/// The first bytes of a file.
+pub const MAGIC: [u8; 8] = *b"\xffRenjuG\xff";
+
+/// The version of the layout written.
+pub const VERSION: u8 = 4;
+
+/// Whether `bytes` start like a file of this format.
+#[must_use]
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// `board` as the bytes of a file.
+pub fn to_bytes<T: Payload + Serialize>(board: &Board<T>) -> crate::Result<Vec<u8>> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    Ok(postcard::to_extend(board, bytes)?)
+}
+
+/// Read a board from the bytes of a file.
+pub fn from_bytes<T: Payload + DeserializeOwned>(bytes: &[u8]) -> crate::Result<Board<T>> {
+    let Some(rest) = bytes.strip_prefix(&MAGIC) else {
+        return Err(ParseError::Other("not a binary graph file".to_owned()).into());
+    };
+    match rest.split_first() {
+        Some((&VERSION, graph)) => Ok(postcard::from_bytes(graph)?),
+        Some((&version, _)) => Err(ParseError::VersionNotSupported {
+            majv: version,
+            minv: 0,
+        }
+        .into()),
+        None => {
+            Err(ParseError::Other("the binary graph file ends after its magic".to_owned()).into())
+        }
+    }
+}
+
+/// Write `board` to `writer`.
+pub fn write<T: Payload + Serialize>(
+    board: &Board<T>,
+    mut writer: impl Write,
+) -> crate::Result<()> {
+    writer.write_all(&to_bytes(board)?)?;
+    Ok(())
+}
+
+/// Read a board from `reader`.
+pub fn read<T: Payload + DeserializeOwned>(mut reader: impl Read) -> crate::Result<Board<T>> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    from_bytes(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardMarker, Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn round_trip() {
+        let mut graph = Board::new();
+        graph.set_title(Some("Sure wins".to_owned()));
+        let root = graph.get_root();
+        let h8 = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let mut i9 = BoardMarker::new(p![I, 9], Stone::White);
+        i9.multiline_comment = Some("the best reply\nfor white".to_owned());
+        i9.board_text = Some("a".to_owned());
+        let i9 = graph.insert_move(h8, i9);
+        graph.set_bookmark("Main", i9);
+        graph.set_color_override(i9, Some(Stone::Black));
+        let other = graph.add_root(BoardMarker::null());
+        graph.insert_move(other, BoardMarker::new(p![A, 1], Stone::Black));
+
+        let bytes = to_bytes(&graph).unwrap();
+        assert!(is_binary(&bytes));
+        let read: Board = from_bytes(&bytes).unwrap();
+        assert_eq!(read.title(), Some("Sure wins"));
+        assert_eq!(read.get_bookmark("Main"), Some(i9));
+        assert_eq!(read.roots().len(), 2);
+        let marker = read.get_move(i9).unwrap();
+        assert_eq!(
+            marker.multiline_comment.as_deref(),
+            Some("the best reply\nfor white")
+        );
+        assert_eq!(marker.board_text.as_deref(), Some("a"));
+        assert_eq!(read.color_at(i9), Some(Stone::Black));
+        assert_eq!(read.as_board(&i9).unwrap().1, p![[H, 8], [I, 9]]);
+
+        let mut old = bytes.clone();
+        old[MAGIC.len()] = VERSION + 1;
+        assert!(matches!(
+            from_bytes::<BoardMarker>(&old),
+            Err(crate::Error::Parse(ParseError::VersionNotSupported { .. }))
+        ));
+        assert!(from_bytes::<BoardMarker>(&bytes[..bytes.len() / 2]).is_err());
+        assert!(from_bytes::<BoardMarker>(b"\xffRenLib\xff").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Choosing the part of a library to write, e.g to publish the lines prepared for a tournament.
+//!
+//! An [`ExportFilter`] makes a copy of the graph with only the nodes it keeps, which is then
+//! written like any other graph. [`save_file_path_with_options`](super::save_file_path_with_options)
+//! applies [`WriteOptions::filter`](super::WriteOptions::filter) before writing, so every format
+//! gets the same subset.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+use crate::board::history::{Action, Timestamp};
+use crate::board::{Board, MoveIndex, NodeIndex, Stone};
+
+/// Which nodes of a graph to export. The default keeps every node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportFilter {
+    tags: Vec<String>,
+    max_depth: Option<usize>,
+    repertoire: Option<Stone>,
+    author: Option<String>,
+    added: Option<RangeInclusive<Timestamp>>,
+}
+
+impl ExportFilter {
+    /// Keep the nodes tagged with `tag`, the moves leading to them and everything after them.
+    #[must_use]
+    pub fn tagged(tag: impl Into<String>) -> Self {
+        Self::default().or_tagged(tag)
+    }
+
+    /// Also keep the nodes tagged with `tag`, see [`tagged`](Self::tagged).
+    #[must_use]
+    pub fn or_tagged(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// Keep only the first `depth` moves of every tree.
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Keep only the main continuation where `color` is to move, and every continuation of the
+    /// opponent, i.e the repertoire of `color`.
+    #[must_use]
+    pub fn repertoire(mut self, color: Stone) -> Self {
+        self.repertoire = Some(color);
+        self
+    }
+
+    /// Keep only the moves `author` added, as logged in the [history](crate::board::history), and
+    /// the moves leading to them. Moves added before the history was kept are left out.
+    #[must_use]
+    pub fn added_by(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    /// Keep only the moves added in `range`, as logged in the
+    /// [history](crate::board::history), and the moves leading to them. See
+    /// [`parse_date`](crate::board::history::parse_date) for the start of a day.
+    #[must_use]
+    pub fn added_between(mut self, range: RangeInclusive<Timestamp>) -> Self {
+        self.added = Some(range);
+        self
+    }
+
+    /// A copy of `board` with the nodes kept by the filter, their bookmarks and tags.
+    ///
+    /// Every root is kept, with the title and comment of the library.
+    #[must_use]
+    pub fn apply(&self, board: &Board) -> Board {
+        let on_path = self.on_path(board);
+        let added = self.added_nodes(board);
+        let mut filtered = Board::new();
+        let mut copies = HashMap::new();
+        let mut stack = vec![];
+        for (i, &root) in board.roots().iter().enumerate() {
+            let marker = board
+                .get_move(root)
+                .expect("roots are in the graph")
+                .clone();
+            let copy = if i == 0 {
+                let copy = filtered.get_root();
+                *filtered
+                    .get_move_mut(copy)
+                    .expect("the root is in the graph") = marker;
+                copy
+            } else {
+                filtered.add_root(marker)
+            };
+            copies.insert(root.node_index(), copy);
+            let inside = self.tags.is_empty() || self.is_tagged(board, root);
+            stack.push((root, copy, 0, inside));
+        }
+        while let Some((index, copy, depth, inside)) = stack.pop() {
+            copy_names(board, index, &mut filtered, copy);
+            if self.max_depth.is_some_and(|max| depth >= max) {
+                continue;
+            }
+            // children are listed newest first, a copy keeps the order by adding them oldest first
+            let mut children = board.get_children(&index);
+            children.reverse();
+            if let Some(color) = self.repertoire {
+                let to_move = children
+                    .iter()
+                    .filter_map(|&child| board.get_move(child))
+                    .any(|marker| marker.color == color);
+                if to_move {
+                    children.truncate(1);
+                }
+            }
+            for child in children {
+                let inside = inside || self.is_tagged(board, child);
+                if !inside && !on_path.contains(&child.node_index()) {
+                    continue;
+                }
+                if added
+                    .as_ref()
+                    .is_some_and(|added| !added.contains(&child.node_index()))
+                {
+                    continue;
+                }
+                if let Some(existing) = copies.get(&child.node_index()) {
+                    filtered
+                        .add_edge(&copy, existing)
+                        .expect("a copy of a graph has no cycles");
+                    continue;
+                }
+                let marker = board.get_move(child).expect("children are in the graph");
+                let child_copy = filtered.insert_move(copy, marker.clone());
+                copies.insert(child.node_index(), child_copy);
+                stack.push((child, child_copy, depth + 1, inside));
+            }
+        }
+        filtered
+    }
+
+    fn is_tagged(&self, board: &Board, index: MoveIndex) -> bool {
+        let tags = board.tags_at(index);
+        self.tags.iter().any(|tag| tags.contains(&tag.as_str()))
+    }
+
+    /// The nodes added by the author and in the range of the filter and the nodes on the way to
+    /// them, `None` to keep every node.
+    fn added_nodes(&self, board: &Board) -> Option<HashSet<NodeIndex>> {
+        if self.author.is_none() && self.added.is_none() {
+            return None;
+        }
+        // a line removed and added again belongs to the last one adding it
+        let mut additions = HashMap::new();
+        for edit in board.history() {
+            if edit.action == Action::Add {
+                additions.insert(edit.path.as_str(), edit);
+            }
+        }
+        let paths = additions
+            .into_iter()
+            .filter(|(_, edit)| self.author.as_ref().is_none_or(|a| *a == edit.author))
+            .filter(|(_, edit)| self.added.as_ref().is_none_or(|r| r.contains(&edit.time)))
+            .map(|(path, _)| path)
+            .collect::<HashSet<_>>();
+        let mut kept = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut stack = board.roots().to_vec();
+        while let Some(index) = stack.pop() {
+            if !seen.insert(index.node_index()) {
+                continue;
+            }
+            if paths.contains(board.describe_path(index).as_str()) {
+                kept.extend(board.down_to_root(&index).iter().map(MoveIndex::node_index));
+            }
+            stack.extend(board.get_children(&index));
+        }
+        Some(kept)
+    }
+
+    /// The nodes on the way to a tagged node.
+    fn on_path(&self, board: &Board) -> HashSet<NodeIndex> {
+        self.tags
+            .iter()
+            .flat_map(|tag| board.tagged(tag))
+            .flat_map(|index| board.down_to_root(index))
+            .map(|index| index.node_index())
+            .collect()
+    }
+}
+
+fn copy_names(board: &Board, index: MoveIndex, filtered: &mut Board, copy: MoveIndex) {
+    for name in board.bookmarks_at(index) {
+        filtered.set_bookmark(name, copy);
+    }
+    for tag in board.tags_at(index) {
+        filtered.tag(copy, tag);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardMarker, Point};
+    use crate::p;
+
+    fn lines(board: &Board) -> Vec<String> {
+        let mut text = vec![];
+        super::super::text::write(board, &mut text).unwrap();
+        String::from_utf8(text)
+            .unwrap()
+            .lines()
+            .skip(2)
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn filters() {
+        let mut graph = Board::new();
+        graph.set_title(Some("Prep".to_owned()));
+        let j10 = graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        graph.add_line(&p![[H, 8], [I, 9], [G, 7]]);
+        graph.add_line(&p![[H, 8], [I, 7], [J, 6]]);
+        let j10 = graph.get_parent_strong(&j10).unwrap();
+        graph.tag(j10, "tournament2024");
+        graph.set_bookmark("Trap", j10);
+
+        let tagged = ExportFilter::tagged("tournament2024").apply(&graph);
+        assert_eq!(tagged.title(), Some("Prep"));
+        assert_eq!(
+            lines(&tagged),
+            [
+                "B H8",
+                "W I9",
+                "B J10 bookmark=\"Trap\" tag=\"tournament2024\"",
+                "W K11"
+            ]
+        );
+        assert_eq!(tagged.tagged("tournament2024").len(), 1);
+
+        let shallow = ExportFilter::default().max_depth(2).apply(&graph);
+        assert_eq!(lines(&shallow), ["B H8", "- W I9", "- W I7"]);
+
+        let black = ExportFilter::default()
+            .repertoire(Stone::Black)
+            .apply(&graph);
+        assert_eq!(
+            lines(&black),
+            [
+                "B H8",
+                "- W I9",
+                "  B J10 bookmark=\"Trap\" tag=\"tournament2024\"",
+                "  W K11",
+                "- W I7",
+                "  B J6"
+            ]
+        );
+        assert_eq!(lines(&ExportFilter::default().apply(&graph)), lines(&graph));
+    }
+
+    #[test]
+    fn added() {
+        let mut graph = Board::new();
+        let i9 = graph.add_line(&p![[H, 8], [I, 9]]);
+        graph.record_history(Some("alice".to_owned()));
+        graph.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        graph.record_history(Some("bob".to_owned()));
+        graph.add_line(&p![[H, 8], [I, 7]]);
+        graph.insert_move(i9, BoardMarker::new(p![G, 7], Stone::Black));
+        graph.record_history(None);
+        let times = graph.history().iter().map(|e| e.time).collect::<Vec<_>>();
+        assert_eq!(times.len(), 3);
+
+        let alice = ExportFilter::default().added_by("alice").apply(&graph);
+        assert_eq!(lines(&alice), ["B H8", "W I9", "B J10"]);
+        let bob = ExportFilter::default().added_by("bob").apply(&graph);
+        assert_eq!(lines(&bob), ["B H8", "- W I9", "  B G7", "- W I7"]);
+        let nobody = ExportFilter::default().added_by("carol").apply(&graph);
+        assert!(lines(&nobody).is_empty());
+
+        let everything = ExportFilter::default().added_between(times[0]..=times[2]);
+        assert_eq!(lines(&everything.apply(&graph)).len(), 5);
+        let none = ExportFilter::default().added_between(0..=times[0] - 1);
+        assert!(lines(&none.apply(&graph)).is_empty());
+        let both = ExportFilter::default()
+            .added_by("alice")
+            .added_between(times[0]..=times[2]);
+        assert_eq!(lines(&both.apply(&graph)), lines(&alice));
+    }
+}
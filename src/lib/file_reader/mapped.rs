@@ -0,0 +1,525 @@
+//! A binary layout of the move graph that is read in place, for sharing one large library
+//! between many readers.
+//!
+//! Unlike the [`binary`](super::binary) cache format, nothing is decoded when a file is opened.
+//! The nodes are fixed size records that [`MappedGraph`] reads directly from the bytes of the
+//! file, and comments are borrowed from them. With the `mmap` feature [`MappedGraph::open`] maps
+//! the file into memory, so every process reading the same library shares its pages with the
+//! others. A file is checked once when opened, reading a node afterwards does not fail.
+//!
+//! Only the nodes are kept, not the bookmarks, tags or history of a library.
+//!
+//! # Layout
+//!
+//! All numbers are little endian `u32`, the sections follow each other without padding:
+//!
+//! - The header, 32 bytes: the 8 bytes of [`MAGIC`], the [`VERSION`] byte, 3 bytes of zeros,
+//!   the amount of nodes, roots and children, and the length of the strings, then 4 bytes of
+//!   zeros.
+//! - The nodes, 44 bytes each: the column, row and a byte with bit 0 set for a null point and the
+//!   color in bits 1 and 2 (`0` none, `1` black, `2` white), a byte of zeros, the
+//!   [`Command`](super::renlib::Command) bits, the parent or [`NONE`], the start and amount of
+//!   the children in the children section, and the start and length of the one line comment,
+//!   the multiline comment and the board text in the strings, the start is [`NONE`] for none.
+//! - The roots, a node each.
+//! - The children, a node each, oldest first for every node.
+//! - The strings, UTF-8.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+
+use super::renlib::Command;
+use crate::board::{Board, BoardMarker, MoveIndex, NodeIndex, Point, Stone};
+use crate::errors::ParseError;
+
+/// The first bytes of a file.
+pub const MAGIC: [u8; 8] = *b"\xffRenjuM\xff";
+
+/// The version of the layout written.
+pub const VERSION: u8 = 1;
+
+/// A missing parent or string.
+pub const NONE: u32 = u32::MAX;
+
+/// A node, its index in the nodes of the file.
+pub type NodeId = u32;
+
+const HEADER: usize = 32;
+const NODE: usize = 44;
+
+/// Whether `bytes` start like a file of this layout.
+#[must_use]
+pub fn is_mapped(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().expect("four bytes make a u32"))
+}
+
+/// Where the sections are.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    nodes: usize,
+    roots: usize,
+    children: usize,
+    strings: usize,
+}
+
+impl Layout {
+    fn node(&self, id: NodeId) -> usize {
+        HEADER + id as usize * NODE
+    }
+
+    fn root(&self, i: usize) -> usize {
+        HEADER + self.nodes * NODE + i * 4
+    }
+
+    fn child(&self, i: usize) -> usize {
+        self.root(self.roots) + i * 4
+    }
+
+    fn strings(&self) -> usize {
+        self.child(self.children)
+    }
+
+    fn len(&self) -> usize {
+        self.strings() + self.strings
+    }
+}
+
+/// A library read in place, see the [module documentation](self).
+///
+/// `B` holds the bytes of the file, e.g a `Vec<u8>` or with the `mmap` feature a
+/// [`memmap2::Mmap`].
+#[derive(Debug)]
+pub struct MappedGraph<B> {
+    bytes: B,
+    layout: Layout,
+}
+
+impl<B: AsRef<[u8]>> MappedGraph<B> {
+    /// Check that `bytes` are a valid file and read it from them.
+    pub fn from_bytes(bytes: B) -> crate::Result<Self> {
+        let invalid = |message: &str| ParseError::Other(format!("invalid mapped graph: {message}"));
+        let data = bytes.as_ref();
+        let Some(rest) = data.strip_prefix(&MAGIC) else {
+            return Err(ParseError::Other("not a mapped graph file".to_owned()).into());
+        };
+        match rest.first() {
+            Some(&VERSION) => {}
+            Some(&version) => {
+                return Err(ParseError::VersionNotSupported {
+                    majv: version,
+                    minv: 0,
+                }
+                .into())
+            }
+            None => return Err(invalid("the file ends after its magic").into()),
+        }
+        if data.len() < HEADER {
+            return Err(invalid("the header is cut off").into());
+        }
+        let count = |at| read_u32(data, at) as usize;
+        let layout = Layout {
+            nodes: count(12),
+            roots: count(16),
+            children: count(20),
+            strings: count(24),
+        };
+        // the counts are at most u32::MAX, this does not overflow a 64 bit usize
+        if layout.len() != data.len() {
+            return Err(invalid("the sections do not match the length of the file").into());
+        }
+        let strings = std::str::from_utf8(&data[layout.strings()..])
+            .map_err(|e| invalid(&format!("the strings are not UTF-8, {e}")))?;
+        let is_node = |id: u32| (id as usize) < layout.nodes;
+        for i in 0..layout.roots {
+            if !is_node(read_u32(data, layout.root(i))) {
+                return Err(invalid("a root is not a node").into());
+            }
+        }
+        for i in 0..layout.children {
+            if !is_node(read_u32(data, layout.child(i))) {
+                return Err(invalid("a child is not a node").into());
+            }
+        }
+        for id in 0..layout.nodes as u32 {
+            let node = &data[layout.node(id)..layout.node(id) + NODE];
+            let field = |at| read_u32(node, at);
+            if node[2] >> 1 > 2 {
+                return Err(invalid("a node has an invalid color").into());
+            }
+            Command::new(field(4)).map_err(|e| invalid(&e.to_string()))?;
+            if field(8) != NONE && !is_node(field(8)) {
+                return Err(invalid("a parent is not a node").into());
+            }
+            let (start, len) = (field(12) as usize, field(16) as usize);
+            if start
+                .checked_add(len)
+                .is_none_or(|end| end > layout.children)
+            {
+                return Err(invalid("the children of a node are out of bounds").into());
+            }
+            for at in [20, 28, 36] {
+                let (start, len) = (field(at), field(at + 4) as usize);
+                let range = start as usize..(start as usize).saturating_add(len);
+                if start != NONE && strings.get(range).is_none() {
+                    return Err(invalid("a string of a node is out of bounds").into());
+                }
+            }
+        }
+        Ok(Self { bytes, layout })
+    }
+
+    fn data(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+
+    fn record(&self, id: NodeId) -> &[u8] {
+        let at = self.layout.node(id);
+        &self.data()[at..at + NODE]
+    }
+
+    /// The amount of nodes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.layout.nodes
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.layout.nodes == 0
+    }
+
+    /// The root of every tree, like [`Board::roots`].
+    pub fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.layout.roots).map(|i| read_u32(self.data(), self.layout.root(i)))
+    }
+
+    /// The node `id`, `None` if there is no such node.
+    #[must_use]
+    pub fn node(&self, id: NodeId) -> Option<MappedNode<'_>> {
+        ((id as usize) < self.layout.nodes).then(|| MappedNode {
+            id,
+            record: self.record(id),
+            data: self.data(),
+            layout: self.layout,
+        })
+    }
+
+    /// Read every node into a [`Board`], for editing.
+    #[must_use]
+    pub fn to_board(&self) -> Board {
+        let mut board = Board::new();
+        let mut copies: HashMap<NodeId, MoveIndex> = HashMap::new();
+        let mut stack = vec![];
+        for (i, root) in self.roots().enumerate() {
+            let Some(node) = self.node(root) else {
+                continue;
+            };
+            let copy = match i {
+                0 => {
+                    *board.root_marker_mut() = node.marker();
+                    board.get_root()
+                }
+                _ => board.add_root(node.marker()),
+            };
+            copies.insert(root, copy);
+            stack.push(node);
+        }
+        while let Some(node) = stack.pop() {
+            let parent = copies[&node.id()];
+            for child in node.children() {
+                if let Some(copy) = copies.get(&child) {
+                    if let Err(e) = board.add_edge(&parent, copy) {
+                        tracing::warn!(?e, "could not share node");
+                    }
+                    continue;
+                }
+                let Some(child) = self.node(child) else {
+                    continue;
+                };
+                copies.insert(child.id(), board.insert_move(parent, child.marker()));
+                stack.push(child);
+            }
+        }
+        board
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl MappedGraph<memmap2::Mmap> {
+    /// Map the file at `path` into memory and check it.
+    ///
+    /// The file must not be changed while it is mapped, replace it with a new file instead, like
+    /// [`write_atomic`](super::write_atomic) does.
+    pub fn open(path: &std::path::Path) -> crate::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: libraries are replaced rather than changed in place, see above
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Self::from_bytes(map)
+    }
+}
+
+/// A node of a [`MappedGraph`], read from the bytes of the file when asked for.
+#[derive(Clone, Copy, Debug)]
+pub struct MappedNode<'a> {
+    id: NodeId,
+    record: &'a [u8],
+    data: &'a [u8],
+    layout: Layout,
+}
+
+impl<'a> MappedNode<'a> {
+    fn field(&self, at: usize) -> u32 {
+        read_u32(self.record, at)
+    }
+
+    #[must_use]
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    #[must_use]
+    pub fn point(&self) -> Point {
+        match self.record[2] & 1 {
+            1 => Point::null(),
+            _ => Point::new(u32::from(self.record[0]), u32::from(self.record[1])),
+        }
+    }
+
+    #[must_use]
+    pub fn color(&self) -> Stone {
+        match self.record[2] >> 1 {
+            1 => Stone::Black,
+            2 => Stone::White,
+            _ => Stone::Empty,
+        }
+    }
+
+    #[must_use]
+    pub fn command(&self) -> Command {
+        Command::new(self.field(4)).expect("commands are checked when opened")
+    }
+
+    /// The parent the node was first reached through, `None` for a root.
+    #[must_use]
+    pub fn parent(&self) -> Option<NodeId> {
+        Some(self.field(8)).filter(|&parent| parent != NONE)
+    }
+
+    /// The continuations, oldest first.
+    pub fn children(&self) -> impl Iterator<Item = NodeId> + 'a {
+        let (start, len) = (self.field(12) as usize, self.field(16) as usize);
+        let (data, layout) = (self.data, self.layout);
+        (start..start + len).map(move |i| read_u32(data, layout.child(i)))
+    }
+
+    fn text(&self, at: usize) -> Option<&'a str> {
+        let start = self.field(at);
+        if start == NONE {
+            return None;
+        }
+        let range: Range<usize> = start as usize..start as usize + self.field(at + 4) as usize;
+        let strings = &self.data[self.layout.strings()..];
+        // checked to be UTF-8 on char boundaries when opened
+        std::str::from_utf8(&strings[range]).ok()
+    }
+
+    #[must_use]
+    pub fn oneline_comment(&self) -> Option<&'a str> {
+        self.text(20)
+    }
+
+    #[must_use]
+    pub fn multiline_comment(&self) -> Option<&'a str> {
+        self.text(28)
+    }
+
+    #[must_use]
+    pub fn board_text(&self) -> Option<&'a str> {
+        self.text(36)
+    }
+
+    /// An owned copy of the node.
+    #[must_use]
+    pub fn marker(&self) -> BoardMarker {
+        let mut marker = BoardMarker::new(self.point(), self.color());
+        marker.command = self.command();
+        marker.oneline_comment = self.oneline_comment().map(str::to_owned);
+        marker.multiline_comment = self.multiline_comment().map(str::to_owned);
+        marker.board_text = self.board_text().map(str::to_owned);
+        marker
+    }
+}
+
+/// `board` as the bytes of a file.
+#[must_use]
+pub fn to_bytes(board: &Board) -> Vec<u8> {
+    // number the nodes in the order they are first reached
+    let mut ids: HashMap<NodeIndex, NodeId> = HashMap::new();
+    let mut order: Vec<(MoveIndex, Option<NodeId>)> = vec![];
+    let mut stack = board
+        .roots()
+        .iter()
+        .rev()
+        .map(|root| (*root, None))
+        .collect::<Vec<_>>();
+    while let Some((index, parent)) = stack.pop() {
+        if ids.contains_key(&index.node_index()) {
+            continue;
+        }
+        ids.insert(index.node_index(), order.len() as NodeId);
+        order.push((index, parent));
+        let id = order.len() as NodeId - 1;
+        // children are listed newest first, visit the oldest first
+        stack.extend(
+            board
+                .get_children(&index)
+                .into_iter()
+                .map(|c| (c, Some(id))),
+        );
+    }
+
+    let mut nodes = Vec::with_capacity(order.len() * NODE);
+    let mut children = vec![];
+    let mut strings = String::new();
+    let mut text = |text: &Option<String>| match text {
+        Some(text) => {
+            let start = strings.len() as u32;
+            strings.push_str(text);
+            [start, text.len() as u32]
+        }
+        None => [NONE, 0],
+    };
+    for (index, parent) in &order {
+        let marker = board.get_move(*index).expect("the node was just reached");
+        // the format has no overrides, a move keeps the color it is played with
+        let color = match board.color_at(*index).unwrap_or(marker.color) {
+            Stone::Empty => 0,
+            Stone::Black => 1,
+            Stone::White => 2,
+        };
+        let (x, y) = match marker.point.is_null {
+            true => (0, 0),
+            false => (marker.point.x as u8, marker.point.y as u8),
+        };
+        nodes.extend([x, y, u8::from(marker.point.is_null) | color << 1, 0]);
+        let first_child = children.len() as u32;
+        children.extend(
+            board
+                .get_children(index)
+                .into_iter()
+                .rev()
+                .map(|child| ids[&child.node_index()]),
+        );
+        let mut fields = vec![
+            marker.command.bits(),
+            parent.unwrap_or(NONE),
+            first_child,
+            children.len() as u32 - first_child,
+        ];
+        fields.extend(text(&marker.oneline_comment));
+        fields.extend(text(&marker.multiline_comment));
+        fields.extend(text(&marker.board_text));
+        nodes.extend(fields.into_iter().flat_map(u32::to_le_bytes));
+    }
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend([VERSION, 0, 0, 0]);
+    for count in [
+        order.len(),
+        board.roots().len(),
+        children.len(),
+        strings.len(),
+    ] {
+        bytes.extend((count as u32).to_le_bytes());
+    }
+    bytes.extend([0; 4]);
+    bytes.extend(nodes);
+    for root in board.roots() {
+        bytes.extend(ids[&root.node_index()].to_le_bytes());
+    }
+    bytes.extend(children.into_iter().flat_map(u32::to_le_bytes));
+    bytes.extend(strings.into_bytes());
+    bytes
+}
+
+/// Write `board` to `writer`.
+pub fn write(board: &Board, mut writer: impl Write) -> crate::Result<()> {
+    writer.write_all(&to_bytes(board))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn round_trip() {
+        let mut graph = Board::new();
+        graph.set_title(Some("Shared library".to_owned()));
+        let h8 = graph.add_line(&p![[H, 8]]);
+        let i9 = graph.add_line(&p![[H, 8], [I, 9]]);
+        graph.add_line(&p![[H, 8], [I, 7]]);
+        let mut j10 = BoardMarker::new(p![J, 10], Stone::Black);
+        j10.multiline_comment = Some("Sure win\nfor black ✓".to_owned());
+        let j10 = graph.insert_move(i9, j10);
+        let pass = graph.insert_move(j10, BoardMarker::new(Point::null(), Stone::White));
+        // reached from two nodes
+        graph.add_edge(&h8, &pass).unwrap();
+        let other = graph.add_root(BoardMarker::null());
+        graph.insert_move(other, BoardMarker::new(p![A, 1], Stone::Black));
+
+        let bytes = to_bytes(&graph);
+        assert!(is_mapped(&bytes));
+        let mapped = MappedGraph::from_bytes(bytes.as_slice()).unwrap();
+        assert_eq!(mapped.roots().count(), 2);
+        let root = mapped.node(mapped.roots().next().unwrap()).unwrap();
+        assert_eq!(root.oneline_comment(), Some("Shared library"));
+        let h8 = mapped.node(root.children().next().unwrap()).unwrap();
+        assert_eq!((h8.point(), h8.color()), (p![H, 8], Stone::Black));
+        let replies = h8
+            .children()
+            .map(|child| mapped.node(child).unwrap().point())
+            .collect::<Vec<_>>();
+        assert_eq!(replies, [p![I, 9], p![I, 7], Point::null()]);
+        let i9 = mapped.node(h8.children().next().unwrap()).unwrap();
+        let j10 = mapped.node(i9.children().next().unwrap()).unwrap();
+        assert_eq!(j10.multiline_comment(), Some("Sure win\nfor black ✓"));
+        assert_eq!(j10.parent(), Some(i9.id()));
+        assert!(mapped.node(mapped.len() as NodeId).is_none());
+
+        let board = mapped.to_board();
+        assert_eq!(board.title(), Some("Shared library"));
+        assert_eq!(board.roots().len(), 2);
+        let j10 = board.find_position(&p![[H, 8], [I, 9], [J, 10]]).unwrap();
+        assert_eq!(
+            board.get_move(j10).unwrap().multiline_comment.as_deref(),
+            Some("Sure win\nfor black ✓")
+        );
+        assert_eq!(to_bytes(&board), bytes);
+
+        for len in [0, 8, 31, bytes.len() - 1] {
+            assert!(MappedGraph::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut bad = bytes.clone();
+        bad[HEADER + 2] = 0xff;
+        assert!(MappedGraph::from_bytes(bad).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn open() {
+        let path = std::env::temp_dir().join(format!("renju-mapped-{}.rmap", std::process::id()));
+        let mut graph = Board::new();
+        graph.add_line(&p![[H, 8], [I, 9]]);
+        write(&graph, std::fs::File::create(&path).unwrap()).unwrap();
+        let mapped = MappedGraph::open(&path).unwrap();
+        assert_eq!(mapped.len(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
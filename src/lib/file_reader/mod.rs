@@ -1,14 +1,24 @@
 //! Used for reading files.
 //!
-//! Currently only supports _.pos_ and _.lib_ (`RenLib`) files of version 3.04+.
+//! Currently only supports _.pos_ and _.lib_ (`RenLib`) files of version 3.04+, the plain
+//! [`text`] format, [`mapped`] graphs and, with the `serde` feature, the [`binary`] cache format.
 
 use std::fs::File;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use crate::board::{Board, BoardMarker, MoveIndex, Point, Stone};
 use crate::errors::ParseError;
+use crate::progress::Progress;
 
+pub use filter::ExportFilter;
+
+#[cfg(feature = "serde")]
+pub mod binary;
+pub mod filter;
+pub mod mapped;
 pub mod renlib;
+pub mod text;
 
 /// Describes the file
 #[derive(Debug)]
@@ -98,6 +108,12 @@ pub enum FileType {
     ///
     /// *    See RenLib/RenLibDoc.cpp for implementation.
     Lib,
+    /// Plain text, see [`text`].
+    Text,
+    /// A cached graph, _.rgraph_, see `binary`. Needs the `serde` feature.
+    Graph,
+    /// A graph to read in place, _.rmap_, see [`mapped`].
+    Mapped,
     /// Renju Database File
     ///
     /// These are generally quite large. They include multiple games, so these will really test my
@@ -107,10 +123,14 @@ pub enum FileType {
 }
 
 impl FileType {
-    fn new(path: &Path) -> Option<Self> {
+    /// The type of `path` by its extension, `None` if it can't be read.
+    pub(crate) fn new(path: &Path) -> Option<Self> {
         match path.extension() {
             Some(pos) if (pos == "pos") => Some(Self::Pos),
             Some(lib) if (lib == "lib") => Some(Self::Lib),
+            Some(text) if (text == "txt") => Some(Self::Text),
+            Some(graph) if (graph == "rgraph") => Some(Self::Graph),
+            Some(mapped) if (mapped == "rmap") => Some(Self::Mapped),
             Some(_) => None,
             None => None,
         }
@@ -121,8 +141,89 @@ pub enum FileErr {
     ParseError,
 }
 
-#[tracing::instrument(fields(filetype))]
-pub fn open_file_path(path: &Path) -> Result<Board, color_eyre::Report> {
+/// What the parsers leave out of a library.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ParseOptions {
+    /// Moves deeper than this are skipped, e.g. for a quick preview of a large library. Only
+    /// _.lib_ and _.pos_ files honor it, other formats are read completely.
+    pub max_depth: Option<usize>,
+    /// Keep what can be read of a damaged _.lib_ file, e.g. one cut short, instead of failing,
+    /// with a [`Diagnostic`] for what is left out. Other formats fail as before.
+    pub recover: bool,
+}
+
+impl ParseOptions {
+    /// Read the first `depth` moves of every branch.
+    #[must_use]
+    pub fn max_depth(depth: usize) -> Self {
+        Self {
+            max_depth: Some(depth),
+            ..Self::default()
+        }
+    }
+
+    /// Read as much as possible of a damaged library, see [`ParseOptions::recover`].
+    #[must_use]
+    pub fn recover() -> Self {
+        Self {
+            recover: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// A problem found reading a damaged library with [`ParseOptions::recover`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    /// Where in the file the problem is, in bytes, if it is at a single place.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.offset {
+            Some(offset) => write!(f, "at {offset:#x}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+pub fn open_file_path(path: &Path) -> crate::Result<Board> {
+    open_file_path_with_progress(path, &mut Progress::none())
+}
+
+/// Like [`open_file_path`], reporting how much of the file is parsed.
+///
+/// A cancelled parse returns a [`Cancelled`](crate::progress::Cancelled) error.
+pub fn open_file_path_with_progress(path: &Path, progress: &mut Progress) -> crate::Result<Board> {
+    open_file_path_with_options(path, &ParseOptions::default(), progress)
+}
+
+/// Like [`open_file_path_with_progress`], leaving out what `options` asks for.
+///
+/// The [diagnostics](Diagnostic) of a [recovered](ParseOptions::recover) library are logged, see
+/// [`open_file_path_with_diagnostics`] to get them.
+pub fn open_file_path_with_options(
+    path: &Path,
+    options: &ParseOptions,
+    progress: &mut Progress,
+) -> crate::Result<Board> {
+    let (board, diagnostics) = open_file_path_with_diagnostics(path, options, progress)?;
+    for diagnostic in diagnostics {
+        tracing::warn!(%diagnostic, "recovered from a damaged file");
+    }
+    Ok(board)
+}
+
+/// Like [`open_file_path_with_options`], with the problems of a library read with
+/// [`ParseOptions::recover`].
+#[tracing::instrument(fields(filetype), skip(progress))]
+pub fn open_file_path_with_diagnostics(
+    path: &Path,
+    options: &ParseOptions,
+    progress: &mut Progress,
+) -> crate::Result<(Board, Vec<Diagnostic>)> {
     let mut board = Board::new();
 
     let _display = path.display();
@@ -132,20 +233,188 @@ pub fn open_file_path(path: &Path) -> Result<Board, color_eyre::Report> {
     // XXX: This gives a massive speedup.
     let buffered = std::io::BufReader::new(file);
     tracing::trace!("file opened");
-    read_bytes(buffered, filetype.as_ref(), &mut board)?;
-    Ok(board)
+    let diagnostics =
+        read_bytes_with_options(buffered, filetype.as_ref(), &mut board, options, progress)?;
+    Ok((board, diagnostics))
+}
+
+/// How [`save_file_path_with_options`] writes a library and treats the file it replaces.
+#[derive(Clone, Default, Debug)]
+pub struct WriteOptions {
+    /// Copies of replaced files to keep, the oldest are removed. None are made with `0`.
+    pub backups: usize,
+    /// Where to keep the copies, next to the file if `None`.
+    pub backup_dir: Option<PathBuf>,
+    /// Write only the part of the library kept by the filter.
+    pub filter: Option<ExportFilter>,
+}
+
+/// Write `board` to `path`, as a RenLib library, as text, as a [`mapped`] graph or, with the
+/// `serde` feature, in the binary format, by the extension of `path`. See [`write_atomic`].
+pub fn save_file_path(board: &Board, path: &Path) -> crate::Result<()> {
+    save_file_path_with_options(board, path, &WriteOptions::default())
+}
+
+/// Like [`save_file_path`], first copying the file it replaces and filtering `board` as set by
+/// `options`.
+///
+/// A copy is named after the file and the time it was replaced, `library.txt` becomes e.g
+/// `library.txt.1718000000000.bak` with the milliseconds since the Unix epoch.
+pub fn save_file_path_with_options(
+    board: &Board,
+    path: &Path,
+    options: &WriteOptions,
+) -> crate::Result<()> {
+    if options.backups > 0 && path.exists() {
+        backup(path, options)?;
+    }
+    let filtered;
+    let board = match &options.filter {
+        Some(filter) => {
+            filtered = filter.apply(board);
+            &filtered
+        }
+        None => board,
+    };
+    match FileType::new(path) {
+        Some(FileType::Lib) => write_atomic(path, |writer| {
+            Ok(renlib::write_lib(board, renlib::Version::V30, writer)?)
+        }),
+        Some(FileType::Text) => write_atomic(path, |writer| Ok(text::write(board, writer)?)),
+        #[cfg(feature = "serde")]
+        Some(FileType::Graph) => write_atomic(path, |writer| binary::write(board, writer)),
+        Some(FileType::Mapped) => write_atomic(path, |writer| mapped::write(board, writer)),
+        _ => Err(ParseError::NotSupported.into()),
+    }
+}
+
+/// The copies of `path` made with `options`, oldest first.
+pub fn backups(path: &Path, options: &WriteOptions) -> crate::Result<Vec<PathBuf>> {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(vec![]);
+    };
+    let dir = backup_dir(path, options);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = vec![];
+    for entry in entries {
+        let file_name = entry?.file_name();
+        let stamp = file_name
+            .to_str()
+            .and_then(|file| file.strip_prefix(name)?.strip_prefix('.'))
+            .and_then(|rest| rest.strip_suffix(".bak"))
+            .and_then(|stamp| stamp.parse::<u128>().ok());
+        if let Some(stamp) = stamp {
+            backups.push((stamp, dir.join(file_name)));
+        }
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+fn backup_dir(path: &Path, options: &WriteOptions) -> PathBuf {
+    options
+        .backup_dir
+        .clone()
+        .unwrap_or_else(|| path.parent().map_or_else(PathBuf::new, Path::to_path_buf))
+}
+
+/// Copy `path` to a new backup and remove the ones exceeding `options.backups`.
+fn backup(path: &Path, options: &WriteOptions) -> crate::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| ParseError::Other(format!("{} is not a file", path.display())))?;
+    let dir = backup_dir(path, options);
+    std::fs::create_dir_all(&dir)?;
+    let mut stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis());
+    let copy = loop {
+        let mut copy = name.to_owned();
+        copy.push(format!(".{stamp}.bak"));
+        let copy = dir.join(copy);
+        if !copy.exists() {
+            break copy;
+        }
+        stamp += 1;
+    };
+    std::fs::copy(path, &copy)?;
+    tracing::debug!(?copy, "backed up library");
+    let backups = backups(path, options)?;
+    for old in &backups[..backups.len().saturating_sub(options.backups)] {
+        std::fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Write a file through `write`, replacing `path` only once it is completely written.
+///
+/// The contents go to a temporary file next to `path`, which is synced and then renamed over
+/// it. A failing write or a crash leaves the previous file as it was.
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut std::io::BufWriter<File>) -> crate::Result<()>,
+) -> crate::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| ParseError::Other(format!("{} is not a file", path.display())))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+    let written = File::create(&temp)
+        .map_err(crate::Error::from)
+        .and_then(|file| {
+            let mut writer = std::io::BufWriter::new(file);
+            write(&mut writer)?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            Ok(())
+        })
+        .and_then(|()| Ok(std::fs::rename(&temp, path)?));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    written
 }
 
-#[tracing::instrument(skip(bytes, board))]
 pub fn read_bytes(
     bytes: impl std::io::Read,
     filetype: Option<&FileType>,
     board: &mut Board,
-) -> Result<(), color_eyre::Report> {
+) -> crate::Result<()> {
+    read_bytes_with_progress(bytes, filetype, board, &mut Progress::none())
+}
+
+/// Like [`read_bytes`], reporting how much is parsed.
+pub fn read_bytes_with_progress(
+    bytes: impl std::io::Read,
+    filetype: Option<&FileType>,
+    board: &mut Board,
+    progress: &mut Progress,
+) -> crate::Result<()> {
+    read_bytes_with_options(bytes, filetype, board, &ParseOptions::default(), progress)?;
+    Ok(())
+}
+
+/// Like [`read_bytes_with_progress`], leaving out what `options` asks for. Returns the problems of
+/// a library read with [`ParseOptions::recover`].
+#[tracing::instrument(skip(bytes, board, progress))]
+pub fn read_bytes_with_options(
+    mut bytes: impl std::io::Read,
+    filetype: Option<&FileType>,
+    board: &mut Board,
+    options: &ParseOptions,
+    progress: &mut Progress,
+) -> crate::Result<Vec<Diagnostic>> {
     match filetype {
         Some(FileType::Pos) => {
             let mut sequence: Vec<BoardMarker> = Vec::new();
-            for (index, pos) in bytes.bytes().skip(1).enumerate() {
+            let depth = options.max_depth.unwrap_or(usize::MAX);
+            for (index, pos) in bytes.bytes().skip(1).enumerate().take(depth) {
                 // First value should always be the number of moves.
                 sequence.push(BoardMarker::new(
                     Point::from_1d(u32::from(pos?), 15),
@@ -156,16 +425,28 @@ pub fn read_bytes(
                     },
                 ));
             }
-            let root = board.get_root();
-            let mut latest: MoveIndex = board.insert_move(root, sequence[0].clone());
-            for marker_move in sequence.into_iter().skip(1) {
+            if sequence.is_empty() {
+                return Err(ParseError::PosParseError.into());
+            }
+            let mut latest: MoveIndex = board.get_root();
+            for marker_move in sequence {
                 latest = board.insert_move(latest, marker_move)
             }
         }
-        Some(FileType::Lib) => renlib::parse_lib(bytes, board)?,
+        Some(FileType::Lib) => {
+            return renlib::parse_lib_with_options(bytes, board, options, progress)
+        }
+        Some(FileType::Text) => text::parse(std::io::BufReader::new(bytes), board)?,
+        #[cfg(feature = "serde")]
+        Some(FileType::Graph) => *board = binary::read(bytes)?,
+        Some(FileType::Mapped) => {
+            let mut data = vec![];
+            bytes.read_to_end(&mut data)?;
+            *board = mapped::MappedGraph::from_bytes(data)?.to_board();
+        }
         _ => return Err(ParseError::NotSupported.into()),
     }
-    Ok(())
+    Ok(vec![])
 }
 
 #[cfg(test)]
@@ -175,6 +456,54 @@ mod tests {
 
     use crate::board as mn;
 
+    #[test]
+    fn atomic_writes() {
+        let dir = std::env::temp_dir().join(format!("renju-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("library.txt");
+        let mut graph = mn::Board::new();
+        graph.add_line(&crate::p![[H, 8], [I, 9]]);
+        save_file_path(&graph, &path).unwrap();
+        let saved = std::fs::read(&path).unwrap();
+
+        let failed = write_atomic(&path, |writer| {
+            writer.write_all(b"half a library")?;
+            Err(ParseError::Other("disk full".to_owned()).into())
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read(&path).unwrap(), saved);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        assert!(save_file_path(&graph, &dir.join("library.pos")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_backups() {
+        let dir = std::env::temp_dir().join(format!("renju-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("library.txt");
+        let options = WriteOptions {
+            backups: 2,
+            backup_dir: Some(dir.join("old")),
+            ..Default::default()
+        };
+        let mut graph = mn::Board::new();
+        let mut saved = vec![];
+        for point in crate::p![[H, 8], [I, 9], [J, 10], [K, 11]] {
+            graph.add_line(&[point]);
+            save_file_path_with_options(&graph, &path, &options).unwrap();
+            saved.push(std::fs::read(&path).unwrap());
+        }
+        let copies = backups(&path, &options).unwrap();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(std::fs::read(&copies[0]).unwrap(), saved[1]);
+        assert_eq!(std::fs::read(&copies[1]).unwrap(), saved[2]);
+        assert!(backups(&dir.join("other.txt"), &options)
+            .unwrap()
+            .is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn open_pos_file() {
         let file = Path::new("examplefiles/example.pos");
@@ -194,4 +523,267 @@ mod tests {
         tracing::info!("\n{:?}", graph);
         // panic!("Intended!");
     }
+
+    #[test]
+    fn setup_stones() {
+        // a comment entry that is not a move, H8 I9 J10 set up, then K11 and H7 played
+        let file = Path::new("examplefiles/lib_setup_stones.lib");
+        let graph: mn::Board = open_file_path(file).unwrap();
+        assert_eq!(graph.title(), Some("Setup"));
+        let line = graph.main_line(graph.get_root());
+        assert_eq!(line.len(), 5);
+        let (board, moves) = graph.as_board(line.last().unwrap()).unwrap();
+        assert_eq!(moves, crate::p![[K, 11], [H, 7]]);
+        let colors = crate::p![[H, 8], [I, 9], [J, 10], [K, 11], [H, 7]]
+            .map(|point| board.get_point(point).unwrap().color);
+        assert_eq!(
+            colors,
+            [
+                Stone::Black,
+                Stone::White,
+                Stone::Black,
+                Stone::White,
+                Stone::Black
+            ]
+        );
+    }
+
+    const HEADER: [u8; 20] = [
+        0xff, b'R', b'e', b'n', b'L', b'i', b'b', 0xff, 3, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, 0xff, 0xff, 0xff,
+    ];
+
+    #[test]
+    fn library_comments_on_the_root() {
+        let mut bytes = HEADER.to_vec();
+        // the start entry with a comment, then H8 flagged as the start position and I9
+        bytes.extend([0x00, 0x08]);
+        bytes.extend(b"Title\x08library comment\0");
+        bytes.extend([0x78, 0x04, 0x69, 0x00]);
+        let mut graph = mn::Board::new();
+        read_bytes(&bytes[..], Some(&FileType::Lib), &mut graph).unwrap();
+
+        assert_eq!(graph.title(), Some("Title"));
+        assert_eq!(graph.comment(), Some("library comment"));
+        assert!(graph.root_marker().command.is_comment());
+        assert!(graph.root_marker().point.is_null);
+        let [h8] = graph.get_children(&graph.get_root())[..] else {
+            panic!("expected one move from the root");
+        };
+        let marker = graph.get_move(h8).unwrap();
+        assert_eq!(
+            (marker.point, marker.color),
+            (crate::p![H, 8], Stone::Black)
+        );
+        assert_eq!(graph.start(), Some(h8));
+        assert_eq!(graph.as_board(&graph.start().unwrap()).unwrap().1.len(), 1);
+
+        graph.set_start(None);
+        assert_eq!(graph.start(), None);
+    }
+
+    #[test]
+    fn library_of_renlib_2() {
+        let mut bytes = HEADER.to_vec();
+        bytes[8..10].copy_from_slice(&[2, 5]);
+        // H8 with an old comment, then I9 flagged with the comment of later versions
+        bytes.extend([0x78, 0x20]);
+        bytes.extend(b"Sj{lv\x08G}\0\0");
+        bytes.extend([0x69, 0x08]);
+        bytes.extend(b"|\0");
+        let mut graph = mn::Board::new();
+        read_bytes(&bytes[..], Some(&FileType::Lib), &mut graph).unwrap();
+        let line = graph.main_line(graph.get_root());
+        let h8 = graph.get_move(line[0]).unwrap();
+        assert_eq!(h8.oneline_comment.as_deref(), Some("Själv"));
+        assert_eq!(h8.multiline_comment.as_deref(), Some("Gå"));
+        let i9 = graph.get_move(line[1]).unwrap();
+        assert_eq!((i9.point, i9.color), (crate::p![I, 9], Stone::White));
+        assert_eq!(i9.oneline_comment.as_deref(), Some("ö"));
+
+        // RenLib 2 has no extensions
+        bytes.extend([0x6A, 0x01, 0x00, 0x01]);
+        let mut graph = mn::Board::new();
+        assert!(read_bytes(&bytes[..], Some(&FileType::Lib), &mut graph).is_err());
+        let mut written = vec![];
+        assert!(renlib::write_lib(&graph, renlib::Version::V2x(5), &mut written).is_err());
+    }
+
+    #[test]
+    fn recover() {
+        let read = |bytes: &[u8], options| -> crate::Result<(mn::Board, Vec<Diagnostic>)> {
+            let mut graph = mn::Board::new();
+            let diagnostics = read_bytes_with_options(
+                bytes,
+                Some(&FileType::Lib),
+                &mut graph,
+                &options,
+                &mut Progress::none(),
+            )?;
+            Ok((graph, diagnostics))
+        };
+        let mut bytes = HEADER.to_vec();
+        // H8 I9 J10 with the variation J9 after H8, cut short in the comment of J10 after it
+        bytes.extend([
+            0x78, 0x00, 0x69, 0x80, 0x5A, 0x40, 0x6A, 0x00, 0x5A, 0x48, b'a',
+        ]);
+        assert!(read(&bytes, ParseOptions::default()).is_err());
+        let (graph, diagnostics) = read(&bytes, ParseOptions::recover()).unwrap();
+        let [h8] = graph.get_children(&graph.get_root())[..] else {
+            panic!("expected H8");
+        };
+        assert_eq!(graph.get_children(&h8).len(), 2);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].offset, Some(28));
+
+        // cut after an entry, before the variation it announces
+        let (graph, diagnostics) = read(&bytes[..24], ParseOptions::recover()).unwrap();
+        assert_eq!(graph.main_line(graph.get_root()).len(), 2);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["the file ends before a variation it announces, it may be cut short"]
+        );
+        let (_, diagnostics) = read(&bytes[..28], ParseOptions::recover()).unwrap();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn max_depth() {
+        let mut bytes = HEADER.to_vec();
+        // H8 I9 J10 with the variation J9 J10 after H8, then a tree of H7 I7
+        bytes.extend([0x78, 0x00, 0x69, 0x80, 0x5A, 0x40, 0x6A, 0x00, 0x5A, 0x40]);
+        bytes.extend([0x88, 0x00, 0x89, 0x40]);
+        let read = |depth| {
+            let mut graph = mn::Board::new();
+            let options = ParseOptions::max_depth(depth);
+            read_bytes_with_options(
+                &bytes[..],
+                Some(&FileType::Lib),
+                &mut graph,
+                &options,
+                &mut Progress::none(),
+            )
+            .unwrap();
+            graph
+        };
+        let lines = |graph: &mn::Board| {
+            let mut lines = graph
+                .roots()
+                .iter()
+                .flat_map(|root| {
+                    let mut stack = vec![*root];
+                    let mut leaves = vec![];
+                    while let Some(index) = stack.pop() {
+                        let children = graph.get_children(&index);
+                        if children.is_empty() {
+                            leaves.push(graph.describe_path(index));
+                        }
+                        stack.extend(children);
+                    }
+                    leaves
+                })
+                .collect::<Vec<_>>();
+            lines.sort();
+            lines
+        };
+        let full = read(usize::MAX);
+        assert_eq!(
+            lines(&full),
+            ["(tree 2) H7 I7", "H8 I9 J10", "H8 J9 (var 2) J10"]
+        );
+        assert_eq!(
+            lines(&read(2)),
+            ["(tree 2) H7 I7", "H8 I9", "H8 J9 (var 2)"]
+        );
+        assert_eq!(lines(&read(1)), ["(tree 2) H7", "H8"]);
+
+        let mut pos = mn::Board::new();
+        let options = ParseOptions::max_depth(2);
+        let file = [3, 0x78, 0x69, 0x5A];
+        read_bytes_with_options(
+            &file[..],
+            Some(&FileType::Pos),
+            &mut pos,
+            &options,
+            &mut Progress::none(),
+        )
+        .unwrap();
+        assert_eq!(pos.main_line(pos.get_root()).len(), 2);
+    }
+
+    #[test]
+    fn write_lib() {
+        let mut graph = mn::Board::new();
+        graph.set_title(Some("Title".to_owned()));
+        graph.set_comment(Some("A library".to_owned()));
+        // with the flags of the comments and board text as RenLib has them
+        *graph.root_marker_mut().command |= renlib::CommandVariant::COMMENT;
+        let h8 = graph.add_line(&crate::p![[H, 8]]);
+        let i9 = graph.add_move(h8, BoardMarker::new(crate::p![I, 9], Stone::White));
+        let mut j10 = BoardMarker::new(crate::p![J, 10], Stone::Black);
+        j10.set_oneline_comment("odd".to_owned());
+        j10.multiline_comment = Some("Main\nline".to_owned());
+        *j10.command |= renlib::CommandVariant::COMMENT;
+        j10.command.set_priority(Some(renlib::Priority::Best));
+        let j10 = graph.add_move(i9, j10);
+        graph.add_move(j10, BoardMarker::new(crate::p![K, 11], Stone::White));
+        graph.add_move(i9, BoardMarker::new(crate::p![G, 7], Stone::Black));
+        let mut text = BoardMarker::new(crate::p![A, 1], Stone::Empty);
+        text.board_text = Some("AB".to_owned());
+        *text.command = renlib::CommandVariant::NOMOVE
+            | renlib::CommandVariant::BOARDTEXT
+            | renlib::CommandVariant::EXTENSION;
+        graph.add_move(h8, text);
+        let other = graph.add_root(BoardMarker::null());
+        graph.add_move(other, BoardMarker::new(crate::p![H, 7], Stone::Black));
+
+        let mut bytes = vec![];
+        renlib::write_lib(&graph, renlib::Version::V30, &mut bytes).unwrap();
+        assert_eq!(bytes[..10], HEADER[..10]);
+        assert!(bytes.windows(12).any(|text| text == b"\x08Main\r\nline\0"));
+        let mut read = mn::Board::new();
+        read_bytes(&bytes[..], Some(&FileType::Lib), &mut read).unwrap();
+        let as_text = |graph: &mn::Board| {
+            let mut out = vec![];
+            text::write(graph, &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(as_text(&read), as_text(&graph));
+        let mut again = vec![];
+        renlib::write_lib(&read, renlib::Version::V30, &mut again).unwrap();
+        assert_eq!(again, bytes);
+    }
+
+    #[test]
+    fn several_trees() {
+        let mut bytes = HEADER.to_vec();
+        // H8 ends the first tree, the second one is H7 I7
+        bytes.extend([0x78, 0x40, 0x88, 0x00, 0x89, 0x40]);
+        let mut graph = mn::Board::new();
+        read_bytes(&bytes[..], Some(&FileType::Lib), &mut graph).unwrap();
+
+        let roots = graph.roots().to_vec();
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0], graph.get_root());
+        assert_eq!(graph.get_children(&roots[0]).len(), 1);
+        let [h7] = graph.get_children(&roots[1])[..] else {
+            panic!("expected one move in the second tree");
+        };
+        let [i7] = graph.get_children(&h7)[..] else {
+            panic!("expected one reply");
+        };
+        let (board, moves) = graph.as_board(&i7).unwrap();
+        assert_eq!(moves, crate::p![[H, 7], [I, 7]]);
+        assert_eq!(
+            board.get_point(crate::p![I, 7]).unwrap().color,
+            Stone::White
+        );
+        assert_eq!(
+            board.get_point(crate::p![H, 8]).unwrap().color,
+            Stone::Empty
+        );
+    }
 }
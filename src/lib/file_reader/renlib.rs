@@ -1,16 +1,38 @@
 //! Functions for handling renlib files.
+//!
+//! A tree of a library can start with entries that are not moves, flagged
+//! [`NOMOVE`](CommandVariant::NOMOVE). The ones without a point add their comments to the root of
+//! the tree, like the start entry of the library. The ones on a point, but for marks and board
+//! text, are the stones set up before the first move, black and white in turn, and are kept as
+//! nodes with a stone that are not moves.
+//!
+//! [`write_lib`] writes a graph back as a library RenLib opens. RenLib doesn't store the color of
+//! a move, so [color overrides](Board::set_color_override) are lost.
+//!
+//! Libraries of RenLib 2 are read too, but not written. They have the entries of later versions
+//! without extensions, and comments in the [old encoding](parser::parse_old_comments).
+//!
+//! Entries are two bytes, a point and flags, and four with an extension. The texts after them end
+//! with a zero, padded with another one to an even length, and break lines with `\r\n`, which
+//! are read and written as `\n`. A line break after the last entry, as added by text editors, is
+//! skipped, any other byte left over is an error.
 use bitflags::bitflags;
 
 use crate::{board::Stone, errors::ParseError};
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::board::Board;
+use crate::board::{Board, BoardMarker, MoveIndex};
+use crate::progress::Progress;
+
+use super::{Diagnostic, ParseOptions};
 
 pub mod parser;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[non_exhaustive]
 pub enum Version {
+    /// A library of RenLib 2, by its minor version, see [`parser::parse_v2x`].
+    V2x(u8),
     V30,
     V34,
 }
@@ -24,6 +46,8 @@ bitflags! {
     pub struct CommandVariant: u32 {
         // Extensions
 
+        /// The [`Priority`] of the move, three bits.
+        const PRIORITY = 0xE00;
         const BOARDTEXT = 0x100; //
 
         const DOWN = 0x80;       // 0b10000000
@@ -42,6 +66,46 @@ bitflags! {
     }
 }
 
+/// The grade of a move, which RenLib shows as the color of its mark.
+///
+/// It is kept in the [`PRIORITY`](CommandVariant::PRIORITY) bits of the extension, `0` for a move
+/// without a grade.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Priority {
+    Best = 1,
+    Good,
+    Interesting,
+    Unclear,
+    Dubious,
+    Bad,
+    Losing,
+}
+
+impl Priority {
+    const SHIFT: u32 = CommandVariant::PRIORITY.bits().trailing_zeros();
+
+    /// Every grade, best first.
+    pub const ALL: [Priority; 7] = [
+        Priority::Best,
+        Priority::Good,
+        Priority::Interesting,
+        Priority::Unclear,
+        Priority::Dubious,
+        Priority::Bad,
+        Priority::Losing,
+    ];
+
+    fn from_bits(bits: u32) -> Option<Self> {
+        let value = (bits & CommandVariant::PRIORITY.bits()) >> Self::SHIFT;
+        Self::ALL.get((value as usize).checked_sub(1)?).copied()
+    }
+
+    fn bits(self) -> u32 {
+        (self as u32) << Self::SHIFT
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Command(CommandVariant);
@@ -131,76 +195,249 @@ impl Command {
     pub fn is_move(&self) -> bool {
         !self.is_no_move()
     }
+
+    #[must_use]
+    pub fn priority(&self) -> Option<Priority> {
+        Priority::from_bits(self.0.bits())
+    }
+
+    /// Grade the move, or remove its grade with `None`. The extension flag is kept in sync.
+    pub fn set_priority(&mut self, priority: Option<Priority>) {
+        self.0.remove(CommandVariant::PRIORITY);
+        if let Some(priority) = priority {
+            self.0 |= CommandVariant::from_bits_retain(priority.bits());
+        }
+        let extended = self.0.bits() & !0xFF != 0;
+        self.0.set(CommandVariant::EXTENSION, extended);
+    }
 }
 
-#[tracing::instrument(skip(file, board))]
-pub fn parse_lib(mut file: impl Read, board: &mut Board) -> Result<(), color_eyre::Report> {
+pub fn parse_lib(file: impl Read, board: &mut Board) -> crate::Result<()> {
+    parse_lib_with_progress(file, board, &mut Progress::none())
+}
+
+/// Like [`parse_lib`], reporting the markers added to `board` as the `"parse"` stage.
+pub fn parse_lib_with_progress(
+    file: impl Read,
+    board: &mut Board,
+    progress: &mut Progress,
+) -> crate::Result<()> {
+    parse_lib_with_options(file, board, &ParseOptions::default(), progress)?;
+    Ok(())
+}
+
+/// Like [`parse_lib_with_progress`], leaving out what `options` asks for.
+///
+/// With a [`max_depth`](ParseOptions::max_depth) the whole file is still read, the moves below
+/// it are skipped while following the structure of the tree.
+///
+/// With [`recover`](ParseOptions::recover) the entries are read up to the first one that can't
+/// be, and the tree is built up to the first entry going back to a move that isn't there.
+/// Returns what was left out, and the variations announced but missing at the end of the file.
+#[tracing::instrument(skip(file, board, progress))]
+pub fn parse_lib_with_options(
+    mut file: impl Read,
+    board: &mut Board,
+    options: &ParseOptions,
+    progress: &mut Progress,
+) -> crate::Result<Vec<Diagnostic>> {
+    let mut diagnostics = vec![];
     let moves = match read_header(&mut file)? {
-        (v @ (Version::V30 | Version::V34), i) => parser::parse_v3x(file, v, i),
-    }?;
-    let mut _new_moves = 0;
-    let mut first_move = None;
+        (version, i) if options.recover => {
+            let (moves, error) = parser::parse_until_error(file, version, i);
+            if let Some((offset, error)) = error {
+                diagnostics.push(Diagnostic {
+                    offset: Some(offset),
+                    message: format!("reading stopped, {error}"),
+                });
+            }
+            moves
+        }
+        (Version::V2x(minv), i) => parser::parse_v2x(file, minv, i)?,
+        (v @ (Version::V30 | Version::V34), i) => parser::parse_v3x(file, v, i)?,
+    };
     let mut check_root = true;
     let mut stack = vec![];
+    // set when a tree ended, the next move starts a new one
+    let mut tree_ended = false;
+    // moves below `options.max_depth` on the current line, which are not in the graph
+    let mut skipped = 0;
+    // the index of the root of the current tree in the move list
+    let mut tree_root = 0;
+    // the stones set up in the current tree, `None` after its first move
+    let mut setup = Some(0);
     // An adaptation of CRenLibDoc::AddLibrary
     board.move_to_root();
     let mut cur_move = board.current_move();
     tracing::debug!("starting parse of file");
-    let ten_percent = moves.len() / 10;
+    let _phase = tracing::debug_span!("build_graph").entered();
+    let ten_percent = (moves.len() / 10).max(1);
+    let total = moves.len() as u64;
+    let one_percent = (moves.len() / 100).max(1);
     for (i, mut marker) in moves.into_iter().enumerate() {
+        if i % one_percent == 0 {
+            progress.update("parse", i as u64, Some(total))?;
+        } else {
+            progress.check()?;
+        }
         let span = tracing::debug_span!("processing", ?i);
         let _enter = span.enter();
         if i % ten_percent == 0 {
             tracing::debug!("processing");
         }
         //tracing::trace!(marker = format!("{:#?}", marker), ?cur_move, "processing");
-        if marker.command.is_move() {
-            let last_move = board
-                .move_list()
-                .iter()
-                .rev()
-                .filter_map(|i| board.get_move(*i))
-                .map(|m| m.color)
-                .find(|c| !c.is_empty())
-                .unwrap_or(Stone::White);
-            marker.color = match last_move {
-                Stone::Black => Stone::White,
-                _ => Stone::Black,
-            };
+        if tree_ended {
+            tree_ended = false;
+            tracing::debug!("starting another tree");
+            board.move_to_root();
+            cur_move = board.add_root(BoardMarker::null());
+            board.add_move_to_move_list(cur_move);
+            tree_root = board.index();
+            setup = Some(0);
         }
-        // Not sure this is correct, oh well...
-        let next_move = board.get_variant_weird(&cur_move, &marker.point, &marker.color);
-        if let Some((m, next)) = next_move {
-            tracing::debug!(variant = ?(m, next), "found variant");
-            cur_move = next;
-        } else {
-            let next = board.insert_move(cur_move, marker.clone());
-            cur_move = next;
-            if marker.command.is_move() {
-                _new_moves += 1;
-                if first_move.is_none() {
-                    first_move = Some(cur_move)
-                }
+        if i == 0 && marker.point.is_null {
+            // the start entry is not a move, its comments belong to the library
+            merge_into_root(board.root_marker_mut(), marker);
+            check_root = false;
+            continue;
+        }
+        if marker.command.is_no_move() && setup == Some(0) && marker.point.is_null {
+            if let Some(root) = board.get_move_mut(cur_move) {
+                merge_into_root(root, marker);
             }
+            continue;
         }
-        if check_root && marker.point.is_null {
-            check_root = false;
+        let is_stone =
+            !marker.point.is_null && !marker.command.is_mark() && !marker.command.is_board_text();
+        match &mut setup {
+            Some(stones) if marker.command.is_no_move() && is_stone => {
+                marker.color = Stone::from_bool(*stones % 2 == 0);
+                *stones += 1;
+            }
+            Some(_) if marker.command.is_move() => setup = None,
+            _ => {}
+        }
+        let too_deep = match options.max_depth {
+            // a branch of a damaged file can go back to before the start of the tree
+            Some(max) => match (board.index() + skipped).checked_sub(tree_root) {
+                Some(depth) => depth >= max,
+                None if options.recover => {
+                    diagnostics.push(Diagnostic {
+                        offset: marker.index_in_file,
+                        message: "a branch goes back out of its tree, the rest of the file is \
+                            left out"
+                            .to_owned(),
+                    });
+                    break;
+                }
+                None => return Err(ParseError::LibParseError.into()),
+            },
+            None => false,
+        };
+        if too_deep && !(check_root && marker.point.is_null) {
+            skipped += 1;
         } else {
-            board.add_move_to_move_list(cur_move);
+            cur_move = add_marker(board, cur_move, &mut marker, &mut check_root);
         }
 
         if marker.command.is_down() {
-            stack.push(board.index())
+            stack.push(board.index() + skipped)
         }
 
-        if marker.command.is_right() && !stack.is_empty() {
-            // FIXME: Proper error pls
-            let top = stack.pop().expect("stack should not be empty");
-            board.set_index(top - 1)?;
-            cur_move = board.current_move();
+        if marker.command.is_right() {
+            if let Some(top) = stack.pop() {
+                // a branch announced on the first entry of a tree has no move to go back to
+                let back = top.checked_sub(1);
+                if let Some(back) = back.filter(|back| *back > board.index()) {
+                    skipped = back - board.index();
+                } else {
+                    skipped = 0;
+                    let moved = back
+                        .ok_or_else(|| crate::Error::from(ParseError::LibParseError))
+                        .and_then(|back| Ok(board.set_index(back)?));
+                    if let Err(e) = moved {
+                        if !options.recover {
+                            return Err(e);
+                        }
+                        diagnostics.push(Diagnostic {
+                            offset: marker.index_in_file,
+                            message: "the branch ends, but not after a move the tree has, the \
+                                rest of the file is left out"
+                                .to_owned(),
+                        });
+                        break;
+                    }
+                    cur_move = board.current_move();
+                }
+            } else {
+                // nothing to go back to, anything after is an independent tree
+                tree_ended = true;
+                skipped = 0;
+            }
         }
     }
-    Ok(())
+    progress.update("parse", total, Some(total))?;
+    // entries flagged with a sibling following later, with the file ending before it
+    if options.recover && diagnostics.is_empty() && !stack.is_empty() {
+        diagnostics.push(Diagnostic {
+            offset: None,
+            message: match stack.len() {
+                1 => {
+                    "the file ends before a variation it announces, it may be cut short".to_owned()
+                }
+                n => {
+                    format!("the file ends before {n} variations it announces, it may be cut short")
+                }
+            },
+        });
+    }
+    Ok(diagnostics)
+}
+
+/// Add the flags and comments of `marker`, an entry that is not a move, to the root of a tree.
+fn merge_into_root(root: &mut BoardMarker, marker: BoardMarker) {
+    *root.command |= *marker.command;
+    root.oneline_comment = root.oneline_comment.take().or(marker.oneline_comment);
+    root.multiline_comment = root.multiline_comment.take().or(marker.multiline_comment);
+    root.board_text = root.board_text.take().or(marker.board_text);
+    root.index_in_file = root.index_in_file.or(marker.index_in_file);
+}
+
+/// Add `marker` after `cur_move`, or follow the node already playing it, returning the node.
+fn add_marker(
+    board: &mut Board,
+    cur_move: MoveIndex,
+    marker: &mut BoardMarker,
+    check_root: &mut bool,
+) -> MoveIndex {
+    if marker.command.is_move() {
+        let last_move = board
+            .move_list()
+            .iter()
+            .rev()
+            .filter_map(|i| board.get_move(*i))
+            .map(|m| m.color)
+            .find(|c| !c.is_empty())
+            .unwrap_or(Stone::White);
+        marker.color = match last_move {
+            Stone::Black => Stone::White,
+            _ => Stone::Black,
+        };
+    }
+    // Not sure this is correct, oh well...
+    let next_move = board.get_variant_weird(&cur_move, &marker.point, &marker.color);
+    let cur_move = if let Some((m, next)) = next_move {
+        tracing::debug!(variant = ?(m, next), "found variant");
+        next
+    } else {
+        board.insert_move(cur_move, marker.clone())
+    };
+    if *check_root && marker.point.is_null {
+        *check_root = false;
+    } else {
+        board.add_move_to_move_list(cur_move);
+    }
+    cur_move
 }
 
 pub fn read_header(mut file: impl Read) -> Result<(Version, usize), ParseError> {
@@ -209,10 +446,117 @@ pub fn read_header(mut file: impl Read) -> Result<(Version, usize), ParseError>
     Ok((validate_lib(&header)?, 20))
 }
 
+/// Write `board` as a RenLib library of `version`, every tree from its root with the nodes in
+/// the order they were added. Nodes with several parents are written once for every parent, trees
+/// without moves but the first are left out.
+pub fn write_lib(board: &Board, version: Version, mut writer: impl Write) -> std::io::Result<()> {
+    let (majv, minv) = match version {
+        Version::V2x(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "libraries of RenLib 2 can only be read",
+            ))
+        }
+        Version::V30 => (3, 0),
+        Version::V34 => (3, 4),
+    };
+    let mut bytes = vec![0xff, b'R', b'e', b'n', b'L', b'i', b'b', 0xff, majv, minv];
+    bytes.extend([0xff; 10]);
+    for (tree, root) in board.roots().iter().enumerate() {
+        let children = board.get_children(root);
+        if tree > 0 && children.is_empty() {
+            continue;
+        }
+        let marker = board.get_move(*root).expect("a root is in the graph");
+        // the start entry of the library, or the comments of another tree
+        if tree == 0 || marker.oneline_comment.is_some() || marker.multiline_comment.is_some() {
+            // RenLib writes the start entry as a pass
+            let mut entry = marker.clone();
+            entry.command.set(CommandVariant::NOMOVE, tree > 0);
+            write_entry(&mut bytes, &entry, false, false);
+        }
+        // the nodes left to write, the next one last, and whether a sibling comes after them
+        let mut stack = children
+            .iter()
+            .enumerate()
+            .map(|(i, child)| (*child, i > 0))
+            .collect::<Vec<_>>();
+        while let Some((index, down)) = stack.pop() {
+            let Some(marker) = board.get_move(index) else {
+                continue;
+            };
+            // children are listed newest first
+            let children = board.get_children(&index);
+            write_entry(&mut bytes, marker, down, children.is_empty());
+            stack.extend(
+                children
+                    .iter()
+                    .enumerate()
+                    .map(|(i, child)| (*child, i > 0)),
+            );
+        }
+    }
+    writer.write_all(&bytes)?;
+    writer.flush()
+}
+
+/// Write the entry of `marker`, flagged as followed by a sibling if `down` and as the end of a line
+/// if `leaf`.
+fn write_entry(bytes: &mut Vec<u8>, marker: &BoardMarker, down: bool, leaf: bool) {
+    let mut command = *marker.command
+        - (CommandVariant::DOWN
+            | CommandVariant::RIGHT
+            | CommandVariant::OLDCOMMENT
+            | CommandVariant::COMMENT
+            | CommandVariant::BOARDTEXT
+            | CommandVariant::EXTENSION);
+    let comments = marker.oneline_comment.is_some() || marker.multiline_comment.is_some();
+    command.set(CommandVariant::DOWN, down);
+    command.set(CommandVariant::RIGHT, leaf);
+    command.set(CommandVariant::COMMENT, comments);
+    command.set(CommandVariant::BOARDTEXT, marker.board_text.is_some());
+    let bits = command.bits();
+    bytes.push(marker.point.to_byte());
+    match bits > 0xFF {
+        true => bytes.extend([
+            (bits as u8) | CommandVariant::EXTENSION.bits() as u8,
+            (bits >> 16) as u8,
+            (bits >> 8) as u8,
+        ]),
+        false => bytes.push(bits as u8),
+    }
+    if comments {
+        let mut text = marker.oneline_comment.clone().unwrap_or_default();
+        if let Some(multi) = &marker.multiline_comment {
+            text.push('\u{8}');
+            text.push_str(multi);
+        }
+        write_text(bytes, &text);
+    }
+    if let Some(board_text) = &marker.board_text {
+        write_text(bytes, board_text);
+    }
+}
+
+/// Write `text` with `\r\n` line breaks, ending with a zero padded with another one to an even
+/// length.
+fn write_text(bytes: &mut Vec<u8>, text: &str) {
+    let text = text
+        .replace('\0', "")
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n");
+    bytes.extend(text.as_bytes());
+    bytes.push(0);
+    if text.len().is_multiple_of(2) {
+        bytes.push(0);
+    }
+}
+
 pub fn validate_lib(header: &[u8]) -> Result<Version, ParseError> {
     match *header {
         [0xff, 0x52, 0x65, 0x6e, 0x4c, 0x69, 0x62, 0xff, majv, minv, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff] => {
             match (majv, minv) {
+                (2, minv) => Ok(Version::V2x(minv)),
                 (3, 0) => Ok(Version::V30),
                 (3, 4) => Ok(Version::V34),
                 (majv, minv) => Err(ParseError::VersionNotSupported { majv, minv }),
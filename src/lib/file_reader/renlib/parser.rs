@@ -1,4 +1,7 @@
+use std::io::BufRead;
+
 use crate::board::{BoardMarker, Point, Stone};
+use crate::errors::ParseError;
 
 use super::Version;
 pub use super::{Command, CommandVariant};
@@ -16,7 +19,7 @@ mod tests {
         b
     }
 
-    fn parse_v30(bytes: &'static [u8]) -> Result<Vec<BoardMarker>, color_eyre::Report> {
+    fn parse_v30(bytes: &'static [u8]) -> crate::Result<Vec<BoardMarker>> {
         let mut bytes = buf(bytes);
         parse_v3x(&mut bytes, Version::V30, 0)
     }
@@ -182,6 +185,27 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn line_breaks() -> Result<(), color_eyre::Report> {
+        // a line feed or a line break after the last entry ends the file
+        assert_eq!(parse_v30(&[0x78, 0x00, 0x0A])?.len(), 1);
+        assert_eq!(parse_v30(&[0x78, 0x00, 0x0D, 0x0A])?.len(), 1);
+        let error = parse_v30(&[0x78, 0x00, 0x79]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the file ends in the middle of an entry, 0x79 at 0x2"
+        );
+        // but for the last entry, M15 with a comment is an entry
+        let m15 = parse_v30(&[0x0D, 0x0A, b'a', 0x00, 0x78, 0x00])?;
+        assert_eq!(m15[0].point, p![M, 15]);
+        assert_eq!(m15[0].oneline_comment.as_deref(), Some("a"));
+
+        let comment = parse_v30(b"\x78\x08one\x08two\r\nlines\0\0")?;
+        assert_eq!(comment[0].oneline_comment.as_deref(), Some("one"));
+        assert_eq!(comment[0].multiline_comment.as_deref(), Some("two\nlines"));
+        Ok(())
+    }
+
     #[test]
     fn comment() -> Result<(), color_eyre::Report> {
         assert_eq!(
@@ -308,75 +332,208 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn priority() -> Result<(), color_eyre::Report> {
+        use super::super::Priority;
+
+        // H8 graded good, I9 bad with a board text, J10 without a grade
+        let moves = parse_v30(&[
+            0x78, 0x01, 0x00, 0x04, 0x69, 0x01, 0x00, 0x0D, 0x41, 0x00, 0x5A, 0x00,
+        ])?;
+        let priorities = moves
+            .iter()
+            .map(|m| m.command.priority())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            priorities,
+            [Some(Priority::Good), Some(Priority::Bad), None]
+        );
+        assert_eq!(moves[1].board_text.as_deref(), Some("A"));
+
+        let mut command = moves[0].command.clone();
+        command.set_priority(Some(Priority::Losing));
+        assert_eq!(command.priority(), Some(Priority::Losing));
+        assert!(command.is_extension());
+        command.set_priority(None);
+        assert_eq!(command, Command(CommandVariant::empty()));
+
+        // kept by the text format
+        let mut graph = crate::board::Board::new();
+        let h8 = graph.add_line(&[p![H, 8]]);
+        graph
+            .get_move_mut(h8)
+            .unwrap()
+            .command
+            .set_priority(Some(Priority::Interesting));
+        let mut text = vec![];
+        crate::file_reader::text::write(&graph, &mut text)?;
+        let mut read = crate::board::Board::new();
+        crate::file_reader::text::parse(&text[..], &mut read)?;
+        let [h8] = read.get_children(&read.get_root())[..] else {
+            panic!("expected H8");
+        };
+        assert_eq!(
+            read.get_move(h8).unwrap().command.priority(),
+            Some(Priority::Interesting)
+        );
+        Ok(())
+    }
 }
 
-#[tracing::instrument(skip(bytes, index))]
+/// Read the entries of a library of RenLib 3, from `index` in the file.
 pub fn parse_v3x(
-    mut bytes: impl std::io::Read,
-    _version: Version,
+    bytes: impl std::io::Read,
+    version: Version,
+    index: usize,
+) -> crate::Result<Vec<BoardMarker>> {
+    parse_entries(bytes, version, index)
+}
+
+/// Read the entries of a library of RenLib 2.`minor`, from `index` in the file.
+///
+/// The entries are the ones of RenLib 3 without extensions, so without board text and priorities.
+/// Every comment is in the old encoding, whichever flag it has.
+pub fn parse_v2x(
+    bytes: impl std::io::Read,
+    minor: u8,
+    index: usize,
+) -> crate::Result<Vec<BoardMarker>> {
+    parse_entries(bytes, Version::V2x(minor), index)
+}
+
+/// Read the entries of a library of `version` up to the first one that can't be read, from `index`
+/// in the file, with the error and where the entry starts, for a damaged library.
+#[tracing::instrument(skip(bytes, index))]
+pub fn parse_until_error(
+    bytes: impl std::io::Read,
+    version: Version,
     mut index: usize,
-) -> Result<Vec<BoardMarker>, color_eyre::eyre::Report> {
+) -> (Vec<BoardMarker>, Option<(usize, crate::Error)>) {
+    let old = matches!(version, Version::V2x(_));
+    let mut bytes = std::io::BufReader::new(bytes);
     let mut vec = vec![];
-    let mut buf: [u8; 2] = [0, 0];
     let mut string_buf = Vec::new();
-
     loop {
-        match bytes.read_exact(&mut buf) {
-            Ok(_) => index += 2,
-            Err(e) => match e.kind() {
-                std::io::ErrorKind::UnexpectedEof => break,
-                _ => todo!(),
-            },
+        let start = index;
+        match read_marker(&mut bytes, old, &mut index, &mut string_buf) {
+            Ok(Some(mark)) => vec.push(mark),
+            Ok(None) => return (vec, None),
+            Err(e) => return (vec, Some((start, e))),
         }
-        let point = if buf[0] == 0x00 {
-            Point::null()
-        } else {
-            Point::from_byte(buf[0])?
-        };
-        let mut mark = BoardMarker::new(point, Stone::Empty);
-        mark.index_in_file = Some(index - 2);
-        let command = Command::new(u32::from(buf[1]))?;
-
-        let command = if command.is_extension() {
-            bytes.read_exact(&mut buf)?;
-            index += 2;
-            // tracing::trace!("extension: {:#4b}, {:#4b}", buf[0], buf[1]);
-            let mut cmd = command.0.bits() & 0xFF;
-
-            cmd |= ((u32::from(buf[0]) << 8) | u32::from(buf[1])) << 8;
-            Command::new(cmd)?
-        } else {
-            command
-        };
+        string_buf.clear();
+    }
+}
 
-        if command.is_comment() {
-            let ((one, multi), read) = parse_comments(&mut bytes, &mut string_buf)?;
-            mark.oneline_comment = one;
-            mark.multiline_comment = multi;
-            // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
-            index += read;
-            string_buf.clear();
-        } else if command.is_old_comment() {
-            let ((one, multi), read) = parse_old_comments(&mut bytes, &mut string_buf)?;
-            mark.oneline_comment = one;
-            mark.multiline_comment = multi;
-            // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
-            index += read;
-            string_buf.clear();
-        }
+fn parse_entries(
+    bytes: impl std::io::Read,
+    version: Version,
+    index: usize,
+) -> crate::Result<Vec<BoardMarker>> {
+    match parse_until_error(bytes, version, index) {
+        (vec, None) => Ok(vec),
+        (_, Some((_, e))) => Err(e),
+    }
+}
 
-        if command.is_board_text() {
-            let (board_text, read) = parse_board_text(&mut bytes, &mut string_buf)?;
-            mark.board_text = Some(board_text);
-            index += read;
-            string_buf.clear();
-        }
+/// Read the entry at `index` with its texts, moving `index` past them. `None` at the end of the
+/// file.
+fn read_marker(
+    bytes: &mut impl BufRead,
+    old: bool,
+    index: &mut usize,
+    string_buf: &mut Vec<u8>,
+) -> crate::Result<Option<BoardMarker>> {
+    let Some(mut buf) = read_entry(bytes, *index)? else {
+        return Ok(None);
+    };
+    *index += 2;
+    let point = if buf[0] == 0x00 {
+        Point::null()
+    } else {
+        Point::from_byte(buf[0])?
+    };
+    let mut mark = BoardMarker::new(point, Stone::Empty);
+    mark.index_in_file = Some(*index - 2);
+    let command = Command::new(u32::from(buf[1]))?;
+
+    let command = if command.is_extension() && old {
+        return Err(ParseError::Other(format!(
+            "an extended entry at {:#x}, which RenLib 2 does not have",
+            *index - 2
+        ))
+        .into());
+    } else if command.is_extension() {
+        bytes.read_exact(&mut buf)?;
+        *index += 2;
+        // tracing::trace!("extension: {:#4b}, {:#4b}", buf[0], buf[1]);
+        let mut cmd = command.0.bits() & 0xFF;
+
+        cmd |= ((u32::from(buf[0]) << 8) | u32::from(buf[1])) << 8;
+        Command::new(cmd)?
+    } else {
+        command
+    };
+
+    if command.is_comment() && !old {
+        let ((one, multi), read) = parse_comments(&mut *bytes, string_buf)?;
+        mark.oneline_comment = one;
+        mark.multiline_comment = multi;
+        // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
+        *index += read;
+        string_buf.clear();
+    } else if command.is_comment() || command.is_old_comment() {
+        let ((one, multi), read) = parse_old_comments(&mut *bytes, string_buf)?;
+        mark.oneline_comment = one;
+        mark.multiline_comment = multi;
+        // tracing::trace!(?mark.oneline_comment, ?mark.multiline_comment);
+        *index += read;
+        string_buf.clear();
+    }
+
+    if command.is_board_text() {
+        let (board_text, read) = parse_board_text(&mut *bytes, string_buf)?;
+        mark.board_text = Some(board_text);
+        *index += read;
+    }
 
-        // tracing::trace!(?mark, ?command, "evaluated");
-        mark.command = command;
-        vec.push(mark)
+    // tracing::trace!(?mark, ?command, "evaluated");
+    mark.command = command;
+    Ok(Some(mark))
+}
+
+/// Read the two bytes of an entry, `None` at the end of the file.
+///
+/// A line break after the last entry, `\n` or `\r\n` as added by e.g a text editor, ends the file
+/// too. Any other byte left over is an error, the file ends in the middle of an entry.
+fn read_entry(bytes: &mut impl BufRead, index: usize) -> crate::Result<Option<[u8; 2]>> {
+    let mut buf = [0; 2];
+    let mut read = 0;
+    while read < buf.len() {
+        match bytes.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    match (read, buf) {
+        (0, _) => Ok(None),
+        (1, [b'\n', _]) => {
+            tracing::debug!(index, "skipping a line feed after the last entry");
+            Ok(None)
+        }
+        (1, [byte, _]) => Err(ParseError::Other(format!(
+            "the file ends in the middle of an entry, {byte:#04x} at {index:#x}"
+        ))
+        .into()),
+        // a comment entry on M15 can't end the file, it is a line break
+        (_, [b'\r', b'\n']) if bytes.fill_buf()?.is_empty() => {
+            tracing::debug!(index, "skipping a line break after the last entry");
+            Ok(None)
+        }
+        _ => Ok(Some(buf)),
     }
-    Ok(vec)
 }
 
 pub fn read_text(
@@ -401,10 +558,38 @@ pub fn read_text(
             break;
         }
     }
-    assert!(buf.len() > 1);
+    // the text ends at the first zero, anything after it in the last chunk is padding
+    if let Some(end) = buf.iter().position(|&b| b == 0) {
+        if let Some(&padding) = buf.get(end + 1).filter(|&&b| b != 0) {
+            tracing::warn!(padding, "a text is padded with another byte than zero");
+        }
+        buf.truncate(end + 1);
+    }
     Ok(index)
 }
 
+/// The text of `bytes`, with the `\r\n` line breaks of RenLib as `\n`.
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).replace("\r\n", "\n")
+}
+
+/// The text of `bytes` in the old encoding, see [`parse_old_comments`].
+fn old_text(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            b'{' => 'ä',
+            b'|' => 'ö',
+            b'}' => 'å',
+            b'[' => 'Ä',
+            b'\\' => 'Ö',
+            b']' => 'Å',
+            b => char::from(b),
+        })
+        .collect::<String>()
+        .replace("\r\n", "\n")
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ParseBoardTextError {
     #[error("read from board text buffer failed")]
@@ -419,13 +604,9 @@ fn parse_board_text(
     // so: the string "AA\0" becomes "AA\0\0"
 
     let read = read_text(bytes, buf)?;
-    assert!(buf.len() > 1);
-    assert!(buf.last().unwrap() == &0);
+    debug_assert!(buf.last() == Some(&0));
 
-    Ok((
-        String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string(),
-        read,
-    ))
+    Ok((text(&buf[..buf.len() - 1]), read))
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -451,19 +632,23 @@ pub fn parse_comments(
 
     let read = read_text(bytes, buf)?;
 
-    if &0x08 == buf.first().unwrap() {
+    if buf.first() == Some(&0x08) {
         // FIXME: Could be empty
-        multi = Some(String::from_utf8_lossy(&buf[1..buf.len() - 1]).to_string())
+        multi = Some(text(&buf[1..buf.len() - 1]))
     } else if let Some(pos) = buf.iter().position(|b| *b == 0x08) {
-        one = Some(String::from_utf8_lossy(&buf[0..pos]).to_string());
-        multi = Some(String::from_utf8_lossy(&buf[(pos + 1)..buf.len() - 1]).to_string());
+        one = Some(text(&buf[0..pos]));
+        multi = Some(text(&buf[(pos + 1)..buf.len() - 1]));
     } else {
-        one = Some(String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string());
+        one = Some(text(&buf[..buf.len() - 1]));
     }
 
     Ok(((one, multi), read))
 }
 
+/// Read a comment in the old encoding, of RenLib 2 and older libraries of RenLib 3.
+///
+/// The old encoding is the Swedish variant of ASCII, where `{|}[\\]` are `äöåÄÖÅ`, and Latin-1
+/// above it.
 #[allow(clippy::type_complexity)]
 pub fn parse_old_comments(
     bytes: impl std::io::Read,
@@ -472,28 +657,15 @@ pub fn parse_old_comments(
     let mut one = None;
     let mut multi = None;
     let read = read_text(bytes, buf)?;
-    let buf = buf
-        .iter_mut()
-        .map(|c| match c {
-            // FIXME: There has to be more like this, no?
-            b'}' => 0xE5,
-            b'{' => 0xE4,
-            b'|' => 0xF6,
-            b']' => 0xC5,
-            b'[' => 0xC4,
-            b'\\' => 0xD6,
-            other => *other,
-        })
-        .collect::<Vec<_>>();
 
-    if &0x08 == buf.first().unwrap() {
+    if buf.first() == Some(&0x08) {
         // FIXME: Could be empty
-        multi = Some(String::from_utf8_lossy(&buf[1..buf.len() - 1]).to_string())
+        multi = Some(old_text(&buf[1..buf.len() - 1]))
     } else if let Some(pos) = buf.iter().position(|b| *b == 0x08) {
-        one = Some(String::from_utf8_lossy(&buf[0..pos]).to_string());
-        multi = Some(String::from_utf8_lossy(&buf[(pos + 1)..buf.len() - 1]).to_string());
+        one = Some(old_text(&buf[0..pos]));
+        multi = Some(old_text(&buf[(pos + 1)..buf.len() - 1]));
     } else {
-        one = Some(String::from_utf8_lossy(&buf[..buf.len() - 1]).to_string());
+        one = Some(old_text(&buf[..buf.len() - 1]));
     }
     Ok(((one, multi), read))
 }
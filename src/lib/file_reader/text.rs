@@ -0,0 +1,458 @@
+//! A plain text format for libraries, stable enough to keep in git and review with diffs.
+//!
+//! The first line is `renju-tree 1`, then every node is one line, starting with the root of
+//! every tree:
+//!
+//! ```text
+//! renju-tree 1
+//! root flags=0xa one="Title" multi="Library comment"
+//! B H8
+//! W I9
+//! - B J10 one="Main line"
+//!   W K11
+//! - B G7
+//! ```
+//!
+//! A move is its color (`B`, `W` or `.` for markers without a stone) and point (or `pass`). A
+//! node with a single continuation is followed by it on the same indentation. The continuations
+//! of a node with several are each started with `- ` and indented one level, two columns, deeper
+//! with the `- ` counted as part of the indentation. Continuations are listed in the order they
+//! were added.
+//!
+//! After the move come the flags of [`Command`](super::renlib::Command) other than the layout
+//! flags of RenLib, the comments and board text and the names of the bookmarks and tags on the
+//! node, as escaped strings. Nodes with several parents are written once for every parent.
+//! A move not alternating in color with the move before it, like a second black move after setup
+//! stones, is kept with a [color override](Board::set_color_override).
+//!
+//! The [history](crate::board::history) of the library comes before the trees, one edit a line:
+//!
+//! ```text
+//! edit time=1718022896 author="alice" action=bookmark label="Trap" path="H8 I9 J10"
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write};
+
+use crate::board::history::{Action, Edit, Timestamp};
+use crate::board::{Board, BoardMarker, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+
+use super::renlib::{Command, CommandVariant};
+
+const HEADER: &str = "renju-tree 1";
+
+/// Write `board` in the text format.
+pub fn write(board: &Board, mut writer: impl Write) -> std::io::Result<()> {
+    let mut out = String::new();
+    writeln!(out, "{HEADER}").expect("writing to a string");
+    for edit in board.history() {
+        write!(
+            out,
+            "edit time={} author=\"{}\" action={}",
+            edit.time,
+            escape(&edit.author),
+            edit.action.name()
+        )
+        .expect("writing to a string");
+        if let Some(label) = edit.action.label() {
+            write!(out, " label=\"{}\"", escape(label)).expect("writing to a string");
+        }
+        writeln!(out, " path=\"{}\"", escape(&edit.path)).expect("writing to a string");
+    }
+    for root in board.roots() {
+        line(&mut out, board, *root, 0, false);
+        continuations(&mut out, board, *root, 0);
+    }
+    writer.write_all(out.as_bytes())
+}
+
+/// Write the continuations of `index`, which is on indentation `level`.
+fn continuations(out: &mut String, board: &Board, index: MoveIndex, level: usize) {
+    // children are listed newest first
+    let children = board
+        .get_children(&index)
+        .into_iter()
+        .rev()
+        .collect::<Vec<_>>();
+    match children[..] {
+        [] => {}
+        [child] => {
+            line(out, board, child, level, false);
+            continuations(out, board, child, level);
+        }
+        _ => {
+            for child in children {
+                line(out, board, child, level + 1, true);
+                continuations(out, board, child, level + 1);
+            }
+        }
+    }
+}
+
+fn line(out: &mut String, board: &Board, index: MoveIndex, level: usize, variation: bool) {
+    let Some(marker) = board.get_move(index) else {
+        return;
+    };
+    for _ in 0..level - usize::from(variation) {
+        out.push_str("  ");
+    }
+    if variation {
+        out.push_str("- ");
+    }
+    if board.roots().contains(&index) {
+        out.push_str("root");
+    } else {
+        let stone = match board.color_at(index).unwrap_or(marker.color) {
+            Stone::Black => 'B',
+            Stone::White => 'W',
+            Stone::Empty => '.',
+        };
+        write!(out, "{stone} {}", marker.point).expect("writing to a string");
+    }
+    let flags = marker.command.bits() & !(CommandVariant::DOWN | CommandVariant::RIGHT).bits();
+    if flags != 0 {
+        write!(out, " flags={flags:#x}").expect("writing to a string");
+    }
+    for (key, text) in [
+        ("one", &marker.oneline_comment),
+        ("multi", &marker.multiline_comment),
+        ("text", &marker.board_text),
+    ] {
+        if let Some(text) = text {
+            write!(out, " {key}=\"{}\"", escape(text)).expect("writing to a string");
+        }
+    }
+    for name in board.bookmarks_at(index) {
+        write!(out, " bookmark=\"{}\"", escape(name)).expect("writing to a string");
+    }
+    for tag in board.tags_at(index) {
+        write!(out, " tag=\"{}\"", escape(tag)).expect("writing to a string");
+    }
+    out.push('\n');
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Read a library written by [`write`] into `board`, which should be empty.
+pub fn parse(reader: impl BufRead, board: &mut Board) -> Result<(), ParseError> {
+    let mut lines = reader.lines().enumerate();
+    let header = lines.next().map(|(_, line)| line).transpose()?;
+    if header.as_deref().map(str::trim_end) != Some(HEADER) {
+        return Err(ParseError::NotSupported);
+    }
+    // the last node on every indentation
+    let mut last: Vec<MoveIndex> = vec![];
+    let mut roots = 0;
+    for (number, line) in lines {
+        let line = line?;
+        let error = |message: &str| ParseError::Other(format!("line {}: {message}", number + 1));
+        if line.trim().is_empty() {
+            continue;
+        }
+        let content = line.trim_start_matches(' ');
+        let indent = line.len() - content.len();
+        if indent % 2 != 0 {
+            return Err(error("odd indentation"));
+        }
+        let (variation, content) = match content.strip_prefix("- ") {
+            Some(content) => (true, content),
+            None => (false, content),
+        };
+        let level = indent / 2 + usize::from(variation);
+        let (head, attributes) = split_head(content);
+        if head == "edit" {
+            if level != 0 || roots != 0 {
+                return Err(error("edits must come before the trees"));
+            }
+            board.push_history(parse_edit(attributes).map_err(|e| error(&e))?);
+            continue;
+        }
+        let index = if head == "root" {
+            if level != 0 {
+                return Err(error("a root must not be indented"));
+            }
+            let root = match roots {
+                0 => board.get_root(),
+                _ => board.add_root(BoardMarker::null()),
+            };
+            roots += 1;
+            let marker = board.get_move_mut(root).expect("the root was just added");
+            let names = apply_attributes(marker, attributes).map_err(|e| error(&e))?;
+            names.add(board, root);
+            root
+        } else {
+            let parent = match variation {
+                true => last.get(level - 1),
+                false => last.get(level),
+            }
+            .copied()
+            .ok_or_else(|| error("move without a parent"))?;
+            let mut marker = parse_move(head).map_err(|e| error(&e))?;
+            let names = apply_attributes(&mut marker, attributes).map_err(|e| error(&e))?;
+            let color = marker.color;
+            if marker.command.is_move() && !color.is_empty() {
+                marker.color = board.inferred_color(parent);
+            }
+            let inferred = marker.color;
+            let index = board.insert_move(parent, marker);
+            if color != inferred {
+                board.set_color_override(index, Some(color));
+            }
+            names.add(board, index);
+            index
+        };
+        last.truncate(level);
+        last.resize(level + 1, index);
+    }
+    Ok(())
+}
+
+/// Split `content` into the move, e.g `B H8`, and the attributes after it.
+fn split_head(content: &str) -> (&str, &str) {
+    let end = content
+        .match_indices(' ')
+        .map(|(i, _)| i)
+        .find(|&i| {
+            content[i + 1..]
+                .split_once('=')
+                .is_some_and(|(key, _)| !key.contains(' '))
+        })
+        .unwrap_or(content.len());
+    (&content[..end], content[end..].trim_start())
+}
+
+fn parse_move(head: &str) -> Result<BoardMarker, String> {
+    let (stone, point) = head
+        .split_once(' ')
+        .ok_or_else(|| format!("invalid move {head:?}"))?;
+    let stone = match stone {
+        "B" => Stone::Black,
+        "W" => Stone::White,
+        "." => Stone::Empty,
+        other => return Err(format!("invalid color {other:?}")),
+    };
+    let point = match point {
+        "pass" => Point::null(),
+        point => point.parse().map_err(|e: ParseError| e.to_string())?,
+    };
+    Ok(BoardMarker::new(point, stone))
+}
+
+fn parse_edit(mut attributes: &str) -> Result<Edit, String> {
+    let (mut time, mut author, mut action, mut label, mut path) = (None, None, None, None, None);
+    while !attributes.is_empty() {
+        let (key, rest) = attributes
+            .split_once('=')
+            .ok_or_else(|| format!("invalid attribute {attributes:?}"))?;
+        if matches!(key, "time" | "action") {
+            let (value, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+            match key {
+                "time" => time = Some(value.parse::<Timestamp>().map_err(|e| e.to_string())?),
+                _ => action = Some(value),
+            }
+            attributes = rest.trim_start();
+            continue;
+        }
+        let (text, rest) = unescape(rest)?;
+        match key {
+            "author" => author = Some(text),
+            "label" => label = Some(text),
+            "path" => path = Some(text),
+            key => return Err(format!("unknown attribute {key:?}")),
+        }
+        attributes = rest.trim_start();
+    }
+    let action = action.ok_or("an edit without an action")?;
+    Ok(Edit {
+        author: author.ok_or("an edit without an author")?,
+        time: time.ok_or("an edit without a time")?,
+        action: Action::from_name(action, label)
+            .ok_or_else(|| format!("invalid action {action:?}"))?,
+        path: path.unwrap_or_default(),
+    })
+}
+
+/// The bookmarks and tags of a node, which are kept by the board rather than the marker.
+#[derive(Default)]
+struct Names {
+    bookmarks: Vec<String>,
+    tags: Vec<String>,
+}
+
+impl Names {
+    fn add(self, board: &mut Board, index: MoveIndex) {
+        for name in self.bookmarks {
+            board.set_bookmark(name, index);
+        }
+        for tag in self.tags {
+            board.tag(index, tag);
+        }
+    }
+}
+
+/// Set the attributes on `marker`, returning the bookmarks and tags of the node.
+fn apply_attributes(marker: &mut BoardMarker, mut attributes: &str) -> Result<Names, String> {
+    let mut names = Names::default();
+    while !attributes.is_empty() {
+        let (key, rest) = attributes
+            .split_once('=')
+            .ok_or_else(|| format!("invalid attribute {attributes:?}"))?;
+        if key == "flags" {
+            let (value, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+            let bits = u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                .map_err(|e| e.to_string())?;
+            marker.command = Command::new(bits).map_err(|e| e.to_string())?;
+            attributes = rest.trim_start();
+            continue;
+        }
+        let (text, rest) = unescape(rest)?;
+        match key {
+            "one" => marker.oneline_comment = Some(text),
+            "multi" => marker.multiline_comment = Some(text),
+            "text" => marker.board_text = Some(text),
+            "bookmark" => names.bookmarks.push(text),
+            "tag" => names.tags.push(text),
+            key => return Err(format!("unknown attribute {key:?}")),
+        }
+        attributes = rest.trim_start();
+    }
+    Ok(names)
+}
+
+/// Read a quoted string at the start of `s`, returning it and what follows.
+fn unescape(s: &str) -> Result<(String, &str), String> {
+    let mut chars = s
+        .strip_prefix('"')
+        .ok_or_else(|| format!("expected a quoted string at {s:?}"))?
+        .char_indices();
+    let mut text = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((text, &s[i + 2..])),
+            '\\' => text.push(match chars.next() {
+                Some((_, 'n')) => '\n',
+                Some((_, 'r')) => '\r',
+                Some((_, 't')) => '\t',
+                Some((_, c @ ('\\' | '"'))) => c,
+                other => return Err(format!("invalid escape {other:?}")),
+            }),
+            c => text.push(c),
+        }
+    }
+    Err("unterminated string".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    fn library() -> Board {
+        let mut board = Board::new();
+        board.set_title(Some("Title".to_owned()));
+        board.set_comment(Some(
+            "A \"library\"\nwith two lines \\ a backslash".to_owned(),
+        ));
+        let root = board.get_root();
+        let h8 = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let i9 = board.insert_move(h8, BoardMarker::new(p![I, 9], Stone::White));
+        let mut j10 = BoardMarker::new(p![J, 10], Stone::Black);
+        j10.set_oneline_comment("Main line".to_owned());
+        let j10 = board.insert_move(i9, j10);
+        board.set_bookmark("Main trap", j10);
+        board.tag(j10, "tournament2024");
+        board.push_history(Edit {
+            author: "alice".to_owned(),
+            time: 1_718_022_896,
+            action: Action::Bookmark("Main trap".to_owned()),
+            path: "H8 I9 J10".to_owned(),
+        });
+        board.insert_move(j10, BoardMarker::new(p![K, 11], Stone::White));
+        board.insert_move(i9, BoardMarker::new(p![G, 7], Stone::Black));
+        let pass = board.insert_move(i9, BoardMarker::new(Point::null(), Stone::Black));
+        let mut text = BoardMarker::new(p![A, 1], Stone::Empty);
+        text.board_text = Some("A".to_owned());
+        *text.command = CommandVariant::NOMOVE | CommandVariant::BOARDTEXT;
+        board.insert_move(pass, text);
+        let other = board.add_root(BoardMarker::null());
+        board.insert_move(other, BoardMarker::new(p![H, 7], Stone::Black));
+        board
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut text = vec![];
+        write(&library(), &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert_eq!(
+            text,
+            r#"renju-tree 1
+edit time=1718022896 author="alice" action=bookmark label="Main trap" path="H8 I9 J10"
+root flags=0x2 one="Title" multi="A \"library\"\nwith two lines \\ a backslash"
+B H8
+W I9
+- B J10 one="Main line" bookmark="Main trap" tag="tournament2024"
+  W K11
+- B G7
+- B pass
+  . A1 flags=0x102 text="A"
+root flags=0x2
+B H7
+"#
+        );
+
+        let mut board = Board::new();
+        parse(text.as_bytes(), &mut board).unwrap();
+        assert_eq!(board.title(), Some("Title"));
+        assert_eq!(
+            board.comment(),
+            Some("A \"library\"\nwith two lines \\ a backslash")
+        );
+        assert_eq!(board.roots().len(), 2);
+        let bookmark = board.get_bookmark("Main trap").unwrap();
+        assert_eq!(board.describe_path(bookmark), "H8 I9 J10");
+        assert_eq!(board.tagged("tournament2024"), [bookmark]);
+        assert_eq!(board.history()[0].author, "alice");
+        let mut again = vec![];
+        write(&board, &mut again).unwrap();
+        assert_eq!(String::from_utf8(again).unwrap(), text);
+
+        // black plays twice after setting up a stone
+        let text = "renju-tree 1\nroot flags=0x2\nB H8\nB I9\nW J10\n";
+        let mut board = Board::new();
+        parse(text.as_bytes(), &mut board).unwrap();
+        let overrides = board.color_overrides().collect::<Vec<_>>();
+        assert_eq!(overrides.len(), 1);
+        assert_eq!(board.describe_path(overrides[0].0), "H8 I9");
+        assert_eq!(board.get_move(overrides[0].0).unwrap().color, Stone::White);
+        assert_eq!(board.color_at(overrides[0].0), Some(Stone::Black));
+        let mut again = vec![];
+        write(&board, &mut again).unwrap();
+        assert_eq!(String::from_utf8(again).unwrap(), text);
+    }
+
+    #[test]
+    fn errors() {
+        let parse = |text: &str| parse(text.as_bytes(), &mut Board::new());
+        assert!(matches!(parse("not a tree"), Err(ParseError::NotSupported)));
+        assert!(parse("renju-tree 1\nroot\n- B H8\n").is_ok());
+        let error = parse("renju-tree 1\nroot\n  - B H8\n").unwrap_err();
+        assert_eq!(error.to_string(), "line 3: move without a parent");
+        assert!(parse("renju-tree 1\nroot\nB Z99\n").is_err());
+        assert!(parse("renju-tree 1\nroot one=\"open\n").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Game records, a single line of play from the empty board.
+
+use crate::board::evaluator::ForbiddenReason;
+use crate::board::{Board, BoardArr, BoardMarker, Evaluate, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+
+/// A played game. Black moves first, a null point is a pass.
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub black: Option<String>,
+    pub white: Option<String>,
+    pub moves: Vec<Point>,
+}
+
+impl GameRecord {
+    #[must_use]
+    pub fn new(moves: Vec<Point>) -> Self {
+        Self {
+            black: None,
+            white: None,
+            moves,
+        }
+    }
+
+    /// The line from the root of `graph` to `end`.
+    pub fn from_graph(graph: &Board, end: MoveIndex) -> Result<Self, ParseError> {
+        let (_, moves) = graph.as_board(&end)?;
+        Ok(Self::new(moves))
+    }
+
+    /// The color playing move `ply`, counting from zero.
+    #[must_use]
+    pub fn stone_at(ply: usize) -> Stone {
+        Stone::from_bool(ply.is_multiple_of(2))
+    }
+
+    /// The position before move `ply` is played.
+    #[must_use]
+    pub fn position(&self, ply: usize) -> BoardArr {
+        let mut board = BoardArr::new(15);
+        for (ply, point) in self.moves.iter().enumerate().take(ply) {
+            if !point.is_null {
+                board.set_point(*point, Self::stone_at(ply));
+            }
+        }
+        board
+    }
+
+    /// The game as a graph with a single line, along with the index of every move.
+    #[must_use]
+    pub fn to_graph(&self) -> (Board, Vec<MoveIndex>) {
+        let mut graph = Board::new();
+        let mut parent = graph.get_root();
+        let mut indices = Vec::with_capacity(self.moves.len());
+        for (ply, point) in self.moves.iter().enumerate() {
+            parent = graph.insert_move(parent, BoardMarker::new(*point, Self::stone_at(ply)));
+            indices.push(parent);
+        }
+        (graph, indices)
+    }
+
+    /// The first move of black on a forbidden point, losing the game, with the index of the move
+    /// and why the point is forbidden. Moves recorded after it don't change the result.
+    #[must_use]
+    pub fn forbidden_loss(&self) -> Option<(usize, ForbiddenReason)> {
+        let mut board = BoardArr::new(15);
+        for (ply, point) in self.moves.iter().enumerate() {
+            if point.is_null {
+                continue;
+            }
+            let stone = Self::stone_at(ply);
+            if stone.is_black() && board.forbidden_points(stone).contains(point) {
+                let reason = board.renju_conditions(stone, None).reason(*point)?;
+                return Some((ply, reason));
+            }
+            board.set_point(*point, stone);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn graph_round_trip() {
+        let game = GameRecord::new(p![[H, 8], [I, 9], [J, 10]].to_vec());
+        let (graph, indices) = game.to_graph();
+        assert_eq!(indices.len(), 3);
+        assert_eq!(GameRecord::from_graph(&graph, indices[2]).unwrap(), game);
+
+        let position = game.position(2);
+        assert_eq!(position.get_point(p![H, 8]).unwrap().color, Stone::Black);
+        assert_eq!(position.get_point(p![I, 9]).unwrap().color, Stone::White);
+        assert_eq!(position.get_point(p![J, 10]).unwrap().color, Stone::Empty);
+    }
+
+    #[test]
+    fn forbidden_loss() {
+        // black's G8 makes two open threes, with H8 I8 and with G9 G10
+        let mut moves = p![
+            [H, 8],
+            [A, 1],
+            [I, 8],
+            [A, 3],
+            [G, 9],
+            [A, 5],
+            [G, 10],
+            [A, 7]
+        ]
+        .to_vec();
+        assert_eq!(GameRecord::new(moves.clone()).forbidden_loss(), None);
+        moves.extend(p![[G, 8], [A, 9]]);
+        assert_eq!(
+            GameRecord::new(moves).forbidden_loss(),
+            Some((8, ForbiddenReason::DoubleThree))
+        );
+    }
+}
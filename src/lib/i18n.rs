@@ -0,0 +1,282 @@
+//! Translations of user facing text, in English, Swedish, Russian and Chinese.
+//!
+//! Renju terms differ between communities, so condition names and forbidden reasons are
+//! translated along with the messages of the CLI. The language is set once for the process with
+//! [`set_language`], every [`Message`] is then shown with [`tr`].
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::board::evaluator::ForbiddenReason;
+use crate::board::Stone;
+use crate::errors::ParseError;
+
+/// A language with a translation table.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Language {
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "en"))]
+    English,
+    #[cfg_attr(feature = "serde", serde(rename = "sv"))]
+    Swedish,
+    #[cfg_attr(feature = "serde", serde(rename = "ru"))]
+    Russian,
+    #[cfg_attr(feature = "serde", serde(rename = "zh"))]
+    Chinese,
+}
+
+impl Language {
+    const ALL: [Language; 4] = [
+        Language::English,
+        Language::Swedish,
+        Language::Russian,
+        Language::Chinese,
+    ];
+
+    /// The language of the locale in `LC_ALL`, `LC_MESSAGES` or `LANG`, English if none is
+    /// translated.
+    #[must_use]
+    pub fn from_locale() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|variable| std::env::var(variable).ok())
+            .find(|locale| !locale.is_empty())
+            .and_then(|locale| locale.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl std::str::FromStr for Language {
+    type Err = ParseError;
+
+    /// Parse a language code, optionally with a region and encoding like `sv_SE.UTF-8`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let code = s.split(['_', '-', '.']).next().unwrap_or_default();
+        match code.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Ok(Language::English),
+            "sv" => Ok(Language::Swedish),
+            "ru" => Ok(Language::Russian),
+            "zh" => Ok(Language::Chinese),
+            _ => Err(ParseError::Other(format!("no translation for {s:?}"))),
+        }
+    }
+}
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(0);
+
+/// Show text in `language` from now on.
+pub fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// The language set with [`set_language`], English by default.
+#[must_use]
+pub fn language() -> Language {
+    Language::ALL[usize::from(LANGUAGE.load(Ordering::Relaxed))]
+}
+
+/// Every translated text. Texts with arguments have a `{}` for each.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Message {
+    Black,
+    White,
+    Three,
+    DoubleThree,
+    Four,
+    OpenFour,
+    DoubleFour,
+    FourThree,
+    Five,
+    Overline,
+    /// Making `{}`, e.g a three.
+    Making,
+    /// Joins two descriptions of a move.
+    And,
+    BlockingTheFour,
+    StoppingTheThree,
+    /// A move on a forbidden point, for the reason `{}`.
+    OnForbiddenPoint,
+    /// `{}` passes.
+    Passes,
+    /// `{}` is forbidden, for the reason `{}`.
+    Forbidden,
+    /// `{}` to move.
+    ToMove,
+    /// `{}` wins.
+    Wins,
+    /// `{}` of `{}` puzzles due.
+    PuzzlesDue,
+    /// Correct, the reply is `{}`.
+    Correct,
+    Wrong,
+    /// Wrong, refuted by `{}`.
+    WrongRefuted,
+    Solved,
+    /// The solution is `{}`.
+    Solution,
+    /// Moves `{}` not in the library.
+    NotInLibrary,
+    /// No bookmark named `{}`.
+    NoBookmark,
+}
+
+impl Message {
+    /// The text in `language`.
+    #[must_use]
+    pub fn text(self, language: Language) -> &'static str {
+        use Message::*;
+        let [english, swedish, russian, chinese] = match self {
+            Black => ["Black", "Svart", "Чёрные", "黑方"],
+            White => ["White", "Vit", "Белые", "白方"],
+            Three => ["a three", "en trea", "тройку", "活三"],
+            DoubleThree => ["a double three", "en dubbeltrea", "вилку 3×3", "三三"],
+            Four => ["a four", "en fyra", "четвёрку", "冲四"],
+            OpenFour => ["an open four", "en öppen fyra", "открытую четвёрку", "活四"],
+            DoubleFour => ["a double four", "en dubbelfyra", "вилку 4×4", "四四"],
+            FourThree => ["a four-three", "en fyra-trea", "вилку 4×3", "四三"],
+            Five => ["five", "fem i rad", "пятёрку", "五连"],
+            Overline => ["overline", "långrad", "длинный ряд", "长连"],
+            Making => ["making {}", "som gör {}", "делая {}", "形成{}"],
+            And => [" and ", " och ", " и ", "，"],
+            BlockingTheFour => [
+                "blocking the four",
+                "blockerar fyran",
+                "закрывая четвёрку",
+                "挡住冲四",
+            ],
+            StoppingTheThree => [
+                "stopping the three",
+                "stoppar trean",
+                "защищаясь от тройки",
+                "防守活三",
+            ],
+            OnForbiddenPoint => [
+                "on a forbidden point, {}",
+                "på en förbjuden punkt, {}",
+                "в запрещённый пункт, {}",
+                "禁手，{}",
+            ],
+            Passes => ["{} passes", "{} passar", "{} пасуют", "{}弃权"],
+            Forbidden => [
+                "{} is forbidden, {}",
+                "{} är förbjuden, {}",
+                "{} — запрещённый ход, {}",
+                "{}是禁手，{}",
+            ],
+            ToMove => ["{} to move", "{} vid draget", "ходят {}", "{}走"],
+            Wins => ["{} wins", "{} vinner", "{} выигрывают", "{}胜"],
+            PuzzlesDue => [
+                "{} of {} puzzles due",
+                "{} av {} problem att repetera",
+                "задач к повторению: {} из {}",
+                "{}/{} 道题待复习",
+            ],
+            Correct => [
+                "Correct, the opponent plays {}",
+                "Rätt, motståndaren spelar {}",
+                "Верно, соперник отвечает {}",
+                "正确，对手下在{}",
+            ],
+            Wrong => ["Wrong", "Fel", "Неверно", "错误"],
+            WrongRefuted => [
+                "Wrong, refuted by {}",
+                "Fel, motbevisas av {}",
+                "Неверно, опровержение {}",
+                "错误，被{}反驳",
+            ],
+            Solved => ["Solved!", "Löst!", "Решено!", "解出！"],
+            Solution => ["Solution: {}", "Lösning: {}", "Решение: {}", "答案：{}"],
+            NotInLibrary => [
+                "{} is not in the library",
+                "{} finns inte i biblioteket",
+                "{} нет в библиотеке",
+                "库中没有{}",
+            ],
+            NoBookmark => [
+                "No bookmark named {}",
+                "Inget bokmärke heter {}",
+                "Нет закладки {}",
+                "没有名为{}的书签",
+            ],
+        };
+        match language {
+            Language::English => english,
+            Language::Swedish => swedish,
+            Language::Russian => russian,
+            Language::Chinese => chinese,
+        }
+    }
+
+    /// The name of `stone`, or `None` for an empty point.
+    #[must_use]
+    pub fn stone(stone: Stone) -> Option<Self> {
+        match stone {
+            Stone::Black => Some(Message::Black),
+            Stone::White => Some(Message::White),
+            Stone::Empty => None,
+        }
+    }
+}
+
+impl From<ForbiddenReason> for Message {
+    fn from(reason: ForbiddenReason) -> Self {
+        match reason {
+            ForbiddenReason::Overline => Message::Overline,
+            ForbiddenReason::DoubleFour => Message::DoubleFour,
+            ForbiddenReason::DoubleThree => Message::DoubleThree,
+        }
+    }
+}
+
+/// The name of `stone` in the language set with [`set_language`], empty for an empty point.
+#[must_use]
+pub fn stone_name(stone: Stone) -> String {
+    Message::stone(stone).map_or_else(String::new, |name| tr(name, &[]))
+}
+
+/// `message` in the language set with [`set_language`], with every `{}` replaced by the next of
+/// `args`.
+#[must_use]
+pub fn tr(message: Message, args: &[&dyn Display]) -> String {
+    fill(message.text(language()), args)
+}
+
+fn fill(text: &str, args: &[&dyn Display]) -> String {
+    let mut filled = String::with_capacity(text.len());
+    let mut args = args.iter();
+    let mut parts = text.split("{}");
+    filled.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        match args.next() {
+            Some(arg) => filled.push_str(&arg.to_string()),
+            None => filled.push_str("{}"),
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translations() {
+        assert_eq!(
+            "sv_SE.UTF-8".parse::<Language>().unwrap(),
+            Language::Swedish
+        );
+        assert_eq!("zh-Hans".parse::<Language>().unwrap(), Language::Chinese);
+        assert!("de_DE".parse::<Language>().is_err());
+        assert_eq!(language(), Language::English);
+
+        let text = Message::Making.text(Language::Swedish);
+        assert_eq!(fill(text, &[&"en trea"]), "som gör en trea");
+        assert_eq!(fill("{} and {}", &[&1]), "1 and {}");
+        for language in Language::ALL {
+            let text = Message::PuzzlesDue.text(language);
+            assert_eq!(text.matches("{}").count(), 2, "{language:?}");
+        }
+    }
+}
@@ -0,0 +1,486 @@
+//! Printable game sheets, every move of a game numbered on a single board the way games are
+//! published in magazines.
+//!
+//! A move that can't be numbered on the board, a pass or a move on a point already played in an
+//! edited record, is listed under it instead, like `23 = 15` for move 23 played where move 15
+//! was. [`Sheet::to_svg`] draws the sheet for the web and [`Sheet::to_latex`] as a TikZ picture
+//! for print. A [cropped](Sheet::cropped) sheet only draws the part of the board around the
+//! moves, for compact figures in articles.
+//!
+//! A [`Figure`] sets several sheets side by side in one SVG image with a caption under each, like
+//! a line shown every few moves.
+
+use std::fmt::Write as _;
+
+use crate::board::setup::Area;
+use crate::board::{Point, Stone};
+use crate::game::GameRecord;
+
+const SIZE: u32 = 15;
+/// Pixels between lines of the SVG.
+const CELL: u32 = 32;
+/// Pixels around the grid of the SVG, room for the coordinates.
+const MARGIN: u32 = 40;
+
+/// A move not numbered on the board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Overflow {
+    pub number: usize,
+    /// The number on the point played, `None` for a pass.
+    pub at: Option<usize>,
+}
+
+/// A game as a numbered diagram, see the [module documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sheet {
+    pub black: Option<String>,
+    pub white: Option<String>,
+    /// The moves on the board with their numbers, counting from 1.
+    pub moves: Vec<(Point, Stone, usize)>,
+    pub overflow: Vec<Overflow>,
+    /// The part of the board drawn, all of it if `None`. Moves outside it are left out.
+    pub area: Option<Area>,
+}
+
+impl Sheet {
+    #[must_use]
+    pub fn new(game: &GameRecord) -> Self {
+        let mut moves: Vec<(Point, Stone, usize)> = vec![];
+        let mut overflow = vec![];
+        for (ply, point) in game.moves.iter().enumerate() {
+            let number = ply + 1;
+            if point.is_null {
+                overflow.push(Overflow { number, at: None });
+                continue;
+            }
+            match moves.iter().find(|(p, _, _)| p == point) {
+                Some((_, _, at)) => overflow.push(Overflow {
+                    number,
+                    at: Some(*at),
+                }),
+                None => moves.push((*point, GameRecord::stone_at(ply), number)),
+            }
+        }
+        Self {
+            black: game.black.clone(),
+            white: game.white.clone(),
+            moves,
+            overflow,
+            area: None,
+        }
+    }
+
+    /// Draw only the moves and `margin` lines around them.
+    #[must_use]
+    pub fn cropped(mut self, margin: u32) -> Self {
+        self.area = Area::around(self.moves.iter().map(|(point, ..)| *point), margin, SIZE);
+        self
+    }
+
+    /// The area drawn.
+    fn area(&self) -> Area {
+        self.area.unwrap_or(Area {
+            from: Point::new(0, 0),
+            to: Point::new(SIZE - 1, SIZE - 1),
+        })
+    }
+
+    /// The moves in the area drawn.
+    fn shown(&self) -> impl Iterator<Item = &(Point, Stone, usize)> + '_ {
+        let area = self.area();
+        self.moves
+            .iter()
+            .filter(move |(point, ..)| area.contains(*point))
+    }
+
+    /// The players, like `Black: A - White: B`, empty if neither is known.
+    fn players(&self) -> String {
+        match (&self.black, &self.white) {
+            (None, None) => String::new(),
+            (black, white) => format!(
+                "Black: {} - White: {}",
+                black.as_deref().unwrap_or("?"),
+                white.as_deref().unwrap_or("?")
+            ),
+        }
+    }
+
+    /// The moves listed under the board, like `23 = 15, 40 pass`.
+    fn overflow_text(&self) -> String {
+        self.overflow
+            .iter()
+            .map(|o| match o.at {
+                Some(at) => format!("{} = {at}", o.number),
+                None => format!("{} pass", o.number),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The players and the moves listed under the board, the ones there are.
+    fn notes(&self) -> Vec<String> {
+        [self.players(), self.overflow_text()]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .collect()
+    }
+
+    /// The size of the SVG image, and the height of the board in it.
+    fn svg_size(&self) -> (u32, u32, u32) {
+        let area = self.area();
+        let (columns, rows) = (area.columns(), area.rows());
+        let width = CELL * (columns.end() - columns.start()) + 2 * MARGIN;
+        let board_height = CELL * (rows.end() - rows.start()) + 2 * MARGIN;
+        let height = board_height + 20 * self.notes().len() as u32 + 10;
+        (width, height, board_height)
+    }
+
+    /// The sheet as a standalone SVG image.
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        let (width, height, _) = self.svg_size();
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" text-anchor="middle">"#
+        );
+        let _ = writeln!(
+            svg,
+            r##"<rect width="{width}" height="{height}" fill="#fff"/>"##
+        );
+        self.write_svg(&mut svg);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The board and notes of the SVG image, without the image around them.
+    fn write_svg(&self, svg: &mut String) {
+        let area = self.area();
+        let (columns, rows) = (area.columns(), area.rows());
+        let (left, top) = (*columns.start(), *rows.start());
+        let grid_width = CELL * (columns.end() - left);
+        let grid_height = CELL * (rows.end() - top);
+        let (width, _, board_height) = self.svg_size();
+        let _ = write!(svg, r##"<g stroke="#000" stroke-width="1">"##);
+        let (right, bottom) = (MARGIN + grid_width, MARGIN + grid_height);
+        for row in rows.clone() {
+            let at = MARGIN + (row - top) * CELL;
+            let _ = write!(
+                svg,
+                r#"<line x1="{MARGIN}" y1="{at}" x2="{right}" y2="{at}"/>"#
+            );
+        }
+        for column in columns.clone() {
+            let at = MARGIN + (column - left) * CELL;
+            let _ = write!(
+                svg,
+                r#"<line x1="{at}" y1="{MARGIN}" x2="{at}" y2="{bottom}"/>"#
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+        for column in columns {
+            let letter = char::from(b'A' + column as u8);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="12">{letter}</text>"#,
+                MARGIN + (column - left) * CELL,
+                bottom + 26
+            );
+        }
+        for row in rows {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="12">{}</text>"#,
+                MARGIN - 22,
+                MARGIN + (row - top) * CELL + 4,
+                SIZE - row
+            );
+        }
+        for (point, stone, number) in self.shown() {
+            let (x, y) = (
+                MARGIN + (point.x - left) * CELL,
+                MARGIN + (point.y - top) * CELL,
+            );
+            let (fill, text) = match stone {
+                Stone::Black => ("#000", "#fff"),
+                _ => ("#fff", "#000"),
+            };
+            let size = if *number < 100 { 14 } else { 11 };
+            let _ = writeln!(
+                svg,
+                r##"<circle cx="{x}" cy="{y}" r="{}" fill="{fill}" stroke="#000"/><text x="{x}" y="{}" font-size="{size}" fill="{text}">{number}</text>"##,
+                CELL * 9 / 20,
+                y + size / 3
+            );
+        }
+        for (i, note) in self.notes().iter().enumerate() {
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="14">{}</text>"#,
+                width / 2,
+                board_height + 6 + 20 * i as u32,
+                escape_xml(note)
+            );
+        }
+    }
+
+    /// The sheet as a TikZ picture, to `\input` in a document loading `tikz`.
+    #[must_use]
+    pub fn to_latex(&self) -> String {
+        // TikZ counts rows up from the bottom of the board, as board notation does
+        let last = SIZE - 1;
+        let area = self.area();
+        let (columns, rows) = (area.columns(), area.rows());
+        let (left, right) = (*columns.start(), *columns.end());
+        let (bottom, top) = (last - rows.end(), last - rows.start());
+        let mut tex = String::new();
+        let _ = writeln!(tex, "\\begin{{tikzpicture}}[x=6mm,y=6mm]");
+        let _ = writeln!(tex, "\\draw ({left},{bottom}) grid ({right},{top});");
+        for i in columns {
+            let letter = char::from(b'A' + i as u8);
+            let _ = writeln!(
+                tex,
+                "\\node[below] at ({i},{}) {{\\scriptsize {letter}}};",
+                f64::from(bottom) - 0.3
+            );
+        }
+        for i in bottom..=top {
+            let _ = writeln!(
+                tex,
+                "\\node[left] at ({},{i}) {{\\scriptsize {}}};",
+                f64::from(left) - 0.3,
+                i + 1
+            );
+        }
+        for (point, stone, number) in self.shown() {
+            let style = match stone {
+                Stone::Black => "fill=black,text=white",
+                _ => "fill=white",
+            };
+            let _ = writeln!(
+                tex,
+                "\\node[circle,draw,{style},inner sep=0pt,minimum size=5.4mm,font=\\tiny] at ({},{}) {{{number}}};",
+                point.x,
+                last - point.y
+            );
+        }
+        let _ = writeln!(tex, "\\end{{tikzpicture}}");
+        for note in [self.players(), self.overflow_text()] {
+            if !note.is_empty() {
+                let _ = writeln!(tex, "\n{}", escape_latex(&note));
+            }
+        }
+        tex
+    }
+}
+
+/// Sheets in a grid, each with a caption, see the [module documentation](self).
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Figure {
+    pub panels: Vec<(Sheet, String)>,
+    /// The sheets in a row of the grid.
+    pub columns: usize,
+}
+
+impl Figure {
+    #[must_use]
+    pub fn new(columns: usize) -> Self {
+        Self {
+            panels: vec![],
+            columns: columns.max(1),
+        }
+    }
+
+    /// `game` every `step` moves, a sheet of the moves so far for every step, captioned with the
+    /// moves it adds, like `Moves 5-8`. The players are left out of the sheets.
+    #[must_use]
+    pub fn line(game: &GameRecord, step: usize, columns: usize) -> Self {
+        let step = step.max(1);
+        let mut figure = Self::new(columns);
+        for start in (0..game.moves.len()).step_by(step) {
+            let end = (start + step).min(game.moves.len());
+            let sheet = Sheet::new(&GameRecord::new(game.moves[..end].to_vec()));
+            let caption = match start + 1 == end {
+                true => format!("Move {end}"),
+                false => format!("Moves {}-{end}", start + 1),
+            };
+            figure.push(sheet, caption);
+        }
+        figure
+    }
+
+    pub fn push(&mut self, sheet: Sheet, caption: impl Into<String>) {
+        self.panels.push((sheet, caption.into()));
+    }
+
+    /// Crop every sheet to the moves of all of them and `margin` lines around, so the boards line
+    /// up.
+    #[must_use]
+    pub fn cropped(mut self, margin: u32) -> Self {
+        let points = self
+            .panels
+            .iter()
+            .flat_map(|(sheet, _)| sheet.moves.iter().map(|(point, ..)| *point))
+            .collect::<Vec<_>>();
+        let area = Area::around(points, margin, SIZE);
+        for (sheet, _) in &mut self.panels {
+            sheet.area = area;
+        }
+        self
+    }
+
+    /// The figure as a standalone SVG image, every panel as large as the largest sheet.
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        let (panel_width, panel_height) = self
+            .panels
+            .iter()
+            .map(|(sheet, _)| sheet.svg_size())
+            .fold((0, 0), |(width, height), (w, h, _)| {
+                (width.max(w), height.max(h + 24))
+            });
+        let columns = self.columns.min(self.panels.len()).max(1);
+        let rows = self.panels.len().div_ceil(columns);
+        let width = panel_width * columns as u32;
+        let height = panel_height * rows as u32;
+        let mut svg = String::new();
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" text-anchor="middle">"#
+        );
+        let _ = writeln!(
+            svg,
+            r##"<rect width="{width}" height="{height}" fill="#fff"/>"##
+        );
+        for (i, (sheet, caption)) in self.panels.iter().enumerate() {
+            let (x, y) = (
+                panel_width * (i % columns) as u32,
+                panel_height * (i / columns) as u32,
+            );
+            let (sheet_width, sheet_height, _) = sheet.svg_size();
+            let _ = writeln!(
+                svg,
+                r#"<g transform="translate({} {y})">"#,
+                x + (panel_width - sheet_width) / 2
+            );
+            sheet.write_svg(&mut svg);
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="16">{}</text>"#,
+                sheet_width / 2,
+                sheet_height + 10,
+                escape_xml(caption)
+            );
+            let _ = writeln!(svg, "</g>");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn numbers_and_overflow() {
+        let mut moves = p![[H, 8], [I, 9], [J, 10]].to_vec();
+        moves.push(Point::null());
+        moves.push(p![I, 9]);
+        let mut game = GameRecord::new(moves);
+        game.black = Some("R&D_1".to_owned());
+
+        let sheet = Sheet::new(&game);
+        assert_eq!(sheet.moves.len(), 3);
+        assert_eq!(sheet.moves[1], (p![I, 9], Stone::White, 2));
+        assert_eq!(
+            sheet.overflow,
+            [
+                Overflow {
+                    number: 4,
+                    at: None
+                },
+                Overflow {
+                    number: 5,
+                    at: Some(2)
+                }
+            ]
+        );
+
+        let svg = sheet.to_svg();
+        assert!(svg.starts_with("<svg "), "{svg}");
+        assert_eq!(svg.matches("<circle ").count(), 3);
+        assert!(svg.contains(">4 pass, 5 = 2</text>"), "{svg}");
+        assert!(svg.contains("Black: R&amp;D_1 - White: ?"), "{svg}");
+        assert!(svg.trim_end().ends_with("</svg>"));
+
+        let tex = sheet.to_latex();
+        assert_eq!(tex.matches("\\node[circle").count(), 3);
+        // H8 is the center of the board
+        assert!(tex.contains("at (7,7) {1};"), "{tex}");
+        assert!(tex.contains("Black: R\\&D\\_1 - White: ?"), "{tex}");
+        assert!(tex.contains("4 pass, 5 = 2"));
+    }
+
+    #[test]
+    fn cropped() {
+        let sheet = Sheet::new(&GameRecord::new(p![[H, 8], [I, 9], [J, 10]].to_vec())).cropped(1);
+        // G to K and 7 to 11
+        let svg = sheet.to_svg();
+        assert!(svg.contains(r#"width="208" height="218""#), "{svg}");
+        assert_eq!(svg.matches("<line ").count(), 10);
+        assert!(
+            svg.contains(">G</text>") && !svg.contains(">F</text>"),
+            "{svg}"
+        );
+        assert!(svg.contains(r#"<circle cx="72" cy="136""#), "{svg}");
+        let tex = sheet.to_latex();
+        assert!(tex.contains("\\draw (6,6) grid (10,10);"), "{tex}");
+        assert!(tex.contains("at (7,7) {1};"), "{tex}");
+        assert_eq!(tex.matches("\\scriptsize").count(), 10);
+    }
+
+    #[test]
+    fn figure() {
+        let game = GameRecord::new(p![[H, 8], [I, 9], [J, 10], [G, 7], [K, 11]].to_vec());
+        let figure = Figure::line(&game, 2, 2).cropped(1);
+        let captions = figure.panels.iter().map(|(_, caption)| caption.as_str());
+        assert_eq!(
+            captions.collect::<Vec<_>>(),
+            ["Moves 1-2", "Moves 3-4", "Move 5"]
+        );
+        assert_eq!(figure.panels[1].0.moves.len(), 4);
+        // F to L and 6 to 12 on every board, two panels a row
+        let svg = figure.to_svg();
+        assert!(svg.contains(r#"width="544" height="612""#), "{svg}");
+        assert_eq!(svg.matches("<g transform").count(), 3);
+        assert!(svg.contains(r#"<g transform="translate(0 306)">"#), "{svg}");
+        assert_eq!(svg.matches("<circle ").count(), 2 + 4 + 5);
+        assert!(svg.contains(">Moves 3-4</text>"), "{svg}");
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+}
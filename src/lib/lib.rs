@@ -1,4 +1,68 @@
+//! Renju boards, libraries of games and analysis.
+//!
+//! # Panics
+//!
+//! Public functions do not panic on any input, including points outside the board, boards of
+//! size zero, indices of nodes from another graph and malformed files. They return a
+//! [`Result`] or an [`Option`] instead, or do nothing, like [`BoardArr::set_point`] for a point
+//! off the board. This is checked by `tests/no_panic.rs`, which also fuzzes the readers.
+//!
+//! The exceptions are documented under `# Panics`: [`Board::insert_move`] with a parent that is
+//! not in the graph, which has [`Board::try_insert_move`], and [`LineTable::line`] with an id it
+//! did not give out, like indexing a slice. Invariants the library keeps itself, e.g. that the
+//! root is in the graph or that white has no forbidden points, remain as `expect`s and
+//! `debug_assert`s.
+//!
+//! [`BoardArr::set_point`]: board::BoardArr::set_point
+//! [`Board::insert_move`]: board::Board::insert_move
+//! [`Board::try_insert_move`]: board::Board::try_insert_move
+//! [`LineTable::line`]: board::lines::LineTable::line
+
+#[cfg(feature = "solver")]
+pub mod analysis;
+#[cfg(feature = "engine")]
+pub mod annotations;
+pub mod autosave;
 pub mod board;
+pub mod config;
+#[cfg(feature = "engine")]
+pub mod engine;
 pub mod errors;
 pub mod file_reader;
+pub mod game;
+pub mod i18n;
+pub mod kifu;
+pub mod limits;
+#[cfg(feature = "engine")]
+pub mod live;
+#[cfg(feature = "ml")]
+pub mod ml;
+pub mod motif;
+pub mod narration;
+pub mod progress;
+pub mod project;
+#[cfg(feature = "solver")]
+pub mod puzzle;
+pub mod ratings;
+#[cfg(feature = "image")]
+pub mod render;
+#[cfg(feature = "solver")]
+pub mod report;
+#[cfg(feature = "image")]
+pub mod screenshot;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "solver")]
+pub mod session;
+pub mod share;
+#[cfg(feature = "solver")]
+pub mod solver;
+pub mod theme;
+pub mod theory;
+#[cfg(feature = "cli")]
+pub mod timing;
+#[cfg(feature = "solver")]
+pub mod training;
 pub mod util;
+
+pub use errors::{Error, Result};
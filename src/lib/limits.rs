@@ -0,0 +1,126 @@
+//! Resource limits for the evaluator, the solvers and the search engines.
+//!
+//! Pathological positions can make the double-three recursion of
+//! [`BoardArr::renju_conditions`](crate::board::BoardArr::renju_conditions) or a VCF search run for
+//! a very long time. The `_with_limits` variants stop when a [`Limits`] is reached and return
+//! [`Outcome::Unknown`] instead.
+
+use std::time::{Duration, Instant};
+
+/// Budget for a search. The search stops at whichever limit is reached first.
+#[derive(Clone, Debug)]
+pub struct Limits {
+    /// Maximum nominal depth. Plies for the engines, forcing moves are searched deeper than this.
+    /// Fours for [`vcf_with_limits`](crate::solver::vcf_with_limits), and nested double-three
+    /// checks for the evaluator.
+    pub depth: u32,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    /// Maximum amount of candidates searched in every position.
+    pub width: usize,
+    /// Seed for backends using randomness, a random one if `None`. The seed used is logged.
+    pub seed: Option<u64>,
+    /// Approximate maximum memory used by transposition tables, in bytes. When full, only
+    /// existing entries are replaced.
+    pub table_bytes: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            nodes: None,
+            time: None,
+            width: 12,
+            seed: None,
+            table_bytes: None,
+        }
+    }
+}
+
+/// The result of an operation that can run out of budget.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome<T> {
+    /// The operation finished.
+    Known(T),
+    /// A limit was reached before the answer was known.
+    Unknown,
+}
+
+impl<T> Outcome<T> {
+    #[must_use]
+    pub fn known(self) -> Option<T> {
+        match self {
+            Outcome::Known(value) => Some(value),
+            Outcome::Unknown => None,
+        }
+    }
+
+    #[must_use]
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Outcome::Unknown)
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Outcome<U> {
+        match self {
+            Outcome::Known(value) => Outcome::Known(f(value)),
+            Outcome::Unknown => Outcome::Unknown,
+        }
+    }
+}
+
+/// A limit was reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Exhausted;
+
+/// Nodes and time left of a [`Limits`].
+#[derive(Clone, Debug)]
+pub(crate) struct Budget {
+    nodes: u64,
+    max_nodes: Option<u64>,
+    deadline: Option<Instant>,
+    pub(crate) max_depth: Option<u32>,
+}
+
+impl Budget {
+    pub(crate) fn new(limits: &Limits) -> Self {
+        Self {
+            nodes: 0,
+            max_nodes: limits.nodes,
+            deadline: limits.time.map(|time| Instant::now() + time),
+            max_depth: Some(limits.depth),
+        }
+    }
+
+    pub(crate) fn unlimited() -> Self {
+        Self {
+            nodes: 0,
+            max_nodes: None,
+            deadline: None,
+            max_depth: None,
+        }
+    }
+
+    /// Count a node, failing if the budget is spent.
+    pub(crate) fn spend(&mut self) -> Result<(), Exhausted> {
+        self.nodes += 1;
+        if self.max_nodes.is_some_and(|max| self.nodes > max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            Err(Exhausted)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fail if `depth` is deeper than allowed.
+    pub(crate) fn check_depth(&self, depth: u32) -> Result<(), Exhausted> {
+        if self.max_depth.is_some_and(|max| depth > max) {
+            Err(Exhausted)
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! A board fed one line at a time by another program, like a screen scraper or a bot.
+//!
+//! Every line is a [`Command`]: a stone as `B h8` or `W i9`, `pass`, `undo` to take back the last
+//! stone or pass, or `clear` to start over. Stones may be put in any order, e.g. to set up a
+//! position, and the side to move is the one that didn't put the last stone.
+//!
+//! [`LiveBoard::respond`] answers every line with the board, the move of the engine, a VCF if
+//! there is one and the continuations of the libraries of a [`Collection`] reaching the position,
+//! ended by an empty line. The commands are read from stdin with `--stream`, or from a unix socket
+//! with [`listen`] to drive a process that keeps its libraries loaded.
+
+use std::io::{BufRead, Write};
+use std::sync::{PoisonError, RwLock};
+
+use crate::board::{notation, BoardArr, BoardRead, Point, Stone, Transformation};
+use crate::engine::{self, EngineKind, Limits};
+use crate::errors::ParseError;
+use crate::project::Collection;
+use crate::solver;
+use crate::Error;
+
+/// A line of the protocol.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    Put(Stone, Point),
+    /// The side to move passes.
+    Pass,
+    Undo,
+    Clear,
+}
+
+impl std::str::FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().unwrap_or_default().to_ascii_lowercase();
+        let command = match (command.as_str(), words.next()) {
+            ("b" | "black", Some(point)) => Self::Put(Stone::Black, point.parse()?),
+            ("w" | "white", Some(point)) => Self::Put(Stone::White, point.parse()?),
+            ("pass", None) => Self::Pass,
+            ("undo", None) => Self::Undo,
+            ("clear", None) => Self::Clear,
+            _ => return Err(ParseError::Other(format!("unknown command {s:?}"))),
+        };
+        match words.next() {
+            Some(extra) => Err(ParseError::Other(format!("unexpected {extra:?} in {s:?}"))),
+            None => Ok(command),
+        }
+    }
+}
+
+/// The board built by the commands.
+#[derive(Clone, Debug)]
+pub struct LiveBoard {
+    board: BoardArr,
+    /// Stones and passes in the order they came, a pass as a null point.
+    moves: Vec<(Point, Stone)>,
+}
+
+impl Default for LiveBoard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiveBoard {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            board: BoardArr::new(15),
+            moves: vec![],
+        }
+    }
+
+    #[must_use]
+    pub fn board(&self) -> &BoardArr {
+        &self.board
+    }
+
+    /// The stones and passes in the order they came.
+    #[must_use]
+    pub fn moves(&self) -> &[(Point, Stone)] {
+        &self.moves
+    }
+
+    /// The side that didn't put the last stone, black on an empty board.
+    #[must_use]
+    pub fn to_move(&self) -> Stone {
+        self.moves
+            .last()
+            .map_or(Stone::Black, |(_, stone)| stone.opposite())
+    }
+
+    /// Apply `command`, leaving the board as it was if it can't be.
+    pub fn apply(&mut self, command: Command) -> crate::Result<()> {
+        match command {
+            Command::Put(stone, point) => {
+                if self.board.get_point(point).map(|m| m.color) != Some(Stone::Empty) {
+                    return Err(Error::Rules(format!("{point} is taken or off the board")));
+                }
+                self.board.set_point(point, stone);
+                self.moves.push((point, stone));
+            }
+            Command::Pass => self.moves.push((Point::null(), self.to_move())),
+            Command::Undo => {
+                let (point, _) = self
+                    .moves
+                    .pop()
+                    .ok_or_else(|| Error::Rules("nothing to undo".to_owned()))?;
+                self.board.set_point(point, Stone::Empty);
+            }
+            Command::Clear => *self = Self::new(),
+        }
+        Ok(())
+    }
+
+    /// Parse and apply a line.
+    pub fn apply_line(&mut self, line: &str) -> crate::Result<()> {
+        self.apply(line.parse()?)
+    }
+
+    /// Apply `line` and describe the position after it, or the error, with the moves of `kind`
+    /// and of every library of `libraries` reaching the position.
+    pub fn respond(
+        &mut self,
+        line: &str,
+        kind: EngineKind,
+        limits: &Limits,
+        libraries: Option<&Collection>,
+    ) -> String {
+        if let Err(e) = self.apply_line(line) {
+            return format!("error: {e}\n\n");
+        }
+        let (board, to_move) = (&self.board, self.to_move());
+        let mut response = format!("{board}\n");
+        let result = engine::search(board, to_move, kind, limits);
+        response += &match result.best {
+            Some(best) => format!("{to_move:?} to move, best {best} score {}\n", result.score),
+            None => format!("{to_move:?} to move, no moves left\n"),
+        };
+        if let Some(win) = solver::vcf(board, to_move, 8) {
+            response += &format!("VCF {}\n", notation::write_moves(&win));
+        }
+        for (name, moves) in libraries.map_or(vec![], |libraries| self.continuations(libraries)) {
+            response += &format!("Library {name}: {}\n", notation::write_moves(&moves));
+        }
+        response + "\n"
+    }
+
+    /// The continuations of the position in every library of `libraries` reaching it, oldest
+    /// first, turned like the board where the library has the position rotated or mirrored.
+    fn continuations(&self, libraries: &Collection) -> Vec<(String, Vec<Point>)> {
+        let mut stones = BoardRead::stones(&self.board).collect::<Vec<_>>();
+        stones.sort();
+        let mut found = vec![];
+        for location in libraries.find(&self.board) {
+            let library = &libraries.libraries()[location.file];
+            let graph = &library.graph;
+            let mut moves = vec![];
+            for node in &location.nodes {
+                let Ok((board, _)) = graph.as_board(node) else {
+                    continue;
+                };
+                let Some(transformation) = turning(&board, &stones) else {
+                    continue;
+                };
+                let mut children = graph.get_children(node);
+                children.reverse();
+                let points = children
+                    .into_iter()
+                    .filter_map(|child| graph.get_move(child).map(|m| m.point))
+                    .filter(|point| !point.is_null)
+                    .map(|point| transformation.apply(point));
+                for point in points {
+                    if !moves.contains(&point) {
+                        moves.push(point);
+                    }
+                }
+            }
+            found.push((library.name.clone(), moves));
+        }
+        found
+    }
+
+    /// Answer every line of `input` to `output` until it ends, see [`LiveBoard::respond`].
+    ///
+    /// `libraries` is only locked while answering a line, so that it can be changed by others,
+    /// like the uploads of a [server](crate::server), in between.
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        mut output: impl Write,
+        kind: EngineKind,
+        limits: &Limits,
+        libraries: Option<&RwLock<Collection>>,
+    ) -> std::io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let libraries = libraries.map(|l| l.read().unwrap_or_else(PoisonError::into_inner));
+            let response = self.respond(&line, kind, limits, libraries.as_deref());
+            drop(libraries);
+            output.write_all(response.as_bytes())?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// The transformation turning the stones of `board` into `stones`, which are sorted.
+fn turning(board: &BoardArr, stones: &[(Point, Stone)]) -> Option<Transformation> {
+    Transformation::types().into_iter().find(|transformation| {
+        let mut turned = BoardRead::stones(board)
+            .map(|(point, stone)| (transformation.apply(point), stone))
+            .collect::<Vec<_>>();
+        turned.sort();
+        turned == stones
+    })
+}
+
+/// Answer the commands of every connection to the unix socket `path`, one connection at a time,
+/// until the process ends. The board is kept between connections.
+///
+/// A file left at `path` by an earlier process is replaced.
+#[cfg(unix)]
+pub fn listen(
+    path: &std::path::Path,
+    kind: EngineKind,
+    limits: &Limits,
+    libraries: Option<&RwLock<Collection>>,
+) -> crate::Result<()> {
+    use std::os::unix::net::UnixListener;
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let mut live = LiveBoard::new();
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let reader = std::io::BufReader::new(stream.try_clone()?);
+        // a client going away is not an error of the server
+        if let Err(e) = live.run(reader, stream, kind, limits, libraries) {
+            tracing::warn!("connection lost: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Named pipes are not supported, see the unix version.
+#[cfg(not(unix))]
+pub fn listen(
+    path: &std::path::Path,
+    _kind: EngineKind,
+    _limits: &Limits,
+    _libraries: Option<&RwLock<Collection>>,
+) -> crate::Result<()> {
+    Err(Error::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("can't listen on {path:?}, sockets are only supported on unix"),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::p;
+
+    #[test]
+    fn commands() {
+        let mut live = LiveBoard::new();
+        for line in ["B h8", "W I9", "pass", "b J10"] {
+            live.apply_line(line).unwrap();
+        }
+        assert_eq!(live.to_move(), Stone::White);
+        assert_eq!(live.moves().len(), 4);
+        assert_eq!(live.moves()[2], (Point::null(), Stone::Black));
+        assert!(matches!(live.apply_line("W h8"), Err(Error::Rules(_))));
+        assert!(matches!(live.apply_line("W h8 i9"), Err(Error::Parse(_))));
+        assert!(live.apply_line("move h8").is_err());
+        assert_eq!(live.moves().len(), 4);
+
+        live.apply_line("undo").unwrap();
+        assert_eq!(
+            live.board().get_point(p![J, 10]).unwrap().color,
+            Stone::Empty
+        );
+        live.apply_line("undo").unwrap();
+        assert_eq!(live.to_move(), Stone::Black);
+        live.apply_line("clear").unwrap();
+        assert!(live.moves().is_empty());
+        assert!(live.apply_line("undo").is_err());
+    }
+
+    #[test]
+    fn responses() {
+        let mut library = Board::new();
+        library.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        library.add_line(&p![[H, 8], [I, 9], [G, 7]]);
+        // the same opening mirrored
+        let mut mirrored = Board::new();
+        mirrored.add_line(&p![[H, 8], [G, 9], [F, 10]]);
+        let mut libraries = Collection::new();
+        libraries.add("a.lib", library);
+        libraries.add("b.lib", mirrored);
+        let libraries = RwLock::new(libraries);
+        let limits = Limits {
+            depth: 1,
+            ..Limits::default()
+        };
+        let mut output = vec![];
+        LiveBoard::new()
+            .run(
+                "B h8\n\nW i9\nW a1 a2\n".as_bytes(),
+                &mut output,
+                EngineKind::AlphaBeta,
+                &limits,
+                Some(&libraries),
+            )
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let responses = output.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(responses.len(), 4, "{output}");
+        assert!(responses[0].contains("White to move, best"));
+        assert!(responses[0].ends_with("Library a.lib: I9\nLibrary b.lib: G9"));
+        assert!(responses[1].ends_with("Library a.lib: J10 G7\nLibrary b.lib: J10"));
+        assert!(responses[2].starts_with("error: "));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket() {
+        use std::os::unix::net::UnixStream;
+        let path = std::env::temp_dir().join(format!("renju-live-{}.sock", std::process::id()));
+        let listening = path.clone();
+        std::thread::spawn(move || {
+            let limits = Limits {
+                depth: 1,
+                ..Limits::default()
+            };
+            listen(&listening, EngineKind::AlphaBeta, &limits, None)
+        });
+        let connect = || {
+            (0..100)
+                .find_map(|_| {
+                    UnixStream::connect(&path)
+                        .map_err(|_| std::thread::sleep(std::time::Duration::from_millis(10)))
+                        .ok()
+                })
+                .unwrap()
+        };
+        let ask = |line: &str| {
+            let mut stream = connect();
+            writeln!(stream, "{line}").unwrap();
+            stream.shutdown(std::net::Shutdown::Write).unwrap();
+            let mut response = String::new();
+            std::io::Read::read_to_string(&mut stream, &mut response).unwrap();
+            response
+        };
+        assert!(ask("B h8").contains("White to move"));
+        // the board is kept for the next connection
+        assert!(ask("W i9").contains("Black to move"));
+        assert!(ask("W i9").starts_with("error: "));
+        let _ = std::fs::remove_file(&path);
+    }
+}
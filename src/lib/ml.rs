@@ -0,0 +1,189 @@
+//! Utilities for machine learning on renju positions.
+//!
+//! [`encode`] turns a position into feature planes for a model, [`symmetries`] and
+//! [`transform_planes`] augment positions and planes with the eight symmetries of the board.
+
+use crate::board::{BoardArr, Point, Stone, Transformation};
+
+pub mod legality;
+pub mod selfplay;
+
+/// A feature plane produced by an [`Encoder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plane {
+    /// `1.0` on stones of the side to move.
+    Own,
+    /// `1.0` on stones of the opponent.
+    Opponent,
+    /// `1.0` on empty points.
+    Empty,
+    /// `1.0` on points forbidden for the side to move, always empty for white.
+    Forbidden,
+    /// `1.0` on the last move, if given to [`Encoder::encode`].
+    LastMove,
+    /// The amount of stones on the board divided by the amount of points, on every point.
+    MoveNumber,
+    /// `1.0` everywhere if black is to move, otherwise `0.0`.
+    BlackToMove,
+}
+
+/// Encodes positions into feature planes.
+///
+/// The output has one `size * size` block per plane, in the order of `planes`. Within a plane,
+/// points are in [`Point::to_1d`] order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encoder {
+    pub planes: Vec<Plane>,
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self {
+            planes: vec![Plane::Own, Plane::Opponent, Plane::Forbidden],
+        }
+    }
+}
+
+impl Encoder {
+    #[must_use]
+    pub fn new(planes: Vec<Plane>) -> Self {
+        Self { planes }
+    }
+
+    /// Encode `board` with `stone` to move.
+    #[must_use]
+    pub fn encode(&self, board: &BoardArr, stone: Stone, last_move: Option<Point>) -> Vec<f32> {
+        let size = board.size();
+        let area = board.len();
+        let mut out = vec![0.0; area * self.planes.len()];
+        for (plane, chunk) in self.planes.iter().zip(out.chunks_mut(area)) {
+            match plane {
+                Plane::Own | Plane::Opponent | Plane::Empty => {
+                    let color = match plane {
+                        Plane::Own => stone,
+                        Plane::Opponent => stone.opposite(),
+                        _ => Stone::Empty,
+                    };
+                    for marker in board.iter().filter(|m| m.color == color) {
+                        chunk[marker.point.to_1d(size) as usize] = 1.0;
+                    }
+                }
+                Plane::Forbidden if stone.is_black() => {
+                    for point in board.forbidden_points(stone) {
+                        chunk[point.to_1d(size) as usize] = 1.0;
+                    }
+                }
+                Plane::Forbidden => {}
+                Plane::LastMove => {
+                    if let Some(point) = last_move.filter(|p| !p.is_null) {
+                        chunk[point.to_1d(size) as usize] = 1.0;
+                    }
+                }
+                Plane::MoveNumber => {
+                    let stones = board.iter().filter(|m| !m.color.is_empty()).count();
+                    chunk.fill(stones as f32 / area as f32);
+                }
+                Plane::BlackToMove => chunk.fill(if stone.is_black() { 1.0 } else { 0.0 }),
+            }
+        }
+        out
+    }
+}
+
+/// Encode `board` with `stone` to move using the default [`Encoder`].
+#[must_use]
+pub fn encode(board: &BoardArr, stone: Stone) -> Vec<f32> {
+    Encoder::default().encode(board, stone, None)
+}
+
+/// `board` with `transformation` applied to every stone.
+#[must_use]
+pub fn transform_board(board: &BoardArr, transformation: Transformation) -> BoardArr {
+    let mut transformed = BoardArr::new(board.size());
+    for marker in board.iter().filter(|m| !m.color.is_empty()) {
+        transformed.set_point(transformation.apply(marker.point), marker.color);
+    }
+    transformed
+}
+
+/// `board` in all eight orientations, starting with the identity.
+pub fn symmetries(board: &BoardArr) -> impl Iterator<Item = (Transformation, BoardArr)> + '_ {
+    Transformation::types()
+        .into_iter()
+        .map(move |t| (t, transform_board(board, t)))
+}
+
+/// Apply `transformation` to planes from [`Encoder::encode`], or to a policy over the points of
+/// the board, which is a single plane.
+///
+/// Transformations turn around the center of a 15x15 board, values moved off a smaller board are
+/// dropped. `None` if the length of `planes` is not a multiple of `size * size`.
+#[must_use]
+pub fn transform_planes(
+    planes: &[f32],
+    size: u32,
+    transformation: Transformation,
+) -> Option<Vec<f32>> {
+    let area = size as usize * size as usize;
+    if area == 0 || !planes.len().is_multiple_of(area) {
+        return planes.is_empty().then(Vec::new);
+    }
+    let mut out = vec![0.0; planes.len()];
+    for (from, to) in planes.chunks(area).zip(out.chunks_mut(area)) {
+        for (i, value) in from.iter().enumerate() {
+            let point = transformation.apply(Point::from_1d(i as u32, size));
+            if let Some(to) = (point.x < size && point.y < size)
+                .then(|| to.get_mut(point.to_1d(size) as usize))
+                .flatten()
+            {
+                *to = *value;
+            }
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn encode_planes() {
+        let mut board = BoardArr::new(15);
+        for point in p![[G, 8], [I, 8], [H, 7], [H, 9]] {
+            board.set_point(point, Stone::Black);
+        }
+        board.set_point(p![A, 1], Stone::White);
+        let index = |point: Point| point.to_1d(15) as usize;
+
+        let planes = encode(&board, Stone::Black);
+        assert_eq!(planes.len(), 3 * 225);
+        assert_eq!(planes[index(p![G, 8])], 1.0);
+        assert_eq!(planes[225 + index(p![A, 1])], 1.0);
+        // double three
+        assert_eq!(planes[450 + index(p![H, 8])], 1.0);
+        assert_eq!(planes[450..].iter().sum::<f32>(), 1.0);
+        assert_eq!(encode(&board, Stone::White)[450..].iter().sum::<f32>(), 0.0);
+
+        let encoder = Encoder::new(vec![Plane::LastMove, Plane::MoveNumber]);
+        let planes = encoder.encode(&board, Stone::White, Some(p![A, 1]));
+        assert_eq!(planes[..225].iter().sum::<f32>(), 1.0);
+        assert_eq!(planes[225], 5.0 / 225.0);
+    }
+
+    #[test]
+    fn transforms_agree() {
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        board.set_point(p![J, 12], Stone::White);
+        board.set_point(p![B, 3], Stone::Black);
+        let planes = encode(&board, Stone::White);
+        for (t, transformed) in symmetries(&board) {
+            assert_eq!(
+                transform_planes(&planes, 15, t).unwrap(),
+                encode(&transformed, Stone::White)
+            );
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Labeled forbidden point datasets.
+//!
+//! Positions are exported as CSV with the header `position,point,reason`:
+//!
+//! - `position` is the board as `size * size` characters in [`Point::to_1d`] order, starting at
+//!   A15. `x` is black, `o` is white and `.` is empty.
+//! - `point` is an empty point in the position, e.g. `H8`.
+//! - `reason` is a [`ForbiddenReason`], `overline`, `double-four` or `double-three`, or `legal`
+//!   if legal points are included.
+//!
+//! Reasons are for black, white has no forbidden points.
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+
+use crate::board::evaluator::ForbiddenReason;
+use crate::board::{BoardArr, Evaluate, Point, Stone};
+
+/// Options for [`random_position`] and [`write_csv`].
+#[derive(Clone, Debug)]
+pub struct LegalityOptions {
+    /// The amount of stones in a position, an even amount in the range, or the one above it if
+    /// there is none.
+    pub stones: RangeInclusive<usize>,
+    /// Stones are placed at most this far from the center, forbidden points need crowded boards.
+    pub radius: u32,
+    /// Write a row for every legal empty point as well.
+    pub include_legal: bool,
+}
+
+impl Default for LegalityOptions {
+    fn default() -> Self {
+        Self {
+            stones: 8..=30,
+            radius: 4,
+            include_legal: false,
+        }
+    }
+}
+
+/// A random position with black to move, i.e. as many black stones as white.
+///
+/// With fewer points within the radius than stones, the position has as many pairs of stones as
+/// fit.
+pub fn random_position(rng: &mut impl Rng, options: &LegalityOptions) -> BoardArr {
+    let mut board = BoardArr::new(15);
+    let center = 7;
+    let fewest = options.stones.start().div_ceil(2);
+    let pairs = rng.gen_range(fewest..=(options.stones.end() / 2).max(fewest));
+    let mut points = board
+        .iter()
+        .filter(|m| {
+            m.point.x.abs_diff(center) <= options.radius
+                && m.point.y.abs_diff(center) <= options.radius
+        })
+        .map(|m| m.point)
+        .choose_multiple(rng, pairs * 2);
+    points.truncate(points.len() & !1);
+    // the points are chosen in no random order
+    points.shuffle(rng);
+    for (i, point) in points.into_iter().enumerate() {
+        board.set_point(point, Stone::from_bool(i.is_multiple_of(2)));
+    }
+    board
+}
+
+/// Every forbidden point in `board` with its reason.
+#[must_use]
+pub fn forbidden_points(board: &BoardArr) -> Vec<(Point, ForbiddenReason)> {
+    board
+        .renju_conditions(Stone::Black, None)
+        .reasons
+        .into_iter()
+        .collect()
+}
+
+/// `board` in the format of the `position` column.
+#[must_use]
+pub fn position_string(board: &BoardArr) -> String {
+    board
+        .iter()
+        .map(|m| match m.color {
+            Stone::Black => 'x',
+            Stone::White => 'o',
+            Stone::Empty => '.',
+        })
+        .collect()
+}
+
+/// Write the labels of `positions` as CSV, returning the amount of rows written.
+pub fn write_csv<'a>(
+    positions: impl IntoIterator<Item = &'a BoardArr>,
+    options: &LegalityOptions,
+    mut writer: impl Write,
+) -> io::Result<usize> {
+    writeln!(writer, "position,point,reason")?;
+    let mut rows = 0;
+    for board in positions {
+        let position = position_string(board);
+        let conditions = board.renju_conditions(Stone::Black, None);
+        for marker in board.iter().filter(|m| m.color.is_empty()) {
+            let reason = match conditions.reason(marker.point) {
+                Some(reason) => reason.as_str(),
+                None if options.include_legal => "legal",
+                None => continue,
+            };
+            writeln!(writer, "{position},{},{reason}", marker.point)?;
+            rows += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn csv_rows() {
+        let mut board = BoardArr::new(15);
+        for point in p![[G, 8], [I, 8], [H, 7], [H, 9]] {
+            board.set_point(point, Stone::Black);
+        }
+        let mut out = vec![];
+        let rows = write_csv([&board], &LegalityOptions::default(), &mut out).unwrap();
+        assert_eq!(rows, 1);
+        let csv = String::from_utf8(out).unwrap();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.ends_with(",H8,double-three"));
+        assert_eq!(row.split(',').next().unwrap().len(), 225);
+
+        let options = LegalityOptions {
+            include_legal: true,
+            ..LegalityOptions::default()
+        };
+        let rows = write_csv([&board], &options, io::sink()).unwrap();
+        assert_eq!(rows, 225 - 4);
+    }
+
+    #[test]
+    fn random_positions() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let options = LegalityOptions::default();
+        for _ in 0..20 {
+            let board = random_position(&mut rng, &options);
+            let black = board.iter().filter(|m| m.color.is_black()).count();
+            let white = board.iter().filter(|m| m.color.is_white()).count();
+            assert_eq!(black, white);
+            assert!(options.stones.contains(&(black + white)));
+        }
+        // an odd amount is rounded up, a full radius leaves out a pair
+        let odd = LegalityOptions {
+            stones: 9..=9,
+            ..LegalityOptions::default()
+        };
+        let board = random_position(&mut rng, &odd);
+        assert_eq!(board.iter().filter(|m| !m.color.is_empty()).count(), 10);
+        let full = LegalityOptions {
+            stones: 9..=9,
+            radius: 1,
+            ..LegalityOptions::default()
+        };
+        let board = random_position(&mut rng, &full);
+        let black = board.iter().filter(|m| m.color.is_black()).count();
+        let white = board.iter().filter(|m| m.color.is_white()).count();
+        assert_eq!((black, white), (4, 4));
+    }
+}
@@ -0,0 +1,376 @@
+//! Self-play data generation.
+//!
+//! The built-in engine plays against itself and every move becomes a [`Sample`]. Games start with
+//! a few random moves near the center, the engine is deterministic so this is what makes the games
+//! differ.
+//!
+//! # File format
+//!
+//! All numbers are unsigned bytes unless noted. A file starts with a header
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | 4     | magic `RJSP` |
+//! | 1     | format version, `2` |
+//! | 1     | board size `n`, at most 15 |
+//! | 1     | amount of planes, `2` |
+//! | 1     | reserved, `0` |
+//! | 8     | seed the games were played with, little-endian `u64` |
+//!
+//! followed by samples until the end of the file
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | `ceil(n²/8)` | plane 0, stones of the side to move |
+//! | `ceil(n²/8)` | plane 1, stones of the opponent |
+//! | 1     | side to move, `1` black or `2` white |
+//! | 1     | move played, `x + y * n` |
+//! | 1     | result for the side to move as `i8`, `1` win, `0` draw, `-1` loss |
+//!
+//! Planes are bitsets in row-major order starting at the top left (A15), least significant bit
+//! first. Positions are canonicalized: of the eight symmetries of the board, the one with the
+//! smallest plane bytes is stored, and the move is transformed the same way.
+
+use std::io::{self, Read, Write};
+
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::transform_board;
+use crate::board::{BoardArr, Point, Stone, Transformation};
+use crate::engine::{self, EngineKind, Limits};
+use crate::game::GameRecord;
+use crate::ratings::Score;
+use crate::solver;
+
+const MAGIC: &[u8; 4] = b"RJSP";
+const VERSION: u8 = 2;
+const PLANES: u8 = 2;
+/// The largest board whose moves fit the byte of a sample.
+const MAX_SIZE: u32 = 15;
+
+/// A position from a self-play game and the move played in it.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub position: BoardArr,
+    pub to_move: Stone,
+    pub point: Point,
+    /// The result of the game for `to_move`.
+    pub result: Score,
+}
+
+impl Sample {
+    /// The same sample in the orientation with the smallest planes.
+    #[must_use]
+    pub fn canonical(&self) -> Self {
+        Transformation::types()
+            .into_iter()
+            .map(|t| Self {
+                position: transform_board(&self.position, t),
+                point: t.apply(self.point),
+                ..self.clone()
+            })
+            .min_by_key(|sample| sample.planes())
+            .expect("there are transformations")
+    }
+
+    fn planes(&self) -> Vec<u8> {
+        let plane = |stone: Stone| {
+            let mut bits = vec![0u8; self.position.len().div_ceil(8)];
+            for (i, marker) in self.position.iter().enumerate() {
+                if marker.color == stone {
+                    bits[i / 8] |= 1 << (i % 8);
+                }
+            }
+            bits
+        };
+        let mut planes = plane(self.to_move);
+        planes.extend(plane(self.to_move.opposite()));
+        planes
+    }
+}
+
+/// Writes samples in the format described in the [module documentation](self).
+pub struct SampleWriter<W> {
+    writer: W,
+    size: u32,
+}
+
+impl<W: Write> SampleWriter<W> {
+    /// Create a writer for boards of size `size` from games played with `seed`, writing the
+    /// header. Boards larger than 15 are an error, their moves don't fit a byte.
+    pub fn new(mut writer: W, size: u32, seed: u64) -> io::Result<Self> {
+        if size > MAX_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("samples of {size}x{size} boards are not supported, at most {MAX_SIZE}x{MAX_SIZE}"),
+            ));
+        }
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, size as u8, PLANES, 0])?;
+        writer.write_all(&seed.to_le_bytes())?;
+        Ok(Self { writer, size })
+    }
+
+    pub fn write(&mut self, sample: &Sample) -> io::Result<()> {
+        if sample.position.size() != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sample has the wrong board size",
+            ));
+        }
+        self.writer.write_all(&sample.planes())?;
+        let result = match sample.result {
+            Score::Win => 1i8,
+            Score::Draw => 0,
+            Score::Loss => -1,
+        };
+        self.writer.write_all(&[
+            if sample.to_move.is_black() { 1 } else { 2 },
+            sample.point.to_1d(self.size) as u8,
+            result as u8,
+        ])
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// The contents of a file written by [`SampleWriter`].
+#[derive(Clone, Debug)]
+pub struct Samples {
+    pub seed: u64,
+    pub samples: Vec<Sample>,
+}
+
+/// Read every sample from a file written by [`SampleWriter`].
+pub fn read_samples(mut reader: impl Read) -> io::Result<Samples> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let mut header = [0u8; 16];
+    reader.read_exact(&mut header)?;
+    if &header[..4] != MAGIC || header[4] != VERSION || header[6] != PLANES {
+        return Err(invalid("not a version 2 self-play file"));
+    }
+    let size = u32::from(header[5]);
+    let seed = u64::from_le_bytes(header[8..].try_into().expect("header is 16 bytes"));
+    let plane_len = ((size * size) as usize).div_ceil(8);
+    let mut record = vec![0u8; plane_len * 2 + 3];
+    let mut samples = vec![];
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let (planes, rest) = record.split_at(plane_len * 2);
+        let to_move = match rest[0] {
+            1 => Stone::Black,
+            2 => Stone::White,
+            _ => return Err(invalid("invalid side to move")),
+        };
+        let mut position = BoardArr::new(size);
+        for i in 0..(size * size) as usize {
+            let bit = |plane: usize| planes[plane * plane_len + i / 8] & (1 << (i % 8)) != 0;
+            let point = Point::from_1d(i as u32, size);
+            if bit(0) {
+                position.set_point(point, to_move);
+            } else if bit(1) {
+                position.set_point(point, to_move.opposite());
+            }
+        }
+        let result = match rest[2] as i8 {
+            1 => Score::Win,
+            0 => Score::Draw,
+            -1 => Score::Loss,
+            _ => return Err(invalid("invalid result")),
+        };
+        samples.push(Sample {
+            position,
+            to_move,
+            point: Point::from_1d(u32::from(rest[1]), size),
+            result,
+        });
+    }
+    Ok(Samples { seed, samples })
+}
+
+/// Options for [`play_game`] and [`selfplay`].
+#[derive(Clone, Debug)]
+pub struct SelfPlayOptions {
+    pub kind: EngineKind,
+    pub limits: Limits,
+    /// Random moves played before the engine takes over.
+    pub opening_moves: usize,
+    /// Games longer than this are draws.
+    pub max_moves: usize,
+}
+
+impl Default for SelfPlayOptions {
+    fn default() -> Self {
+        Self {
+            kind: EngineKind::AlphaBeta,
+            limits: Limits {
+                depth: 2,
+                ..Limits::default()
+            },
+            opening_moves: 3,
+            max_moves: 225,
+        }
+    }
+}
+
+/// Play a single game, returning it and its result for black.
+///
+/// The seed in `options.limits` is not used, every search is seeded from `rng`.
+pub fn play_game(options: &SelfPlayOptions, rng: &mut impl Rng) -> (GameRecord, Score) {
+    let mut board = BoardArr::new(15);
+    let mut game = GameRecord::default();
+    let mut stone = Stone::Black;
+    while game.moves.len() < options.max_moves {
+        let point = if game.moves.len() < options.opening_moves {
+            board.candidate_moves(stone, 2, 8).choose(rng).copied()
+        } else {
+            let limits = Limits {
+                seed: Some(rng.gen()),
+                ..options.limits.clone()
+            };
+            engine::search(&board, stone, options.kind, &limits).best
+        };
+        let Some(point) = point else {
+            break;
+        };
+        let five = solver::makes_five(&board, point, stone);
+        board.set_point(point, stone);
+        game.moves.push(point);
+        if five {
+            let score = if stone.is_black() {
+                Score::Win
+            } else {
+                Score::Loss
+            };
+            return (game, score);
+        }
+        stone = stone.opposite();
+    }
+    (game, Score::Draw)
+}
+
+/// Play `games` games and write every position of them to `writer`. Returns the amount of samples
+/// written.
+///
+/// The same `seed` and options give the same games, as long as `options.limits` has no time limit.
+#[tracing::instrument(skip(options, writer))]
+pub fn selfplay(
+    games: usize,
+    options: &SelfPlayOptions,
+    seed: u64,
+    writer: impl Write,
+) -> io::Result<usize> {
+    let mut rng = SmallRng::seed_from_u64(seed);
+    let mut writer = SampleWriter::new(writer, 15, seed)?;
+    let mut samples = 0;
+    for i in 0..games {
+        let (game, black_score) = play_game(options, &mut rng);
+        tracing::info!(
+            game = i,
+            moves = game.moves.len(),
+            ?black_score,
+            "finished game"
+        );
+        for (ply, point) in game.moves.iter().enumerate() {
+            let to_move = GameRecord::stone_at(ply);
+            let sample = Sample {
+                position: game.position(ply),
+                to_move,
+                point: *point,
+                result: if to_move.is_black() {
+                    black_score
+                } else {
+                    black_score.opposite()
+                },
+            };
+            writer.write(&sample.canonical())?;
+            samples += 1;
+        }
+    }
+    writer.into_inner().flush()?;
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn canonical_is_symmetric() {
+        let mut position = BoardArr::new(15);
+        position.set_point(p![H, 8], Stone::Black);
+        position.set_point(p![I, 9], Stone::White);
+        let sample = Sample {
+            position,
+            to_move: Stone::Black,
+            point: p![J, 10],
+            result: Score::Win,
+        };
+        let canonical = sample.canonical();
+        for t in Transformation::types() {
+            let transformed = Sample {
+                position: transform_board(&sample.position, t),
+                point: t.apply(sample.point),
+                ..sample.clone()
+            };
+            let transformed = transformed.canonical();
+            assert_eq!(transformed.planes(), canonical.planes());
+            assert_eq!(transformed.point, canonical.point);
+        }
+    }
+
+    #[test]
+    fn selfplay_round_trip() {
+        let error = SampleWriter::new(vec![], 19, 0).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let options = SelfPlayOptions {
+            limits: Limits {
+                depth: 1,
+                width: 4,
+                ..Limits::default()
+            },
+            max_moves: 8,
+            ..SelfPlayOptions::default()
+        };
+        let mut buffer = vec![];
+        let written = selfplay(1, &options, 42, &mut buffer).unwrap();
+        assert_eq!(written, 8);
+        assert_eq!(buffer.len(), 16 + written * (29 * 2 + 3));
+
+        let mut again = vec![];
+        selfplay(1, &options, 42, &mut again).unwrap();
+        assert_eq!(buffer, again);
+
+        let Samples { seed, samples } = read_samples(buffer.as_slice()).unwrap();
+        assert_eq!(seed, 42);
+        assert_eq!(samples.len(), written);
+        for (ply, sample) in samples.iter().enumerate() {
+            assert_eq!(sample.to_move, GameRecord::stone_at(ply));
+            assert_eq!(sample.result, Score::Draw);
+            assert_eq!(
+                sample
+                    .position
+                    .iter()
+                    .filter(|m| !m.color.is_empty())
+                    .count(),
+                ply
+            );
+            assert!(sample
+                .position
+                .get_point(sample.point)
+                .unwrap()
+                .color
+                .is_empty());
+        }
+    }
+}
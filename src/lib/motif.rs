@@ -0,0 +1,189 @@
+//! What a position or puzzle is about, a small vocabulary shared by libraries and puzzles.
+//!
+//! The [`Motif`]s every tool knows cover the usual lessons, other names are kept as
+//! [`Motif::Other`] so a pack or library can bring its own. A motif is written in kebab case,
+//! like `double-threat`.
+//!
+//! Nodes of a [`Board`] carry motifs as [tags](Board::tag) named `motif:` and the motif, so they
+//! are kept by every format keeping tags and can be exported with `--tag motif:vcf`.
+
+use std::fmt;
+
+use crate::board::{Board, MoveIndex};
+use crate::errors::ParseError;
+
+/// The prefix of the tags holding motifs.
+pub const TAG_PREFIX: &str = "motif:";
+
+/// A motif, see the [module documentation](self).
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub enum Motif {
+    /// Winning with a row of fours.
+    Vcf,
+    /// Winning with a row of fours and threes.
+    Vct,
+    /// A move making two threats at once, like a four-three.
+    DoubleThreat,
+    /// Forcing black to a forbidden point.
+    ForbiddenTrap,
+    /// Stopping the threats of the opponent.
+    Defense,
+    /// A full board, where the last empty points decide.
+    Endgame,
+    /// A motif of its own, in kebab case.
+    Other(String),
+}
+
+impl Motif {
+    /// Every motif but [`Motif::Other`].
+    pub const KNOWN: [Motif; 6] = [
+        Motif::Vcf,
+        Motif::Vct,
+        Motif::DoubleThreat,
+        Motif::ForbiddenTrap,
+        Motif::Defense,
+        Motif::Endgame,
+    ];
+
+    /// The name of the motif, like `double-threat`.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Motif::Vcf => "vcf",
+            Motif::Vct => "vct",
+            Motif::DoubleThreat => "double-threat",
+            Motif::ForbiddenTrap => "forbidden-trap",
+            Motif::Defense => "defense",
+            Motif::Endgame => "endgame",
+            Motif::Other(name) => name,
+        }
+    }
+
+    /// The tag of the nodes with the motif.
+    #[must_use]
+    pub fn tag(&self) -> String {
+        format!("{TAG_PREFIX}{}", self.name())
+    }
+
+    /// The motif of `tag`, if it holds one.
+    #[must_use]
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        tag.strip_prefix(TAG_PREFIX)?.parse().ok()
+    }
+}
+
+impl std::str::FromStr for Motif {
+    type Err = ParseError;
+
+    /// Any case and spaces or underscores for dashes, `Double threat` is [`Motif::DoubleThreat`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s
+            .trim()
+            .to_lowercase()
+            .split([' ', '_', '-'])
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        if name.is_empty() || name.contains([',', '=', ':']) {
+            return Err(ParseError::Other(format!("invalid motif {s:?}")));
+        }
+        Ok(Motif::KNOWN
+            .into_iter()
+            .find(|motif| motif.name() == name)
+            .unwrap_or(Motif::Other(name)))
+    }
+}
+
+impl TryFrom<String> for Motif {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Motif> for String {
+    fn from(motif: Motif) -> String {
+        motif.to_string()
+    }
+}
+
+impl fmt::Display for Motif {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Give `index` the motif `motif`.
+pub fn add(board: &mut Board, index: MoveIndex, motif: &Motif) {
+    board.tag(index, motif.tag());
+}
+
+/// Take the motif `motif` from `index`, returning whether it had it.
+pub fn remove(board: &mut Board, index: MoveIndex, motif: &Motif) -> bool {
+    board.untag(index, &motif.tag())
+}
+
+/// The motifs of `index`.
+#[must_use]
+pub fn motifs_at(board: &Board, index: MoveIndex) -> Vec<Motif> {
+    board
+        .tags_at(index)
+        .into_iter()
+        .filter_map(Motif::from_tag)
+        .collect()
+}
+
+/// The nodes with the motif `motif`, in the order they were given it.
+#[must_use]
+pub fn with_motif<'a>(board: &'a Board, motif: &Motif) -> &'a [MoveIndex] {
+    board.tagged(&motif.tag())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardMarker, Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn names() {
+        assert_eq!(
+            "Double threat".parse::<Motif>().unwrap(),
+            Motif::DoubleThreat
+        );
+        assert_eq!("VCF".parse::<Motif>().unwrap(), Motif::Vcf);
+        assert_eq!(
+            "Broken_three".parse::<Motif>().unwrap(),
+            Motif::Other("broken-three".to_owned())
+        );
+        assert!(" ".parse::<Motif>().is_err());
+        assert!("a=b".parse::<Motif>().is_err());
+        for motif in Motif::KNOWN {
+            assert_eq!(motif.to_string().parse::<Motif>().unwrap(), motif);
+        }
+    }
+
+    #[test]
+    fn nodes() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let h8 = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        board.tag(h8, "prep");
+        add(&mut board, h8, &Motif::Vcf);
+        add(&mut board, h8, &Motif::Other("renju-trick".to_owned()));
+        assert_eq!(
+            motifs_at(&board, h8),
+            [Motif::Other("renju-trick".to_owned()), Motif::Vcf]
+        );
+        assert_eq!(with_motif(&board, &Motif::Vcf), [h8]);
+        assert!(remove(&mut board, h8, &Motif::Vcf));
+        assert!(with_motif(&board, &Motif::Vcf).is_empty());
+        assert_eq!(board.tags_at(h8).len(), 2);
+    }
+}
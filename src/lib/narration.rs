@@ -0,0 +1,143 @@
+//! Lines of moves as prose, e.g "Black J8 making a three, White K8 stopping the three", for audio
+//! and screen readers.
+//!
+//! Every move is described by what it makes for the player, from the
+//! [conditions](crate::board::evaluator::RenjuConditions) of the position before it, and what it
+//! stops of the opponent's threats, in the language of [`crate::i18n`].
+
+use crate::board::evaluator::{RenjuCondition, RenjuConditions};
+use crate::board::{Board, BoardArr, Evaluate, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+use crate::i18n::{self, tr, Message};
+
+/// Describe every move of `moves` played on `board` alternately, starting with `stone`.
+#[must_use]
+pub fn narrate(board: &BoardArr, stone: Stone, moves: &[Point]) -> Vec<String> {
+    let mut board = board.clone();
+    let mut stone = stone;
+    let mut described = Vec::with_capacity(moves.len());
+    for &point in moves {
+        let name = i18n::stone_name(stone);
+        if point.is_null {
+            described.push(tr(Message::Passes, &[&name]));
+            stone = stone.opposite();
+            continue;
+        }
+        let (black, white) = board.renju_conditions_both(None);
+        let (own, theirs) = match stone {
+            Stone::White => (white, black),
+            _ => (black, white),
+        };
+        let mut clauses = vec![];
+        if let Some(reason) = own.reason(point) {
+            let reason = tr(reason.into(), &[]);
+            clauses.push(tr(Message::OnForbiddenPoint, &[&reason]));
+        } else if let Some(threat) = threat(&own, point) {
+            clauses.push(tr(Message::Making, &[&tr(threat, &[])]));
+        }
+        if let Some(defense) = defense(&theirs, point) {
+            clauses.push(tr(defense, &[]));
+        }
+        let mut sentence = format!("{name} {point}");
+        if !clauses.is_empty() {
+            sentence.push(' ');
+            sentence.push_str(&clauses.join(&tr(Message::And, &[])));
+        }
+        described.push(sentence);
+        board.set_point(point, stone);
+        stone = stone.opposite();
+    }
+    described
+}
+
+/// The moves leading to `index` as one sentence.
+pub fn narrate_line(graph: &Board, index: MoveIndex) -> Result<String, ParseError> {
+    let (_, moves) = graph.as_board(&index)?;
+    let moves = narrate(&BoardArr::new(15), Stone::Black, &moves);
+    Ok(format!("{}.", moves.join(", ")))
+}
+
+/// What `stone` playing `point` makes, for the player with `conditions`.
+fn threat(conditions: &RenjuConditions, point: Point) -> Option<Message> {
+    let made = conditions
+        .conditions
+        .iter()
+        .filter(|c| *c.place() == point)
+        .collect::<Vec<_>>();
+    let count = |kind: fn(&RenjuCondition) -> bool| made.iter().filter(|c| kind(c)).count();
+    let fives = count(|c| matches!(c, RenjuCondition::Five { .. }));
+    let open_fours = count(|c| matches!(c, RenjuCondition::StraightFour { .. }));
+    let fours = open_fours
+        + count(|c| {
+            matches!(
+                c,
+                RenjuCondition::ClosedFour { .. } | RenjuCondition::BrokenFour { .. }
+            )
+        });
+    let threes = count(|c| {
+        matches!(
+            c,
+            RenjuCondition::UnbrokenThree { .. } | RenjuCondition::BrokenThree { .. }
+        )
+    });
+    Some(match (fives, fours, threes) {
+        (1.., _, _) => Message::Five,
+        (_, 2.., _) => Message::DoubleFour,
+        (_, 1, 1..) => Message::FourThree,
+        _ if open_fours > 0 => Message::OpenFour,
+        (_, 1, _) => Message::Four,
+        (_, _, 2..) => Message::DoubleThree,
+        (_, _, 1) => Message::Three,
+        _ => return None,
+    })
+}
+
+/// What playing `point` stops, for an opponent with `conditions`.
+fn defense(conditions: &RenjuConditions, point: Point) -> Option<Message> {
+    let stops = |kind: fn(&RenjuCondition) -> bool| {
+        conditions
+            .conditions
+            .iter()
+            .any(|c| *c.place() == point && kind(c))
+    };
+    if stops(|c| matches!(c, RenjuCondition::Five { .. })) {
+        Some(Message::BlockingTheFour)
+    } else if stops(|c| matches!(c, RenjuCondition::StraightFour { .. })) {
+        Some(Message::StoppingTheThree)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn prose() {
+        let moves = [
+            p![H, 8],
+            p![A, 1],
+            p![I, 8],
+            p![A, 2],
+            p![J, 8],
+            p![K, 8],
+            p![G, 8],
+            p![F, 8],
+        ];
+        assert_eq!(
+            narrate(&BoardArr::new(15), Stone::Black, &moves),
+            [
+                "Black H8",
+                "White A1",
+                "Black I8",
+                "White A2",
+                "Black J8 making a three",
+                "White K8 stopping the three",
+                "Black G8 making a four",
+                "White F8 blocking the four",
+            ]
+        );
+    }
+}
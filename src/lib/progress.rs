@@ -0,0 +1,138 @@
+//! Progress reporting and cancellation for long operations.
+//!
+//! Functions that can run for a long time have a `_with_progress` variant taking a [`Progress`].
+//! It is given an [`Update`] every now and then, and the operation returns [`Cancelled`] soon
+//! after its [`CancelToken`] is cancelled, e.g. from another thread or a UI callback.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A shared flag to abort an operation.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the operations using this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The operation was stopped through its [`CancelToken`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, thiserror::Error)]
+#[error("operation was cancelled")]
+pub struct Cancelled;
+
+/// How far an operation has come.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Update {
+    /// What is being done, e.g. `"parse"` or `"analyze"`.
+    pub stage: &'static str,
+    pub done: u64,
+    /// The amount of work in this stage, if known.
+    pub total: Option<u64>,
+}
+
+impl Update {
+    /// `done` out of `total`, between `0.0` and `1.0`.
+    #[must_use]
+    pub fn fraction(&self) -> Option<f64> {
+        self.total
+            .map(|total| (self.done as f64 / total.max(1) as f64).min(1.0))
+    }
+}
+
+/// A progress callback and a cancellation token.
+#[derive(Default)]
+pub struct Progress<'a> {
+    callback: Option<Box<dyn FnMut(Update) + 'a>>,
+    token: CancelToken,
+}
+
+impl std::fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Progress")
+            .field("callback", &self.callback.is_some())
+            .field("token", &self.token)
+            .finish()
+    }
+}
+
+impl<'a> Progress<'a> {
+    /// No reporting, and never cancelled unless [`Self::token`] is cancelled.
+    #[must_use]
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Report updates to `callback`.
+    #[must_use]
+    pub fn new(callback: impl FnMut(Update) + 'a) -> Self {
+        Self {
+            callback: Some(Box::new(callback)),
+            token: CancelToken::new(),
+        }
+    }
+
+    /// Use `token` for cancellation.
+    #[must_use]
+    pub fn with_token(self, token: CancelToken) -> Self {
+        Self { token, ..self }
+    }
+
+    #[must_use]
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+
+    /// Report an update, failing if the operation should stop.
+    pub fn update(
+        &mut self,
+        stage: &'static str,
+        done: u64,
+        total: Option<u64>,
+    ) -> Result<(), Cancelled> {
+        if let Some(callback) = &mut self.callback {
+            callback(Update { stage, done, total });
+        }
+        self.check()
+    }
+
+    /// Fail if the operation should stop.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.token.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn updates_and_cancels() {
+        let token = CancelToken::new();
+        let mut updates = vec![];
+        let mut progress = Progress::new(|update| updates.push(update)).with_token(token.clone());
+        assert_eq!(progress.update("work", 1, Some(2)), Ok(()));
+        token.cancel();
+        assert_eq!(progress.update("work", 2, Some(2)), Err(Cancelled));
+        assert_eq!(progress.check(), Err(Cancelled));
+        drop(progress);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].fraction(), Some(0.5));
+    }
+}
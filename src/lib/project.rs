@@ -0,0 +1,764 @@
+//! Several libraries and game collections studied together.
+//!
+//! A [`Project`] is kept in a small TOML manifest listing its files, relative to the manifest,
+//! and the settings shared by them, which take the place of the [config](crate::config):
+//!
+//! ```toml
+//! name = "Sure wins"
+//! libraries = ["openings.lib", "sure-wins.txt"]
+//! games = ["tournament-2024/round-1.pos"]
+//!
+//! [settings]
+//! engine = "mcts"
+//! language = "sv"
+//! ```
+//!
+//! [`Project::load`] reads every file, and a [`PositionIndex`] finds a position in all of them.
+//! A [`Collection`] keeps libraries in memory with their index, for a process answering many
+//! queries, like a daemon preloading a directory.
+//! [`import_dir`] merges every game of a directory into one opening graph, and
+//! [`find_novelties`] tells where the games of a directory leave it. [`ForbiddenStats`] counts
+//! the games of a collection lost to forbidden moves.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::board::duplicates::position_key;
+use crate::board::evaluator::ForbiddenReason;
+use crate::board::{Board, BoardArr, MoveIndex, Point, Stone};
+use crate::config::Config;
+use crate::file_reader::{open_file_path, FileType};
+use crate::game::GameRecord;
+use crate::progress::Progress;
+use crate::theory::{self, Deviation};
+
+/// A set of files, see the [module documentation](self).
+#[derive(Clone, Default, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct Project {
+    pub name: String,
+    /// Libraries of analysis, relative to [`dir`](Self::dir).
+    pub libraries: Vec<PathBuf>,
+    /// Files of played games, relative to [`dir`](Self::dir).
+    pub games: Vec<PathBuf>,
+    pub settings: Config,
+    /// The directory of the manifest.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub dir: PathBuf,
+}
+
+impl Project {
+    /// An empty project kept in `dir`.
+    #[must_use]
+    pub fn new(name: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            dir: dir.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Every file, the libraries first, as given in the manifest.
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.libraries
+            .iter()
+            .chain(&self.games)
+            .map(PathBuf::as_path)
+    }
+
+    /// Where `file` of the project is.
+    #[must_use]
+    pub fn path(&self, file: &Path) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    /// Read every file of [`files`](Self::files), in the same order. A file that can't be read
+    /// doesn't stop the others.
+    #[must_use]
+    pub fn load(&self) -> Vec<crate::Result<Board>> {
+        self.files()
+            .map(|file| open_file_path(&self.path(file)))
+            .collect()
+    }
+
+    /// Read a manifest.
+    #[cfg(feature = "serde")]
+    pub fn open(manifest: &Path) -> crate::Result<Self> {
+        let mut project: Self = toml::from_str(&std::fs::read_to_string(manifest)?)?;
+        project.dir = manifest
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf);
+        Ok(project)
+    }
+
+    /// Write the manifest, see [`write_atomic`](crate::file_reader::write_atomic).
+    #[cfg(feature = "serde")]
+    pub fn save(&self, manifest: &Path) -> crate::Result<()> {
+        use std::io::Write;
+
+        let text = toml::to_string(self)?;
+        crate::file_reader::write_atomic(manifest, |writer| Ok(writer.write_all(text.as_bytes())?))
+    }
+}
+
+/// Where a position is reached in a project.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Location {
+    /// The index of the file in [`Project::files`].
+    pub file: usize,
+    /// Every node of the file reaching the position, possibly rotated or mirrored.
+    pub nodes: Vec<MoveIndex>,
+}
+
+/// The positions of several graphs, to find where a position is studied.
+#[derive(Clone, Default, Debug)]
+pub struct PositionIndex {
+    positions: BTreeMap<Vec<(Point, Stone)>, Vec<Location>>,
+}
+
+impl PositionIndex {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the files read with [`Project::load`], up to `max_depth` moves from
+    /// their roots. Files that could not be read are left out.
+    #[must_use]
+    pub fn of(loaded: &[crate::Result<Board>], max_depth: usize) -> Self {
+        let mut index = Self::new();
+        for (file, board) in loaded.iter().enumerate() {
+            if let Ok(board) = board {
+                index.add(file, board, max_depth);
+            }
+        }
+        index
+    }
+
+    /// Index the positions of `graph` up to `max_depth` moves from its roots as `file`.
+    pub fn add(&mut self, file: usize, graph: &Board, max_depth: usize) {
+        for (position, nodes) in graph.positions(max_depth) {
+            self.positions
+                .entry(position_key(&position))
+                .or_default()
+                .push(Location { file, nodes });
+        }
+    }
+
+    /// Where `position`, or a rotation or mirroring of it, is reached.
+    #[must_use]
+    pub fn find(&self, position: &BoardArr) -> &[Location] {
+        self.positions
+            .get(&position_key(position))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The amount of different positions.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+/// Positions deeper than this are not indexed by a [`Collection`].
+pub const INDEX_DEPTH: usize = 40;
+
+/// A library read into memory, with its name.
+#[derive(Debug)]
+pub struct Library {
+    pub name: String,
+    pub graph: Board,
+}
+
+/// Libraries kept in memory with an index of their positions, to look up positions in all of
+/// them without reading or walking them again.
+#[derive(Default, Debug)]
+pub struct Collection {
+    libraries: Vec<Library>,
+    index: PositionIndex,
+}
+
+impl Collection {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `graph` as `name`, returning its number.
+    pub fn add(&mut self, name: impl Into<String>, graph: Board) -> usize {
+        let library = self.libraries.len();
+        self.index.add(library, &graph, INDEX_DEPTH);
+        self.libraries.push(Library {
+            name: name.into(),
+            graph,
+        });
+        library
+    }
+
+    /// Read every library below `dir`, named by its path relative to `dir`, in the order of
+    /// their paths. The files that can't be read are returned with their errors instead.
+    pub fn preload(&mut self, dir: &Path) -> crate::Result<Vec<(PathBuf, crate::Error)>> {
+        let mut failed = vec![];
+        for path in library_files(dir)? {
+            match open_file_path(&path) {
+                Ok(graph) => {
+                    let name = path.strip_prefix(dir).unwrap_or(&path);
+                    self.add(name.display().to_string(), graph);
+                }
+                Err(error) => {
+                    tracing::warn!(?path, %error, "could not preload");
+                    failed.push((path, error));
+                }
+            }
+        }
+        Ok(failed)
+    }
+
+    #[must_use]
+    pub fn libraries(&self) -> &[Library] {
+        &self.libraries
+    }
+
+    /// Count the games of every library lost to a forbidden move.
+    #[must_use]
+    pub fn forbidden_stats(&self) -> ForbiddenStats {
+        let mut stats = ForbiddenStats::default();
+        for library in &self.libraries {
+            stats.add_graph(&library.graph);
+        }
+        stats
+    }
+
+    /// Where `position`, or a rotation or mirroring of it, is reached, the
+    /// [`file`](Location::file) being the number of the library.
+    #[must_use]
+    pub fn find(&self, position: &BoardArr) -> &[Location] {
+        self.index.find(position)
+    }
+
+    /// The amount of different positions indexed.
+    #[must_use]
+    pub fn positions(&self) -> usize {
+        self.index.len()
+    }
+
+    /// The amount of libraries.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.libraries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.libraries.is_empty()
+    }
+}
+
+/// How often games are lost to a forbidden move, see [`GameRecord::forbidden_loss`].
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct ForbiddenStats {
+    /// The games counted.
+    pub games: usize,
+    /// The games lost to a forbidden move.
+    pub losses: usize,
+    /// The losses for every reason.
+    pub reasons: BTreeMap<ForbiddenReason, usize>,
+    /// The losses at every move number, counting from 1.
+    pub moves: BTreeMap<usize, usize>,
+}
+
+impl ForbiddenStats {
+    /// Count `game`.
+    pub fn add(&mut self, game: &GameRecord) {
+        self.games += 1;
+        if let Some((ply, reason)) = game.forbidden_loss() {
+            self.losses += 1;
+            *self.reasons.entry(reason).or_default() += 1;
+            *self.moves.entry(ply + 1).or_default() += 1;
+        }
+    }
+
+    /// Count every game of `graph`, the lines from a root to the end of a branch.
+    pub fn add_graph(&mut self, graph: &Board) {
+        for moves in games(graph) {
+            self.add(&GameRecord::new(moves));
+        }
+    }
+}
+
+/// Like `40 games, 3 lost to a forbidden move (7.5%)`, followed by the losses by reason and by
+/// move number.
+impl fmt::Display for ForbiddenStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let share = match self.games {
+            0 => 0.0,
+            games => 100.0 * self.losses as f64 / games as f64,
+        };
+        write!(
+            f,
+            "{} games, {} lost to a forbidden move ({share:.1}%)",
+            self.games, self.losses
+        )?;
+        for (reason, count) in &self.reasons {
+            write!(f, "\n  {reason}: {count}")?;
+        }
+        for (number, count) in &self.moves {
+            write!(f, "\n  move {number}: {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`import_dir`] took from a file.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct FileImport {
+    /// Games merged into the graph.
+    pub games: usize,
+    /// Games left out as they were already in the graph.
+    pub duplicates: usize,
+}
+
+/// A file read by [`import_dir`].
+#[derive(Debug)]
+pub struct Imported {
+    pub path: PathBuf,
+    pub result: crate::Result<FileImport>,
+}
+
+/// Merge every game of the files below `dir` into `graph`, see [`import_dir_with_progress`].
+pub fn import_dir(dir: &Path, graph: &mut Board) -> crate::Result<Vec<Imported>> {
+    import_dir_with_progress(dir, graph, &mut Progress::none())
+}
+
+/// Merge every game of the files below `dir` into `graph`, reporting the files read as the
+/// `"import"` stage.
+///
+/// Every file [`open_file_path`] recognizes is read, in the order of their paths. A game is a
+/// line from a root to the end of a branch, a file of a single game has one. Games with the same
+/// moves as one already in `graph` are left out. A file that can't be read is reported without
+/// stopping the import, only an unreadable directory or a cancel does that.
+pub fn import_dir_with_progress(
+    dir: &Path,
+    graph: &mut Board,
+    progress: &mut Progress,
+) -> crate::Result<Vec<Imported>> {
+    let files = library_files(dir)?;
+    let mut seen = games(graph).into_iter().collect::<BTreeSet<_>>();
+    let total = files.len() as u64;
+    let mut imported = Vec::with_capacity(files.len());
+    for (i, path) in files.into_iter().enumerate() {
+        progress.update("import", i as u64, Some(total))?;
+        let result = open_file_path(&path).map(|file| {
+            let mut import = FileImport::default();
+            for moves in games(&file) {
+                if !seen.contains(&moves) {
+                    graph.add_line(&moves);
+                    seen.insert(moves);
+                    import.games += 1;
+                } else {
+                    import.duplicates += 1;
+                }
+            }
+            import
+        });
+        if let Err(error) = &result {
+            tracing::warn!(?path, %error, "could not import");
+        }
+        imported.push(Imported { path, result });
+    }
+    progress.update("import", total, Some(total))?;
+    Ok(imported)
+}
+
+/// The first move of a game leaving the opening graph.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Novelty {
+    /// The file of the game.
+    pub path: PathBuf,
+    pub moves: Vec<Point>,
+    /// The name of the opening of the game among the [standard ones](theory::Library::bundled).
+    pub opening: Option<String>,
+    /// The move, with the moves of the graph from the position before it.
+    pub deviation: Deviation,
+}
+
+/// What [`find_novelties`] found, the novelties sorted by opening.
+#[derive(Debug, Default)]
+pub struct NoveltyReport {
+    pub novelties: Vec<Novelty>,
+    /// Games that never left the graph.
+    pub known: usize,
+    /// Files that could not be read.
+    pub failed: Vec<(PathBuf, crate::Error)>,
+}
+
+/// The novelties listed under their openings, like `  a.pos: move 4 K11, known: J10`.
+impl fmt::Display for NoveltyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut opening = None;
+        for novelty in &self.novelties {
+            if opening != Some(&novelty.opening) {
+                opening = Some(&novelty.opening);
+                writeln!(
+                    f,
+                    "{}",
+                    novelty.opening.as_deref().unwrap_or("Other openings")
+                )?;
+            }
+            let deviation = &novelty.deviation;
+            write!(
+                f,
+                "  {}: move {} {}",
+                novelty.path.display(),
+                deviation.ply + 1,
+                deviation.played
+            )?;
+            if !deviation.known.is_empty() {
+                write!(
+                    f,
+                    ", known: {}",
+                    crate::board::notation::write_moves(&deviation.known)
+                )?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "{} novelties, {} games known to the end",
+            self.novelties.len(),
+            self.known
+        )
+    }
+}
+
+/// Find the novelty of every game of the files below `dir` against `graph`, see
+/// [`find_novelties_with_progress`].
+pub fn find_novelties(dir: &Path, graph: &Board) -> crate::Result<NoveltyReport> {
+    find_novelties_with_progress(dir, graph, &mut Progress::none())
+}
+
+/// Find the novelty of every game of the files below `dir` against `graph`, the first position
+/// of the game not in the graph, on any rotated or mirrored board. The files read are reported
+/// as the `"novelties"` stage.
+///
+/// Games are read like [`import_dir`] does. The novelties are sorted by opening, then by how
+/// early they come.
+pub fn find_novelties_with_progress(
+    dir: &Path,
+    graph: &Board,
+    progress: &mut Progress,
+) -> crate::Result<NoveltyReport> {
+    let files = library_files(dir)?;
+    let library = theory::Library::from_graph(graph);
+    let openings = theory::Library::bundled();
+    let mut report = NoveltyReport::default();
+    let total = files.len() as u64;
+    for (i, path) in files.into_iter().enumerate() {
+        progress.update("novelties", i as u64, Some(total))?;
+        let file = match open_file_path(&path) {
+            Ok(file) => file,
+            Err(error) => {
+                report.failed.push((path, error));
+                continue;
+            }
+        };
+        for moves in games(&file) {
+            let game = GameRecord::new(moves);
+            let Some(deviation) = theory::match_game_with(&game, &library).deviation else {
+                report.known += 1;
+                continue;
+            };
+            report.novelties.push(Novelty {
+                path: path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+                opening: theory::match_game_with(&game, &openings).name,
+                moves: game.moves,
+                deviation,
+            });
+        }
+    }
+    progress.update("novelties", total, Some(total))?;
+    report.novelties.sort_by(|a, b| {
+        // the unnamed openings last
+        (a.opening.is_none(), &a.opening, a.deviation.ply, &a.path).cmp(&(
+            b.opening.is_none(),
+            &b.opening,
+            b.deviation.ply,
+            &b.path,
+        ))
+    });
+    Ok(report)
+}
+
+/// The files below `dir` of a type [`open_file_path`] recognizes, by their paths.
+fn library_files(dir: &Path) -> crate::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if FileType::new(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The moves of every line of `graph` from a root to the end of a branch. A branch reached
+/// through several parents is listed once, with the moves of [`Board::as_board`].
+pub(crate) fn games(graph: &Board) -> Vec<Vec<Point>> {
+    let mut games = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = graph.roots().to_vec();
+    while let Some(index) = stack.pop() {
+        if !visited.insert(index.node_index()) {
+            continue;
+        }
+        let children = graph.get_children(&index);
+        if children.is_empty() {
+            match graph.as_board(&index) {
+                Ok((_, moves)) if !moves.is_empty() => games.push(moves),
+                _ => {}
+            }
+        }
+        stack.extend(children);
+    }
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardMarker;
+    use crate::p;
+
+    #[test]
+    fn positions_across_files() {
+        let mut first = Board::new();
+        let i9 = first.add_line(&p![[H, 8], [I, 9]]);
+        let mut second = Board::new();
+        // the same position, mirrored
+        let g9 = second.add_line(&p![[H, 8], [G, 9]]);
+        second.add_line(&p![[H, 8], [J, 10]]);
+        let loaded = vec![
+            Ok(first),
+            Err(crate::errors::ParseError::NotSupported.into()),
+            Ok(second),
+        ];
+        let index = PositionIndex::of(&loaded, 4);
+
+        let mut position = BoardArr::new(15);
+        position.set_point(p![H, 8], Stone::Black);
+        position.set_point(p![G, 9], Stone::White);
+        let found = index.find(&position);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].file, &found[0].nodes[..]), (0, &[i9][..]));
+        assert_eq!((found[1].file, &found[1].nodes[..]), (2, &[g9][..]));
+        position.set_point(p![A, 1], Stone::Black);
+        assert!(index.find(&position).is_empty());
+        // the empty board, H8, H8 I9 and H8 J10
+        assert_eq!(index.len(), 4);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn manifest() {
+        let dir = std::env::temp_dir().join(format!("renju-project-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut project = Project::new("Sure wins", &dir);
+        project.libraries.push("openings.txt".into());
+        project.games.push("missing.pos".into());
+        project.settings.language = Some(crate::i18n::Language::Swedish);
+        let mut openings = Board::new();
+        openings.add_line(&p![[H, 8], [I, 9]]);
+        crate::file_reader::save_file_path(&openings, &dir.join("openings.txt")).unwrap();
+
+        let manifest = dir.join("project.toml");
+        project.save(&manifest).unwrap();
+        let text = std::fs::read_to_string(&manifest).unwrap();
+        assert!(text.contains("libraries = [\"openings.txt\"]"), "{text}");
+        assert!(!text.contains("engine"), "{text}");
+        let opened = Project::open(&manifest).unwrap();
+        assert_eq!(opened, project);
+        let loaded = opened.load();
+        assert!(loaded[0].is_ok() && loaded[1].is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn imports_directory() {
+        let dir = std::env::temp_dir().join(format!("renju-import-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("round-2")).unwrap();
+        // H8 I9 J10 twice, once as a game and once in a library with H8 I9 K11
+        std::fs::write(dir.join("a.pos"), [3, 112, 98, 84]).unwrap();
+        let mut library = Board::new();
+        library.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        library.add_line(&p![[H, 8], [I, 9], [K, 11]]);
+        crate::file_reader::save_file_path(&library, &dir.join("round-2/b.txt")).unwrap();
+        std::fs::write(dir.join("broken.txt"), "not a library").unwrap();
+        std::fs::write(dir.join("notes.md"), "skipped").unwrap();
+
+        let mut graph = Board::new();
+        graph.add_line(&p![[H, 8], [I, 9], [K, 11]]);
+        let mut stages = vec![];
+        let mut progress = Progress::new(|update| stages.push((update.done, update.total)));
+        let imported = import_dir_with_progress(&dir, &mut graph, &mut progress).unwrap();
+        drop(progress);
+        let names = imported
+            .iter()
+            .map(|i| i.path.strip_prefix(&dir).unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.pos", "broken.txt", "round-2/b.txt"]);
+        let results = imported
+            .iter()
+            .map(|i| i.result.as_ref().ok().copied())
+            .collect::<Vec<_>>();
+        let import = |games, duplicates| Some(FileImport { games, duplicates });
+        assert_eq!(results, [import(1, 0), None, import(0, 2)]);
+        assert_eq!(stages.last(), Some(&(3, Some(3))));
+        assert_eq!(games(&graph).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn games_of_transpositions() {
+        let mut graph = Board::new();
+        let mut index = graph.get_root();
+        for _ in 0..20 {
+            // two moves reaching the same node
+            let left = graph.insert_move(index, BoardMarker::new(p![H, 8], Stone::Black));
+            let right = graph.insert_move(index, BoardMarker::new(p![I, 9], Stone::Black));
+            index = graph.insert_move(left, BoardMarker::new(p![J, 10], Stone::White));
+            graph.add_edge(&right, &index).unwrap();
+        }
+        assert_eq!(games(&graph).len(), 1);
+    }
+
+    #[test]
+    fn novelties() {
+        let dir = std::env::temp_dir().join(format!("renju-novelties-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // kagetsu, mirrored, left with move 4, and the graph followed to its end
+        std::fs::write(dir.join("a.pos"), [4, 112, 97, 96, 210]).unwrap();
+        let mut library = Board::new();
+        library.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        library.add_line(&p![[H, 8], [I, 9], [L, 12]]);
+        crate::file_reader::save_file_path(&library, &dir.join("b.txt")).unwrap();
+        std::fs::write(dir.join("broken.txt"), "not a library").unwrap();
+
+        let mut graph = Board::new();
+        graph.add_line(&p![[H, 8], [H, 9], [I, 9], [J, 10]]);
+        graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        let report = find_novelties(&dir, &graph).unwrap();
+        assert_eq!(report.known, 1);
+        assert_eq!(report.failed.len(), 1);
+        let novelties = report
+            .novelties
+            .iter()
+            .map(|n| {
+                (
+                    n.path.to_str().unwrap(),
+                    n.opening.as_deref(),
+                    n.deviation.ply,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            novelties,
+            [
+                ("a.pos", Some("Kagetsu"), 3),
+                ("b.txt", Some("diagonal opening"), 2)
+            ]
+        );
+        assert_eq!(report.novelties[0].deviation.known, p![[F, 10]]);
+        let text = report.to_string();
+        assert!(
+            text.starts_with("Kagetsu\n  a.pos: move 4 A1, known: F10\n"),
+            "{text}"
+        );
+        assert!(
+            text.ends_with("2 novelties, 1 games known to the end"),
+            "{text}"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn forbidden_stats() {
+        let mut graph = Board::new();
+        // black's G8 is a double three, unless G11 makes it a four-three, and J8 an overline
+        let start = p![
+            [H, 8],
+            [A, 1],
+            [I, 8],
+            [A, 3],
+            [G, 9],
+            [A, 5],
+            [G, 10],
+            [A, 7]
+        ];
+        let branch = graph.add_line(&start);
+        graph.add_variation(branch, &p![[G, 8]]);
+        graph.add_variation(branch, &p![[G, 11], [A, 9], [G, 8]]);
+        graph.add_variation(
+            branch,
+            &p![[K, 8], [A, 9], [L, 8], [A, 11], [M, 8], [A, 13], [J, 8]],
+        );
+        graph.add_variation(branch, &p![[A, 9]]);
+        let mut collection = Collection::new();
+        collection.add("games", graph);
+
+        let stats = collection.forbidden_stats();
+        assert_eq!((stats.games, stats.losses), (4, 2));
+        assert_eq!(stats.reasons.get(&ForbiddenReason::DoubleThree), Some(&1));
+        assert_eq!(stats.reasons.get(&ForbiddenReason::Overline), Some(&1));
+        assert_eq!(stats.moves.get(&9), Some(&1));
+        assert_eq!(stats.moves.get(&15), Some(&1));
+        assert!(stats
+            .to_string()
+            .starts_with("4 games, 2 lost to a forbidden move (50.0%)\n  overline: 1\n"));
+    }
+
+    #[test]
+    fn preloads() {
+        let dir = std::env::temp_dir().join(format!("renju-preload-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sure-wins")).unwrap();
+        let mut library = Board::new();
+        library.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        crate::file_reader::save_file_path(&library, &dir.join("sure-wins/a.txt")).unwrap();
+        std::fs::write(dir.join("b.pos"), [2, 112, 96]).unwrap();
+        std::fs::write(dir.join("broken.txt"), "not a library").unwrap();
+
+        let mut collection = Collection::new();
+        let failed = collection.preload(&dir).unwrap();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].0.ends_with("broken.txt"));
+        let names = collection
+            .libraries()
+            .iter()
+            .map(|library| library.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["b.pos", "sure-wins/a.txt"]);
+
+        let mut position = BoardArr::new(15);
+        position.set_point(p![H, 8], Stone::Black);
+        let found = collection.find(&position);
+        assert_eq!(found.iter().map(|l| l.file).collect::<Vec<_>>(), [0, 1]);
+        assert!(collection.positions() >= 4);
+        assert!(collection.preload(&dir.join("missing")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,663 @@
+//! Puzzles, a position with a side to move and a tree of solutions.
+//!
+//! A solution tree alternates between the solver's moves and the opponent's replies. On the
+//! solver's turn every candidate is either correct, or a tempting wrong move followed by its
+//! refutation.
+//!
+//! [`Puzzle::verify`] checks a VCF puzzle with the [solver](crate::solver), that its solution wins
+//! and that nothing else does. [`generate`] finds VCF puzzles and, with threes as well, VCT
+//! puzzles.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::board::{Board, BoardArr, MoveIndex, Point, Stone};
+use crate::errors::ParseError;
+use crate::limits::{Limits, Outcome};
+use crate::motif::{self, Motif};
+use crate::solver::{self, vct::VctTree};
+
+pub mod pack;
+
+/// How hard a puzzle is.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Advanced,
+    Expert,
+}
+
+/// A move in a solution tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PuzzleMove {
+    pub point: Point,
+    /// Whether this move is part of the solution. Opponent replies are always correct.
+    pub correct: bool,
+    pub comment: Option<String>,
+    /// Moves following this one.
+    pub continuations: Vec<PuzzleMove>,
+}
+
+impl PuzzleMove {
+    #[must_use]
+    pub fn new(point: Point, correct: bool) -> Self {
+        Self {
+            point,
+            correct,
+            comment: None,
+            continuations: vec![],
+        }
+    }
+
+    /// A single line of correct moves, each one the only continuation of the previous.
+    #[must_use]
+    pub fn line(points: &[Point]) -> Vec<PuzzleMove> {
+        points.iter().rev().fold(vec![], |continuations, point| {
+            vec![PuzzleMove::new(*point, true).with_continuations(continuations)]
+        })
+    }
+
+    #[must_use]
+    pub fn with_continuations(mut self, continuations: Vec<PuzzleMove>) -> Self {
+        self.continuations = continuations;
+        self
+    }
+}
+
+/// The result of checking an attempt against a [`Puzzle`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Verdict {
+    /// The attempt follows the solution all the way to the end.
+    Solved,
+    /// The attempt is correct so far, `next` is what the solution continues with.
+    Correct { next: Vec<Point> },
+    /// The move at `ply` is wrong. `refutation` is the opponent reply showing why, if known.
+    Wrong {
+        ply: usize,
+        refutation: Option<Point>,
+    },
+    /// The opponent move at `ply` is not covered by the solution tree.
+    Unknown { ply: usize },
+}
+
+/// A problem, a start position with a side to move and a solution tree.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Puzzle {
+    pub position: BoardArr,
+    pub to_move: Stone,
+    /// Candidate moves for `to_move`, correct ones and refuted ones.
+    pub solution: Vec<PuzzleMove>,
+    pub difficulty: Option<Difficulty>,
+    /// What the puzzle trains.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub themes: Vec<Motif>,
+}
+
+impl Puzzle {
+    #[must_use]
+    pub fn new(position: BoardArr, to_move: Stone, solution: Vec<PuzzleMove>) -> Self {
+        Self {
+            position,
+            to_move,
+            solution,
+            difficulty: None,
+            themes: vec![],
+        }
+    }
+
+    /// A stable key identifying the position and side to move, used to track puzzles across runs.
+    #[must_use]
+    pub fn key(&self) -> String {
+        let stones = self
+            .position
+            .iter()
+            .filter(|m| !m.color.is_empty())
+            .flat_map(|m| [m.point.x as u8, m.point.y as u8, m.color as u8]);
+        crate::util::stable_hash(std::iter::once(self.to_move as u8).chain(stones))
+    }
+
+    /// Build a puzzle from the subtree starting at `start` in `graph`.
+    ///
+    /// The position is the board at `start`, the themes its [motifs](motif::motifs_at). On the solver's turn, children with the mark flag set
+    /// are the solution, unmarked children are wrong moves and their children the refutations.
+    pub fn from_graph(graph: &Board, start: MoveIndex) -> Result<Self, ParseError> {
+        let (position, _) = graph.as_board(&start)?;
+        let to_move = match graph.color_at(start) {
+            Some(color) if !color.is_empty() => color.opposite(),
+            _ => Stone::Black,
+        };
+
+        fn walk(graph: &Board, index: MoveIndex, solver: bool) -> Vec<PuzzleMove> {
+            graph
+                .get_children(&index)
+                .into_iter()
+                .filter_map(|child| {
+                    let marker = graph.get_move(child)?;
+                    if marker.point.is_null {
+                        return None;
+                    }
+                    Some(PuzzleMove {
+                        point: marker.point,
+                        correct: !solver || marker.command.is_mark(),
+                        comment: marker
+                            .multiline_comment
+                            .clone()
+                            .or_else(|| marker.oneline_comment.clone()),
+                        continuations: walk(graph, child, !solver),
+                    })
+                })
+                .collect()
+        }
+
+        let solution = walk(graph, start, true);
+        if !solution.iter().any(|m| m.correct) {
+            return Err(ParseError::Other(format!(
+                "no marked solution below {:?}",
+                start
+            )));
+        }
+        let mut puzzle = Self::new(position, to_move, solution);
+        puzzle.themes = motif::motifs_at(graph, start);
+        Ok(puzzle)
+    }
+
+    /// The first correct move of the solver and the first reply of the opponent, to the end.
+    #[must_use]
+    pub fn main_line(&self) -> Vec<Point> {
+        let mut line = vec![];
+        let mut candidates = &self.solution;
+        while let Some(next) = candidates.iter().find(|m| m.correct) {
+            line.push(next.point);
+            candidates = &next.continuations;
+        }
+        line
+    }
+
+    /// Check the [main line](Self::main_line) with the solver, using at most `max_depth` fours.
+    ///
+    /// On every turn of the solver the move of the solution has to start a VCF, and any other move
+    /// starting one is a cook on the first turn or a dual later. The replies of the opponent are
+    /// taken as they are. Puzzles won other than by fours can't be confirmed.
+    #[must_use]
+    pub fn verify(&self, max_depth: usize) -> Verification {
+        let mut board = self.position.clone();
+        let mut issues = vec![];
+        for (ply, point) in self.main_line().into_iter().enumerate() {
+            if ply % 2 == 1 {
+                board.set_point(point, self.to_move.opposite());
+                continue;
+            }
+            let mut wins =
+                solver::vcf_first_moves(&board, self.to_move, max_depth.saturating_sub(ply / 2))
+                    .into_iter()
+                    .filter_map(|line| line.first().copied())
+                    .collect::<Vec<_>>();
+            wins.sort();
+            wins.dedup();
+            if !wins.contains(&point) {
+                issues.push(Issue::NoWin { ply, point });
+                break;
+            }
+            wins.retain(|win| *win != point);
+            if !wins.is_empty() {
+                issues.push(match ply {
+                    0 => Issue::Cook { moves: wins },
+                    _ => Issue::Dual { ply, moves: wins },
+                });
+            }
+            board.set_point(point, self.to_move);
+        }
+        Verification { issues }
+    }
+
+    /// Whether the puzzle trains any of `motifs`, or `motifs` is empty.
+    #[must_use]
+    pub fn has_any(&self, motifs: &[Motif]) -> bool {
+        motifs.is_empty() || self.themes.iter().any(|theme| motifs.contains(theme))
+    }
+
+    /// Check an attempt, a sequence of alternating solver and opponent moves.
+    #[must_use]
+    pub fn check_attempt(&self, attempt: &[Point]) -> Verdict {
+        let mut candidates = &self.solution;
+        for (ply, point) in attempt.iter().enumerate() {
+            let solver = ply % 2 == 0;
+            let Some(found) = candidates.iter().find(|m| &m.point == point) else {
+                return if solver {
+                    Verdict::Wrong {
+                        ply,
+                        refutation: None,
+                    }
+                } else {
+                    Verdict::Unknown { ply }
+                };
+            };
+            if !found.correct {
+                return Verdict::Wrong {
+                    ply,
+                    refutation: found.continuations.first().map(|m| m.point),
+                };
+            }
+            candidates = &found.continuations;
+        }
+        // a finished attempt has to end with a solver move
+        if candidates.is_empty() && attempt.len() % 2 == 1 {
+            return Verdict::Solved;
+        }
+        Verdict::Correct {
+            next: candidates
+                .iter()
+                .filter(|m| m.correct)
+                .map(|m| m.point)
+                .collect(),
+        }
+    }
+}
+
+/// What is wrong with the solution of a [`Puzzle`], see [`Puzzle::verify`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Issue {
+    /// The move at `ply` does not win by fours.
+    NoWin { ply: usize, point: Point },
+    /// Other first moves win too.
+    Cook { moves: Vec<Point> },
+    /// Other moves win at `ply` too, a later turn of the solver.
+    Dual { ply: usize, moves: Vec<Point> },
+}
+
+/// Like `move 3 K8 does not win` or `also wins with G8 J10`, counting moves from 1.
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let moves = |moves: &[Point]| {
+            moves
+                .iter()
+                .map(Point::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match self {
+            Issue::NoWin { ply, point } => write!(f, "move {} {point} does not win", ply + 1),
+            Issue::Cook { moves: others } => write!(f, "cook, also wins with {}", moves(others)),
+            Issue::Dual { ply, moves: others } => {
+                write!(f, "dual at move {}, also {}", ply + 1, moves(others))
+            }
+        }
+    }
+}
+
+/// The result of [`Puzzle::verify`].
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Verification {
+    pub issues: Vec<Issue>,
+}
+
+impl Verification {
+    /// Whether the solution wins.
+    #[must_use]
+    pub fn is_sound(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, Issue::NoWin { .. }))
+    }
+
+    /// Whether the solution is the only win.
+    #[must_use]
+    pub fn is_unique(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Like `ok` or `cook, also wins with G8; dual at move 3, also K7`.
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.issues.is_empty() {
+            return write!(f, "ok");
+        }
+        let issues = self.issues.iter().map(Issue::to_string).collect::<Vec<_>>();
+        write!(f, "{}", issues.join("; "))
+    }
+}
+
+/// Options for [`generate`].
+#[derive(Clone, Debug)]
+pub struct GenerateOptions {
+    /// Maximum amount of fours in a solution.
+    pub max_depth: usize,
+    /// Minimum amount of threats in a solution, to skip trivial positions.
+    pub min_depth: usize,
+    /// Maximum amount of fours and threes in a VCT solution, `0` to only look for VCFs. A VCT is
+    /// looked for when there is no VCF, and to make sure no other first move wins as fast with
+    /// threes.
+    pub vct_depth: usize,
+    /// Nodes the VCT search may use in every position, positions it can't decide are skipped.
+    pub vct_nodes: u64,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            min_depth: 2,
+            vct_depth: 3,
+            vct_nodes: 20_000,
+        }
+    }
+}
+
+/// Scan every position in `graph` for a VCF or VCT that only one first move wins with.
+///
+/// Every node with a stone is checked for the side to move after it, so both games and libraries
+/// work. A VCT puzzle has every defence of the opponent in its solution tree. A node reached
+/// through several parents is only checked once.
+#[tracing::instrument(skip(graph))]
+pub fn generate(graph: &Board, options: &GenerateOptions) -> Result<Vec<Puzzle>, ParseError> {
+    let mut puzzles = vec![];
+    let mut visited = HashSet::new();
+    let mut stack = graph.roots().to_vec();
+    while let Some(index) = stack.pop() {
+        if !visited.insert(index.node_index()) {
+            continue;
+        }
+        stack.extend(graph.get_children(&index));
+        let Some(color) = graph.color_at(index) else {
+            continue;
+        };
+        if color.is_empty() {
+            continue;
+        }
+        let to_move = color.opposite();
+        let (position, _) = graph.as_board(&index)?;
+        let lines = solver::vcf_first_moves(&position, to_move, options.max_depth);
+        if lines.len() > 1 {
+            continue;
+        }
+        // only wins as short as the VCF count, longer ones just add threats in front of it
+        let shortest = lines
+            .first()
+            .map_or(options.vct_depth, |line| line.len().div_ceil(2) - 1)
+            .min(options.vct_depth);
+        let Outcome::Known(trees) = shortest_vcts(&position, to_move, shortest, options.vct_nodes)
+        else {
+            tracing::debug!(path = graph.describe_path(index), "vct search gave up");
+            continue;
+        };
+        let (solution, threats, motif) = match (&lines[..], &trees[..]) {
+            ([line], _) if trees.iter().all(|tree| tree.attack == line[0]) => {
+                (PuzzleMove::line(line), line.len().div_ceil(2), Motif::Vcf)
+            }
+            ([], [tree]) => (vec![vct_move(tree)], tree.depth(), Motif::Vct),
+            _ => continue,
+        };
+        if threats < options.min_depth {
+            continue;
+        }
+        tracing::debug!(path = graph.describe_path(index), %motif, "found puzzle");
+        let mut puzzle = Puzzle::new(position, to_move, solution);
+        puzzle.difficulty = Some(match threats {
+            ..=3 => Difficulty::Beginner,
+            4..=5 => Difficulty::Intermediate,
+            6..=8 => Difficulty::Advanced,
+            _ => Difficulty::Expert,
+        });
+        puzzle.themes.push(motif);
+        puzzles.push(puzzle);
+    }
+    Ok(puzzles)
+}
+
+/// The first moves of the shortest VCTs using at most `max_depth` threats, searching deeper only
+/// when none is found.
+fn shortest_vcts(
+    position: &BoardArr,
+    to_move: Stone,
+    max_depth: usize,
+    nodes: u64,
+) -> Outcome<Vec<VctTree>> {
+    for depth in 1..=max_depth {
+        let limits = Limits {
+            depth: depth as u32,
+            nodes: Some(nodes),
+            ..Limits::default()
+        };
+        match solver::vct::vct_first_moves_with_limits(position, to_move, &limits) {
+            Outcome::Known(trees) if trees.is_empty() => {}
+            found => return found,
+        }
+    }
+    Outcome::Known(vec![])
+}
+
+/// The solution tree of a VCT, every defence followed by the attack winning against it.
+fn vct_move(tree: &VctTree) -> PuzzleMove {
+    PuzzleMove::new(tree.attack, true).with_continuations(
+        tree.replies
+            .iter()
+            .map(|(reply, tree)| {
+                PuzzleMove::new(*reply, true).with_continuations(vec![vct_move(tree)])
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardMarker;
+    use crate::file_reader::renlib::CommandVariant;
+    use crate::p;
+
+    fn puzzle() -> Puzzle {
+        Puzzle::new(
+            BoardArr::new(15),
+            Stone::Black,
+            vec![
+                PuzzleMove::new(p![H, 8], true).with_continuations(vec![
+                    PuzzleMove::new(p![I, 9], true)
+                        .with_continuations(vec![PuzzleMove::new(p![J, 10], true)]),
+                    PuzzleMove::new(p![G, 7], true)
+                        .with_continuations(vec![PuzzleMove::new(p![F, 6], true)]),
+                ]),
+                PuzzleMove::new(p![A, 1], false)
+                    .with_continuations(vec![PuzzleMove::new(p![H, 8], true)]),
+            ],
+        )
+    }
+
+    #[test]
+    fn attempts() {
+        let puzzle = puzzle();
+        assert_eq!(
+            puzzle.check_attempt(&p![[H, 8], [I, 9], [J, 10]]),
+            Verdict::Solved
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[H, 8], [G, 7]]),
+            Verdict::Correct {
+                next: vec![p![F, 6]]
+            }
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[A, 1]]),
+            Verdict::Wrong {
+                ply: 0,
+                refutation: Some(p![H, 8])
+            }
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[B, 2]]),
+            Verdict::Wrong {
+                ply: 0,
+                refutation: None
+            }
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[H, 8], [B, 2]]),
+            Verdict::Unknown { ply: 1 }
+        );
+    }
+
+    #[test]
+    fn from_graph() {
+        let mut graph = Board::new();
+        let root = graph.get_root();
+        let start = graph.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        let mut marked = BoardMarker::new(p![I, 9], Stone::White);
+        *marked.command |= CommandVariant::MARK;
+        let solution = graph.insert_move(start, marked.clone());
+        let reply = graph.insert_move(solution, BoardMarker::new(p![J, 10], Stone::Black));
+        marked.point = p![K, 11];
+        graph.insert_move(reply, marked);
+        let wrong = graph.insert_move(start, BoardMarker::new(p![G, 7], Stone::White));
+        graph.insert_move(wrong, BoardMarker::new(p![I, 9], Stone::Black));
+        motif::add(&mut graph, start, &Motif::Defense);
+
+        let puzzle = Puzzle::from_graph(&graph, start).unwrap();
+        assert_eq!(puzzle.to_move, Stone::White);
+        assert_eq!(puzzle.themes, [Motif::Defense]);
+        assert!(puzzle.has_any(&[Motif::Vcf, Motif::Defense]));
+        assert!(!puzzle.has_any(&[Motif::Vcf]));
+        assert_eq!(
+            puzzle.position.get_point(p![H, 8]).unwrap().color,
+            Stone::Black
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[I, 9], [J, 10]]),
+            Verdict::Correct {
+                next: vec![p![K, 11]]
+            }
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[I, 9], [J, 10], [K, 11]]),
+            Verdict::Solved
+        );
+        assert_eq!(
+            puzzle.check_attempt(&p![[G, 7]]),
+            Verdict::Wrong {
+                ply: 0,
+                refutation: Some(p![I, 9])
+            }
+        );
+
+        assert!(Puzzle::from_graph(&graph, wrong).is_err());
+        graph.set_color_override(start, Some(Stone::White));
+        let puzzle = Puzzle::from_graph(&graph, start).unwrap();
+        assert_eq!(puzzle.to_move, Stone::Black);
+    }
+
+    #[test]
+    fn verify() {
+        let pack = "black=H8,I8,J8 white=H9,I9 move=black solution=K8,G8,L8\n\
+            black=J3,A1,A15,O1,O15,N8 white=J4,J5,J6,G7,H7 solution=J7,J8,I7\n\
+            black=J3,A1,A15,O1,O15,N8 white=J4,J5,J6,G7,H7 solution=K7\n";
+        let puzzles = pack::parse(pack.as_bytes()).unwrap();
+        let [open_three, vcf, wrong] = &puzzles[..] else {
+            panic!("expected three puzzles, got {puzzles:?}");
+        };
+        let verified = open_three.verify(8);
+        assert_eq!(
+            verified.issues,
+            [Issue::Cook {
+                moves: vec![p![G, 8]]
+            }]
+        );
+        assert!(verified.is_sound() && !verified.is_unique());
+        assert_eq!(verified.to_string(), "cook, also wins with G8");
+        assert!(vcf.verify(8).is_unique());
+        let verified = wrong.verify(8);
+        assert!(!verified.is_sound());
+        assert_eq!(verified.to_string(), "move 1 K7 does not win");
+    }
+
+    #[test]
+    fn generate_from_game() {
+        let mut graph = Board::new();
+        let mut index = graph.get_root();
+        let mut stone = Stone::White;
+        let mut line = vec![];
+        for point in p![
+            [J, 3],
+            [J, 4],
+            [A, 1],
+            [J, 5],
+            [A, 15],
+            [J, 6],
+            [O, 1],
+            [G, 7],
+            [O, 15],
+            [H, 7],
+            [N, 8]
+        ] {
+            stone = stone.opposite();
+            index = graph.insert_move(index, BoardMarker::new(point, stone));
+            line.push(index);
+        }
+        // O15 before O1 transposes into the same H7
+        let o15 = graph.insert_move(line[5], BoardMarker::new(p![O, 15], Stone::Black));
+        let g7 = graph.insert_move(o15, BoardMarker::new(p![G, 7], Stone::White));
+        let o1 = graph.insert_move(g7, BoardMarker::new(p![O, 1], Stone::Black));
+        graph.add_edge(&o1, &line[9]).unwrap();
+
+        // only the last position has a VCF, the four-three on J7
+        let puzzles = generate(&graph, &GenerateOptions::default()).unwrap();
+        let [puzzle] = &puzzles[..] else {
+            panic!("expected one puzzle, got {puzzles:?}");
+        };
+        assert_eq!(puzzle.to_move, Stone::White);
+        assert_eq!(puzzle.difficulty, Some(Difficulty::Beginner));
+        assert_eq!(
+            puzzle.check_attempt(&p![[J, 7], [J, 8]]),
+            Verdict::Correct {
+                next: vec![p![I, 7]]
+            }
+        );
+    }
+
+    #[test]
+    fn generate_vct() {
+        let mut graph = Board::new();
+        let mut index = graph.get_root();
+        let mut stone = Stone::White;
+        for point in p![
+            [A, 1],
+            [F, 8],
+            [A, 15],
+            [G, 8],
+            [O, 1],
+            [H, 10],
+            [O, 15],
+            [H, 11],
+            [N, 2]
+        ] {
+            stone = stone.opposite();
+            index = graph.insert_move(index, BoardMarker::new(point, stone));
+        }
+
+        // H8 is a double three, with no four to win by VCF
+        let puzzles = generate(&graph, &GenerateOptions::default()).unwrap();
+        let [puzzle] = &puzzles[..] else {
+            panic!("expected one puzzle, got {puzzles:?}");
+        };
+        assert_eq!(puzzle.themes, [Motif::Vct]);
+        let Verdict::Correct { next } = puzzle.check_attempt(&p![[H, 8]]) else {
+            panic!("H8 should be correct");
+        };
+        assert!(next.len() > 1, "{next:?}");
+        assert_eq!(
+            puzzle.check_attempt(&p![[H, 8], [H, 9]]),
+            Verdict::Correct {
+                next: vec![p![E, 8]]
+            }
+        );
+
+        let options = GenerateOptions {
+            vct_depth: 0,
+            ..GenerateOptions::default()
+        };
+        assert!(generate(&graph, &options).unwrap().is_empty());
+    }
+}
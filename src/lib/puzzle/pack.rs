@@ -0,0 +1,210 @@
+//! Puzzle packs, the plain text format puzzles are exchanged in between community sites.
+//!
+//! A pack holds a puzzle a line, as fields separated by spaces:
+//!
+//! ```text
+//! # VCF problems, week 12
+//! black=H8,I8,J8 white=H9,I9,J9 move=black solution=K8,G8,L8 themes=vcf difficulty=beginner
+//! ```
+//!
+//! `black` and `white` are the stones of the position and `solution` the moves of the solution,
+//! alternating between the side to move and the replies of the opponent, all in board notation.
+//! `move` is the side to move, black if left out and both sides have as many stones, white
+//! otherwise. `themes` are the [`Motif`]s of the puzzle. `themes` and `difficulty` are optional.
+//! Fields the format doesn't know are skipped and lines starting with `#` are comments.
+//!
+//! Only the main line of a [`Puzzle`] is written, the first correct move on every turn of the
+//! solver and the first reply of the opponent.
+
+use std::io::{BufRead, Write};
+
+use super::{Difficulty, Puzzle, PuzzleMove};
+use crate::board::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+use crate::motif::Motif;
+
+/// Read every puzzle of a pack.
+pub fn parse(reader: impl BufRead) -> Result<Vec<Puzzle>, ParseError> {
+    let mut puzzles = vec![];
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let puzzle = parse_puzzle(line)
+            .map_err(|message| ParseError::Other(format!("line {}: {message}", number + 1)))?;
+        puzzles.push(puzzle);
+    }
+    Ok(puzzles)
+}
+
+fn parse_puzzle(line: &str) -> Result<Puzzle, String> {
+    let mut position = BoardArr::new(15);
+    let mut stones = [0, 0];
+    let mut to_move = None;
+    let mut solution = None;
+    let mut themes = vec![];
+    let mut difficulty = None;
+    for field in line.split_whitespace() {
+        let Some((name, value)) = field.split_once('=') else {
+            return Err(format!("expected `name=value`, found {field:?}"));
+        };
+        match name {
+            "black" | "white" => {
+                let stone = match name {
+                    "black" => Stone::Black,
+                    _ => Stone::White,
+                };
+                for point in points(value)? {
+                    if !position
+                        .get_point(point)
+                        .is_some_and(|m| m.color.is_empty())
+                    {
+                        return Err(format!("{point} is given twice"));
+                    }
+                    position.set_point(point, stone);
+                    stones[usize::from(stone == Stone::White)] += 1;
+                }
+            }
+            "move" => {
+                to_move = Some(match value {
+                    "black" => Stone::Black,
+                    "white" => Stone::White,
+                    _ => return Err(format!("unknown side to move {value:?}")),
+                });
+            }
+            "solution" => solution = Some(points(value)?),
+            "themes" => {
+                themes = value
+                    .split(',')
+                    .filter(|theme| !theme.is_empty())
+                    .map(|theme| theme.parse::<Motif>().map_err(|e| e.to_string()))
+                    .collect::<Result<_, _>>()?;
+            }
+            "difficulty" => {
+                difficulty = Some(match value {
+                    "beginner" => Difficulty::Beginner,
+                    "intermediate" => Difficulty::Intermediate,
+                    "advanced" => Difficulty::Advanced,
+                    "expert" => Difficulty::Expert,
+                    _ => return Err(format!("unknown difficulty {value:?}")),
+                });
+            }
+            _ => {}
+        }
+    }
+    let solution = solution
+        .filter(|solution| !solution.is_empty())
+        .ok_or("no solution")?;
+    let to_move = to_move.unwrap_or(match stones[0] == stones[1] {
+        true => Stone::Black,
+        false => Stone::White,
+    });
+    let mut puzzle = Puzzle::new(position, to_move, PuzzleMove::line(&solution));
+    puzzle.themes = themes;
+    puzzle.difficulty = difficulty;
+    Ok(puzzle)
+}
+
+/// Comma separated points in board notation.
+fn points(value: &str) -> Result<Vec<Point>, String> {
+    value
+        .split(',')
+        .filter(|point| !point.is_empty())
+        .map(|point| point.parse().map_err(|e: ParseError| e.to_string()))
+        .collect()
+}
+
+/// Write `puzzles` as a pack, see the [module documentation](self).
+pub fn write<'a>(
+    puzzles: impl IntoIterator<Item = &'a Puzzle>,
+    mut writer: impl Write,
+) -> std::io::Result<()> {
+    for puzzle in puzzles {
+        let stones = |stone| {
+            let mut points = puzzle
+                .position
+                .iter()
+                .filter(|m| m.color == stone)
+                .map(|m| m.point)
+                .collect::<Vec<_>>();
+            points.sort();
+            join(&points)
+        };
+        write!(
+            writer,
+            "black={} white={} move={} solution={}",
+            stones(Stone::Black),
+            stones(Stone::White),
+            format!("{:?}", puzzle.to_move).to_lowercase(),
+            join(&puzzle.main_line())
+        )?;
+        if !puzzle.themes.is_empty() {
+            let themes = puzzle.themes.iter().map(Motif::name).collect::<Vec<_>>();
+            write!(writer, " themes={}", themes.join(","))?;
+        }
+        if let Some(difficulty) = puzzle.difficulty {
+            write!(
+                writer,
+                " difficulty={}",
+                format!("{difficulty:?}").to_lowercase()
+            )?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+fn join(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(Point::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use crate::puzzle::Verdict;
+
+    #[test]
+    fn round_trip() {
+        let pack = "# a comment\n\n\
+            black=H8,I8,J8 white=H9,I9,J9 solution=K8,G8,L8 themes=vcf,Four_three source=x\n\
+            black=H8 white=  move=white solution=I9 difficulty=expert\n";
+        let puzzles = parse(pack.as_bytes()).unwrap();
+        assert_eq!(puzzles.len(), 2);
+        assert_eq!(puzzles[0].to_move, Stone::Black);
+        assert_eq!(
+            puzzles[0].themes,
+            [Motif::Vcf, Motif::Other("four-three".to_owned())]
+        );
+        assert_eq!(
+            puzzles[0].check_attempt(&p![[K, 8], [G, 8], [L, 8]]),
+            Verdict::Solved
+        );
+        assert_eq!(puzzles[1].to_move, Stone::White);
+        assert_eq!(puzzles[1].difficulty, Some(Difficulty::Expert));
+
+        let mut written = vec![];
+        write(&puzzles, &mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(
+            written,
+            "black=H8,I8,J8 white=H9,I9,J9 move=black solution=K8,G8,L8 themes=vcf,four-three\n\
+             black=H8 white= move=white solution=I9 difficulty=expert\n"
+        );
+        let again = parse(written.as_bytes()).unwrap();
+        assert_eq!(again.len(), 2);
+        assert_eq!(again[0].key(), puzzles[0].key());
+        assert_eq!(again[1].key(), puzzles[1].key());
+
+        let error = parse("black=H8\nblack=H8,H8 solution=I9\n".as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: no solution");
+        let error = parse("black=H8,H8 solution=I9\n".as_bytes()).unwrap_err();
+        assert_eq!(error.to_string(), "line 1: H8 is given twice");
+    }
+}
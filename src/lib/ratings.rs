@@ -0,0 +1,299 @@
+//! Rating calculations for recurring leagues, Elo and Glicko-2.
+//!
+//! Both systems consume a list of [`MatchResult`]s and update a map of ratings keyed by whatever
+//! the caller uses to identify a player (a name, an engine id...). Players not yet in the map
+//! start at the configured initial rating.
+
+use std::collections::BTreeMap;
+
+/// The score of a game, seen from the first player in a [`MatchResult`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Score {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl Score {
+    /// The score as a number, `1.0` for a win, `0.5` for a draw and `0.0` for a loss.
+    #[must_use]
+    pub fn value(self) -> f64 {
+        match self {
+            Score::Win => 1.0,
+            Score::Draw => 0.5,
+            Score::Loss => 0.0,
+        }
+    }
+
+    /// The same score seen from the other player.
+    #[must_use]
+    pub fn opposite(self) -> Self {
+        match self {
+            Score::Win => Score::Loss,
+            Score::Draw => Score::Draw,
+            Score::Loss => Score::Win,
+        }
+    }
+}
+
+/// A finished game between two players.
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchResult<P> {
+    pub first: P,
+    pub second: P,
+    /// Score for `first`
+    pub score: Score,
+}
+
+impl<P> MatchResult<P> {
+    pub fn new(first: P, second: P, score: Score) -> Self {
+        Self {
+            first,
+            second,
+            score,
+        }
+    }
+}
+
+/// Classic Elo rating.
+#[derive(Clone, Copy, Debug)]
+pub struct Elo {
+    /// Maximum rating change for a single game.
+    pub k_factor: f64,
+    /// Rating for players without one.
+    pub initial: f64,
+}
+
+impl Default for Elo {
+    fn default() -> Self {
+        Self {
+            k_factor: 32.0,
+            initial: 1500.0,
+        }
+    }
+}
+
+impl Elo {
+    /// Expected score for a player rated `rating` against an opponent rated `opponent`.
+    #[must_use]
+    pub fn expected_score(rating: f64, opponent: f64) -> f64 {
+        1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+    }
+
+    /// Update `ratings` with `results`, one game at a time in the given order.
+    pub fn rate<P: Ord + Clone>(&self, ratings: &mut BTreeMap<P, f64>, results: &[MatchResult<P>]) {
+        for result in results {
+            let first = *ratings.entry(result.first.clone()).or_insert(self.initial);
+            let second = *ratings.entry(result.second.clone()).or_insert(self.initial);
+            let delta =
+                self.k_factor * (result.score.value() - Self::expected_score(first, second));
+            ratings.insert(result.first.clone(), first + delta);
+            ratings.insert(result.second.clone(), second - delta);
+        }
+    }
+}
+
+/// A Glicko-2 rating, in the familiar Glicko scale.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    /// Rating deviation, how uncertain the rating is.
+    pub deviation: f64,
+    /// Expected fluctuation of the rating.
+    pub volatility: f64,
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Self {
+        Self {
+            rating: 1500.0,
+            deviation: 350.0,
+            volatility: 0.06,
+        }
+    }
+}
+
+/// Conversion factor between the Glicko and Glicko-2 scales.
+const GLICKO2_SCALE: f64 = 173.7178;
+
+/// Glicko-2 rating, see <http://www.glicko.net/glicko/glicko2.pdf>.
+#[derive(Clone, Copy, Debug)]
+pub struct Glicko2 {
+    /// System constant constraining the volatility change, between 0.3 and 1.2 is reasonable.
+    pub tau: f64,
+    /// Rating for players without one.
+    pub initial: Glicko2Rating,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Self {
+            tau: 0.5,
+            initial: Glicko2Rating::default(),
+        }
+    }
+}
+
+impl Glicko2 {
+    /// Update `ratings` with all `results` played during one rating period.
+    ///
+    /// Every player in `ratings` is updated, players who didn't play only get their deviation
+    /// increased.
+    pub fn rate_period<P: Ord + Clone>(
+        &self,
+        ratings: &mut BTreeMap<P, Glicko2Rating>,
+        results: &[MatchResult<P>],
+    ) {
+        let mut games: BTreeMap<P, Vec<(Glicko2Rating, f64)>> = BTreeMap::new();
+        for result in results {
+            let first = *ratings.entry(result.first.clone()).or_insert(self.initial);
+            let second = *ratings.entry(result.second.clone()).or_insert(self.initial);
+            games
+                .entry(result.first.clone())
+                .or_default()
+                .push((second, result.score.value()));
+            games
+                .entry(result.second.clone())
+                .or_default()
+                .push((first, result.score.opposite().value()));
+        }
+        for (player, rating) in ratings.iter_mut() {
+            *rating = self.rate_player(*rating, games.get(player).map_or(&[], Vec::as_slice));
+        }
+    }
+
+    /// Compute the new rating for a player given the opponents and scores of the period.
+    #[must_use]
+    pub fn rate_player(
+        &self,
+        player: Glicko2Rating,
+        games: &[(Glicko2Rating, f64)],
+    ) -> Glicko2Rating {
+        let mu = (player.rating - 1500.0) / GLICKO2_SCALE;
+        let phi = player.deviation / GLICKO2_SCALE;
+        let sigma = player.volatility;
+
+        if games.is_empty() {
+            return Glicko2Rating {
+                deviation: (phi * phi + sigma * sigma).sqrt() * GLICKO2_SCALE,
+                ..player
+            };
+        }
+
+        let g = |phi: f64| 1.0 / (1.0 + 3.0 * phi * phi / std::f64::consts::PI.powi(2)).sqrt();
+        let e = |mu_j: f64, phi_j: f64| 1.0 / (1.0 + (-g(phi_j) * (mu - mu_j)).exp());
+
+        let mut v_inv = 0.0;
+        let mut delta_sum = 0.0;
+        for (opponent, score) in games {
+            let mu_j = (opponent.rating - 1500.0) / GLICKO2_SCALE;
+            let phi_j = opponent.deviation / GLICKO2_SCALE;
+            let e = e(mu_j, phi_j);
+            v_inv += g(phi_j).powi(2) * e * (1.0 - e);
+            delta_sum += g(phi_j) * (score - e);
+        }
+        let v = 1.0 / v_inv;
+        let delta = v * delta_sum;
+
+        // Find the new volatility with the Illinois algorithm.
+        let a = (sigma * sigma).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            ex * (delta * delta - phi * phi - v - ex) / (2.0 * (phi * phi + v + ex).powi(2))
+                - (x - a) / (self.tau * self.tau)
+        };
+        const EPSILON: f64 = 0.000_001;
+        let mut big_a = a;
+        let mut big_b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.0;
+            while f(a - k * self.tau) < 0.0 {
+                k += 1.0;
+            }
+            a - k * self.tau
+        };
+        let mut f_a = f(big_a);
+        let mut f_b = f(big_b);
+        while (big_b - big_a).abs() > EPSILON {
+            let big_c = big_a + (big_a - big_b) * f_a / (f_b - f_a);
+            let f_c = f(big_c);
+            if f_c * f_b <= 0.0 {
+                big_a = big_b;
+                f_a = f_b;
+            } else {
+                f_a /= 2.0;
+            }
+            big_b = big_c;
+            f_b = f_c;
+        }
+        let sigma = (big_a / 2.0).exp();
+
+        let phi_star = (phi * phi + sigma * sigma).sqrt();
+        let phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / v).sqrt();
+        let mu = mu + phi * phi * delta_sum;
+
+        Glicko2Rating {
+            rating: mu * GLICKO2_SCALE + 1500.0,
+            deviation: phi * GLICKO2_SCALE,
+            volatility: sigma,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elo_equal_players() {
+        let mut ratings = BTreeMap::new();
+        Elo::default().rate(&mut ratings, &[MatchResult::new("a", "b", Score::Win)]);
+        assert_eq!(ratings["a"], 1516.0);
+        assert_eq!(ratings["b"], 1484.0);
+
+        Elo::default().rate(&mut ratings, &[MatchResult::new("a", "b", Score::Draw)]);
+        assert!(ratings["a"] < 1516.0);
+        assert!((ratings["a"] + ratings["b"] - 3000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn glicko2_paper_example() {
+        // Example from section 4 in the Glicko-2 paper.
+        let player = Glicko2Rating {
+            rating: 1500.0,
+            deviation: 200.0,
+            volatility: 0.06,
+        };
+        let opponent = |rating, deviation| Glicko2Rating {
+            rating,
+            deviation,
+            volatility: 0.06,
+        };
+        let new = Glicko2::default().rate_player(
+            player,
+            &[
+                (opponent(1400.0, 30.0), 1.0),
+                (opponent(1550.0, 100.0), 0.0),
+                (opponent(1700.0, 300.0), 0.0),
+            ],
+        );
+        assert!((new.rating - 1464.06).abs() < 0.01, "{new:?}");
+        assert!((new.deviation - 151.52).abs() < 0.01, "{new:?}");
+        assert!((new.volatility - 0.05999).abs() < 0.00001, "{new:?}");
+    }
+
+    #[test]
+    fn glicko2_inactive_player() {
+        let mut ratings = BTreeMap::new();
+        ratings.insert("idle", Glicko2Rating::default());
+        Glicko2::default().rate_period(&mut ratings, &[MatchResult::new("a", "b", Score::Loss)]);
+        assert_eq!(ratings["idle"].rating, 1500.0);
+        assert!(ratings["idle"].deviation > 350.0);
+        assert!(ratings["a"].rating < 1500.0);
+        assert!(ratings["b"].rating > 1500.0);
+    }
+}
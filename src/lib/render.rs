@@ -0,0 +1,244 @@
+//! Boards drawn as images, and lines of play as animated PNGs to share tactics.
+//!
+//! [`render`] draws a position on a wooden board, [`write_apng`] steps through a line from a
+//! position one move per frame, ending on a frame that rings every stone of the line.
+//! [`render_influence`] adds the [`Influence`] of the stones as dots on the empty points.
+//! [`crop`] cuts an image down to an [`Area`] of the board, for compact figures.
+
+use std::io::Write;
+use std::time::Duration;
+
+use image::{Rgb, RgbImage};
+
+use crate::board::influence::Influence;
+use crate::board::setup::Area;
+use crate::board::{BoardArr, BoardRead, Point, Stone};
+use crate::errors::Error;
+
+const SIZE: u32 = 15;
+/// Pixels between lines.
+const CELL: u32 = 32;
+/// Pixels between the edge of the image and the outer lines.
+const MARGIN: u32 = 24;
+/// The width and height of every image.
+pub const PIXELS: u32 = 2 * MARGIN + (SIZE - 1) * CELL;
+
+const WOOD: Rgb<u8> = Rgb([220, 179, 92]);
+const LINE: Rgb<u8> = Rgb([40, 30, 10]);
+const BLACK: Rgb<u8> = Rgb([20, 20, 20]);
+const WHITE: Rgb<u8> = Rgb([245, 245, 245]);
+const MARK: Rgb<u8> = Rgb([210, 30, 30]);
+
+/// `board`, with a dot on `last` and a ring around the stones of `marked`.
+#[must_use]
+pub fn render(board: &BoardArr, last: Option<Point>, marked: &[Point]) -> RgbImage {
+    let mut image = RgbImage::from_pixel(PIXELS, PIXELS, WOOD);
+    let end = MARGIN + (SIZE - 1) * CELL;
+    for i in 0..SIZE {
+        let at = MARGIN + i * CELL;
+        for t in MARGIN..=end {
+            image.put_pixel(t, at, LINE);
+            image.put_pixel(at, t, LINE);
+        }
+    }
+    let radius = f64::from(CELL) * 0.46;
+    for marker in board.iter() {
+        let point = marker.point;
+        let color = match board.stone_at(point) {
+            Stone::Black => BLACK,
+            Stone::White => WHITE,
+            Stone::Empty => continue,
+        };
+        disc(&mut image, point, 0.0, radius, LINE);
+        disc(&mut image, point, 0.0, radius - 1.0, color);
+        if marked.contains(&point) {
+            disc(&mut image, point, radius - 4.0, radius - 1.0, MARK);
+        }
+    }
+    if let Some(last) = last.filter(|p| !p.is_null) {
+        disc(&mut image, last, 0.0, 4.0, MARK);
+    }
+    image
+}
+
+/// `board` with a dot on every empty point in the color of the side influencing it, larger the
+/// stronger the influence.
+#[must_use]
+pub fn render_influence(board: &BoardArr, influence: &Influence) -> RgbImage {
+    let mut image = render(board, None, &[]);
+    for marker in board.iter() {
+        let point = marker.point;
+        let value = influence.at(point);
+        if board.stone_at(point) != Stone::Empty || value.abs() < 0.25 {
+            continue;
+        }
+        let color = if value > 0.0 { BLACK } else { WHITE };
+        let radius = f64::from(value.abs().min(2.0)) / 2.0 * f64::from(CELL) * 0.3;
+        disc(&mut image, point, 0.0, radius, color);
+    }
+    image
+}
+
+/// The part of `image`, drawn by [`render`], showing `area` with the usual margin around it.
+#[must_use]
+pub fn crop(image: &RgbImage, area: Area) -> RgbImage {
+    let (columns, rows) = (area.columns(), area.rows());
+    let (x, y) = (columns.start() * CELL, rows.start() * CELL);
+    let width = (columns.end() - columns.start()) * CELL + 2 * MARGIN;
+    let height = (rows.end() - rows.start()) * CELL + 2 * MARGIN;
+    image::imageops::crop_imm(image, x, y, width, height).to_image()
+}
+
+/// Paint the pixels between `inner` and `outer` pixels from the center of `point`.
+fn disc(image: &mut RgbImage, point: Point, inner: f64, outer: f64, color: Rgb<u8>) {
+    let (cx, cy) = (
+        f64::from(MARGIN + point.x * CELL),
+        f64::from(MARGIN + point.y * CELL),
+    );
+    let reach = outer.ceil() as u32;
+    let (x0, y0) = (MARGIN + point.x * CELL, MARGIN + point.y * CELL);
+    for y in y0.saturating_sub(reach)..=(y0 + reach).min(PIXELS - 1) {
+        for x in x0.saturating_sub(reach)..=(x0 + reach).min(PIXELS - 1) {
+            let distance = (f64::from(x) - cx).hypot(f64::from(y) - cy);
+            if distance >= inner && distance <= outer {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// How an animation is played.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnimationOptions {
+    /// The time every move is shown.
+    pub delay: Duration,
+    /// The time the final frame is shown before starting over.
+    pub hold: Duration,
+    /// Crop every frame to the stones of the final one and this many lines around them, the
+    /// whole board if `None`.
+    pub margin: Option<u32>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(800),
+            hold: Duration::from_secs(3),
+            margin: None,
+        }
+    }
+}
+
+/// Write an animated PNG playing `moves` from `start`, `first` to move, to `out`.
+///
+/// The first frame is `start`, then one frame per move and a final frame with the stones of
+/// `moves` ringed. A null point is a pass and only changes who is to move.
+pub fn write_apng(
+    out: impl Write,
+    start: &BoardArr,
+    moves: &[Point],
+    first: Stone,
+    options: &AnimationOptions,
+) -> crate::Result<()> {
+    let mut board = start.clone();
+    let mut frames = vec![render(&board, None, &[])];
+    let mut stone = first;
+    for point in moves {
+        if !point.is_null {
+            board.set_point(*point, stone);
+        }
+        frames.push(render(&board, Some(*point), &[]));
+        stone = stone.opposite();
+    }
+    frames.push(render(&board, None, moves));
+    let stones = board
+        .iter()
+        .filter(|m| !m.color.is_empty())
+        .map(|m| m.point);
+    if let Some(area) = options
+        .margin
+        .and_then(|margin| Area::around(stones, margin, SIZE))
+    {
+        frames = frames.iter().map(|frame| crop(frame, area)).collect();
+    }
+
+    let (width, height) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(encoding)?;
+    let mut writer = encoder.write_header().map_err(encoding)?;
+    let last = frames.len() - 1;
+    for (i, frame) in frames.iter().enumerate() {
+        let delay = if i == last {
+            options.hold
+        } else {
+            options.delay
+        };
+        let millis = u16::try_from(delay.as_millis()).unwrap_or(u16::MAX);
+        writer.set_frame_delay(millis, 1000).map_err(encoding)?;
+        writer.write_image_data(frame.as_raw()).map_err(encoding)?;
+    }
+    writer.finish().map_err(encoding)
+}
+
+fn encoding(error: png::EncodingError) -> Error {
+    match error {
+        png::EncodingError::IoError(error) => Error::Io(error),
+        error => Error::Io(std::io::Error::other(error)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn animates_a_line() {
+        let center = |point: Point| (MARGIN + point.x * CELL, MARGIN + point.y * CELL);
+        let mut board = BoardArr::new(15);
+        board.set_point(p![H, 8], Stone::Black);
+        let image = render(&board, None, &[]);
+        assert_eq!(image.dimensions(), (PIXELS, PIXELS));
+        let (x, y) = center(p![H, 8]);
+        assert_eq!(*image.get_pixel(x, y), BLACK);
+        assert_eq!(*image.get_pixel(x + CELL / 2, y + CELL / 2), WOOD);
+        let marked = render(&board, Some(p![H, 8]), &[p![H, 8]]);
+        assert_eq!(*marked.get_pixel(x, y), MARK);
+        assert_eq!(*marked.get_pixel(x + CELL * 44 / 100 - 1, y), MARK);
+
+        let influence = render_influence(&board, &Influence::new(&board));
+        let (nx, ny) = center(p![I, 9]);
+        assert_eq!(*influence.get_pixel(nx, ny), BLACK);
+        let (fx, fy) = center(p![A, 1]);
+        assert_ne!(*influence.get_pixel(fx + 1, fy + 1), BLACK);
+
+        let mut apng = vec![];
+        let moves = p![[I, 9], [J, 10]];
+        let options = AnimationOptions::default();
+        write_apng(&mut apng, &board, &moves, Stone::White, &options).unwrap();
+        let reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        let control = reader.info().animation_control.unwrap();
+        assert_eq!(control.num_frames, 4);
+        assert_eq!(control.num_plays, 0);
+
+        // H8 to J10 and a line around them
+        let cropped = crop(&image, Area::around(p![[H, 8], [J, 10]], 1, 15).unwrap());
+        assert_eq!(
+            cropped.dimensions(),
+            (4 * CELL + 2 * MARGIN, 4 * CELL + 2 * MARGIN)
+        );
+        assert_eq!(*cropped.get_pixel(MARGIN + CELL, MARGIN + 3 * CELL), BLACK);
+        let options = AnimationOptions {
+            margin: Some(1),
+            ..AnimationOptions::default()
+        };
+        let mut apng = vec![];
+        write_apng(&mut apng, &board, &moves, Stone::White, &options).unwrap();
+        let reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().size(), cropped.dimensions());
+    }
+}
@@ -0,0 +1,297 @@
+//! Reports on what a project knows, to read before a game.
+//!
+//! [`preparation`] gathers everything about one of the standard openings: the lines of the
+//! libraries playing it, how deep they go, the strong replies they have no answer to, see
+//! [`find_holes`], and the latest games played in it. The [`Preparation`] is written as markdown
+//! or as an HTML page.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::analysis::{find_holes, Hole, HoleKind, HoleOptions};
+use crate::board::duplicates::position_key;
+use crate::board::notation::write_moves;
+use crate::board::{Board, Point};
+use crate::errors::ParseError;
+use crate::game::GameRecord;
+use crate::project::{games, Project};
+use crate::theory;
+
+/// The holes listed in a [`Preparation`], the strongest.
+pub const HOLES: usize = 10;
+/// The games listed in a [`Preparation`].
+pub const RECENT: usize = 10;
+
+/// A line of a library.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Line {
+    /// The library, as given in the manifest.
+    pub file: PathBuf,
+    pub moves: Vec<Point>,
+}
+
+/// A game of the project.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Game {
+    /// The file of the game, as given in the manifest.
+    pub file: PathBuf,
+    pub game: GameRecord,
+}
+
+/// What a project knows of an opening, see the [module documentation](self).
+#[derive(Debug)]
+pub struct Preparation {
+    /// The name of the opening, as written in [`theory`].
+    pub opening: String,
+    /// The lines of the libraries playing the opening, deepest first.
+    pub lines: Vec<Line>,
+    /// The different positions of the lines, after the opening.
+    pub positions: usize,
+    /// The strongest replies missing from the lines.
+    pub holes: Vec<Hole>,
+    /// The games played in the opening, the latest first. Games are taken to be in the order of
+    /// the files of the manifest and of the games in every file.
+    pub games: Vec<Game>,
+    /// Files that could not be read.
+    pub failed: Vec<(PathBuf, crate::Error)>,
+}
+
+/// Gather what `project` knows of the standard opening `opening`, like `Kagetsu`, on any rotated
+/// or mirrored board.
+///
+/// Every file of the project is read, a file that can't be read is listed in
+/// [`Preparation::failed`]. The holes are searched for with the default [`HoleOptions`] in the
+/// lines of the opening.
+pub fn preparation(project: &Project, opening: &str) -> crate::Result<Preparation> {
+    let moves = theory::opening(opening)
+        .ok_or_else(|| ParseError::Other(format!("{opening:?} is not a standard opening")))?;
+    let key = position_key(&GameRecord::new(moves.clone()).position(moves.len()));
+    let in_opening = |line: &[Point]| {
+        line.len() >= moves.len()
+            && position_key(&GameRecord::new(line.to_vec()).position(moves.len())) == key
+    };
+    let name = theory::match_game(&GameRecord::new(moves.clone()))
+        .name
+        .unwrap_or_else(|| opening.to_owned());
+
+    let mut preparation = Preparation {
+        opening: name,
+        lines: vec![],
+        positions: 0,
+        holes: vec![],
+        games: vec![],
+        failed: vec![],
+    };
+    let mut graph = Board::new();
+    for (i, (file, loaded)) in project.files().zip(project.load()).enumerate() {
+        let loaded = match loaded {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                preparation.failed.push((file.to_path_buf(), error));
+                continue;
+            }
+        };
+        for line in games(&loaded).into_iter().filter(|line| in_opening(line)) {
+            if i < project.libraries.len() {
+                graph.add_line(&line);
+                preparation.lines.push(Line {
+                    file: file.to_path_buf(),
+                    moves: line,
+                });
+            } else {
+                preparation.games.push(Game {
+                    file: file.to_path_buf(),
+                    game: GameRecord::new(line),
+                });
+            }
+        }
+    }
+    // the root and the moves of the opening are not counted
+    preparation.positions = graph
+        .positions(usize::MAX)
+        .count()
+        .saturating_sub(moves.len() + 1);
+    preparation
+        .lines
+        .sort_by_key(|line| std::cmp::Reverse(line.moves.len()));
+    preparation.games.reverse();
+    preparation.games.truncate(RECENT);
+    preparation.holes = find_holes(&graph, &HoleOptions::default())?;
+    preparation.holes.truncate(HOLES);
+    Ok(preparation)
+}
+
+impl Preparation {
+    /// The moves of the deepest line.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.lines.first().map_or(0, |line| line.moves.len())
+    }
+
+    /// The report as markdown.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# Preparation: {}\n", self.opening);
+        let _ = writeln!(md, "## My lines\n\n{}\n", self.coverage());
+        for line in &self.lines {
+            let _ = writeln!(
+                md,
+                "- `{}` ({})",
+                write_moves(&line.moves),
+                line.file.display()
+            );
+        }
+        let _ = writeln!(md, "\n## Holes\n");
+        if self.holes.is_empty() {
+            let _ = writeln!(md, "None found.");
+        }
+        for hole in &self.holes {
+            let _ = writeln!(md, "- {}", describe(hole, |moves| format!("`{moves}`")));
+        }
+        let _ = writeln!(md, "\n## Recent games\n");
+        if self.games.is_empty() {
+            let _ = writeln!(md, "None played.");
+        }
+        for game in &self.games {
+            let _ = writeln!(
+                md,
+                "- {}, {}: `{}`",
+                game.file.display(),
+                players(&game.game),
+                write_moves(&game.game.moves)
+            );
+        }
+        md
+    }
+
+    /// The report as a standalone HTML page.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let title = format!("Preparation: {}", escape_html(&self.opening));
+        let mut html = String::new();
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{title}</title></head>\n<body>\n<h1>{title}</h1>"
+        );
+        let _ = writeln!(html, "<h2>My lines</h2>\n<p>{}</p>\n<ul>", self.coverage());
+        for line in &self.lines {
+            let _ = writeln!(
+                html,
+                "<li><code>{}</code> ({})</li>",
+                write_moves(&line.moves),
+                escape_html(&line.file.display().to_string())
+            );
+        }
+        let _ = writeln!(html, "</ul>\n<h2>Holes</h2>\n<ul>");
+        for hole in &self.holes {
+            let text = describe(hole, |moves| format!("<code>{moves}</code>"));
+            let _ = writeln!(html, "<li>{text}</li>");
+        }
+        let _ = writeln!(html, "</ul>\n<h2>Recent games</h2>\n<ul>");
+        for game in &self.games {
+            let _ = writeln!(
+                html,
+                "<li>{}, {}: <code>{}</code></li>",
+                escape_html(&game.file.display().to_string()),
+                escape_html(&players(&game.game)),
+                write_moves(&game.game.moves)
+            );
+        }
+        let _ = writeln!(html, "</ul>\n</body>\n</html>");
+        html
+    }
+
+    /// Like `3 lines, 12 positions after the opening, the deepest 9 moves`.
+    fn coverage(&self) -> String {
+        format!(
+            "{} lines, {} positions after the opening, the deepest {} moves",
+            self.lines.len(),
+            self.positions,
+            self.depth()
+        )
+    }
+}
+
+/// Like `after H8 H9 I9 J10, white wins with K11 in 5 moves`, with the moves written by `code`.
+fn describe(hole: &Hole, code: impl Fn(String) -> String) -> String {
+    let stone = format!("{:?}", hole.stone).to_lowercase();
+    let after = code(write_moves(&hole.path));
+    match &hole.kind {
+        HoleKind::Win { win } => format!(
+            "after {after}, {stone} wins with {} in {} moves",
+            hole.reply,
+            win.len()
+        ),
+        HoleKind::Four => format!("after {after}, {stone} has a four at {}", hole.reply),
+    }
+}
+
+fn players(game: &GameRecord) -> String {
+    format!(
+        "{} - {}",
+        game.black.as_deref().unwrap_or("?"),
+        game.white.as_deref().unwrap_or("?")
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn kagetsu() {
+        let dir = std::env::temp_dir().join(format!("renju-report-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut library = Board::new();
+        // kagetsu, mirrored, where white leaves black's open three
+        library.add_line(&p![
+            [H, 8],
+            [H, 9],
+            [G, 9],
+            [G, 10],
+            [F, 10],
+            [A, 1],
+            [A, 3],
+            [A, 2]
+        ]);
+        library.add_line(&p![[H, 8], [H, 9], [I, 9], [J, 10]]);
+        library.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        crate::file_reader::save_file_path(&library, &dir.join("openings.txt")).unwrap();
+        // H8 H9 I9, then H8 I9
+        std::fs::write(dir.join("old.pos"), [3, 112, 97, 98]).unwrap();
+        std::fs::write(dir.join("new.pos"), [2, 112, 98]).unwrap();
+
+        let mut project = Project::new("Prep", &dir);
+        project.libraries.push("openings.txt".into());
+        project.libraries.push("missing.lib".into());
+        project.games.push("old.pos".into());
+        project.games.push("new.pos".into());
+        assert!(preparation(&project, "Nothing").is_err());
+        let preparation = preparation(&project, "kagetsu").unwrap();
+        assert_eq!(preparation.opening, "Kagetsu");
+        assert_eq!(preparation.lines.len(), 2);
+        assert_eq!(preparation.depth(), 8);
+        assert_eq!(preparation.positions, 6);
+        assert_eq!(preparation.games.len(), 1);
+        assert_eq!(preparation.failed.len(), 1);
+        assert!(!preparation.holes.is_empty());
+
+        let md = preparation.to_markdown();
+        assert!(md.starts_with("# Preparation: Kagetsu\n"), "{md}");
+        assert!(md.contains("2 lines, 6 positions after the opening, the deepest 8 moves"));
+        assert!(md.contains("- old.pos, ? - ?: `H8 H9 I9`"), "{md}");
+        assert!(md.contains("black wins with"), "{md}");
+        let html = preparation.to_html();
+        assert!(html.contains("<h1>Preparation: Kagetsu</h1>"));
+        assert_eq!(html.matches("<li>").count(), 3 + preparation.holes.len());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
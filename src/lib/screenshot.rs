@@ -0,0 +1,208 @@
+//! Read positions from screenshots of digital boards, like the ones of videos and web apps.
+//!
+//! The image should be cropped to the board, coordinates and a frame around the grid are fine.
+//! The grid is found from rows and columns of pixels that are mostly darker than the board, and
+//! every intersection is then classified by sampling its diagonals, which grid lines never cross:
+//! dark inside is a black stone, brighter than the board or an outline around a bright inside is a
+//! white stone. Photos of real boards are not supported.
+
+use std::path::Path;
+
+use image::GrayImage;
+
+use crate::board::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+
+const SIZE: u32 = 15;
+
+/// How much darker than the board a pixel must be to count as a line or stone.
+const DARK: u8 = 48;
+/// How much brighter than the board a white stone must be, without an outline.
+const BRIGHT: u8 = 24;
+
+/// Read the position in the image at `path`.
+pub fn open(path: &Path) -> Result<BoardArr, ParseError> {
+    let image = image::open(path).map_err(|e| ParseError::Other(e.to_string()))?;
+    recognize(&image.to_luma8())
+}
+
+/// Read the position in `image`.
+pub fn recognize(image: &GrayImage) -> Result<BoardArr, ParseError> {
+    let mut lumas = image.pixels().map(|p| p.0[0]).collect::<Vec<_>>();
+    if lumas.is_empty() {
+        return Err(ParseError::Other("empty image".to_owned()));
+    }
+    let middle = lumas.len() / 2;
+    let background = *lumas.select_nth_unstable(middle).1;
+    let dark = |x: u32, y: u32| image.get_pixel(x, y).0[0].saturating_add(DARK) < background;
+
+    let (width, height) = image.dimensions();
+    let columns = (0..width)
+        .map(|x| (0..height).filter(|&y| dark(x, y)).count())
+        .collect::<Vec<_>>();
+    let rows = (0..height)
+        .map(|y| (0..width).filter(|&x| dark(x, y)).count())
+        .collect::<Vec<_>>();
+    let (Some((left, dx)), Some((top, dy))) = (grid(&columns, height), grid(&rows, width)) else {
+        return Err(ParseError::Other("no grid found in the image".to_owned()));
+    };
+
+    let luma = |x: f64, y: f64| {
+        let (x, y) = (x.round() as u32, y.round() as u32);
+        (x < width && y < height).then(|| image.get_pixel(x, y).0[0])
+    };
+    let spacing = dx.min(dy);
+    let mut board = BoardArr::new(SIZE);
+    for row in 0..SIZE {
+        for column in 0..SIZE {
+            let (cx, cy) = (left + dx * f64::from(column), top + dy * f64::from(row));
+            let diagonal = |distance: f64| {
+                [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)].map(|(sx, sy)| {
+                    luma(cx + sx * distance * spacing, cy + sy * distance * spacing)
+                })
+            };
+            let inside = [0.12, 0.17]
+                .into_iter()
+                .flat_map(diagonal)
+                .flatten()
+                .map(u32::from)
+                .collect::<Vec<_>>();
+            let inside = inside.iter().sum::<u32>() / inside.len().max(1) as u32;
+            // a circle through the diagonals, from within a small stone to outside of a large one
+            let outlined = (0..4).all(|corner| {
+                [0.22, 0.25, 0.28, 0.31, 0.34]
+                    .into_iter()
+                    .filter_map(|distance| diagonal(distance)[corner])
+                    .any(|luma| luma.saturating_add(DARK) < background)
+            });
+            let stone = if inside + u32::from(DARK) < u32::from(background) {
+                Stone::Black
+            } else if inside > u32::from(background) + u32::from(BRIGHT) || outlined {
+                Stone::White
+            } else {
+                continue;
+            };
+            board.set_point(Point::new(column, row), stone);
+        }
+    }
+    Ok(board)
+}
+
+/// The first of 15 evenly spaced lines in `profile`, the count of dark pixels along each column
+/// or row of `length` pixels, and the spacing between them.
+fn grid(profile: &[usize], length: u32) -> Option<(f64, f64)> {
+    let threshold = length as usize * 6 / 10;
+    // the middle of every run of lines
+    let mut lines = vec![];
+    let mut start = None;
+    for (i, &count) in profile.iter().chain([&0]).enumerate() {
+        match (start, count >= threshold) {
+            (None, true) => start = Some(i),
+            (Some(first), false) => {
+                lines.push((first + i - 1) as f64 / 2.0);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    // the pair of outer lines with the most lines found between them, preferring wider grids
+    let mut best = None;
+    for (i, &first) in lines.iter().enumerate() {
+        for &last in &lines[i + 1..] {
+            let spacing = (last - first) / f64::from(SIZE - 1);
+            if spacing < 4.0 {
+                continue;
+            }
+            let found = (0..SIZE)
+                .filter(|&k| {
+                    let expected = first + spacing * f64::from(k);
+                    lines
+                        .iter()
+                        .any(|line| (line - expected).abs() <= (spacing / 8.0).max(1.0))
+                })
+                .count();
+            if found >= 12 && best.is_none_or(|(f, _, s)| (found, spacing) > (f, s)) {
+                best = Some((found, first, spacing));
+            }
+        }
+    }
+    best.map(|(_, first, spacing)| (first, spacing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+    use image::Luma;
+
+    /// Render `board` like a digital board, with a frame around the grid.
+    fn render(board: &BoardArr, background: u8, white: u8) -> GrayImage {
+        let (margin, spacing, radius) = (30, 24, 11);
+        let size = margin * 2 + spacing * (SIZE - 1);
+        let mut image = GrayImage::from_pixel(size, size, Luma([background]));
+        for i in 0..size {
+            for edge in [8, size - 9] {
+                image.put_pixel(i, edge, Luma([30]));
+                image.put_pixel(edge, i, Luma([30]));
+            }
+        }
+        for k in 0..SIZE {
+            let line = margin + k * spacing;
+            for i in margin..=margin + spacing * (SIZE - 1) {
+                image.put_pixel(i, line, Luma([60]));
+                image.put_pixel(line, i, Luma([60]));
+            }
+        }
+        for x in 0..size {
+            for y in 0..size {
+                let nearest = |i: u32| (i + spacing / 2).saturating_sub(margin) / spacing;
+                let (column, row) = (nearest(x), nearest(y));
+                let Some(marker) = board.get_xy(column.min(SIZE - 1), row.min(SIZE - 1)) else {
+                    continue;
+                };
+                let (cx, cy) = (margin + column * spacing, margin + row * spacing);
+                let distance = f64::from(x.abs_diff(cx).pow(2) + y.abs_diff(cy).pow(2)).sqrt();
+                let luma = match marker.color {
+                    Stone::Black if distance <= f64::from(radius) => 20,
+                    Stone::White if (distance - f64::from(radius)).abs() < 1.0 => 40,
+                    Stone::White if distance < f64::from(radius) => white,
+                    _ => continue,
+                };
+                image.put_pixel(x, y, Luma([luma]));
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn recognize_render() {
+        let mut board = BoardArr::new(SIZE);
+        for (point, stone) in [
+            (p![H, 8], Stone::Black),
+            (p![I, 9], Stone::White),
+            (p![A, 1], Stone::Black),
+            (p![O, 15], Stone::White),
+            (p![A, 15], Stone::White),
+            (p![J, 9], Stone::Black),
+        ] {
+            board.set_point(point, stone);
+        }
+        let stones = |board: &BoardArr| {
+            board
+                .iter()
+                .filter(|m| !m.color.is_empty())
+                .map(|m| (m.point, m.color))
+                .collect::<Vec<_>>()
+        };
+        // wooden board with bright white stones, and a white board with outlined white stones
+        for (background, white) in [(200, 250), (255, 255)] {
+            let recognized = recognize(&render(&board, background, white)).unwrap();
+            assert_eq!(
+                stones(&recognized),
+                stones(&board),
+                "background {background}"
+            );
+        }
+        assert!(recognize(&GrayImage::from_pixel(100, 100, Luma([200]))).is_err());
+    }
+}
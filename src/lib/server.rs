@@ -0,0 +1,472 @@
+//! A small HTTP server for browsing libraries from a web page. Needs the `server` feature.
+//!
+//! The server keeps the libraries it is given and the ones uploaded, and answers:
+//!
+//! * `GET /position/{hash}`: where a position is reached, `{hash}` being a link of
+//!   [`share`](crate::share) with or without `renju:`. Rotations and mirrorings are found too.
+//! * `GET /node/{id}/children?library=0&offset=0&limit=50`: a [`Page`] of [`NodeSummary`]s.
+//! * `GET /analyze?moves=H8 I9&engine=alphabeta`: a [`SearchResult`] for the side to move.
+//! * `POST /libraries?name=game.lib`: reads the body as a library of the type of `name`, and
+//!   answers with its number.
+//!
+//! Answers are JSON. Errors have the status of the [`Error`] and its message as the body.
+//!
+//! To serve privately, give an [`Auth`] to [`serve_with_auth`]. Requests then need a token, as
+//! `Authorization: Bearer <token>` or, for clients that can't set headers, as `?token=<token>`.
+//! Requests without a valid token are answered with `401 Unauthorized`.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use axum::extract::{DefaultBodyLimit, Path as UrlPath, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+
+use crate::board::notation;
+use crate::board::pages::{NodeSummary, Page};
+use crate::board::{Board, BoardArr, MoveIndex, Stone};
+use crate::engine::{self, EngineKind, Limits, SearchResult};
+use crate::errors::ParseError;
+use crate::file_reader::{self, FileType};
+use crate::project::Collection;
+use crate::share;
+use crate::Error;
+
+/// The largest library accepted by `/libraries`, in bytes.
+pub const UPLOAD_LIMIT: usize = 64 * 1024 * 1024;
+
+/// The most children of a `/node/{id}/children` page.
+pub const PAGE_LIMIT: usize = 200;
+
+/// The libraries of a server, shared by its requests.
+///
+/// Positions deeper than [`INDEX_DEPTH`](crate::project::INDEX_DEPTH) are not found by `/position`.
+#[derive(Default)]
+pub struct Libraries {
+    inner: Arc<RwLock<Collection>>,
+    /// The budget of `/analyze`.
+    pub limits: Limits,
+}
+
+/// Decides which requests are allowed, by their token.
+///
+/// Closures taking the token are [`Auth`]s as well, to check tokens against a database or
+/// another service.
+pub trait Auth: Send + Sync + 'static {
+    /// Whether `token` may use the server.
+    fn validate(&self, token: &str) -> bool;
+}
+
+impl<F: Fn(&str) -> bool + Send + Sync + 'static> Auth for F {
+    fn validate(&self, token: &str) -> bool {
+        self(token)
+    }
+}
+
+/// A single token shared by every client.
+#[derive(Clone)]
+pub struct Token(String);
+
+impl Token {
+    #[must_use]
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Token(..)")
+    }
+}
+
+impl Auth for Token {
+    fn validate(&self, token: &str) -> bool {
+        // walk every byte given, whatever its length, so that the time taken tells neither how
+        // much of the token is right nor how long it is
+        let expected = self.0.as_bytes();
+        let diff = token
+            .bytes()
+            .enumerate()
+            .fold(expected.len() ^ token.len(), |diff, (i, b)| {
+                let a = expected
+                    .get(i % expected.len().max(1))
+                    .copied()
+                    .unwrap_or(0);
+                diff | usize::from(a ^ b)
+            });
+        diff == 0
+    }
+}
+
+/// A node reaching a position, answered by `/position/{hash}`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Found {
+    pub library: usize,
+    pub name: String,
+    /// The numbers of the nodes, as in `/node/{id}/children`.
+    pub nodes: Vec<usize>,
+}
+
+impl Libraries {
+    #[must_use]
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            inner: Arc::default(),
+            limits,
+        }
+    }
+
+    /// Serve the libraries of `collection`, shared with e.g. a [`listen`](crate::live::listen)ing
+    /// socket. Uploads are added to it.
+    #[must_use]
+    pub fn shared(collection: Arc<RwLock<Collection>>, limits: Limits) -> Self {
+        Self {
+            inner: collection,
+            limits,
+        }
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Collection> {
+        // a panic in a request can't leave the libraries half changed
+        self.inner
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Serve `graph` as `name`, returning its number.
+    pub fn add(&self, name: impl Into<String>, graph: Board) -> usize {
+        self.inner
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .add(name, graph)
+    }
+
+    /// Read `bytes` as a library of the type of the extension of `name` and serve it.
+    pub fn upload(&self, name: &str, bytes: &[u8]) -> crate::Result<usize> {
+        let filetype = FileType::new(Path::new(name))
+            .ok_or_else(|| ParseError::Other(format!("unknown type of library {name:?}")))?;
+        let mut graph = Board::new();
+        file_reader::read_bytes(bytes, Some(&filetype), &mut graph)?;
+        Ok(self.add(name, graph))
+    }
+
+    /// The amount of libraries served.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Where the position of the link `hash` is reached.
+    pub fn position(&self, hash: &str) -> crate::Result<Vec<Found>> {
+        let url = match hash.starts_with(share::SCHEME) {
+            true => hash.to_owned(),
+            false => format!("{}{hash}", share::SCHEME),
+        };
+        let board = share::decode_url(&url)?.board();
+        let inner = self.read();
+        Ok(inner
+            .find(&board)
+            .iter()
+            .map(|location| Found {
+                library: location.file,
+                name: inner.libraries()[location.file].name.clone(),
+                nodes: location
+                    .nodes
+                    .iter()
+                    .map(|node| node.node_index().index())
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// At most `limit` continuations of the node `id` of `library` from the `offset`th.
+    pub fn children(
+        &self,
+        library: usize,
+        id: &str,
+        offset: usize,
+        limit: usize,
+    ) -> crate::Result<Page<NodeSummary>> {
+        let inner = self.read();
+        let graph = &inner
+            .libraries()
+            .get(library)
+            .ok_or_else(|| Error::Graph(format!("no library {library}")))?
+            .graph;
+        let index = id.parse::<MoveIndex>()?;
+        if graph.get_move(index).is_none() {
+            return Err(Error::Graph(format!("no node {id} in library {library}")));
+        }
+        Ok(graph.children_page(index, offset, limit.min(PAGE_LIMIT)))
+    }
+
+    /// Search the position after `moves` for the side to move.
+    pub fn analyze(&self, moves: &str, engine: EngineKind) -> crate::Result<SearchResult> {
+        let moves = notation::parse_moves(moves)?;
+        let mut board = BoardArr::new(15);
+        let mut stone = Stone::Black;
+        for point in moves {
+            if !point.is_null {
+                if board.get_point(point).map(|m| m.color) != Some(Stone::Empty) {
+                    return Err(Error::Rules(format!("{point} is taken or off the board")));
+                }
+                board.set_point(point, stone);
+            }
+            stone = stone.opposite();
+        }
+        Ok(engine::search(&board, stone, engine, &self.limits))
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Error::Parse(_) | Error::Rules(_) => StatusCode::BAD_REQUEST,
+            Error::Graph(_) => StatusCode::NOT_FOUND,
+            Error::Cancelled(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::Changed(_) => StatusCode::CONFLICT,
+            Error::Io(_) | Error::Engine(_) | Error::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// The routes of the server, see the [module](self).
+pub fn router(libraries: Arc<Libraries>) -> Router {
+    Router::new()
+        .route("/position/{hash}", get(position))
+        .route("/node/{id}/children", get(children))
+        .route("/analyze", get(analyze))
+        .route("/libraries", post(upload))
+        .layer(DefaultBodyLimit::max(UPLOAD_LIMIT))
+        .with_state(libraries)
+}
+
+/// Like [`router`], answering only requests with a token allowed by `auth`.
+pub fn router_with_auth(libraries: Arc<Libraries>, auth: impl Auth) -> Router {
+    let auth: Arc<dyn Auth> = Arc::new(auth);
+    router(libraries).layer(middleware::from_fn_with_state(auth, authorize))
+}
+
+/// Serve `libraries` on `addr` until the process ends.
+pub fn serve(addr: SocketAddr, libraries: Arc<Libraries>) -> crate::Result<()> {
+    tracing::info!("Serving {} libraries on {addr}", libraries.len());
+    run(addr, router(libraries))
+}
+
+/// Like [`serve`], answering only requests with a token allowed by `auth`.
+pub fn serve_with_auth(
+    addr: SocketAddr,
+    libraries: Arc<Libraries>,
+    auth: impl Auth,
+) -> crate::Result<()> {
+    tracing::info!(
+        "Serving {} libraries on {addr}, with tokens",
+        libraries.len()
+    );
+    run(addr, router_with_auth(libraries, auth))
+}
+
+fn run(addr: SocketAddr, router: Router) -> crate::Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_io()
+        .build()?;
+    runtime.block_on(async {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        axum::serve(listener, router).await?;
+        Ok(())
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct TokenQuery {
+    token: String,
+}
+
+/// The token of `request`, from the `Authorization` header or else the `token` parameter.
+fn token(request: &Request) -> Option<String> {
+    let header = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match header {
+        Some(token) => Some(token.trim().to_owned()),
+        None => Query::<TokenQuery>::try_from_uri(request.uri())
+            .ok()
+            .map(|query| query.0.token),
+    }
+}
+
+async fn authorize(State(auth): State<Arc<dyn Auth>>, request: Request, next: Next) -> Response {
+    match token(&request) {
+        Some(token) if auth.validate(&token) => next.run(request).await,
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "a valid token is needed",
+        )
+            .into_response(),
+    }
+}
+
+async fn position(
+    State(libraries): State<Arc<Libraries>>,
+    UrlPath(hash): UrlPath<String>,
+) -> crate::Result<Json<Vec<Found>>> {
+    libraries.position(&hash).map(Json)
+}
+
+#[derive(serde::Deserialize)]
+struct ChildrenQuery {
+    #[serde(default)]
+    library: usize,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+async fn children(
+    State(libraries): State<Arc<Libraries>>,
+    UrlPath(id): UrlPath<String>,
+    Query(query): Query<ChildrenQuery>,
+) -> crate::Result<Json<Page<NodeSummary>>> {
+    libraries
+        .children(query.library, &id, query.offset, query.limit)
+        .map(Json)
+}
+
+#[derive(serde::Deserialize)]
+struct AnalyzeQuery {
+    #[serde(default)]
+    moves: String,
+    #[serde(default)]
+    engine: EngineKind,
+}
+
+async fn analyze(
+    State(libraries): State<Arc<Libraries>>,
+    Query(query): Query<AnalyzeQuery>,
+) -> crate::Result<Json<SearchResult>> {
+    // searches take a while, keep them off the threads answering requests
+    tokio::task::spawn_blocking(move || libraries.analyze(&query.moves, query.engine))
+        .await
+        .map_err(|e| Error::Engine(e.to_string()))?
+        .map(Json)
+}
+
+#[derive(serde::Deserialize)]
+struct UploadQuery {
+    name: String,
+}
+
+async fn upload(
+    State(libraries): State<Arc<Libraries>>,
+    Query(query): Query<UploadQuery>,
+    body: axum::body::Bytes,
+) -> crate::Result<(StatusCode, Json<usize>)> {
+    let library = libraries.upload(&query.name, &body)?;
+    Ok((StatusCode::CREATED, Json(library)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Point;
+    use crate::p;
+
+    #[test]
+    fn answers() {
+        let libraries = Libraries::new(Limits {
+            depth: 2,
+            ..Limits::default()
+        });
+        let mut graph = Board::new();
+        let h8 = graph.add_line(&p![[H, 8]]);
+        graph.add_line(&p![[H, 8], [I, 9], [J, 10]]);
+        graph.add_line(&p![[H, 8], [I, 8]]);
+        assert_eq!(libraries.add("a.lib", graph), 0);
+
+        let mut text = vec![];
+        let mut uploaded = Board::new();
+        uploaded.add_line(&p![[H, 8], [G, 7]]);
+        file_reader::text::write(&uploaded, &mut text).unwrap();
+        assert_eq!(libraries.upload("b.txt", &text).unwrap(), 1);
+        assert!(matches!(
+            libraries.upload("b.doc", &text),
+            Err(Error::Parse(_))
+        ));
+
+        let id = h8.node_index().index().to_string();
+        let page = libraries.children(0, &id, 0, 10).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.items[0].point, p![I, 9]);
+        assert!(matches!(
+            libraries.children(2, &id, 0, 10),
+            Err(Error::Graph(_))
+        ));
+
+        // I9 is G7 mirrored, so both libraries reach the position
+        let hash = share::encode_url(&p![[H, 8], [I, 9]]);
+        let found = libraries.position(&hash).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].name, "b.txt");
+        let hash = hash.strip_prefix(share::SCHEME).unwrap();
+        assert_eq!(libraries.position(hash).unwrap(), found);
+        assert!(libraries.position("!").is_err());
+
+        let result = libraries
+            .analyze("H8 I9 H9", EngineKind::AlphaBeta)
+            .unwrap();
+        assert!(result.best.is_some());
+        assert!(matches!(
+            libraries.analyze("H8 H8", EngineKind::AlphaBeta),
+            Err(Error::Rules(_))
+        ));
+    }
+
+    #[test]
+    fn tokens() {
+        let request = |uri: &str, header: Option<&str>| {
+            let mut request = axum::http::Request::builder().uri(uri);
+            if let Some(header) = header {
+                request = request.header(header::AUTHORIZATION, header);
+            }
+            request.body(axum::body::Body::empty()).unwrap()
+        };
+        assert_eq!(token(&request("/analyze", None)), None);
+        assert_eq!(
+            token(&request("/analyze", Some("Bearer secret"))).as_deref(),
+            Some("secret")
+        );
+        assert_eq!(
+            token(&request("/analyze?moves=H8&token=se%20cret", None)).as_deref(),
+            Some("se cret")
+        );
+        assert_eq!(token(&request("/analyze", Some("Basic secret"))), None);
+
+        let auth = Token::new("secret");
+        assert!(auth.validate("secret"));
+        assert!(!auth.validate("secreT") && !auth.validate("secrets") && !auth.validate(""));
+        assert!(!auth.validate("secretsecret") && !auth.validate("\0"));
+        assert!(Token::new("").validate("") && !Token::new("").validate("\0"));
+        let auth = |token: &str| token.starts_with("team-");
+        assert!(auth.validate("team-alice") && !Auth::validate(&auth, "alice"));
+    }
+}
@@ -0,0 +1,110 @@
+//! State of the CLI kept between runs on the same library.
+//!
+//! A [`Session`] is keyed by a hash of the library file, see [`library_key`], so renaming or
+//! moving a library keeps its session while changing it starts a new one.
+
+use std::collections::BTreeMap;
+
+use crate::board::{Board, MoveIndex};
+use crate::training::Training;
+
+/// Per library state, the last visited node, bookmarks and quiz progress.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    /// The node shown last.
+    pub last: Option<MoveIndex>,
+    /// Bookmarks made in the CLI, on top of the ones stored in the library.
+    pub bookmarks: BTreeMap<String, MoveIndex>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub training: Training,
+}
+
+/// Key for the library with the contents `bytes`.
+#[must_use]
+pub fn library_key(bytes: &[u8]) -> String {
+    crate::util::stable_hash(bytes.iter().copied())
+}
+
+/// Where the session of the library with `key` is kept, see [`crate::util::data_dir`].
+#[must_use]
+pub fn path(key: &str) -> Option<std::path::PathBuf> {
+    Some(
+        crate::util::data_dir()?
+            .join("sessions")
+            .join(format!("{key}.json")),
+    )
+}
+
+impl Session {
+    /// Add the bookmarks of the session to `board`, skipping nodes it doesn't have, and forget
+    /// a last node it doesn't have.
+    pub fn restore(&mut self, board: &mut Board) {
+        self.last = self.last.filter(|&last| board.get_move(last).is_some());
+        for (name, &index) in &self.bookmarks {
+            if board.get_move(index).is_some() && board.get_bookmark(name).is_none() {
+                board.set_bookmark(name.clone(), index);
+            }
+        }
+    }
+
+    /// Remember the bookmarks of `board`.
+    pub fn update(&mut self, board: &Board) {
+        self.bookmarks.clone_from(board.bookmarks());
+    }
+
+    /// Load from a JSON file, a missing file gives an empty session.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> crate::Result<Self> {
+        match std::fs::File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save as a JSON file, creating the directories leading to it.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &std::path::Path) -> crate::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardMarker, Point, Stone};
+    use crate::p;
+
+    #[test]
+    fn restore() {
+        let mut board = Board::new();
+        let root = board.get_root();
+        let h8 = board.insert_move(root, BoardMarker::new(p![H, 8], Stone::Black));
+        board.set_bookmark("Library", h8);
+
+        let mut session = Session::default();
+        session.update(&board);
+        session.bookmarks.insert(
+            "Gone".to_owned(),
+            MoveIndex::new_node(daggy::NodeIndex::new(99)),
+        );
+        session.bookmarks.insert("Mine".to_owned(), root);
+        session.last = Some(MoveIndex::new_node(daggy::NodeIndex::new(99)));
+
+        board.remove_bookmark("Library");
+        session.restore(&mut board);
+        assert_eq!(session.last, None);
+        assert_eq!(
+            board.bookmarks().keys().collect::<Vec<_>>(),
+            ["Library", "Mine"]
+        );
+        assert_eq!(library_key(b""), "cbf29ce484222325");
+        assert_ne!(library_key(b"a"), library_key(b"b"));
+    }
+}
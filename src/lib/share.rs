@@ -0,0 +1,176 @@
+//! Positions as short links, to share them outside of a library.
+//!
+//! A link is `renju:` followed by the base64url encoding, without padding, of a kind byte and
+//! the points as [`Point::to_byte`]. A line of moves is the moves in order, a position without a
+//! move order is the black stones, a pass and the white stones.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine as _;
+
+use crate::board::notation;
+use crate::board::{BoardArr, Point, Stone};
+use crate::errors::ParseError;
+
+/// The start of every link.
+pub const SCHEME: &str = "renju:";
+
+const MOVES: u8 = 1;
+const POSITION: u8 = 2;
+
+/// What a link holds.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shared {
+    /// Moves in order, passes included.
+    Moves(Vec<Point>),
+    /// Black and white stones, sorted.
+    Position {
+        black: Vec<Point>,
+        white: Vec<Point>,
+    },
+}
+
+impl Shared {
+    /// The moves of the link, for a position the ones of [`notation::position_moves`].
+    #[must_use]
+    pub fn moves(&self) -> Option<Vec<Point>> {
+        match self {
+            Shared::Moves(moves) => Some(moves.clone()),
+            Shared::Position { .. } => notation::position_moves(&self.board()),
+        }
+    }
+
+    /// The board after the moves or with the stones of the link.
+    #[must_use]
+    pub fn board(&self) -> BoardArr {
+        let mut board = BoardArr::new(15);
+        match self {
+            Shared::Moves(moves) => {
+                let mut stone = Stone::Black;
+                for point in moves {
+                    if !point.is_null {
+                        board.set_point(*point, stone);
+                    }
+                    stone = stone.opposite();
+                }
+            }
+            Shared::Position { black, white } => {
+                for point in black {
+                    board.set_point(*point, Stone::Black);
+                }
+                for point in white {
+                    board.set_point(*point, Stone::White);
+                }
+            }
+        }
+        board
+    }
+}
+
+/// A link to the position after `moves`.
+#[must_use]
+pub fn encode_url(moves: &[Point]) -> String {
+    encode(MOVES, moves.iter().copied())
+}
+
+/// A link to the stones of `board`, without a move order.
+#[must_use]
+pub fn encode_position_url(board: &BoardArr) -> String {
+    let stones = |stone: Stone| {
+        board
+            .iter()
+            .filter(move |m| m.color == stone)
+            .map(|m| m.point)
+    };
+    let points = stones(Stone::Black)
+        .chain([Point::null()])
+        .chain(stones(Stone::White));
+    encode(POSITION, points)
+}
+
+fn encode(kind: u8, points: impl Iterator<Item = Point>) -> String {
+    let bytes = std::iter::once(kind)
+        .chain(points.map(Point::to_byte))
+        .collect::<Vec<_>>();
+    format!("{SCHEME}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Read a link made by [`encode_url`] or [`encode_position_url`].
+pub fn decode_url(url: &str) -> Result<Shared, ParseError> {
+    let invalid = |reason: &str| ParseError::Other(format!("invalid link {url:?}, {reason}"));
+    let encoded = url
+        .trim()
+        .strip_prefix(SCHEME)
+        .ok_or_else(|| invalid("it should start with renju:"))?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|e| invalid(&e.to_string()))?;
+    let point = |byte: u8| match byte {
+        0 => Ok(Point::null()),
+        byte if byte & 0x0f == 0 || byte >> 4 > 14 => Err(invalid("a point is outside the board")),
+        byte => Point::from_byte(byte),
+    };
+    match bytes.split_first() {
+        Some((&MOVES, moves)) => Ok(Shared::Moves(
+            moves.iter().map(|&b| point(b)).collect::<Result<_, _>>()?,
+        )),
+        Some((&POSITION, stones)) => {
+            let separator = stones
+                .iter()
+                .position(|&b| b == 0)
+                .ok_or_else(|| invalid("the white stones are missing"))?;
+            let points = |bytes: &[u8]| {
+                let mut points = bytes
+                    .iter()
+                    .map(|&b| point(b))
+                    .collect::<Result<Vec<_>, _>>()?;
+                points.sort();
+                if points.iter().any(|p| p.is_null) {
+                    return Err(invalid("a position can't have passes"));
+                }
+                Ok(points)
+            };
+            Ok(Shared::Position {
+                black: points(&stones[..separator])?,
+                white: points(&stones[separator + 1..])?,
+            })
+        }
+        _ => Err(invalid("unknown kind")),
+    }
+}
+
+/// `url` as a QR code of unicode blocks, to print in a terminal.
+#[cfg(feature = "qr")]
+pub fn qr(url: &str) -> Result<String, ParseError> {
+    let code = qrcode::QrCode::new(url).map_err(|e| ParseError::Other(e.to_string()))?;
+    Ok(code
+        .render::<qrcode::render::unicode::Dense1x2>()
+        .quiet_zone(true)
+        .build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn round_trip() {
+        let moves = vec![p![H, 8], p![I, 9], Point::null(), p![O, 1], p![A, 15]];
+        let url = encode_url(&moves);
+        assert_eq!(url, "renju:AXhpAO8B");
+        assert_eq!(decode_url(&url).unwrap(), Shared::Moves(moves.clone()));
+
+        let board = Shared::Moves(moves).board();
+        let url = encode_position_url(&board);
+        let Shared::Position { black, white } = decode_url(&url).unwrap() else {
+            panic!("not a position");
+        };
+        assert_eq!(black, [p![A, 15], p![H, 8]]);
+        assert_eq!(white, [p![I, 9], p![O, 1]]);
+
+        assert!(decode_url("renju:AQ").is_ok());
+        assert!(decode_url("AXiI").is_err());
+        assert!(decode_url("renju:A!").is_err());
+        assert!(decode_url("renju:ARA").is_err());
+    }
+}
@@ -0,0 +1,519 @@
+//! Threat space solvers.
+//!
+//! This module searches for victories by continuous fours (VCF). Every attacker move has to be a
+//! four, so the defender only ever has one reply, which keeps the search narrow. Forbidden points
+//! come from [`Evaluate::renju_conditions`]. [`explain`] turns a winning line into a [`Proof`]
+//! stating the threat of every move.
+//!
+//! The solvers work on every [board backend](crate::board::backend), e.g. a
+//! [`BoardConst`](crate::board::BoardConst) for searches which clone the board often.
+//!
+//! Wins using threes as well are found by [`vct`], and for certainty on small or nearly finished
+//! positions, [`pns`] proves them won, lost or drawn.
+
+use crate::board::evaluator::Conditions;
+use crate::board::{Evaluate, Point, Stone};
+use crate::limits::{Budget, Exhausted, Limits, Outcome};
+use crate::progress::{Cancelled, Progress};
+
+pub mod pns;
+pub mod vct;
+
+/// Whether `stone` placed on `point` makes a five. For black an overline is not a five.
+#[must_use]
+pub fn makes_five<B: Evaluate>(board: &B, point: Point, stone: Stone) -> bool {
+    let table = board.table();
+    let Some(through) = table.through(point) else {
+        return false;
+    };
+    through.iter().any(|&(line, index)| {
+        let points = table.line(line).1;
+        let same = |&i: &usize| board.line_stone(line, i, points[i]) == stone;
+        let before = (0..index).rev().take_while(same).count();
+        let after = (index + 1..points.len()).take_while(same).count();
+        let length = 1 + before + after;
+        length == 5 || (length > 5 && stone.is_white())
+    })
+}
+
+/// Places where `stone` can make a five.
+pub(crate) fn five_points<B: Evaluate>(board: &B, stone: Stone) -> Vec<Point> {
+    board
+        .points()
+        .filter(|p| board.stone_at(*p).is_empty() && makes_five(board, *p, stone))
+        .collect()
+}
+
+/// Places where `stone` can make a four, excluding forbidden points.
+pub(crate) fn four_points<B: Evaluate>(board: &B, stone: Stone) -> Vec<Point> {
+    let forbidden = board.forbidden(stone);
+    let empty = board
+        .points()
+        .filter(|p| board.stone_at(*p).is_empty() && !forbidden.contains(p))
+        .collect::<Vec<_>>();
+    let mut board = board.clone();
+    empty
+        .into_iter()
+        .filter(|point| {
+            board.set_stone(*point, stone);
+            let table = board.table();
+            let four = table
+                .through(*point)
+                .into_iter()
+                .flatten()
+                .any(|&(line, index)| {
+                    let points = table.line(line).1;
+                    (index.saturating_sub(4)..(index + 5).min(points.len())).any(|i| {
+                        board.line_stone(line, i, points[i]).is_empty()
+                            && makes_five(&board, points[i], stone)
+                    })
+                });
+            board.set_stone(*point, Stone::Empty);
+            four
+        })
+        .collect()
+}
+
+/// Find a victory by continuous fours for `stone`, using at most `max_depth` fours.
+///
+/// The returned line alternates between attacker and defender moves, starting and ending with an
+/// attacker move. The last move makes a five, or is a four the defender can't stop.
+#[must_use]
+pub fn vcf<B: Evaluate>(board: &B, stone: Stone, max_depth: usize) -> Option<Vec<Point>> {
+    vcf_with_progress(board, stone, max_depth, &mut Progress::none()).expect("no token to cancel")
+}
+
+/// Like [`vcf`], reporting every first four tried.
+pub fn vcf_with_progress<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    max_depth: usize,
+    progress: &mut Progress,
+) -> Result<Option<Vec<Point>>, Cancelled> {
+    vcf_search(board, stone, max_depth, progress, &mut Budget::unlimited()).map_err(Stop::unlimited)
+}
+
+/// Like [`vcf`], using at most `limits.depth` fours and giving up when `limits` is reached. Every
+/// four played counts as a node.
+#[must_use]
+pub fn vcf_with_limits<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    limits: &Limits,
+) -> Outcome<Option<Vec<Point>>> {
+    let mut budget = Budget::new(limits);
+    // the depth limits the fours, not the double-three checks
+    budget.max_depth = None;
+    match vcf_search(
+        board,
+        stone,
+        limits.depth as usize,
+        &mut Progress::none(),
+        &mut budget,
+    ) {
+        Ok(line) => Outcome::Known(line),
+        Err(Stop::Exhausted) => Outcome::Unknown,
+        Err(Stop::Cancelled) => unreachable!("no token to cancel"),
+    }
+}
+
+/// Why a VCF search stopped early.
+enum Stop {
+    Cancelled,
+    Exhausted,
+}
+
+impl Stop {
+    /// For searches without limits, which can only be cancelled.
+    fn unlimited(self) -> Cancelled {
+        match self {
+            Stop::Cancelled => Cancelled,
+            Stop::Exhausted => unreachable!("unlimited budget"),
+        }
+    }
+}
+
+impl From<Cancelled> for Stop {
+    fn from(_: Cancelled) -> Self {
+        Stop::Cancelled
+    }
+}
+
+impl From<Exhausted> for Stop {
+    fn from(_: Exhausted) -> Self {
+        Stop::Exhausted
+    }
+}
+
+fn vcf_search<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    max_depth: usize,
+    progress: &mut Progress,
+    budget: &mut Budget,
+) -> Result<Option<Vec<Point>>, Stop> {
+    if let Some(five) = five_points(board, stone).first() {
+        return Ok(Some(vec![*five]));
+    }
+    let fours = four_points(board, stone);
+    let total = fours.len() as u64;
+    for (i, four) in fours.into_iter().enumerate() {
+        progress.update("vcf", i as u64, Some(total))?;
+        if let Some(line) = vcf_after(board, stone, four, max_depth, progress, budget)? {
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+/// Every first move that starts a VCF for `stone`, each with its winning line.
+#[must_use]
+pub fn vcf_first_moves<B: Evaluate>(board: &B, stone: Stone, max_depth: usize) -> Vec<Vec<Point>> {
+    vcf_first_moves_with_progress(board, stone, max_depth, &mut Progress::none())
+        .expect("no token to cancel")
+}
+
+/// Like [`vcf_first_moves`], reporting every first four tried.
+pub fn vcf_first_moves_with_progress<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    max_depth: usize,
+    progress: &mut Progress,
+) -> Result<Vec<Vec<Point>>, Cancelled> {
+    let fives = five_points(board, stone);
+    if !fives.is_empty() {
+        return Ok(fives.into_iter().map(|p| vec![p]).collect());
+    }
+    let fours = four_points(board, stone);
+    let total = fours.len() as u64;
+    let mut lines = vec![];
+    for (i, four) in fours.into_iter().enumerate() {
+        progress.update("vcf", i as u64, Some(total))?;
+        lines.extend(
+            vcf_after(
+                board,
+                stone,
+                four,
+                max_depth,
+                progress,
+                &mut Budget::unlimited(),
+            )
+            .map_err(Stop::unlimited)?,
+        );
+    }
+    Ok(lines)
+}
+
+/// Continue a VCF after `stone` plays the four at `four`.
+fn vcf_after<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    four: Point,
+    max_depth: usize,
+    progress: &mut Progress,
+    budget: &mut Budget,
+) -> Result<Option<Vec<Point>>, Stop> {
+    progress.check()?;
+    budget.spend()?;
+    if max_depth == 0 {
+        return Ok(None);
+    }
+    let defender = stone.opposite();
+    let mut board = board.clone();
+    board.set_stone(four, stone);
+
+    // the defender wins before having to block
+    if !five_points(&board, defender).is_empty() {
+        return Ok(None);
+    }
+    let line = match five_points(&board, stone)[..] {
+        [] => return Ok(None),
+        // a straight four or a double four, can't be stopped
+        [block, win, ..] => vec![four, block, win],
+        [block] => {
+            if defender.is_black()
+                && board
+                    .conditions_with_budget(Stone::Black, Some(&[block]), budget, 0)?
+                    .forbidden
+                    .contains(&block)
+            {
+                // black can't block on a forbidden point, the four wins
+                vec![four]
+            } else {
+                board.set_stone(block, defender);
+                let mut line = vec![four, block];
+                let mut rest = None;
+                for next in four_points(&board, stone) {
+                    rest = vcf_after(&board, stone, next, max_depth - 1, progress, budget)?;
+                    if rest.is_some() {
+                        break;
+                    }
+                }
+                let Some(rest) = rest else {
+                    return Ok(None);
+                };
+                line.extend(rest);
+                line
+            }
+        }
+    };
+    Ok(Some(line))
+}
+
+/// What an attacker move of a winning line threatens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Threat {
+    /// The move makes five, the game is won.
+    Five,
+    /// A four with a single point to block, which the defender has to play.
+    Four { block: Point },
+    /// A straight four or two fours, the defender can only block one of `fives`.
+    DoubleFour { fives: Vec<Point> },
+    /// A four which black can't block, because `block` is forbidden.
+    ForbiddenBlock { block: Point },
+}
+
+/// An attacker move of a [`Proof`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofStep {
+    pub attack: Point,
+    pub threat: Threat,
+}
+
+impl ProofStep {
+    /// The forced reply, `None` if the step ends the proof.
+    #[must_use]
+    pub fn defense(&self) -> Option<Point> {
+        match self.threat {
+            Threat::Four { block } => Some(block),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ProofStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.threat {
+            Threat::Five => write!(f, "{} makes five", self.attack),
+            Threat::Four { block } => write!(f, "{} four, {block} is forced", self.attack),
+            Threat::DoubleFour { fives } => write!(
+                f,
+                "{} fours at {}, only one can be blocked",
+                self.attack,
+                fives
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            ),
+            Threat::ForbiddenBlock { block } => {
+                write!(f, "{} four, the block at {block} is forbidden", self.attack)
+            }
+        }
+    }
+}
+
+/// A winning line with the threat made by every attacker move, see [`explain`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+    pub stone: Stone,
+    pub steps: Vec<ProofStep>,
+}
+
+impl Proof {
+    /// The moves of the proof, alternating between attacker and defender.
+    #[must_use]
+    pub fn line(&self) -> Vec<Point> {
+        self.steps
+            .iter()
+            .flat_map(|step| std::iter::once(step.attack).chain(step.defense()))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Proof {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{:?} wins by VCF", self.stone)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{:>3}. {step}", i + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Explain a winning `line` for `stone`, as returned by [`vcf`]. `None` if an attacker move of
+/// `line` is not a four or the defender doesn't block it.
+#[must_use]
+pub fn explain<B: Evaluate>(board: &B, stone: Stone, line: &[Point]) -> Option<Proof> {
+    let defender = stone.opposite();
+    let mut board = board.clone();
+    let mut steps = vec![];
+    let mut moves = line.iter();
+    while let Some(&attack) = moves.next() {
+        if !board.contains(attack) || !board.stone_at(attack).is_empty() {
+            return None;
+        }
+        let five = makes_five(&board, attack, stone);
+        board.set_stone(attack, stone);
+        let threat = match five_points(&board, stone)[..] {
+            _ if five => Threat::Five,
+            [] => return None,
+            [block] if defender.is_black() && board.forbidden(defender).contains(&block) => {
+                Threat::ForbiddenBlock { block }
+            }
+            [block] => Threat::Four { block },
+            ref fives => Threat::DoubleFour {
+                fives: fives.to_vec(),
+            },
+        };
+        let defense = match threat {
+            Threat::Four { block } => block,
+            // the rest of the line only shows how the win is completed
+            _ => {
+                steps.push(ProofStep { attack, threat });
+                break;
+            }
+        };
+        if moves.next() != Some(&defense) {
+            return None;
+        }
+        board.set_stone(defense, defender);
+        steps.push(ProofStep { attack, threat });
+    }
+    steps
+        .last()
+        .is_some_and(|step| step.defense().is_none())
+        .then_some(Proof { stone, steps })
+}
+
+/// Like [`vcf`], explaining the winning line.
+#[must_use]
+pub fn vcf_proof<B: Evaluate>(board: &B, stone: Stone, max_depth: usize) -> Option<Proof> {
+    explain(board, stone, &vcf(board, stone, max_depth)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardArr;
+    use crate::p;
+
+    #[test]
+    fn immediate_five() {
+        let board = BoardArr::from_stones(15, &p![[D, 8], [E, 8], [F, 8], [G, 8]], &[]);
+        let line = vcf(&board, Stone::Black, 3).unwrap();
+        assert!(line == [p![C, 8]] || line == [p![H, 8]], "{line:?}");
+    }
+
+    #[test]
+    fn no_vcf() {
+        let board = BoardArr::from_stones(15, &p![[H, 8]], &p![[I, 9]]);
+        assert_eq!(vcf(&board, Stone::Black, 5), None);
+        assert_eq!(vcf(&board, Stone::White, 5), None);
+    }
+
+    #[test]
+    fn four_three() {
+        // J7 is a four on column J and a three on row 7. After black blocks at J8, I7 makes a
+        // straight four.
+        let board =
+            BoardArr::from_stones(15, &p![[J, 3]], &p![[J, 4], [J, 5], [J, 6], [G, 7], [H, 7]]);
+        assert_eq!(vcf(&board, Stone::White, 1), None);
+        let line = vcf(&board, Stone::White, 2).unwrap();
+        assert_eq!(line[..3], p![[J, 7], [J, 8], [I, 7]], "{line:?}");
+        assert_eq!(line.len(), 5, "{line:?}");
+
+        assert_eq!(vcf(&board, Stone::Black, 5), None);
+    }
+
+    #[test]
+    fn explained_four_three() {
+        let board =
+            BoardArr::from_stones(15, &p![[J, 3]], &p![[J, 4], [J, 5], [J, 6], [G, 7], [H, 7]]);
+        let proof = vcf_proof(&board, Stone::White, 2).unwrap();
+        assert_eq!(proof.steps[0].attack, p![J, 7]);
+        assert_eq!(proof.steps[0].threat, Threat::Four { block: p![J, 8] });
+        assert_eq!(proof.steps[1].attack, p![I, 7]);
+        assert!(matches!(proof.steps[1].threat, Threat::DoubleFour { .. }));
+        assert_eq!(proof.line()[..3], p![[J, 7], [J, 8], [I, 7]]);
+        assert!(proof.to_string().contains("J7 four, J8 is forced"));
+
+        // the block has to be played
+        assert_eq!(
+            explain(&board, Stone::White, &p![[J, 7], [A, 1], [I, 7]]),
+            None
+        );
+        // not a four
+        assert_eq!(explain(&board, Stone::White, &p![[A, 1]]), None);
+    }
+
+    #[test]
+    fn forbidden_block() {
+        // G8 is a four, its only block at H8 is a double three for black
+        let board = BoardArr::from_stones(
+            15,
+            &p![[C, 8], [I, 9], [J, 10], [H, 10], [H, 11]],
+            &p![[D, 8], [E, 8], [F, 8]],
+        );
+        let line = vcf(&board, Stone::White, 1).unwrap();
+        assert_eq!(line, [p![G, 8]]);
+        let proof = explain(&board, Stone::White, &line).unwrap();
+        assert_eq!(
+            proof.steps[0].threat,
+            Threat::ForbiddenBlock { block: p![H, 8] }
+        );
+    }
+
+    #[test]
+    fn unique_first_move() {
+        let board = BoardArr::from_stones(
+            15,
+            &p![[C, 8], [H, 12]],
+            &p![[D, 8], [E, 8], [F, 8], [H, 9], [H, 10], [H, 11]],
+        );
+        let moves = vcf_first_moves(&board, Stone::White, 1);
+        assert_eq!(moves.len(), 1, "{moves:?}");
+        assert_eq!(moves[0][0], p![H, 8]);
+    }
+
+    #[test]
+    fn cancelled_vcf() {
+        let board =
+            BoardArr::from_stones(15, &p![[J, 3]], &p![[J, 4], [J, 5], [J, 6], [G, 7], [H, 7]]);
+        let mut updates = 0;
+        let mut progress = Progress::new(|_| updates += 1);
+        assert!(vcf_with_progress(&board, Stone::White, 2, &mut progress)
+            .unwrap()
+            .is_some());
+        progress.token().cancel();
+        assert_eq!(
+            vcf_with_progress(&board, Stone::White, 2, &mut progress),
+            Err(Cancelled)
+        );
+        drop(progress);
+        assert!(updates > 0);
+    }
+
+    #[test]
+    fn limited_vcf() {
+        let board =
+            BoardArr::from_stones(15, &p![[J, 3]], &p![[J, 4], [J, 5], [J, 6], [G, 7], [H, 7]]);
+        let limits = Limits {
+            depth: 2,
+            ..Limits::default()
+        };
+        assert!(matches!(
+            vcf_with_limits(&board, Stone::White, &limits),
+            Outcome::Known(Some(_))
+        ));
+        let limits = Limits {
+            depth: 2,
+            nodes: Some(1),
+            ..Limits::default()
+        };
+        assert_eq!(
+            vcf_with_limits(&board, Stone::White, &limits),
+            Outcome::Unknown
+        );
+    }
+}
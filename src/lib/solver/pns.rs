@@ -0,0 +1,541 @@
+//! Proof-number search, proving positions won, lost or drawn.
+//!
+//! Unlike [`vcf`](super::vcf) every legal move is considered, except that a five has to be made or
+//! blocked when possible, so only small or nearly finished positions can be solved in reasonable
+//! time. Bound the search with [`Limits`]. Solved positions are stored in a [`ProofTable`], which
+//! can be kept between searches to avoid proving the same positions again, and saved to disk with
+//! [`ProofTable::write`].
+//!
+//! # File format
+//!
+//! A proof table file starts with the magic `RJPT`, a format version byte `1` and three reserved
+//! zero bytes, followed by entries until the end of the file
+//!
+//! | bytes | content |
+//! |-------|---------|
+//! | 8     | [`position_hash`], little-endian `u64` |
+//! | 1     | flags, bit 0 the proof is for black, bit 1 won, bit 2 has a best move |
+//! | 1     | best move `x`, `0` if none |
+//! | 1     | best move `y`, `0` if none |
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use super::five_points;
+use crate::board::{BoardArr, Point, Stone};
+use crate::limits::{Budget, Exhausted, Limits, Outcome};
+
+const MAGIC: &[u8; 4] = b"RJPT";
+const VERSION: u8 = 1;
+
+/// Proof and disproof numbers at or above this are infinite.
+const INFINITY: u64 = u64::MAX / 2;
+
+/// The game theoretical value of a position, for the side to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Value {
+    Win,
+    Loss,
+    /// Neither side can force a five before the board is full.
+    Draw,
+}
+
+/// The result of [`solve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub value: Value,
+    /// A move keeping `value`, `None` for a loss or if there are no moves.
+    pub best: Option<Point>,
+    /// Positions expanded by the search.
+    pub nodes: u64,
+}
+
+/// Whether a position is won for a color, with the move proving it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Proven {
+    pub won: bool,
+    /// The winning move if `won` and the color is to move, or the move refuting the win if
+    /// not `won` and the opponent is to move.
+    pub best: Option<Point>,
+}
+
+/// Positions proven by earlier searches, keyed by [`position_hash`] and the color the proof is
+/// for.
+#[derive(Clone, Debug, Default)]
+pub struct ProofTable {
+    entries: HashMap<(u64, bool), Proven>,
+}
+
+impl ProofTable {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `goal` wins the position with hash `hash`, if known.
+    #[must_use]
+    pub fn get(&self, hash: u64, goal: Stone) -> Option<Proven> {
+        self.entries.get(&(hash, goal.is_black())).copied()
+    }
+
+    pub fn insert(&mut self, hash: u64, goal: Stone, proven: Proven) {
+        self.entries.insert((hash, goal.is_black()), proven);
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Add the entries of `other`, replacing those already present.
+    pub fn extend(&mut self, other: ProofTable) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Write the table in the format described in the [module documentation](self).
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, 0, 0, 0])?;
+        // sorted, so the same table always gives the same file
+        let mut entries = self.entries.iter().collect::<Vec<_>>();
+        entries.sort_by_key(|(key, _)| **key);
+        for ((hash, black), proven) in entries {
+            let best = proven.best.unwrap_or(Point::new(0, 0));
+            let flags =
+                u8::from(*black) | u8::from(proven.won) << 1 | u8::from(proven.best.is_some()) << 2;
+            writer.write_all(&hash.to_le_bytes())?;
+            writer.write_all(&[flags, best.x as u8, best.y as u8])?;
+        }
+        writer.flush()
+    }
+
+    /// Read a table written by [`Self::write`].
+    pub fn read(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid("not a version 1 proof table"));
+        }
+        let mut table = Self::new();
+        let mut record = [0u8; 11];
+        loop {
+            match reader.read_exact(&mut record) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            let hash = u64::from_le_bytes(record[..8].try_into().expect("record is 11 bytes"));
+            let flags = record[8];
+            if flags & !0b111 != 0 {
+                return Err(invalid("invalid flags"));
+            }
+            let best = (flags & 0b100 != 0)
+                .then(|| Point::new(u32::from(record[9]), u32::from(record[10])));
+            table.entries.insert(
+                (hash, flags & 1 != 0),
+                Proven {
+                    won: flags & 0b10 != 0,
+                    best,
+                },
+            );
+        }
+        Ok(table)
+    }
+}
+
+/// A zobrist hash of `board` with `to_move` to play.
+///
+/// The keys are fixed, so hashes are stable between runs and versions and can be stored.
+#[must_use]
+pub fn position_hash(board: &BoardArr, to_move: Stone) -> u64 {
+    // splitmix64
+    let key = |i: u64| {
+        let mut z = i.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    };
+    let size = board.size();
+    let stones = board
+        .iter()
+        .filter(|m| !m.color.is_empty())
+        .fold(key(u64::from(size) << 32), |hash, m| {
+            hash ^ key(2 * u64::from(m.point.to_1d(size)) + u64::from(m.color.is_black()))
+        });
+    if to_move.is_black() {
+        !stones
+    } else {
+        stones
+    }
+}
+
+/// Prove the value of `board` with `to_move` to play, giving up when `limits` is reached.
+///
+/// Every expanded position counts as a node, `limits.depth` is not used. Proven positions are
+/// looked up in and added to `table`.
+#[must_use]
+pub fn solve(
+    board: &BoardArr,
+    to_move: Stone,
+    limits: &Limits,
+    table: &mut ProofTable,
+) -> Outcome<Solution> {
+    let mut budget = Budget::new(limits);
+    budget.max_depth = None;
+    let mut nodes = 0;
+    let mut prove = |goal| {
+        let mut search = Search::new(board, to_move, goal, table);
+        let result = search.run(&mut budget);
+        nodes += search.expanded;
+        result
+    };
+    let solution = match prove(to_move) {
+        Ok(Proven { won: true, best }) => Some((Value::Win, best)),
+        Ok(Proven { won: false, .. }) => match prove(to_move.opposite()) {
+            Ok(Proven { won: true, .. }) => Some((Value::Loss, None)),
+            Ok(Proven { won: false, best }) => Some((Value::Draw, best)),
+            Err(Exhausted) => None,
+        },
+        Err(Exhausted) => None,
+    };
+    match solution {
+        Some((value, best)) => Outcome::Known(Solution { value, best, nodes }),
+        None => Outcome::Unknown,
+    }
+}
+
+/// A position before searching it.
+enum Position {
+    /// The side to move makes five on the point.
+    Five(Point),
+    /// The opponent makes a five next move whatever the side to move does.
+    Lost,
+    Drawn,
+    Open(Vec<Point>),
+}
+
+fn classify(board: &BoardArr, to_move: Stone) -> Position {
+    if let Some(five) = five_points(board, to_move).first() {
+        return Position::Five(*five);
+    }
+    let forbidden = board.forbidden_points(to_move);
+    let threats = five_points(board, to_move.opposite());
+    let moves = match threats[..] {
+        [] => board
+            .iter()
+            .filter(|m| m.color.is_empty() && !forbidden.contains(&m.point))
+            .map(|m| m.point)
+            .collect::<Vec<_>>(),
+        [block] if !forbidden.contains(&block) => vec![block],
+        _ => return Position::Lost,
+    };
+    if moves.is_empty() {
+        Position::Drawn
+    } else {
+        Position::Open(moves)
+    }
+}
+
+/// Whether `stone` has room for a five anywhere, ignoring overlines.
+fn can_make_five(board: &BoardArr, stone: Stone) -> bool {
+    let opponent = stone.opposite();
+    board
+        .lines()
+        .iter()
+        .any(|line| line.stones.windows(5).any(|w| !w.contains(&opponent)))
+}
+
+struct Node {
+    /// The move leading to the node, `None` for the root.
+    point: Option<Point>,
+    parent: Option<usize>,
+    to_move: Stone,
+    hash: u64,
+    pn: u64,
+    dn: u64,
+    /// Moves of an open position until it is expanded.
+    moves: Vec<Point>,
+    children: Vec<usize>,
+}
+
+struct Search<'a> {
+    board: BoardArr,
+    goal: Stone,
+    nodes: Vec<Node>,
+    table: &'a mut ProofTable,
+    expanded: u64,
+}
+
+impl<'a> Search<'a> {
+    fn new(board: &BoardArr, to_move: Stone, goal: Stone, table: &'a mut ProofTable) -> Self {
+        let mut search = Self {
+            board: board.clone(),
+            goal,
+            nodes: vec![],
+            table,
+            expanded: 0,
+        };
+        search.add(None, None, to_move);
+        search
+    }
+
+    /// Add a node for the current board.
+    fn add(&mut self, point: Option<Point>, parent: Option<usize>, to_move: Stone) -> usize {
+        let hash = position_hash(&self.board, to_move);
+        let won = |won: bool| if won { (0, INFINITY) } else { (INFINITY, 0) };
+        let mut moves = vec![];
+        let (pn, dn) = match self.table.get(hash, self.goal) {
+            Some(proven) => won(proven.won),
+            None if !can_make_five(&self.board, self.goal) => won(false),
+            None => match classify(&self.board, to_move) {
+                Position::Five(_) => won(to_move == self.goal),
+                Position::Lost => won(to_move != self.goal),
+                Position::Drawn => won(false),
+                Position::Open(open) => {
+                    let count = open.len() as u64;
+                    moves = open;
+                    if to_move == self.goal {
+                        (1, count)
+                    } else {
+                        (count, 1)
+                    }
+                }
+            },
+        };
+        self.nodes.push(Node {
+            point,
+            parent,
+            to_move,
+            hash,
+            pn,
+            dn,
+            moves,
+            children: vec![],
+        });
+        self.nodes.len() - 1
+    }
+
+    fn run(&mut self, budget: &mut Budget) -> Result<Proven, Exhausted> {
+        let result = self.search(budget);
+        self.store();
+        result?;
+        let root = &self.nodes[0];
+        let won = root.pn == 0;
+        let best = match classify(&self.board, root.to_move) {
+            Position::Five(point) if root.to_move == self.goal => Some(point),
+            _ if root.children.is_empty() => self
+                .table
+                .get(root.hash, self.goal)
+                .and_then(|proven| proven.best),
+            _ => self.best(0),
+        };
+        Ok(Proven { won, best })
+    }
+
+    fn search(&mut self, budget: &mut Budget) -> Result<(), Exhausted> {
+        while self.nodes[0].pn != 0 && self.nodes[0].dn != 0 {
+            budget.spend()?;
+            // walk down to the most proving node
+            let mut id = 0;
+            let mut path = vec![];
+            while !self.nodes[id].children.is_empty() {
+                let node = &self.nodes[id];
+                let or = node.to_move == self.goal;
+                let next = *node
+                    .children
+                    .iter()
+                    .min_by_key(|c| {
+                        let child = &self.nodes[**c];
+                        if or {
+                            child.pn
+                        } else {
+                            child.dn
+                        }
+                    })
+                    .expect("children is not empty");
+                let point = self.nodes[next].point.expect("only the root has no move");
+                self.board.set_point(point, node.to_move);
+                path.push(point);
+                id = next;
+            }
+            self.expand(id);
+            for point in path {
+                self.board.set_point(point, Stone::Empty);
+            }
+            self.update(id);
+        }
+        Ok(())
+    }
+
+    fn expand(&mut self, id: usize) {
+        self.expanded += 1;
+        let to_move = self.nodes[id].to_move;
+        let moves = std::mem::take(&mut self.nodes[id].moves);
+        for point in moves {
+            self.board.set_point(point, to_move);
+            let child = self.add(Some(point), Some(id), to_move.opposite());
+            self.board.set_point(point, Stone::Empty);
+            self.nodes[id].children.push(child);
+        }
+    }
+
+    /// Recompute the numbers of `id` and its ancestors.
+    fn update(&mut self, mut id: usize) {
+        loop {
+            let node = &self.nodes[id];
+            let children = node.children.iter().map(|c| &self.nodes[*c]);
+            let (pn, dn) = if node.to_move == self.goal {
+                (
+                    children.clone().map(|c| c.pn).min().unwrap_or(INFINITY),
+                    children.fold(0, |sum, c| (sum + c.dn).min(INFINITY)),
+                )
+            } else {
+                (
+                    children
+                        .clone()
+                        .fold(0, |sum, c| (sum + c.pn).min(INFINITY)),
+                    children.map(|c| c.dn).min().unwrap_or(INFINITY),
+                )
+            };
+            let node = &mut self.nodes[id];
+            node.pn = pn;
+            node.dn = dn;
+            match node.parent {
+                Some(parent) => id = parent,
+                None => break,
+            }
+        }
+    }
+
+    /// The child proving or refuting a solved node.
+    fn best(&self, id: usize) -> Option<Point> {
+        let node = &self.nodes[id];
+        let solved = |c: &&usize| {
+            let child = &self.nodes[**c];
+            if node.pn == 0 {
+                child.pn == 0
+            } else {
+                child.dn == 0
+            }
+        };
+        let proving = (node.pn == 0) == (node.to_move == self.goal);
+        if !proving || (node.pn != 0 && node.dn != 0) {
+            return None;
+        }
+        node.children
+            .iter()
+            .find(solved)
+            .and_then(|c| self.nodes[*c].point)
+    }
+
+    /// Add every solved position to the table.
+    fn store(&mut self) {
+        for id in 0..self.nodes.len() {
+            let node = &self.nodes[id];
+            if !node.children.is_empty() && (node.pn == 0 || node.dn == 0) {
+                let proven = Proven {
+                    won: node.pn == 0,
+                    best: self.best(id),
+                };
+                self.table.insert(node.hash, self.goal, proven);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    fn limits() -> Limits {
+        Limits {
+            nodes: Some(50_000),
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn wins_and_losses() {
+        let mut table = ProofTable::new();
+        // white has an open three
+        let mut board =
+            BoardArr::from_stones(15, &p![[A, 1], [A, 3], [A, 5]], &p![[E, 8], [F, 8], [G, 8]]);
+        let white = solve(&board, Stone::White, &limits(), &mut table).known();
+        let white = white.unwrap();
+        assert_eq!(white.value, Value::Win);
+        assert!(matches!(white.best, Some(p) if p == p![D, 8] || p == p![H, 8]));
+        assert!(!table.is_empty());
+        let proven = table.get(position_hash(&board, Stone::White), Stone::White);
+        assert_eq!(proven.map(|p| p.won), Some(true));
+
+        // and now a straight four
+        board.set_point(white.best.unwrap(), Stone::White);
+        let black = solve(&board, Stone::Black, &limits(), &mut table).known();
+        assert_eq!(black.unwrap().value, Value::Loss);
+    }
+
+    #[test]
+    fn table_round_trip() {
+        let mut table = ProofTable::new();
+        table.insert(
+            1,
+            Stone::Black,
+            Proven {
+                won: true,
+                best: Some(p![H, 8]),
+            },
+        );
+        table.insert(
+            u64::MAX,
+            Stone::White,
+            Proven {
+                won: false,
+                best: None,
+            },
+        );
+        let mut bytes = vec![];
+        table.write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 2 * 11);
+        let read = ProofTable::read(&bytes[..]).unwrap();
+        assert_eq!(read.entries, table.entries);
+        assert!(ProofTable::read(&b"RJSP\x01\0\0\0"[..]).is_err());
+    }
+
+    #[test]
+    fn small_board_draw() {
+        // no line of five fits on a 4x4 board
+        let board = BoardArr::from_stones(4, &[Point::new(0, 0)], &[Point::new(1, 0)]);
+        let solution = solve(&board, Stone::Black, &limits(), &mut ProofTable::new());
+        assert_eq!(solution.known().unwrap().value, Value::Draw);
+    }
+
+    #[test]
+    fn stable_hash() {
+        let board = BoardArr::from_stones(15, &p![[H, 8]], &p![[I, 9]]);
+        let hash = position_hash(&board, Stone::Black);
+        assert_eq!(hash, position_hash(&board.clone(), Stone::Black));
+        assert_ne!(hash, position_hash(&board, Stone::White));
+        assert_ne!(hash, position_hash(&BoardArr::new(15), Stone::Black));
+    }
+
+    #[test]
+    fn limited() {
+        let board = BoardArr::new(15);
+        let limits = Limits {
+            nodes: Some(10),
+            ..Limits::default()
+        };
+        assert!(solve(&board, Stone::Black, &limits, &mut ProofTable::new()).is_unknown());
+    }
+}
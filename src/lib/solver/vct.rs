@@ -0,0 +1,413 @@
+//! Victories by continuous threats (VCT), winning with fours and threes.
+//!
+//! A four forces its block like in [`vcf`](super::vcf). A three only forces a reply when the
+//! defender can't win first, so after every three the search makes sure the defender has no VCF
+//! and then tries every defence: each point after which the attacker has no straight four left,
+//! and each four of the defender, which the attacker has to block before the three goes on. Only
+//! proven wins are returned, a position the search can't decide counts as not won.
+
+use std::collections::BTreeSet;
+
+use super::{five_points, four_points, makes_five, vcf_search, Stop};
+use crate::board::evaluator::Conditions;
+use crate::board::{Evaluate, Point, Stone};
+use crate::limits::{Budget, Limits, Outcome};
+use crate::progress::Progress;
+
+/// Maximum amount of fours in the VCF of a defender answering a three.
+const DEFENDER_VCF_DEPTH: usize = 6;
+
+/// A proven VCT, an attacker move and every defender reply with the win following it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VctTree {
+    pub attack: Point,
+    /// Replies of the defender and how each is won. Empty if `attack` makes five, or black has to
+    /// block it on a forbidden point.
+    pub replies: Vec<(Point, VctTree)>,
+}
+
+impl VctTree {
+    fn leaf(attack: Point) -> Self {
+        Self {
+            attack,
+            replies: vec![],
+        }
+    }
+
+    /// The most attacker moves on any line of the tree.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        1 + self
+            .replies
+            .iter()
+            .map(|(_, tree)| tree.depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The line following the first reply every time, alternating between attacker and defender
+    /// moves.
+    #[must_use]
+    pub fn main_line(&self) -> Vec<Point> {
+        let mut line = vec![self.attack];
+        if let Some((reply, tree)) = self.replies.first() {
+            line.push(*reply);
+            line.extend(tree.main_line());
+        }
+        line
+    }
+}
+
+/// Find a victory by continuous threats for `stone`, using at most `max_depth` fours and threes
+/// on every line.
+#[must_use]
+pub fn vct<B: Evaluate>(board: &B, stone: Stone, max_depth: usize) -> Option<VctTree> {
+    unlimited(attacks(
+        board,
+        stone,
+        max_depth,
+        &mut Budget::unlimited(),
+        false,
+    ))
+    .pop()
+}
+
+/// Like [`vct`], using at most `limits.depth` threats and giving up when `limits` is reached.
+/// Every threat played counts as a node.
+#[must_use]
+pub fn vct_with_limits<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    limits: &Limits,
+) -> Outcome<Option<VctTree>> {
+    limited(board, stone, limits, false).map(|mut wins| wins.pop())
+}
+
+/// Every first move that starts a VCT for `stone`, each with its tree.
+#[must_use]
+pub fn vct_first_moves<B: Evaluate>(board: &B, stone: Stone, max_depth: usize) -> Vec<VctTree> {
+    unlimited(attacks(
+        board,
+        stone,
+        max_depth,
+        &mut Budget::unlimited(),
+        true,
+    ))
+}
+
+/// Like [`vct_first_moves`], bounded by `limits` like [`vct_with_limits`].
+#[must_use]
+pub fn vct_first_moves_with_limits<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    limits: &Limits,
+) -> Outcome<Vec<VctTree>> {
+    limited(board, stone, limits, true)
+}
+
+fn unlimited(result: Result<Vec<VctTree>, Stop>) -> Vec<VctTree> {
+    match result {
+        Ok(wins) => wins,
+        Err(_) => unreachable!("unlimited budget and no token to cancel"),
+    }
+}
+
+fn limited<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    limits: &Limits,
+    all: bool,
+) -> Outcome<Vec<VctTree>> {
+    let mut budget = Budget::new(limits);
+    // the depth limits the threats, not the double-three checks
+    budget.max_depth = None;
+    match attacks(board, stone, limits.depth as usize, &mut budget, all) {
+        Ok(wins) => Outcome::Known(wins),
+        Err(Stop::Exhausted) => Outcome::Unknown,
+        Err(Stop::Cancelled) => unreachable!("no token to cancel"),
+    }
+}
+
+/// The winning threats of `stone`, only the first one unless `all`.
+fn attacks<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    depth: usize,
+    budget: &mut Budget,
+    all: bool,
+) -> Result<Vec<VctTree>, Stop> {
+    budget.spend()?;
+    let mut wins = five_points(board, stone)
+        .into_iter()
+        .map(VctTree::leaf)
+        .collect::<Vec<_>>();
+    if !wins.is_empty() || depth == 0 || !five_points(board, stone.opposite()).is_empty() {
+        wins.truncate(if all { wins.len() } else { 1 });
+        return Ok(wins);
+    }
+    for attack in threats(board, stone, depth) {
+        let mut board = board.clone();
+        board.set_stone(attack, stone);
+        if let Some(replies) = defend(&board, stone, attack, depth - 1, budget)? {
+            wins.push(VctTree { attack, replies });
+            if !all {
+                break;
+            }
+        }
+    }
+    Ok(wins)
+}
+
+/// The fours of `stone`, and with enough depth left to follow them up, the threes.
+fn threats<B: Evaluate>(board: &B, stone: Stone, depth: usize) -> Vec<Point> {
+    let mut threats = four_points(board, stone);
+    if depth < 2 {
+        return threats;
+    }
+    let forbidden = board.forbidden(stone);
+    let candidates = board
+        .points()
+        .filter(|p| {
+            board.stone_at(*p).is_empty()
+                && !forbidden.contains(p)
+                && !threats.contains(p)
+                && !line_points(board, *p, 3, stone).is_empty()
+        })
+        .collect::<Vec<_>>();
+    let mut board = board.clone();
+    for point in candidates {
+        board.set_stone(point, stone);
+        if !straight_fours(&mut board, point, stone).is_empty() {
+            threats.push(point);
+        }
+        board.set_stone(point, Stone::Empty);
+    }
+    threats
+}
+
+/// The replies to `attack` and how each of them loses, `None` if one of them doesn't.
+fn defend<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    attack: Point,
+    depth: usize,
+    budget: &mut Budget,
+) -> Result<Option<Vec<(Point, VctTree)>>, Stop> {
+    budget.spend()?;
+    let defender = stone.opposite();
+    // the defender wins before having to reply
+    if !five_points(board, defender).is_empty() {
+        return Ok(None);
+    }
+    match five_points(board, stone)[..] {
+        // a straight four or a double four, can't be stopped
+        [block, win, ..] => return Ok(Some(vec![(block, VctTree::leaf(win))])),
+        [block] => {
+            if forbidden_block(board, defender, block, budget)? {
+                return Ok(Some(vec![]));
+            }
+            let mut board = board.clone();
+            board.set_stone(block, defender);
+            let win = attacks(&board, stone, depth, budget, false)?.pop();
+            return Ok(win.map(|tree| vec![(block, tree)]));
+        }
+        [] => {}
+    }
+
+    // a three, the attacker needs another threat to make the straight four
+    if depth == 0 {
+        return Ok(None);
+    }
+    let mut board = board.clone();
+    let forbidden = board.forbidden(stone).into_owned();
+    let straight = straight_fours(&mut board, attack, stone)
+        .into_iter()
+        .filter(|p| !forbidden.contains(p))
+        .collect::<Vec<_>>();
+    if straight.is_empty()
+        || vcf_search(
+            &board,
+            defender,
+            DEFENDER_VCF_DEPTH,
+            &mut Progress::none(),
+            budget,
+        )?
+        .is_some()
+    {
+        return Ok(None);
+    }
+
+    let illegal = board.forbidden(defender).into_owned();
+    let candidates = straight
+        .iter()
+        .flat_map(|s| line_points(&board, *s, 5, Stone::Empty))
+        .filter(|p| !illegal.contains(p))
+        .collect::<BTreeSet<_>>();
+    let mut blocks = vec![];
+    for point in candidates {
+        board.set_stone(point, defender);
+        let forbidden = board.forbidden(stone).into_owned();
+        let left = straight
+            .iter()
+            .copied()
+            .filter(|s| {
+                board.stone_at(*s).is_empty()
+                    && !forbidden.contains(s)
+                    && is_straight_four(&mut board, *s, stone)
+            })
+            .collect::<Vec<_>>();
+        blocks.push((point, left));
+        board.set_stone(point, Stone::Empty);
+    }
+    // when no single point stops every straight four, like after a double three, the replies
+    // stopping some of them are tried
+    let stops_all = blocks.iter().any(|(_, left)| left.is_empty());
+    let mut defences = four_points(&board, defender);
+    for (point, left) in blocks {
+        let stops = if stops_all {
+            left.is_empty()
+        } else {
+            left.len() < straight.len()
+        };
+        if stops && !defences.contains(&point) {
+            defences.push(point);
+        }
+    }
+
+    let mut replies = vec![];
+    for defence in defences {
+        let mut board = board.clone();
+        board.set_stone(defence, defender);
+        let tree = match five_points(&board, defender)[..] {
+            [] => attacks(&board, stone, depth, budget, false)?.pop(),
+            // a four in between, the attacker blocks it and the three still stands
+            [block] if !forbidden_block(&board, stone, block, budget)? => {
+                board.set_stone(block, stone);
+                defend(&board, stone, attack, depth, budget)?.map(|replies| VctTree {
+                    attack: block,
+                    replies,
+                })
+            }
+            _ => None,
+        };
+        let Some(tree) = tree else {
+            return Ok(None);
+        };
+        replies.push((defence, tree));
+    }
+    Ok(Some(replies))
+}
+
+/// Whether `stone` has to block on `block` and can't, because it is forbidden for black.
+fn forbidden_block<B: Evaluate>(
+    board: &B,
+    stone: Stone,
+    block: Point,
+    budget: &mut Budget,
+) -> Result<bool, Stop> {
+    Ok(stone.is_black()
+        && board
+            .conditions_with_budget(Stone::Black, Some(&[block]), budget, 0)?
+            .forbidden
+            .contains(&block))
+}
+
+/// Points holding `stone` on the lines through `point`, at most `reach` away.
+fn line_points<B: Evaluate>(board: &B, point: Point, reach: usize, stone: Stone) -> Vec<Point> {
+    let table = board.table();
+    let mut found = vec![];
+    for &(line, index) in table.through(point).into_iter().flatten() {
+        let points = table.line(line).1;
+        let near = points.iter().enumerate().take(index + reach + 1);
+        for (i, point) in near.skip(index.saturating_sub(reach)) {
+            if board.line_stone(line, i, *point) == stone && !found.contains(point) {
+                found.push(*point);
+            }
+        }
+    }
+    found
+}
+
+/// Whether `stone` placed on `point` makes two fives possible, without checking forbidden points.
+fn is_straight_four<B: Evaluate>(board: &mut B, point: Point, stone: Stone) -> bool {
+    board.set_stone(point, stone);
+    let fives = line_points(board, point, 4, Stone::Empty)
+        .into_iter()
+        .filter(|p| makes_five(board, *p, stone))
+        .count();
+    board.set_stone(point, Stone::Empty);
+    fives >= 2
+}
+
+/// Points near `point` where `stone` makes a straight four.
+fn straight_fours<B: Evaluate>(board: &mut B, point: Point, stone: Stone) -> Vec<Point> {
+    line_points(board, point, 4, Stone::Empty)
+        .into_iter()
+        .filter(|p| is_straight_four(board, *p, stone))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BoardArr;
+    use crate::p;
+    use crate::solver::vcf;
+
+    #[test]
+    fn three_three() {
+        // H8 makes an open three on row 8 and a split three on column H
+        let board = BoardArr::from_stones(15, &p![[A, 1]], &p![[F, 8], [G, 8], [H, 10], [H, 11]]);
+        assert_eq!(vcf(&board, Stone::White, 8), None);
+        assert_eq!(vct(&board, Stone::White, 1), None);
+        let wins = vct_first_moves(&board, Stone::White, 2);
+        let [tree] = &wins[..] else {
+            panic!("expected one winning move, got {wins:?}");
+        };
+        assert_eq!(tree.attack, p![H, 8]);
+        assert_eq!(tree.depth(), 3);
+        assert!(!tree.replies.is_empty());
+        assert_eq!(tree.main_line().len(), 5, "{:?}", tree.main_line());
+    }
+
+    #[test]
+    fn forbidden_three_three() {
+        let board = BoardArr::from_stones(15, &p![[F, 8], [G, 8], [H, 10], [H, 11]], &p![[A, 1]]);
+        assert!(vct_first_moves(&board, Stone::Black, 2)
+            .iter()
+            .all(|tree| tree.attack != p![H, 8]));
+    }
+
+    #[test]
+    fn defender_fours_first() {
+        // black answers the threes with fours of its own, ending in a straight four
+        let board = BoardArr::from_stones(
+            15,
+            &p![[B, 3], [B, 4], [B, 5]],
+            &p![[F, 8], [G, 8], [H, 10], [H, 11]],
+        );
+        assert_eq!(vct(&board, Stone::White, 2), None);
+    }
+
+    #[test]
+    fn limited_vct() {
+        let board = BoardArr::from_stones(15, &p![[A, 1]], &p![[F, 8], [G, 8], [H, 10], [H, 11]]);
+        let limits = Limits {
+            depth: 2,
+            ..Limits::default()
+        };
+        assert!(matches!(
+            vct_with_limits(&board, Stone::White, &limits),
+            Outcome::Known(Some(_))
+        ));
+        let limits = Limits {
+            depth: 2,
+            nodes: Some(1),
+            ..Limits::default()
+        };
+        assert_eq!(
+            vct_with_limits(&board, Stone::White, &limits),
+            Outcome::Unknown
+        );
+    }
+}
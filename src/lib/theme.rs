@@ -0,0 +1,372 @@
+//! Colors of the text printed to a terminal.
+//!
+//! Like the language of [`crate::i18n`], the theme is set once for the process with
+//! [`set_theme`], boards are then printed with [`board`] and the explanations and comparisons of
+//! [`crate::board`] use it too. No colors are used until a theme is set, and none at all when
+//! `NO_COLOR` is set, see <https://no-color.org>.
+//!
+//! The colors of a preset can be changed one by one in the config:
+//!
+//! ```toml
+//! theme = "high-contrast"
+//! colors = "black=bright-red,forbidden=magenta"
+//! ```
+
+use std::fmt;
+use std::sync::RwLock;
+
+use crate::board::{BoardArr, BoardRead, Point, Stone};
+use crate::errors::ParseError;
+
+/// The colors of every terminal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl Color {
+    const ALL: [(&'static str, Color); 16] = [
+        ("black", Color::Black),
+        ("red", Color::Red),
+        ("green", Color::Green),
+        ("yellow", Color::Yellow),
+        ("blue", Color::Blue),
+        ("magenta", Color::Magenta),
+        ("cyan", Color::Cyan),
+        ("white", Color::White),
+        ("bright-black", Color::BrightBlack),
+        ("bright-red", Color::BrightRed),
+        ("bright-green", Color::BrightGreen),
+        ("bright-yellow", Color::BrightYellow),
+        ("bright-blue", Color::BrightBlue),
+        ("bright-magenta", Color::BrightMagenta),
+        ("bright-cyan", Color::BrightCyan),
+        ("bright-white", Color::BrightWhite),
+    ];
+
+    /// The SGR code of the foreground color.
+    fn code(self) -> u8 {
+        match self as u8 {
+            dark @ 0..=7 => 30 + dark,
+            bright => 90 + bright - 8,
+        }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Color::ALL
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()))
+            .map(|(_, color)| *color)
+            .ok_or_else(|| ParseError::Other(format!("unknown color {s:?}")))
+    }
+}
+
+/// A built in theme.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Preset {
+    #[default]
+    Default,
+    /// Bright and bold, for low vision and washed out screens.
+    HighContrast,
+    /// No colors, like `NO_COLOR`.
+    None,
+}
+
+impl std::str::FromStr for Preset {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "default" => Ok(Preset::Default),
+            "high-contrast" => Ok(Preset::HighContrast),
+            "none" => Ok(Preset::None),
+            _ => Err(ParseError::Other(format!("unknown theme {s:?}"))),
+        }
+    }
+}
+
+/// Colors replacing the ones of a [`Preset`], like `black=red,highlight=bright-green`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Palette {
+    pub black: Option<Color>,
+    pub white: Option<Color>,
+    pub forbidden: Option<Color>,
+    pub highlight: Option<Color>,
+}
+
+impl std::str::FromStr for Palette {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut palette = Palette::default();
+        for pair in s.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (key, color) = pair
+                .split_once('=')
+                .ok_or_else(|| ParseError::Other(format!("expected key=color, not {pair:?}")))?;
+            let slot = match key.trim() {
+                "black" => &mut palette.black,
+                "white" => &mut palette.white,
+                "forbidden" => &mut palette.forbidden,
+                "highlight" => &mut palette.highlight,
+                key => return Err(ParseError::Other(format!("unknown color key {key:?}"))),
+            };
+            *slot = Some(color.parse()?);
+        }
+        Ok(palette)
+    }
+}
+
+impl TryFrom<String> for Palette {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Palette> for String {
+    fn from(palette: Palette) -> String {
+        palette.to_string()
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |color: Color| {
+            Color::ALL
+                .iter()
+                .find(|(_, c)| *c == color)
+                .map_or("", |(name, _)| name)
+        };
+        let pairs = [
+            ("black", self.black),
+            ("white", self.white),
+            ("forbidden", self.forbidden),
+            ("highlight", self.highlight),
+        ]
+        .into_iter()
+        .filter_map(|(key, color)| Some(format!("{key}={}", name(color?))))
+        .collect::<Vec<_>>();
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+/// The colors used, `None` leaves the text as it is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub black: Option<Color>,
+    pub white: Option<Color>,
+    /// Empty points forbidden for black.
+    pub forbidden: Option<Color>,
+    /// The last move and the point of an explanation.
+    pub highlight: Option<Color>,
+    pub bold: bool,
+}
+
+impl Theme {
+    /// No colors.
+    pub const PLAIN: Theme = Theme {
+        black: None,
+        white: None,
+        forbidden: None,
+        highlight: None,
+        bold: false,
+    };
+
+    /// The colors of `preset` with the ones of `palette`, or none if `NO_COLOR` is set or the
+    /// preset is [`Preset::None`].
+    #[must_use]
+    pub fn new(preset: Preset, palette: Palette) -> Self {
+        if no_color() || preset == Preset::None {
+            return Theme::PLAIN;
+        }
+        let theme = Theme::preset(preset);
+        Theme {
+            black: palette.black.or(theme.black),
+            white: palette.white.or(theme.white),
+            forbidden: palette.forbidden.or(theme.forbidden),
+            highlight: palette.highlight.or(theme.highlight),
+            ..theme
+        }
+    }
+
+    /// The colors of `preset`, even if `NO_COLOR` is set.
+    #[must_use]
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Default => Theme {
+                black: Some(Color::Cyan),
+                white: Some(Color::Yellow),
+                forbidden: Some(Color::Red),
+                highlight: Some(Color::Green),
+                bold: false,
+            },
+            Preset::HighContrast => Theme {
+                black: Some(Color::BrightCyan),
+                white: Some(Color::BrightYellow),
+                forbidden: Some(Color::BrightRed),
+                highlight: Some(Color::BrightMagenta),
+                bold: true,
+            },
+            Preset::None => Theme::PLAIN,
+        }
+    }
+
+    /// `text` in `color`.
+    #[must_use]
+    pub fn paint(&self, color: Option<Color>, text: &str) -> String {
+        match color {
+            Some(color) if self.bold => format!("\x1b[1;{}m{text}\x1b[0m", color.code()),
+            Some(color) => format!("\x1b[{}m{text}\x1b[0m", color.code()),
+            None => text.to_owned(),
+        }
+    }
+
+    /// `board` with the stones in their colors, the points forbidden for black in
+    /// [`forbidden`](Self::forbidden) and `highlight` in [`highlight`](Self::highlight).
+    #[must_use]
+    pub fn board(&self, board: &BoardArr, highlight: &[Point]) -> String {
+        let text = board.to_string();
+        if *self == Theme::PLAIN {
+            return text;
+        }
+        let forbidden = board.forbidden_points(Stone::Black);
+        let mut painted = String::with_capacity(text.len() * 2);
+        for (row, line) in text.split('\n').enumerate() {
+            if row > 0 {
+                painted.push('\n');
+            }
+            let on_board = row < board.size() as usize;
+            for (column, c) in line.chars().enumerate() {
+                let point = (on_board && column >= 3 && (column - 3) % 2 == 0)
+                    .then(|| board.point_at(column, row))
+                    .flatten();
+                let color = point.and_then(|point| match board.stone_at(point) {
+                    _ if highlight.contains(&point) => self.highlight,
+                    Stone::Black => self.black,
+                    Stone::White => self.white,
+                    Stone::Empty if forbidden.contains(&point) => self.forbidden,
+                    Stone::Empty => None,
+                });
+                match color {
+                    Some(_) => painted.push_str(&self.paint(color, &c.to_string())),
+                    None => painted.push(c),
+                }
+            }
+        }
+        painted
+    }
+}
+
+/// Whether `NO_COLOR` asks for no colors.
+#[must_use]
+pub fn no_color() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+static THEME: RwLock<Theme> = RwLock::new(Theme::PLAIN);
+
+/// Use `theme` from now on.
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap_or_else(|e| e.into_inner()) = theme;
+}
+
+/// The theme set with [`set_theme`], [`Theme::PLAIN`] by default.
+#[must_use]
+pub fn theme() -> Theme {
+    *THEME.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `board` in the colors of [`theme`], see [`Theme::board`].
+#[must_use]
+pub fn board(board: &BoardArr, highlight: &[Point]) -> String {
+    theme().board(board, highlight)
+}
+
+/// The columns `text` takes in a terminal, without its colors.
+#[must_use]
+pub fn width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                chars.by_ref().find(|c| *c == 'm');
+            }
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn palette() {
+        let palette: Palette = "black=bright-red, forbidden = Magenta".parse().unwrap();
+        assert_eq!(palette.black, Some(Color::BrightRed));
+        assert_eq!(palette.forbidden, Some(Color::Magenta));
+        assert_eq!(palette.white, None);
+        assert_eq!(palette.to_string(), "black=bright-red,forbidden=magenta");
+        assert!("black=pink".parse::<Palette>().is_err());
+        assert!("stones=red".parse::<Palette>().is_err());
+        assert_eq!(Color::BrightWhite.code(), 97);
+        assert_eq!(Color::Red.code(), 31);
+    }
+
+    #[test]
+    fn paints_boards() {
+        let mut board = BoardArr::new(15);
+        for point in p![[H, 8], [I, 8], [G, 9], [G, 10]] {
+            board.set_point(point, Stone::Black);
+        }
+        board.set_point(p![J, 10], Stone::White);
+        assert_eq!(Theme::PLAIN.board(&board, &[p![J, 10]]), board.to_string());
+
+        let theme = Theme::preset(Preset::Default);
+        let painted = theme.board(&board, &[p![J, 10]]);
+        let rows = painted.lines().collect::<Vec<_>>();
+        assert!(rows[15 - 8].contains("\x1b[36mX\x1b[0m"), "{painted}");
+        assert!(rows[15 - 8].contains("\x1b[31m.\x1b[0m"), "{painted}");
+        assert!(rows[15 - 10].contains("\x1b[32mO\x1b[0m"), "{painted}");
+        let plain = board.to_string();
+        for (painted, plain) in painted.lines().zip(plain.lines()) {
+            assert_eq!(width(painted), plain.chars().count());
+        }
+
+        let bold = Theme::preset(Preset::HighContrast);
+        assert_eq!(bold.paint(Some(Color::Red), "x"), "\x1b[1;31mx\x1b[0m");
+    }
+}
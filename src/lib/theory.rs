@@ -0,0 +1,379 @@
+//! How far a game followed established theory, the lines of a reference library.
+//!
+//! A [`Library`] holds every position of a library, with the moves known from it and the name
+//! of the line, the one line comment of its node or of the closest node before it that has one.
+//! Positions are matched on a rotated or mirrored board as well, so a game opening towards any
+//! side finds its line. [`Library::bundled`] knows the 26 standard openings, any library can be
+//! used with [`Library::from_graph`].
+//!
+//! [`match_game`] replays a game against the bundled library and tells the moves following
+//! theory and the move leaving it, with the moves known there.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::board::duplicates::position_key;
+use crate::board::notation::{parse_moves, write_moves};
+use crate::board::{
+    Board, BoardArr, BoardRead, MoveIndex, NodeIndex, Point, Stone, Transformation,
+};
+use crate::game::GameRecord;
+
+/// The standard openings, black's third move after white's second, direct or diagonal.
+const OPENINGS: [(&str, &str); 26] = [
+    ("Kansei", "H8 H9 H10"),
+    ("Keigetsu", "H8 H9 I10"),
+    ("Sosei", "H8 H9 J10"),
+    ("Kagetsu", "H8 H9 I9"),
+    ("Zangetsu", "H8 H9 J9"),
+    ("Ugetsu", "H8 H9 I8"),
+    ("Kinsei", "H8 H9 J8"),
+    ("Shogetsu", "H8 H9 I7"),
+    ("Kyugetsu", "H8 H9 H7"),
+    ("Shingetsu", "H8 H9 J7"),
+    ("Zuisei", "H8 H9 H6"),
+    ("Sangetsu", "H8 H9 I6"),
+    ("Yusei", "H8 H9 J6"),
+    ("Chosei", "H8 I9 J10"),
+    ("Kyogetsu", "H8 I9 J9"),
+    ("Kosei", "H8 I9 J8"),
+    ("Suigetsu", "H8 I9 J7"),
+    ("Ryusei", "H8 I9 J6"),
+    ("Ungetsu", "H8 I9 I8"),
+    ("Hogetsu", "H8 I9 I7"),
+    ("Rangetsu", "H8 I9 I6"),
+    ("Gingetsu", "H8 I9 H7"),
+    ("Meigetsu", "H8 I9 H6"),
+    ("Shagetsu", "H8 I9 G7"),
+    ("Meisei", "H8 I9 G6"),
+    ("Suisei", "H8 I9 F6"),
+];
+
+/// The moves of the standard opening `name`, like `Kagetsu`, in any case.
+#[must_use]
+pub fn opening(name: &str) -> Option<Vec<Point>> {
+    let (_, moves) = OPENINGS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))?;
+    Some(parse_moves(moves).expect("the openings are valid"))
+}
+
+/// A position of a library.
+#[derive(Clone, Debug)]
+struct Known {
+    /// The stones as first seen in the library, the orientation of `moves`.
+    stones: Vec<(Point, Stone)>,
+    name: Option<String>,
+    moves: Vec<Point>,
+}
+
+/// The positions of a reference library, see the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Library {
+    positions: BTreeMap<Vec<(Point, Stone)>, Known>,
+}
+
+impl Library {
+    /// The standard openings, named, up to black's third move.
+    #[must_use]
+    pub fn bundled() -> Self {
+        let mut graph = Board::new();
+        for (name, moves) in OPENINGS {
+            let moves = parse_moves(moves).expect("the openings are valid");
+            let index = graph.add_line(&moves[..2]);
+            let second = match moves[1].x == moves[0].x {
+                true => "direct opening",
+                false => "diagonal opening",
+            };
+            let marker = graph.get_move_mut(index).expect("just added");
+            marker.oneline_comment = Some(second.to_owned());
+            let index = graph.add_line(&moves);
+            let marker = graph.get_move_mut(index).expect("just added");
+            marker.oneline_comment = Some(name.to_owned());
+        }
+        Self::from_graph(&graph)
+    }
+
+    /// Every position of `graph`.
+    #[must_use]
+    pub fn from_graph(graph: &Board) -> Self {
+        let mut library = Self::default();
+        let mut visited = HashSet::new();
+        let mut board = BoardArr::new(15);
+        for root in graph.roots() {
+            library.visit(graph, *root, &mut board, None, &mut visited);
+        }
+        library
+    }
+
+    fn visit(
+        &mut self,
+        graph: &Board,
+        index: MoveIndex,
+        board: &mut BoardArr,
+        name: Option<&str>,
+        visited: &mut HashSet<NodeIndex>,
+    ) {
+        if !visited.insert(index.node_index()) {
+            return;
+        }
+        let Some(marker) = graph.get_move(index) else {
+            return;
+        };
+        let placed = !marker.color.is_empty() && !marker.point.is_null;
+        if placed {
+            board.set_point(marker.point, marker.color);
+        }
+        let name = marker.oneline_comment.as_deref().or(name);
+        let children = graph.get_children(&index);
+        let key = position_key(board);
+        let stones = sorted_stones(board);
+        let known = self.positions.entry(key).or_insert_with(|| Known {
+            stones: stones.clone(),
+            name: None,
+            moves: vec![],
+        });
+        if known.name.is_none() {
+            known.name = name.map(str::to_owned);
+        }
+        let transformation = orientation(&stones, &known.stones);
+        for child in children.iter().rev() {
+            let Some(point) = graph.get_move(*child).map(|m| m.point) else {
+                continue;
+            };
+            let point = transformation.apply(point);
+            if !point.is_null && !known.moves.contains(&point) {
+                known.moves.push(point);
+            }
+        }
+        for child in children.into_iter().rev() {
+            self.visit(graph, child, board, name, visited);
+        }
+        if placed {
+            board.set_point(marker.point, Stone::Empty);
+        }
+    }
+
+    /// The number of positions known.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The name and the known moves of the position on `board`, in the orientation of `board`.
+    #[must_use]
+    pub fn lookup(&self, board: &BoardArr) -> Option<(Option<&str>, Vec<Point>)> {
+        let known = self.positions.get(&position_key(board))?;
+        let stones = sorted_stones(board);
+        let transformation = orientation(&stones, &known.stones);
+        let moves = known
+            .moves
+            .iter()
+            .map(|point| transformation.inverse_apply(*point))
+            .collect();
+        Some((known.name.as_deref(), moves))
+    }
+}
+
+fn sorted_stones(board: &BoardArr) -> Vec<(Point, Stone)> {
+    let mut stones = BoardRead::stones(board).collect::<Vec<_>>();
+    stones.sort();
+    stones
+}
+
+/// The transformation mapping sorted `stones` onto sorted `onto`, the same position on another board.
+fn orientation(stones: &[(Point, Stone)], onto: &[(Point, Stone)]) -> Transformation {
+    Transformation::types()
+        .into_iter()
+        .find(|transformation| {
+            let mut mapped = stones
+                .iter()
+                .map(|(point, stone)| (transformation.apply(*point), *stone))
+                .collect::<Vec<_>>();
+            mapped.sort();
+            mapped == onto
+        })
+        .unwrap_or(Transformation::identity())
+}
+
+/// Where a game left theory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deviation {
+    /// Index of the move in [`GameRecord::moves`].
+    pub ply: usize,
+    pub played: Point,
+    /// The moves of the library from the position before.
+    pub known: Vec<Point>,
+}
+
+/// The result of [`match_game`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TheoryMatch {
+    /// The moves following theory, from the first.
+    pub followed: usize,
+    /// The name of the last line followed, e.g. the opening.
+    pub name: Option<String>,
+    /// `None` if the game followed theory to its end.
+    pub deviation: Option<Deviation>,
+    /// The name of every move starting a named line, by its index in [`GameRecord::moves`].
+    pub names: Vec<(usize, String)>,
+}
+
+impl TheoryMatch {
+    /// A comment for the moves worth one, by index in [`GameRecord::moves`], to annotate the
+    /// game with.
+    #[must_use]
+    pub fn annotations(&self) -> Vec<(usize, String)> {
+        let mut annotations = self.names.clone();
+        if let Some(deviation) = &self.deviation {
+            let text = match deviation.known.is_empty() {
+                true => "Leaves theory, the library ends here".to_owned(),
+                false => format!("Leaves theory, known: {}", write_moves(&deviation.known)),
+            };
+            annotations.push((deviation.ply, text));
+        }
+        annotations
+    }
+}
+
+/// Like `Followed theory for 3 moves (Kagetsu), left it with move 4 K10, known: J10 I10`.
+impl fmt::Display for TheoryMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Followed theory for {} moves", self.followed)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({name})")?;
+        }
+        match &self.deviation {
+            None => write!(f, " to the end of the game"),
+            Some(deviation) => {
+                write!(
+                    f,
+                    ", left it with move {} {}",
+                    deviation.ply + 1,
+                    deviation.played
+                )?;
+                if !deviation.known.is_empty() {
+                    write!(f, ", known: {}", write_moves(&deviation.known))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Replay `game` against the [bundled](Library::bundled) library.
+#[must_use]
+pub fn match_game(game: &GameRecord) -> TheoryMatch {
+    match_game_with(game, &Library::bundled())
+}
+
+/// Replay `game` against `library`. A pass leaves theory.
+#[must_use]
+pub fn match_game_with(game: &GameRecord, library: &Library) -> TheoryMatch {
+    let mut result = TheoryMatch {
+        followed: 0,
+        name: None,
+        deviation: None,
+        names: vec![],
+    };
+    let mut board = BoardArr::new(15);
+    let mut known = library
+        .lookup(&board)
+        .map(|(_, moves)| moves)
+        .unwrap_or_default();
+    for (ply, point) in game.moves.iter().enumerate() {
+        if !point.is_null {
+            board.set_point(*point, GameRecord::stone_at(ply));
+        }
+        match library.lookup(&board).filter(|_| !point.is_null) {
+            Some((name, moves)) => {
+                result.followed = ply + 1;
+                if let Some(name) = name.filter(|name| result.name.as_deref() != Some(name)) {
+                    result.names.push((ply, name.to_owned()));
+                    result.name = Some(name.to_owned());
+                }
+                known = moves;
+            }
+            None => {
+                result.deviation = Some(Deviation {
+                    ply,
+                    played: *point,
+                    known,
+                });
+                break;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p;
+
+    #[test]
+    fn follows_and_leaves() {
+        let library = Library::bundled();
+        // the empty board, the first move, two second moves and 26 third moves
+        assert_eq!(library.len(), 30);
+
+        // kagetsu, mirrored
+        let game = GameRecord::new(p![[H, 8], [H, 9], [G, 9], [A, 1]].to_vec());
+        let result = match_game_with(&game, &library);
+        assert_eq!(result.followed, 3);
+        assert_eq!(result.name.as_deref(), Some("Kagetsu"));
+        assert_eq!(
+            result.names,
+            [(1, "direct opening".to_owned()), (2, "Kagetsu".to_owned())]
+        );
+        let deviation = result.deviation.as_ref().unwrap();
+        assert_eq!((deviation.ply, deviation.played), (3, p![A, 1]));
+        assert!(deviation.known.is_empty());
+        assert_eq!(
+            result.to_string(),
+            "Followed theory for 3 moves (Kagetsu), left it with move 4 A1"
+        );
+
+        // no third move in the library is this far
+        let game = GameRecord::new(p![[H, 8], [I, 9], [L, 12]].to_vec());
+        let result = match_game(&game);
+        assert_eq!(result.followed, 2);
+        let deviation = result.deviation.unwrap();
+        assert_eq!(deviation.known.len(), 13);
+        assert!(deviation.known.contains(&p![J, 10]));
+    }
+
+    #[test]
+    fn user_library() {
+        let mut graph = Board::new();
+        let end = graph.add_line(&p![[H, 8], [I, 9], [J, 10], [G, 7]]);
+        graph.add_line(&p![[H, 8], [I, 9], [J, 10], [K, 11]]);
+        let library = Library::from_graph(&graph);
+        let (_, moves) = graph.as_board(&end).unwrap();
+
+        // rotated a quarter
+        let game = GameRecord::new(p![[H, 8], [G, 9], [F, 10], [I, 7]].to_vec());
+        let result = match_game_with(&game, &library);
+        assert_eq!(result.followed, 4);
+        assert_eq!(result.deviation, None);
+        assert_eq!(result.name, None);
+        assert!(result.annotations().is_empty());
+        assert_eq!(
+            result.to_string(),
+            "Followed theory for 4 moves to the end of the game"
+        );
+
+        let game = GameRecord::new(moves[..3].iter().copied().chain(p![[A, 1]]).collect());
+        let annotations = match_game_with(&game, &library).annotations();
+        assert_eq!(
+            annotations,
+            [(3, "Leaves theory, known: G7 K11".to_owned())]
+        );
+    }
+}
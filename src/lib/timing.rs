@@ -0,0 +1,222 @@
+//! Time spent per phase, collected from tracing spans.
+//!
+//! The evaluator enters a `debug` span for every phase of [`BoardArr::renju_conditions`]
+//! (`lines`, `fives`, `overlines`, `fours`, `threes` and `double_threes`, which contains the
+//! recursion of rule 9.3), and the RenLib reader for its stages (`parse_v3x` and `build_graph`).
+//! Add a [`TimingLayer`] to the subscriber to aggregate them into a [`TimingReport`], or use
+//! [`build_logger_with_timing`](crate::util::build_logger_with_timing).
+//!
+//! ```rust,ignore
+//! use tracing_subscriber::prelude::*;
+//!
+//! let layer = renju::timing::TimingLayer::new();
+//! let handle = layer.handle();
+//! tracing_subscriber::registry()
+//!     .with(layer.with_filter(renju::timing::filter()))
+//!     .init();
+//! // ...
+//! println!("{}", handle.report());
+//! ```
+//!
+//! [`BoardArr::renju_conditions`]: crate::board::BoardArr::renju_conditions
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tracing::span;
+use tracing::Subscriber;
+use tracing_subscriber::filter::{filter_fn, FilterFn};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+/// Aggregated time of all spans with the same name and target.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PhaseTiming {
+    pub target: &'static str,
+    pub name: &'static str,
+    /// Times the span was entered.
+    pub calls: u64,
+    /// Time spent inside the span, including child spans.
+    pub total: Duration,
+    /// Time spent inside the span, excluding child spans.
+    pub own: Duration,
+}
+
+/// Timings of every phase, with the most expensive first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TimingReport {
+    pub phases: Vec<PhaseTiming>,
+}
+
+impl TimingReport {
+    /// The timing of the span `name`, in any target.
+    #[must_use]
+    pub fn phase(&self, name: &str) -> Option<&PhaseTiming> {
+        self.phases.iter().find(|phase| phase.name == name)
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<56} {:>10} {:>12} {:>12}",
+            "phase", "calls", "own", "total"
+        )?;
+        for phase in &self.phases {
+            writeln!(
+                f,
+                "{:<56} {:>10} {:>12.3?} {:>12.3?}",
+                format!("{}::{}", phase.target, phase.name),
+                phase.calls,
+                phase.own,
+                phase.total
+            )?;
+        }
+        Ok(())
+    }
+}
+
+type Key = (&'static str, &'static str);
+
+/// A per-layer filter for a [`TimingLayer`], enabling only the spans of this crate.
+#[must_use]
+pub fn filter() -> FilterFn<fn(&tracing::Metadata<'_>) -> bool> {
+    filter_fn(|metadata| metadata.is_span() && metadata.target().starts_with("renju"))
+}
+
+/// A handle to the timings collected by a [`TimingLayer`].
+#[derive(Clone, Debug, Default)]
+pub struct TimingHandle(Arc<Mutex<HashMap<Key, PhaseTiming>>>);
+
+impl TimingHandle {
+    #[must_use]
+    pub fn report(&self) -> TimingReport {
+        let mut phases = self
+            .0
+            .lock()
+            .expect("timing lock poisoned")
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        phases.sort_by(|a, b| b.own.cmp(&a.own).then(a.name.cmp(b.name)));
+        TimingReport { phases }
+    }
+
+    /// Forget everything collected so far.
+    pub fn reset(&self) {
+        self.0.lock().expect("timing lock poisoned").clear();
+    }
+}
+
+/// Stored in the extensions of every span while it is entered.
+struct Entered {
+    at: Instant,
+    children: Duration,
+}
+
+/// A [`Layer`] aggregating the time spent in spans of this crate.
+#[derive(Debug, Default)]
+pub struct TimingLayer {
+    handle: TimingHandle,
+}
+
+impl TimingLayer {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn handle(&self) -> TimingHandle {
+        self.handle.clone()
+    }
+}
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_enter(&self, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().replace(Entered {
+                at: Instant::now(),
+                children: Duration::ZERO,
+            });
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let Some(entered) = span.extensions_mut().remove::<Entered>() else {
+            return;
+        };
+        let elapsed = entered.at.elapsed();
+        if let Some(parent) = span.parent() {
+            if let Some(parent) = parent.extensions_mut().get_mut::<Entered>() {
+                parent.children += elapsed;
+            }
+        }
+        let metadata = span.metadata();
+        let mut timings = self.handle.0.lock().expect("timing lock poisoned");
+        let timing = timings
+            .entry((metadata.target(), metadata.name()))
+            .or_insert_with(|| PhaseTiming {
+                target: metadata.target(),
+                name: metadata.name(),
+                calls: 0,
+                total: Duration::ZERO,
+                own: Duration::ZERO,
+            });
+        timing.calls += 1;
+        timing.total += elapsed;
+        timing.own += elapsed.saturating_sub(entered.children);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardArr, Evaluate, Point, Stone};
+    use crate::p;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn evaluator_phases() {
+        let layer = TimingLayer::new();
+        let handle = layer.handle();
+        let subscriber = tracing_subscriber::registry().with(layer.with_filter(filter()));
+        let mut board = BoardArr::new(15);
+        for point in p![[G, 8], [I, 8], [H, 7], [H, 9]] {
+            board.set_point(point, Stone::Black);
+        }
+        tracing::subscriber::with_default(subscriber, || {
+            let _ = board.renju_conditions(Stone::Black, None);
+        });
+        let report = handle.report();
+        for phase in [
+            "lines",
+            "fives",
+            "overlines",
+            "fours",
+            "threes",
+            "double_threes",
+        ] {
+            let timing = report.phase(phase).expect(phase);
+            assert!(timing.calls >= 1);
+            assert!(timing.own <= timing.total);
+        }
+        // the double-three on H8 is checked recursively
+        assert!(report.phase("fives").unwrap().calls > 1);
+        let double_threes = report.phase("double_threes").unwrap();
+        assert!(double_threes.own < double_threes.total);
+
+        handle.reset();
+        assert!(handle.report().phases.is_empty());
+    }
+}
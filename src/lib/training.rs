@@ -0,0 +1,195 @@
+//! Spaced repetition for puzzles, scheduled with a variant of the SM-2 algorithm.
+//!
+//! Each puzzle gets a [`Card`] keyed by [`Puzzle::key`]. Reviewing a card with a quality grade
+//! moves its due day further away the better it's known, and resets it when failed.
+
+use std::collections::BTreeMap;
+
+use crate::puzzle::{Puzzle, Verdict};
+
+/// Days since the unix epoch.
+pub type Day = u64;
+
+/// The current day, in days since the unix epoch.
+#[must_use]
+pub fn today() -> Day {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / (60 * 60 * 24))
+}
+
+/// How well a puzzle was recalled, 0 is a blackout and 5 is a perfect answer.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quality(u8);
+
+impl Quality {
+    pub const PERFECT: Quality = Quality(5);
+    pub const HESITANT: Quality = Quality(4);
+    pub const DIFFICULT: Quality = Quality(3);
+    pub const FAILED: Quality = Quality(1);
+
+    /// Create a quality grade, clamped to 0..=5
+    #[must_use]
+    pub fn new(grade: u8) -> Self {
+        Self(grade.min(5))
+    }
+
+    /// Grade a finished attempt, `mistakes` is how many wrong tries came before it.
+    #[must_use]
+    pub fn from_verdict(verdict: &Verdict, mistakes: usize) -> Self {
+        match (verdict, mistakes) {
+            (Verdict::Solved, 0) => Self::PERFECT,
+            (Verdict::Solved, 1) => Self::HESITANT,
+            (Verdict::Solved, _) => Self::DIFFICULT,
+            _ => Self::FAILED,
+        }
+    }
+
+    #[must_use]
+    pub fn grade(self) -> u8 {
+        self.0
+    }
+}
+
+/// Review state of a single puzzle.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Card {
+    /// Successful reviews in a row.
+    pub repetitions: u32,
+    /// Days until the next review.
+    pub interval: u64,
+    /// Ease factor, never below 1.3
+    pub ease: f64,
+    pub due: Day,
+    /// Every review made, oldest first.
+    pub history: Vec<(Day, Quality)>,
+}
+
+impl Card {
+    #[must_use]
+    pub fn new(today: Day) -> Self {
+        Self {
+            repetitions: 0,
+            interval: 0,
+            ease: 2.5,
+            due: today,
+            history: vec![],
+        }
+    }
+
+    /// Record a review made on `today`.
+    pub fn review(&mut self, quality: Quality, today: Day) {
+        let q = f64::from(quality.grade());
+        self.ease = (self.ease + 0.1 - (5.0 - q) * (0.08 + (5.0 - q) * 0.02)).max(1.3);
+        if quality < Quality::DIFFICULT {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u64,
+            };
+            self.repetitions += 1;
+        }
+        self.due = today + self.interval;
+        self.history.push((today, quality));
+    }
+}
+
+/// All cards of a user.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Training {
+    pub cards: BTreeMap<String, Card>,
+}
+
+impl Training {
+    /// Puzzles that should be reviewed on `today`, most overdue first. Puzzles never seen before
+    /// come last.
+    #[must_use]
+    pub fn due<'a>(&self, puzzles: &'a [Puzzle], today: Day) -> Vec<&'a Puzzle> {
+        let mut due = puzzles
+            .iter()
+            .filter_map(|puzzle| match self.cards.get(&puzzle.key()) {
+                Some(card) if card.due <= today => Some((card.due, puzzle)),
+                Some(_) => None,
+                None => Some((Day::MAX, puzzle)),
+            })
+            .collect::<Vec<_>>();
+        due.sort_by_key(|(due, _)| *due);
+        due.into_iter().map(|(_, puzzle)| puzzle).collect()
+    }
+
+    /// Record a review of `puzzle`.
+    pub fn record(&mut self, puzzle: &Puzzle, quality: Quality, today: Day) {
+        self.cards
+            .entry(puzzle.key())
+            .or_insert_with(|| Card::new(today))
+            .review(quality, today);
+    }
+
+    /// Load from a JSON file, a missing file gives an empty training.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> crate::Result<Self> {
+        match std::fs::File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(std::io::BufReader::new(file))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Save as a JSON file.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &std::path::Path) -> crate::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{BoardArr, Point, Stone};
+    use crate::p;
+    use crate::puzzle::PuzzleMove;
+
+    #[test]
+    fn sm2_intervals() {
+        let mut card = Card::new(100);
+        card.review(Quality::PERFECT, 100);
+        assert_eq!((card.interval, card.due), (1, 101));
+        card.review(Quality::PERFECT, 101);
+        assert_eq!((card.interval, card.due), (6, 107));
+        card.review(Quality::PERFECT, 107);
+        assert_eq!(card.interval, 17);
+        assert!((card.ease - 2.8).abs() < 1e-9);
+
+        card.review(Quality::FAILED, 124);
+        assert_eq!((card.repetitions, card.interval, card.due), (0, 1, 125));
+        assert!(card.ease < 2.8);
+        assert_eq!(card.history.len(), 4);
+    }
+
+    #[test]
+    fn due_puzzles() {
+        let puzzle = |point: Point| {
+            let mut position = BoardArr::new(15);
+            position.set_point(point, Stone::Black);
+            Puzzle::new(position, Stone::White, PuzzleMove::line(&[p![H, 8]]))
+        };
+        let puzzles = [puzzle(p![A, 1]), puzzle(p![B, 1]), puzzle(p![C, 1])];
+        let mut training = Training::default();
+        training.record(&puzzles[0], Quality::PERFECT, 10);
+        training.record(&puzzles[1], Quality::FAILED, 8);
+
+        let due = training.due(&puzzles, 10);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].key(), puzzles[1].key());
+        assert_eq!(due[1].key(), puzzles[2].key());
+        assert_eq!(training.due(&puzzles, 11).len(), 3);
+    }
+}
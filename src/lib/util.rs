@@ -1,9 +1,30 @@
 //! Convenience functions for usage
 
+#[cfg(feature = "cli")]
 use color_eyre::eyre::WrapErr;
+#[cfg(feature = "cli")]
+use tracing_subscriber::prelude::*;
+
+#[cfg(feature = "cli")]
+use crate::timing::{TimingHandle, TimingLayer};
 
 /// Build a logger that does file and term logging.
+#[cfg(feature = "cli")]
 pub fn build_logger() -> Result<(), color_eyre::eyre::Error> {
+    init_logger(None)
+}
+
+/// Like [`build_logger`], also collecting the time spent per phase, see [`crate::timing`].
+#[cfg(feature = "cli")]
+pub fn build_logger_with_timing() -> Result<TimingHandle, color_eyre::eyre::Error> {
+    let layer = TimingLayer::new();
+    let handle = layer.handle();
+    init_logger(Some(layer))?;
+    Ok(handle)
+}
+
+#[cfg(feature = "cli")]
+fn init_logger(timing: Option<TimingLayer>) -> Result<(), color_eyre::eyre::Error> {
     tracing_log::log_tracer::Builder::new()
         .init()
         .context("when building tracing builder")?;
@@ -11,14 +32,52 @@ pub fn build_logger() -> Result<(), color_eyre::eyre::Error> {
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"))
         .add_directive("rustyline=warn".parse()?);
 
-    let subscriber = tracing_subscriber::fmt::fmt()
+    let fmt = tracing_subscriber::fmt::layer()
         .with_target(true)
         .with_writer(std::io::stderr)
-        .with_env_filter(filter)
         .pretty()
         .with_ansi(false)
-        .finish();
+        .with_filter(filter);
+    let subscriber = tracing_subscriber::registry()
+        .with(fmt)
+        .with(timing.map(|layer| layer.with_filter(crate::timing::filter())));
     tracing::subscriber::set_global_default(subscriber)
         .context("could not set global tracing logger")?;
     Ok(())
 }
+
+/// Where to keep data between runs, `$XDG_DATA_HOME/renju-board` or
+/// `~/.local/share/renju-board`.
+#[must_use]
+pub fn data_dir() -> Option<std::path::PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
+
+fn xdg_dir(variable: &str, fallback: &str) -> Option<std::path::PathBuf> {
+    // relative paths are invalid by the spec and should be ignored
+    let base = std::env::var_os(variable)
+        .map(std::path::PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(fallback))
+        })?;
+    Some(base.join("renju-board"))
+}
+
+/// Where to look for configuration, `$XDG_CONFIG_HOME/renju-board` or `~/.config/renju-board`.
+#[must_use]
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// A hash of `bytes` as 16 hex digits, for keys kept on disk. FNV-1a, stable across platforms
+/// and releases unlike std's hasher.
+#[cfg(feature = "solver")]
+pub(crate) fn stable_hash(bytes: impl IntoIterator<Item = u8>) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
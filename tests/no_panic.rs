@@ -0,0 +1,241 @@
+//! The public API must not panic on any input, see the crate documentation. Targeted tests for
+//! inputs that used to panic, and a small deterministic fuzzer for the readers.
+
+use std::panic::catch_unwind;
+
+use renju::board::{Board, BoardArr, BoardMarker, Evaluate, MoveIndex, Point, Stone};
+use renju::file_reader::{
+    mapped, read_bytes, read_bytes_with_options, text, FileType, ParseOptions,
+};
+use renju::progress::Progress;
+
+/// A xorshift generator, so failures reproduce.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next() as u8).collect()
+    }
+}
+
+#[test]
+fn points_out_of_range() {
+    assert!(Point::from_1d(225, 15).is_null);
+    assert!(Point::from_1d(u32::MAX, 15).is_null);
+    assert!(Point::from_1d(0, 0).is_null);
+    assert!(!Point::from_1d(224, 15).is_null);
+    for byte in 0..=u8::MAX {
+        let _ = Point::from_byte(byte);
+    }
+    for text in ["", "A", "Z99", "A0", "a-1", "é", "Aé", "A99999999999"] {
+        assert!(text.parse::<Point>().is_err(), "{text:?}");
+    }
+}
+
+#[test]
+fn zero_size_boards() {
+    let mut board = BoardArr::new(0);
+    assert!(board.is_empty());
+    assert!(board.get_point(Point::new(0, 0)).is_none());
+    board.set_point(Point::new(0, 0), Stone::Black);
+    assert!(board
+        .set(BoardMarker::new(Point::new(0, 0), Stone::Black))
+        .is_err());
+    let _ = board.to_string();
+    let (black, white) = board.renju_conditions_both(None);
+    assert!(black.conditions.is_empty() && white.conditions.is_empty());
+    let board = BoardArr::new(15);
+    assert!(board
+        .renju_conditions(Stone::Empty, None)
+        .conditions
+        .is_empty());
+    #[cfg(feature = "solver")]
+    assert!(board.candidate_moves(Stone::Empty, 2, 10).is_empty());
+    for size in [1, 2, 4] {
+        let board = BoardArr::new(size);
+        let _ = board.to_string();
+        let _ = board.renju_conditions_both(None);
+    }
+}
+
+#[test]
+fn points_off_the_board() {
+    let mut board = BoardArr::new(15);
+    let outside = [
+        Point::new(15, 0),
+        Point::new(0, 15),
+        Point::new(99, 99),
+        Point::null(),
+    ];
+    for point in outside {
+        assert!(board.get_point(point).is_none(), "{point:?}");
+        board.set_point(point, Stone::Black);
+        assert!(board.set(BoardMarker::new(point, Stone::Black)).is_err());
+    }
+    assert!(board.iter().all(|m| m.color.is_empty()));
+}
+
+#[test]
+fn graph_index_misuse() {
+    let mut graph = Board::new();
+    let root = graph.get_root();
+    let h8 = graph.insert_move(root, BoardMarker::new(Point::new(7, 7), Stone::Black));
+    let other = {
+        let mut other = Board::new();
+        let root = other.get_root();
+        let a = other.insert_move(root, BoardMarker::new(Point::new(0, 0), Stone::Black));
+        let b = other.insert_move(a, BoardMarker::new(Point::new(1, 0), Stone::White));
+        other.insert_move(b, BoardMarker::new(Point::new(2, 0), Stone::Black))
+    };
+    let missing: MoveIndex = "1000".parse().unwrap();
+    for index in [other, missing] {
+        assert!(graph.get_move(index).is_none());
+        assert!(graph.get_children(&index).is_empty());
+        assert!(graph.as_board(&index).is_err());
+        assert!(graph.try_insert_move(index, BoardMarker::null()).is_err());
+        assert!(graph.set_pos(index, Point::new(1, 1)).is_err());
+        let _ = graph.describe_path(index);
+        let _ = graph.main_line(index);
+        let _ = graph.up_to_branch(&index);
+        let _ = graph.down_to_branch(&index);
+        let _ = graph.get_variants_and_transformations(index);
+    }
+    assert!(graph.set_index(5).is_err());
+    let _ = graph.as_board(&h8).unwrap();
+}
+
+#[test]
+fn notation_and_links() {
+    for text in ["é", "hé", "h8é", "ø9", "h", "8", "h8 i", ",,", "pass pass"] {
+        let _ = renju::board::notation::parse_moves(text);
+    }
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..2000 {
+        let len = (rng.next() % 24) as usize;
+        let bytes = rng.bytes(len);
+        let text = String::from_utf8_lossy(&bytes);
+        let _ = renju::board::notation::parse_moves(&text);
+        let _ = renju::share::decode_url(&format!("renju:{text}"));
+        let _ = text.parse::<Point>();
+        let _ = text.parse::<MoveIndex>();
+    }
+}
+
+#[test]
+fn lib_branches_out_of_the_tree() {
+    let header = &std::fs::read("tests/one_move.lib").unwrap()[..20];
+    // a branch on the first entry, and a second tree whose branch goes back into the first one
+    for entries in [
+        [0x78, 0xc0, 0x00, 0xc0, 0x00, 0x00],
+        [0x78, 0x40, 0x00, 0xc0, 0x00, 0x00],
+    ] {
+        let bytes = [header, &entries].concat();
+        let read = |options: &ParseOptions| {
+            read_bytes_with_options(
+                &bytes[..],
+                Some(&FileType::Lib),
+                &mut Board::new(),
+                options,
+                &mut Progress::none(),
+            )
+        };
+        assert!(read(&ParseOptions::max_depth(0)).is_err(), "{entries:02x?}");
+        let options = ParseOptions {
+            recover: true,
+            ..ParseOptions::max_depth(0)
+        };
+        assert_eq!(read(&options).unwrap().len(), 1, "{entries:02x?}");
+    }
+}
+
+#[test]
+fn fuzz_readers() {
+    let library = std::fs::read("tests/I7.lib").unwrap();
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for round in 0..500 {
+        // random bytes, and the library with some bytes changed or cut short
+        let bytes = if round % 2 == 0 {
+            let len = (rng.next() % 256) as usize;
+            rng.bytes(len)
+        } else {
+            let mut bytes = library.clone();
+            for _ in 0..(rng.next() % 8) {
+                let i = (rng.next() as usize) % bytes.len();
+                bytes[i] = rng.next() as u8;
+            }
+            bytes.truncate((rng.next() as usize) % (library.len() + 1));
+            bytes
+        };
+        for filetype in [Some(FileType::Lib), Some(FileType::Pos), None] {
+            let result = catch_unwind(|| {
+                let _ = read_bytes(&bytes[..], filetype.as_ref(), &mut Board::new());
+            });
+            assert!(result.is_ok(), "{filetype:?} panicked on {bytes:02x?}");
+        }
+        let result = catch_unwind(|| {
+            let _ = read_bytes_with_options(
+                &bytes[..],
+                Some(&FileType::Lib),
+                &mut Board::new(),
+                &ParseOptions::recover(),
+                &mut Progress::none(),
+            );
+        });
+        assert!(result.is_ok(), "recovering panicked on {bytes:02x?}");
+        let result = catch_unwind(|| {
+            let _ = read_bytes_with_options(
+                &bytes[..],
+                Some(&FileType::Lib),
+                &mut Board::new(),
+                &ParseOptions::max_depth(2),
+                &mut Progress::none(),
+            );
+        });
+        assert!(
+            result.is_ok(),
+            "reading two moves deep panicked on {bytes:02x?}"
+        );
+        let mut header = b"renju-tree 1\n".to_vec();
+        header.extend(&bytes);
+        let result = catch_unwind(|| {
+            let _ = text::parse(&header[..], &mut Board::new());
+        });
+        assert!(result.is_ok(), "text panicked on {header:02x?}");
+    }
+}
+
+#[test]
+fn fuzz_mapped() {
+    let mut graph = Board::new();
+    graph.set_title(Some("Title".to_owned()));
+    let i9 = graph.add_line(&[Point::new(7, 7), Point::new(8, 6)]);
+    let mut marker = BoardMarker::new(Point::new(9, 5), Stone::Black);
+    marker.oneline_comment = Some("comment".to_owned());
+    graph.insert_move(i9, marker);
+    let file = mapped::to_bytes(&graph);
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..500 {
+        let mut bytes = file.clone();
+        for _ in 0..(rng.next() % 4 + 1) {
+            let i = (rng.next() as usize) % bytes.len();
+            bytes[i] = rng.next() as u8;
+        }
+        let result = catch_unwind(|| {
+            if let Ok(mapped) = mapped::MappedGraph::from_bytes(&bytes[..]) {
+                for id in 0..mapped.len() as u32 {
+                    let node = mapped.node(id).unwrap();
+                    let _ = (node.marker(), node.parent(), node.children().count());
+                }
+                let _ = mapped.to_board();
+            }
+        });
+        assert!(result.is_ok(), "mapped panicked on {bytes:02x?}");
+    }
+}